@@ -0,0 +1,165 @@
+//! Symbolic names for Mach return codes.
+
+use mach_sys as sys;
+
+macro_rules! return_codes {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident($raw:ty) {
+            $($variant:ident = $constant:ident, $description:expr;)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+        #[non_exhaustive]
+        pub enum $name {
+            $($variant,)*
+            /// A code without a symbolic name in this version of the crate.
+            Other($raw),
+        }
+
+        impl $name {
+            #[inline]
+            pub fn into_raw(self) -> $raw {
+                match self {
+                    $($name::$variant => sys::$constant as $raw,)*
+                    $name::Other(raw) => raw,
+                }
+            }
+
+            /// The name of the C constant the code corresponds to.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $($name::$variant => Some(stringify!($constant)),)*
+                    $name::Other(_) => None,
+                }
+            }
+
+            /// A short description of the code, from the comments in the Mach headers.
+            pub fn description(self) -> Option<&'static str> {
+                match self {
+                    $($name::$variant => Some($description),)*
+                    $name::Other(_) => None,
+                }
+            }
+        }
+
+        impl From<$raw> for $name {
+            fn from(raw: $raw) -> $name {
+                match raw as u32 {
+                    $(sys::$constant => $name::$variant,)*
+                    _ => $name::Other(raw),
+                }
+            }
+        }
+
+        impl From<$name> for $raw {
+            #[inline]
+            fn from(code: $name) -> $raw {
+                code.into_raw()
+            }
+        }
+    };
+}
+
+return_codes! {
+    /// A `kern_return_t` value (`KERN_*`).
+    pub enum KernReturn(sys::kern_return_t) {
+        Success = KERN_SUCCESS, "success";
+        InvalidAddress = KERN_INVALID_ADDRESS, "specified address is not currently valid";
+        ProtectionFailure = KERN_PROTECTION_FAILURE, "specified memory is valid, but does not permit the required forms of access";
+        NoSpace = KERN_NO_SPACE, "the address range specified is already in use, or no address range of the size specified could be found";
+        InvalidArgument = KERN_INVALID_ARGUMENT, "the function requested was not applicable to this type of argument, or an argument is invalid";
+        Failure = KERN_FAILURE, "the function could not be performed";
+        ResourceShortage = KERN_RESOURCE_SHORTAGE, "a system resource could not be allocated to fulfill this request";
+        NotReceiver = KERN_NOT_RECEIVER, "the task in question does not hold receive rights for the port argument";
+        NoAccess = KERN_NO_ACCESS, "bogus access restriction";
+        MemoryFailure = KERN_MEMORY_FAILURE, "during a page fault, the target address refers to a memory object that has been destroyed";
+        MemoryError = KERN_MEMORY_ERROR, "during a page fault, the memory object indicated that the data could not be returned";
+        AlreadyInSet = KERN_ALREADY_IN_SET, "the receive right is already a member of the portset";
+        NotInSet = KERN_NOT_IN_SET, "the receive right is not a member of a port set";
+        NameExists = KERN_NAME_EXISTS, "the name already denotes a right in the task";
+        Aborted = KERN_ABORTED, "the operation was aborted";
+        InvalidName = KERN_INVALID_NAME, "the name doesn't denote a right in the task";
+        InvalidTask = KERN_INVALID_TASK, "target task isn't an active task";
+        InvalidRight = KERN_INVALID_RIGHT, "the name denotes a right, but not an appropriate right";
+        InvalidValue = KERN_INVALID_VALUE, "a blatant range error";
+        UrefsOverflow = KERN_UREFS_OVERFLOW, "operation would overflow limit on user-references";
+        InvalidCapability = KERN_INVALID_CAPABILITY, "the supplied port capability is improper";
+        RightExists = KERN_RIGHT_EXISTS, "the task already has send or receive rights for the port under another name";
+        InvalidHost = KERN_INVALID_HOST, "target host isn't actually a host";
+        MemoryPresent = KERN_MEMORY_PRESENT, "an attempt was made to supply precious data for memory that is already present";
+        MemoryDataMoved = KERN_MEMORY_DATA_MOVED, "a page was requested of a memory manager via memory_object_data_request for an object using a MEMORY_OBJECT_COPY_CALL strategy";
+        MemoryRestartCopy = KERN_MEMORY_RESTART_COPY, "a strategic copy was attempted of an object upon which a quicker copy is now possible";
+        InvalidProcessorSet = KERN_INVALID_PROCESSOR_SET, "an argument applied to assert processor set privilege was not a processor set control port";
+        PolicyLimit = KERN_POLICY_LIMIT, "the specified scheduling attributes exceed the thread's limits";
+        InvalidPolicy = KERN_INVALID_POLICY, "the specified scheduling policy is not currently enabled for the processor set";
+        InvalidObject = KERN_INVALID_OBJECT, "the external memory manager failed to initialize the memory object";
+        AlreadyWaiting = KERN_ALREADY_WAITING, "a thread is attempting to wait for an event for which there is already a waiting thread";
+        DefaultSet = KERN_DEFAULT_SET, "an attempt was made to destroy the default processor set";
+        ExceptionProtected = KERN_EXCEPTION_PROTECTED, "an attempt was made to fetch an exception port that is protected, or to abort a thread while processing a protected exception";
+        InvalidLedger = KERN_INVALID_LEDGER, "a ledger was required but not supplied";
+        InvalidMemoryControl = KERN_INVALID_MEMORY_CONTROL, "the port was not a memory cache control port";
+        InvalidSecurity = KERN_INVALID_SECURITY, "an argument supplied to assert security privilege was not a host security port";
+        NotDepressed = KERN_NOT_DEPRESSED, "thread_depress_abort was called on a thread which was not currently depressed";
+        Terminated = KERN_TERMINATED, "object has been terminated and is no longer available";
+        LockSetDestroyed = KERN_LOCK_SET_DESTROYED, "lock set has been destroyed and is no longer available";
+        LockUnstable = KERN_LOCK_UNSTABLE, "the thread holding the lock terminated before releasing the lock";
+        LockOwned = KERN_LOCK_OWNED, "the lock is already owned by another thread";
+        LockOwnedSelf = KERN_LOCK_OWNED_SELF, "the lock is already owned by the calling thread";
+        SemaphoreDestroyed = KERN_SEMAPHORE_DESTROYED, "semaphore has been destroyed and is no longer available";
+        RpcServerTerminated = KERN_RPC_SERVER_TERMINATED, "return from RPC indicating the target server was terminated before it successfully replied";
+        RpcTerminateOrphan = KERN_RPC_TERMINATE_ORPHAN, "terminate an orphaned activation";
+        RpcContinueOrphan = KERN_RPC_CONTINUE_ORPHAN, "allow an orphaned activation to continue executing";
+        NotSupported = KERN_NOT_SUPPORTED, "empty thread activation (no thread linked to it)";
+        NodeDown = KERN_NODE_DOWN, "remote node down or inaccessible";
+        NotWaiting = KERN_NOT_WAITING, "a signalled thread was not actually waiting";
+        OperationTimedOut = KERN_OPERATION_TIMED_OUT, "some thread-oriented operation (semaphore_wait) timed out";
+        CodesignError = KERN_CODESIGN_ERROR, "during a page fault, indicates that the page was rejected as a result of a signature check";
+        PolicyStatic = KERN_POLICY_STATIC, "the requested property cannot be changed at this time";
+        InsufficientBufferSize = KERN_INSUFFICIENT_BUFFER_SIZE, "the provided buffer is of insufficient size for the requested data";
+        Denied = KERN_DENIED, "denied by security policy";
+        MissingKc = KERN_MISSING_KC, "the KC on which the function is operating is missing";
+        InvalidKc = KERN_INVALID_KC, "the KC on which the function is operating is invalid";
+        NotFound = KERN_NOT_FOUND, "a search or query operation did not return a result";
+    }
+}
+
+return_codes! {
+    /// A `mach_msg_return_t` value (`MACH_MSG_SUCCESS`, `MACH_SEND_*` and `MACH_RCV_*`).
+    pub enum MachMsgReturn(sys::mach_msg_return_t) {
+        Success = MACH_MSG_SUCCESS, "message sent or received successfully";
+        SendInProgress = MACH_SEND_IN_PROGRESS, "thread is waiting to send";
+        SendInvalidData = MACH_SEND_INVALID_DATA, "bogus in-line data";
+        SendInvalidDest = MACH_SEND_INVALID_DEST, "bogus destination port";
+        SendTimedOut = MACH_SEND_TIMED_OUT, "message not sent before timeout expired";
+        SendInvalidVoucher = MACH_SEND_INVALID_VOUCHER, "bogus voucher port";
+        SendInterrupted = MACH_SEND_INTERRUPTED, "software interrupt";
+        SendMsgTooSmall = MACH_SEND_MSG_TOO_SMALL, "data doesn't contain a complete message";
+        SendInvalidReply = MACH_SEND_INVALID_REPLY, "bogus reply port";
+        SendInvalidRight = MACH_SEND_INVALID_RIGHT, "bogus port rights in the message body";
+        SendInvalidNotify = MACH_SEND_INVALID_NOTIFY, "bogus notify port argument";
+        SendInvalidMemory = MACH_SEND_INVALID_MEMORY, "invalid out-of-line memory pointer";
+        SendNoBuffer = MACH_SEND_NO_BUFFER, "no message buffer is available";
+        SendTooLarge = MACH_SEND_TOO_LARGE, "send is too large for port";
+        SendInvalidType = MACH_SEND_INVALID_TYPE, "invalid message type";
+        SendInvalidHeader = MACH_SEND_INVALID_HEADER, "a field in the header had a bad value";
+        SendInvalidTrailer = MACH_SEND_INVALID_TRAILER, "the trailer to be sent does not match kernel format";
+        RcvInProgress = MACH_RCV_IN_PROGRESS, "thread is waiting for receive";
+        RcvInvalidName = MACH_RCV_INVALID_NAME, "bogus name for receive port or port set";
+        RcvTimedOut = MACH_RCV_TIMED_OUT, "didn't get a message within the timeout value";
+        RcvTooLarge = MACH_RCV_TOO_LARGE, "message buffer is not large enough for inline data";
+        RcvInterrupted = MACH_RCV_INTERRUPTED, "software interrupt";
+        RcvPortChanged = MACH_RCV_PORT_CHANGED, "compatibility: no longer a returned error";
+        RcvInvalidNotify = MACH_RCV_INVALID_NOTIFY, "bogus notify port argument";
+        RcvInvalidData = MACH_RCV_INVALID_DATA, "bogus message buffer for inline data";
+        RcvPortDied = MACH_RCV_PORT_DIED, "port or port set was destroyed while waiting for a message";
+        RcvInSet = MACH_RCV_IN_SET, "compatibility: no longer a returned error";
+        RcvHeaderError = MACH_RCV_HEADER_ERROR, "error receiving message header";
+        RcvBodyError = MACH_RCV_BODY_ERROR, "error receiving message body";
+        RcvInvalidType = MACH_RCV_INVALID_TYPE, "invalid memory type for receiving out-of-line data";
+        RcvScatterSmall = MACH_RCV_SCATTER_SMALL, "out-of-line overwrite region is not large enough";
+        RcvInvalidTrailer = MACH_RCV_INVALID_TRAILER, "trailer type or number of trailer elements not supported";
+        RcvInProgressTimed = MACH_RCV_IN_PROGRESS_TIMED, "waiting for receive with timeout";
+    }
+}
@@ -1,10 +1,39 @@
-use std::{io, fmt};
-use std::ffi::CStr;
+use core::fmt;
+#[cfg(target_vendor = "apple")]
+use core::ffi::{CStr, c_char};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::RawPort;
+use crate::code::{KernReturn, MachMsgReturn};
 
 use mach_sys as sys;
 
+/// Converts a `mach_error_t` result into an `io::Result<()>`.
+///
+/// The `op:` forms attach the name of the operation (and optionally the port it targeted) to the error, and the
+/// `log op:` forms additionally log it, so call sites don't need their own formatting.
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! mach_call {
+    (log op: $op:expr, port: $port:expr, $x:expr) => {
+        $crate::__log_err!(mach_call!(op: $op, port: $port, $x))
+    };
+    (log op: $op:expr, $x:expr) => {
+        $crate::__log_err!(mach_call!(op: $op, $x))
+    };
+    (op: $op:expr, port: $port:expr, $x:expr) => {
+        match $x {
+            0 => Ok(()),
+            code => Err($crate::error::MachError::from_mach_error(code).with_context($op, Some($port))),
+        }
+    };
+    (op: $op:expr, $x:expr) => {
+        match $x {
+            0 => Ok(()),
+            code => Err($crate::error::MachError::from_mach_error(code).with_context($op, None)),
+        }
+    };
     (log: $x:expr, $fmt_str:tt $(, $fmt_arg:expr $(,)*)* ) => {
         match mach_call!($x) {
             Ok(()) => Ok(()),
@@ -25,8 +54,28 @@ macro_rules! mach_call {
     };
 }
 
+/// Converts a `kern_return_t` result into an `io::Result<()>`, accepting the same forms as [`mach_call!`].
+#[cfg(feature = "std")]
 #[macro_export]
 macro_rules! mach_kern_call {
+    (log op: $op:expr, port: $port:expr, $x:expr) => {
+        $crate::__log_err!(mach_kern_call!(op: $op, port: $port, $x))
+    };
+    (log op: $op:expr, $x:expr) => {
+        $crate::__log_err!(mach_kern_call!(op: $op, $x))
+    };
+    (op: $op:expr, port: $port:expr, $x:expr) => {
+        match $x {
+            0 => Ok(()),
+            code => Err($crate::error::MachError::Kern(code).with_context($op, Some($port))),
+        }
+    };
+    (op: $op:expr, $x:expr) => {
+        match $x {
+            0 => Ok(()),
+            code => Err($crate::error::MachError::Kern(code).with_context($op, None)),
+        }
+    };
     (log: $x:expr, $fmt_str:tt $(, $fmt_arg:expr $(,)*)* ) => {
         match mach_kern_call!($x) {
             Ok(()) => Ok(()),
@@ -47,59 +96,495 @@ macro_rules! mach_kern_call {
     };
 }
 
-pub fn rust_from_mach_error(code: sys::mach_error_t) -> io::Error {
-    // TODO: transfer more equivalent codes to io::ErrorKind
-    let kind = match code as u32 {
-        sys::MACH_SEND_TIMED_OUT => io::ErrorKind::TimedOut,
-        sys::MACH_RCV_TIMED_OUT => io::ErrorKind::TimedOut,
-        _ => io::ErrorKind::Other,
+/// Calls a function whose trailing arguments are out-parameters, returning their values on success.
+///
+/// The out-parameters are named after the `->` and initialized to zero, so the usual pattern of declaring mutable
+/// locals before the call is unnecessary:
+///
+/// ```ignore
+/// let port = unsafe { mach_call_out!(sys::mach_port_allocate(task, sys::MACH_PORT_RIGHT_RECEIVE) -> port)? };
+/// let (port, right) = unsafe { mach_call_out!(log op: "mach_port_extract_right", port: task, sys::mach_port_extract_right(task, name, disposition) -> (port, right))? };
+/// ```
+///
+/// The same `log:`, `op:` and `log op:` forms as [`mach_call!`] are accepted before the call. Like [`mach_call!`], this must be
+/// used inside an `unsafe` block.
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! mach_call_out {
+    (@call ($($mode:tt)*) $($f:ident)::+ ( $($arg:expr),* ) -> ($($out:ident),+ $(,)*)) => {{
+        $(let mut $out = ::core::mem::zeroed();)+
+        match $crate::mach_call!($($mode)* $($f)::+ ( $($arg,)* $(&mut $out),+ )) {
+            Ok(()) => Ok(($($out),+)),
+            Err(err) => Err(err),
+        }
+    }};
+    (@call ($($mode:tt)*) $($f:ident)::+ ( $($arg:expr),* ) -> $out:ident) => {{
+        let mut $out = ::core::mem::zeroed();
+        match $crate::mach_call!($($mode)* $($f)::+ ( $($arg,)* &mut $out )) {
+            Ok(()) => Ok($out),
+            Err(err) => Err(err),
+        }
+    }};
+    (log: $($f:ident)::+ ( $($arg:expr),* $(,)* ) -> $out:tt, $fmt_str:tt $(, $fmt_arg:expr $(,)*)* ) => {
+        match $crate::mach_call_out!($($f)::+ ( $($arg),* ) -> $out) {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                ::log::error!($fmt_str, err, $($fmt_arg,)* );
+                Err(err)
+            }
+        }
+    };
+    (log op: $op:expr, port: $port:expr, $($f:ident)::+ ( $($arg:expr),* $(,)* ) -> $out:tt) => {
+        $crate::mach_call_out!(@call (log op: $op, port: $port,) $($f)::+ ( $($arg),* ) -> $out)
+    };
+    (log op: $op:expr, $($f:ident)::+ ( $($arg:expr),* $(,)* ) -> $out:tt) => {
+        $crate::mach_call_out!(@call (log op: $op,) $($f)::+ ( $($arg),* ) -> $out)
+    };
+    (op: $op:expr, port: $port:expr, $($f:ident)::+ ( $($arg:expr),* $(,)* ) -> $out:tt) => {
+        $crate::mach_call_out!(@call (op: $op, port: $port,) $($f)::+ ( $($arg),* ) -> $out)
+    };
+    (op: $op:expr, $($f:ident)::+ ( $($arg:expr),* $(,)* ) -> $out:tt) => {
+        $crate::mach_call_out!(@call (op: $op,) $($f)::+ ( $($arg),* ) -> $out)
+    };
+    ($($f:ident)::+ ( $($arg:expr),* $(,)* ) -> $out:tt) => {
+        $crate::mach_call_out!(@call () $($f)::+ ( $($arg),* ) -> $out)
     };
-    io::Error::new(kind, ErrorWrapper(code))
 }
 
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_err {
+    ($result:expr) => {
+        match $result {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                ::log::error!("{}", err);
+                Err(err)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+pub fn rust_from_mach_error(code: sys::mach_error_t) -> io::Error {
+    MachError::from_mach_error(code).into()
+}
+
+#[cfg(feature = "std")]
 pub fn rust_from_mach_kern_error(code: sys::kern_return_t) -> io::Error {
-    // TODO: transfer equivalent codes to io::ErrorKind
-    io::Error::new(io::ErrorKind::Other, KernErrorWrapper(code))
+    MachError::Kern(code).into()
+}
+
+/// An error returned by a Mach call, carrying the raw code.
+///
+/// The APIs of this crate and `mach-port` return `io::Error`, since many of them can also fail for reasons that have
+/// no Mach code (invalid arguments, malformed messages, short reads). When a Mach call failed, the `io::Error` wraps a
+/// `MachError` (along with the operation and port, for the `op:` forms of [`mach_call!`]), which can be recovered with
+/// [`MachError::from_io`] or [`MachErrorExt`] to match on the exact code. A `MachError` converts back into an
+/// `io::Error` with `Into`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MachError {
+    /// A `mach_msg` send error (`MACH_SEND_*`).
+    Send(sys::mach_msg_return_t),
+    /// A `mach_msg` receive error (`MACH_RCV_*`).
+    Receive(sys::mach_msg_return_t),
+    /// A `kern_return_t` from a kernel call or MIG routine.
+    Kern(sys::kern_return_t),
+    /// An error from the bootstrap server (`BOOTSTRAP_*`).
+    Bootstrap(sys::kern_return_t),
 }
 
+// Error codes from servers/bootstrap.h
+const BOOTSTRAP_NOT_PRIVILEGED: sys::kern_return_t = 1100;
+#[cfg(feature = "std")]
+const BOOTSTRAP_NAME_IN_USE: sys::kern_return_t = 1101;
+#[cfg(feature = "std")]
+const BOOTSTRAP_UNKNOWN_SERVICE: sys::kern_return_t = 1102;
+#[cfg(feature = "std")]
+const BOOTSTRAP_SERVICE_ACTIVE: sys::kern_return_t = 1103;
+#[cfg(feature = "std")]
+const BOOTSTRAP_BAD_COUNT: sys::kern_return_t = 1104;
+const BOOTSTRAP_NO_MEMORY: sys::kern_return_t = 1105;
+const BOOTSTRAP_NO_CHILDREN: sys::kern_return_t = 1106;
+
+const MACH_MSG_SEND_ERROR: u32 = 0x1000_0000;
+const MACH_MSG_RCV_ERROR: u32 = 0x1000_4000;
+// The system and subsystem bits of mach_msg_return_t, separating send errors from receive errors
+const MACH_MSG_ERROR_MASK: u32 = 0xffff_c000;
+
+impl MachError {
+    /// Classifies a `mach_error_t` returned by a call that may fail with `mach_msg`, kernel or bootstrap errors.
+    pub fn from_mach_error(code: sys::mach_error_t) -> MachError {
+        match code as u32 & MACH_MSG_ERROR_MASK {
+            MACH_MSG_SEND_ERROR => MachError::Send(code as _),
+            MACH_MSG_RCV_ERROR => MachError::Receive(code as _),
+            // Bootstrap codes sit well past the last kern_return_t, so they can't be mistaken for kernel errors
+            _ if (BOOTSTRAP_NOT_PRIVILEGED..=BOOTSTRAP_NO_CHILDREN).contains(&(code as sys::kern_return_t)) => MachError::Bootstrap(code as _),
+            _ => MachError::Kern(code as _),
+        }
+    }
+
+    /// Extracts the `MachError` an `io::Error` was created from, if any.
+    #[cfg(feature = "std")]
+    pub fn from_io(err: &io::Error) -> Option<MachError> {
+        let inner = err.get_ref()?;
+        if let Some(err) = inner.downcast_ref::<MachError>() {
+            Some(*err)
+        } else {
+            inner.downcast_ref::<ContextError>().map(|err| err.error)
+        }
+    }
+
+    /// Converts the error into an `io::Error` recording the operation that failed and the port it targeted.
+    #[cfg(feature = "std")]
+    pub fn with_context(self, operation: &'static str, port: Option<RawPort>) -> io::Error {
+        io::Error::new(self.kind(), ContextError {
+            error: self,
+            context: ErrorContext {
+                operation,
+                port,
+            },
+            // Looked up now, since the name may be released (and its label cleared) before the error is displayed
+            label: port.and_then(crate::label::label),
+        })
+    }
+
+    /// The `kern_return_t` of a [`Kern`](MachError::Kern) error, as a symbolic value.
+    #[inline]
+    pub fn kern_return(&self) -> Option<KernReturn> {
+        match *self {
+            MachError::Kern(code) => Some(KernReturn::from(code)),
+            _ => None,
+        }
+    }
+
+    /// The `mach_msg_return_t` of a [`Send`](MachError::Send) or [`Receive`](MachError::Receive) error, as a symbolic
+    /// value.
+    #[inline]
+    pub fn msg_return(&self) -> Option<MachMsgReturn> {
+        match *self {
+            MachError::Send(code) | MachError::Receive(code) => Some(MachMsgReturn::from(code)),
+            _ => None,
+        }
+    }
+
+    /// The raw error code.
+    #[inline]
+    pub fn code(&self) -> sys::mach_error_t {
+        match *self {
+            MachError::Send(code) | MachError::Receive(code) => code as _,
+            MachError::Kern(code) | MachError::Bootstrap(code) => code as _,
+        }
+    }
+
+    /// Whether the operation timed out, in which case it may succeed if retried.
+    pub fn is_timeout(&self) -> bool {
+        match *self {
+            MachError::Send(code) => code as u32 == sys::MACH_SEND_TIMED_OUT,
+            MachError::Receive(code) => code as u32 == sys::MACH_RCV_TIMED_OUT,
+            MachError::Kern(code) => code as u32 == sys::KERN_OPERATION_TIMED_OUT,
+            MachError::Bootstrap(_) => false,
+        }
+    }
+
+    /// Whether the error is caused by a temporary condition (a timeout, interruption or resource shortage), so the
+    /// operation may succeed if retried unchanged.
+    pub fn is_transient(&self) -> bool {
+        if self.is_timeout() {
+            return true;
+        }
+        match *self {
+            MachError::Send(code) => matches!(code as u32, sys::MACH_SEND_INTERRUPTED | sys::MACH_SEND_NO_BUFFER),
+            MachError::Receive(code) => code as u32 == sys::MACH_RCV_INTERRUPTED,
+            MachError::Kern(code) => matches!(code as u32, sys::KERN_ABORTED | sys::KERN_RESOURCE_SHORTAGE),
+            MachError::Bootstrap(code) => code == BOOTSTRAP_NO_MEMORY,
+        }
+    }
 
-// Struct that wraps a mach error code for placement inside a std::io::Error
-struct ErrorWrapper(sys::mach_error_t);
+    /// Whether the other end of the communication has gone away (e.g. the receiver of a port has exited), so
+    /// retrying is pointless without reconnecting.
+    pub fn is_peer_gone(&self) -> bool {
+        match *self {
+            MachError::Send(code) => code as u32 == sys::MACH_SEND_INVALID_DEST,
+            MachError::Receive(code) => code as u32 == sys::MACH_RCV_PORT_DIED,
+            MachError::Kern(code) => matches!(code as u32, sys::KERN_TERMINATED | sys::KERN_RPC_SERVER_TERMINATED),
+            MachError::Bootstrap(_) => false,
+        }
+    }
+
+    /// The closest `io::ErrorKind` to the error, so generic I/O code can react sensibly without knowing about Mach.
+    #[cfg(feature = "std")]
+    pub fn kind(&self) -> io::ErrorKind {
+        match *self {
+            MachError::Send(code) => match code as u32 {
+                sys::MACH_SEND_TIMED_OUT => io::ErrorKind::TimedOut,
+                sys::MACH_SEND_INTERRUPTED => io::ErrorKind::Interrupted,
+                // The kernel could not allocate a buffer for the message, which may succeed later
+                sys::MACH_SEND_NO_BUFFER => io::ErrorKind::WouldBlock,
+                // The destination right has died or was never valid, usually because the receiver has exited
+                sys::MACH_SEND_INVALID_DEST => io::ErrorKind::BrokenPipe,
+                sys::MACH_SEND_INVALID_DATA |
+                sys::MACH_SEND_INVALID_HEADER |
+                sys::MACH_SEND_INVALID_NOTIFY |
+                sys::MACH_SEND_INVALID_REPLY |
+                sys::MACH_SEND_INVALID_RIGHT |
+                sys::MACH_SEND_INVALID_MEMORY |
+                sys::MACH_SEND_INVALID_TYPE |
+                sys::MACH_SEND_INVALID_TRAILER |
+                sys::MACH_SEND_INVALID_VOUCHER |
+                sys::MACH_SEND_MSG_TOO_SMALL |
+                sys::MACH_SEND_TOO_LARGE => io::ErrorKind::InvalidInput,
+                _ => io::ErrorKind::Other,
+            },
+            MachError::Receive(code) => match code as u32 {
+                sys::MACH_RCV_TIMED_OUT => io::ErrorKind::TimedOut,
+                sys::MACH_RCV_INTERRUPTED => io::ErrorKind::Interrupted,
+                sys::MACH_RCV_PORT_DIED => io::ErrorKind::BrokenPipe,
+                sys::MACH_RCV_INVALID_NAME |
+                sys::MACH_RCV_IN_SET |
+                sys::MACH_RCV_INVALID_NOTIFY |
+                sys::MACH_RCV_SCATTER_SMALL |
+                sys::MACH_RCV_TOO_LARGE => io::ErrorKind::InvalidInput,
+                sys::MACH_RCV_HEADER_ERROR |
+                sys::MACH_RCV_BODY_ERROR |
+                sys::MACH_RCV_INVALID_DATA |
+                sys::MACH_RCV_INVALID_TYPE |
+                sys::MACH_RCV_INVALID_TRAILER => io::ErrorKind::InvalidData,
+                _ => io::ErrorKind::Other,
+            },
+            MachError::Kern(code) => match code as u32 {
+                sys::KERN_INVALID_ADDRESS |
+                sys::KERN_INVALID_ARGUMENT |
+                sys::KERN_INVALID_NAME |
+                sys::KERN_INVALID_TASK |
+                sys::KERN_INVALID_RIGHT |
+                sys::KERN_INVALID_VALUE |
+                sys::KERN_INVALID_CAPABILITY |
+                sys::KERN_INVALID_HOST |
+                sys::KERN_INVALID_OBJECT |
+                sys::KERN_INVALID_POLICY => io::ErrorKind::InvalidInput,
+                sys::KERN_PROTECTION_FAILURE |
+                sys::KERN_NO_ACCESS |
+                sys::KERN_NOT_RECEIVER |
+                sys::KERN_DENIED |
+                sys::KERN_CODESIGN_ERROR => io::ErrorKind::PermissionDenied,
+                sys::KERN_NO_SPACE |
+                sys::KERN_RESOURCE_SHORTAGE => io::ErrorKind::OutOfMemory,
+                sys::KERN_NAME_EXISTS |
+                sys::KERN_RIGHT_EXISTS |
+                sys::KERN_ALREADY_IN_SET |
+                sys::KERN_ALREADY_WAITING => io::ErrorKind::AlreadyExists,
+                sys::KERN_NOT_FOUND |
+                sys::KERN_NOT_IN_SET => io::ErrorKind::NotFound,
+                sys::KERN_ABORTED => io::ErrorKind::Interrupted,
+                sys::KERN_OPERATION_TIMED_OUT => io::ErrorKind::TimedOut,
+                sys::KERN_TERMINATED => io::ErrorKind::BrokenPipe,
+                _ => io::ErrorKind::Other,
+            },
+            MachError::Bootstrap(code) => match code {
+                BOOTSTRAP_NOT_PRIVILEGED => io::ErrorKind::PermissionDenied,
+                BOOTSTRAP_NAME_IN_USE | BOOTSTRAP_SERVICE_ACTIVE => io::ErrorKind::AlreadyExists,
+                BOOTSTRAP_UNKNOWN_SERVICE | BOOTSTRAP_NO_CHILDREN => io::ErrorKind::NotFound,
+                BOOTSTRAP_BAD_COUNT => io::ErrorKind::InvalidInput,
+                BOOTSTRAP_NO_MEMORY => io::ErrorKind::OutOfMemory,
+                _ => io::ErrorKind::Other,
+            },
+        }
+    }
+}
 
-impl fmt::Debug for ErrorWrapper {
+#[cfg(feature = "std")]
+impl From<MachError> for io::Error {
+    fn from(err: MachError) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+impl fmt::Debug for MachError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let name = unsafe { CStr::from_ptr(sys::mach_error_string(self.0)) };
-        write!(f, "MachError {{ code: {:#x?}, description: {:?} }}", self.0, name)
+        let variant = match *self {
+            MachError::Send(_) => "Send",
+            MachError::Receive(_) => "Receive",
+            MachError::Kern(_) => "Kern",
+            MachError::Bootstrap(_) => "Bootstrap",
+        };
+        write!(f, "MachError::{} {{ code: {:#x?}, description: \"{}\" }}", variant, self.code(), self)
     }
 }
 
-impl fmt::Display for ErrorWrapper {
+impl fmt::Display for MachError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let name = unsafe { CStr::from_ptr(sys::mach_error_string(self.0)) };
-        write!(f, "{:?}", name)
+        match *self {
+            MachError::Send(code) | MachError::Receive(code) => {
+                let code = MachMsgReturn::from(code);
+                match (code.name(), code.description()) {
+                    (Some(name), Some(description)) => write!(f, "{} ({})", description, name),
+                    #[cfg(target_vendor = "apple")]
+                    _ => {
+                        write_c_str(f, unsafe { CStr::from_ptr(sys::mach_error_string(code.into_raw())) })
+                    },
+                    #[cfg(not(target_vendor = "apple"))]
+                    _ => write!(f, "unknown mach_msg_return_t (code {:#x?})", code.into_raw()),
+                }
+            },
+            MachError::Kern(code) => {
+                let code = KernReturn::from(code);
+                match (code.name(), code.description()) {
+                    (Some(name), Some(description)) => write!(f, "{} ({})", description, name),
+                    _ => write!(f, "unknown kern_return_t (code {:#x?})", code.into_raw()),
+                }
+            },
+            #[cfg(target_vendor = "apple")]
+            MachError::Bootstrap(code) => {
+                write_c_str(f, unsafe { CStr::from_ptr(bootstrap_strerror(code)) })
+            },
+            #[cfg(not(target_vendor = "apple"))]
+            MachError::Bootstrap(code) => write!(f, "bootstrap error (code {:#x?})", code),
+        }
     }
 }
 
-impl std::error::Error for ErrorWrapper {
+// The system's error strings are ASCII, so this doesn't need to allocate for lossy conversion
+#[cfg(target_vendor = "apple")]
+fn write_c_str(f: &mut fmt::Formatter, s: &CStr) -> fmt::Result {
+    f.write_str(s.to_str().unwrap_or("<invalid UTF-8 in error string>"))
+}
 
+#[cfg(feature = "std")]
+impl std::error::Error for MachError {
 }
 
-// Struct that wraps a mach error code for placement inside a std::io::Error
-struct KernErrorWrapper(sys::mach_error_t);
+/// Recovers the Mach error behind errors returned by this crate and `mach-port`.
+pub trait MachErrorExt {
+    /// The `MachError` this error was created from, if any.
+    fn mach_error(&self) -> Option<MachError>;
 
-impl fmt::Debug for KernErrorWrapper {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "MachKernError {{ code: {:#x?} }}", self.0)
+    /// The original `mach_error_t`/`kern_return_t`, e.g. to forward the exact code back to a client over IPC.
+    fn mach_code(&self) -> Option<sys::mach_error_t> {
+        self.mach_error().map(|err| err.code())
+    }
+}
+
+#[cfg(feature = "std")]
+impl MachErrorExt for io::Error {
+    #[inline]
+    fn mach_error(&self) -> Option<MachError> {
+        MachError::from_io(self)
     }
 }
 
-impl fmt::Display for KernErrorWrapper {
+impl MachErrorExt for MachError {
+    #[inline]
+    fn mach_error(&self) -> Option<MachError> {
+        Some(*self)
+    }
+}
+
+/// The operation an error occurred in, attached by the `op:` forms of [`mach_call!`] and [`mach_kern_call!`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ErrorContext {
+    /// The name of the failed call, e.g. `mach_msg(send)`.
+    pub operation: &'static str,
+    pub port: Option<RawPort>,
+}
+
+impl ErrorContext {
+    /// Extracts the context attached to an `io::Error`, if any.
+    #[cfg(feature = "std")]
+    pub fn from_io(err: &io::Error) -> Option<ErrorContext> {
+        err.get_ref()
+            .and_then(|inner| inner.downcast_ref::<ContextError>())
+            .map(|err| err.context)
+    }
+}
+
+// A MachError with context, for placement inside a std::io::Error
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct ContextError {
+    error: MachError,
+    context: ErrorContext,
+    label: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ContextError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: string values, copy from IOService.h
-        write!(f, "(code {:#x?})", self.0)
+        match (self.context.port, self.label.as_ref()) {
+            (Some(port), Some(label)) => write!(f, "{} on port {:#x} ({}) failed: {}", self.context.operation, port, label, self.error),
+            (Some(port), None) => write!(f, "{} on port {:#x} failed: {}", self.context.operation, port, self.error),
+            (None, _) => write!(f, "{} failed: {}", self.context.operation, self.error),
+        }
     }
 }
 
-impl std::error::Error for KernErrorWrapper {
+#[cfg(feature = "std")]
+impl std::error::Error for ContextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+extern "C" {
+    // From servers/bootstrap.h, which also handles non-bootstrap codes
+    fn bootstrap_strerror(code: sys::kern_return_t) -> *const c_char;
+}
 
-}
\ No newline at end of file
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_codes() {
+        assert_eq!(MachError::from_mach_error(sys::MACH_SEND_INVALID_DEST as _), MachError::Send(sys::MACH_SEND_INVALID_DEST as _));
+        assert_eq!(MachError::from_mach_error(sys::MACH_RCV_TIMED_OUT as _), MachError::Receive(sys::MACH_RCV_TIMED_OUT as _));
+        assert_eq!(MachError::from_mach_error(sys::KERN_INVALID_NAME as _), MachError::Kern(sys::KERN_INVALID_NAME as _));
+        assert_eq!(MachError::from_mach_error(BOOTSTRAP_UNKNOWN_SERVICE), MachError::Bootstrap(BOOTSTRAP_UNKNOWN_SERVICE));
+        assert_eq!(MachError::from_mach_error(BOOTSTRAP_NO_CHILDREN + 1), MachError::Kern(BOOTSTRAP_NO_CHILDREN + 1));
+    }
+
+    #[test]
+    fn predicates_and_kinds() {
+        let timeout = MachError::from_mach_error(sys::MACH_RCV_TIMED_OUT as _);
+        assert!(timeout.is_timeout() && timeout.is_transient() && !timeout.is_peer_gone());
+        assert_eq!(timeout.kind(), io::ErrorKind::TimedOut);
+        let dead = MachError::from_mach_error(sys::MACH_SEND_INVALID_DEST as _);
+        assert!(dead.is_peer_gone() && !dead.is_transient());
+        let unknown = MachError::from_mach_error(BOOTSTRAP_UNKNOWN_SERVICE);
+        assert_eq!(unknown.kind(), io::ErrorKind::NotFound);
+        assert!(MachError::Bootstrap(BOOTSTRAP_NO_MEMORY).is_transient());
+    }
+
+    #[test]
+    fn recovered_from_io() {
+        let plain: io::Error = MachError::Kern(sys::KERN_INVALID_RIGHT as _).into();
+        assert_eq!(plain.mach_error(), Some(MachError::Kern(sys::KERN_INVALID_RIGHT as _)));
+        assert_eq!(ErrorContext::from_io(&plain), None);
+
+        let with_context = MachError::Send(sys::MACH_SEND_INVALID_DEST as _).with_context("mach_msg(send)", Some(0x90b));
+        assert_eq!(with_context.mach_code(), Some(sys::MACH_SEND_INVALID_DEST as _));
+        assert_eq!(ErrorContext::from_io(&with_context), Some(ErrorContext { operation: "mach_msg(send)", port: Some(0x90b) }));
+        assert!(with_context.to_string().starts_with("mach_msg(send) on port 0x90b failed: "));
+
+        assert_eq!(io::Error::other("not mach").mach_error(), None);
+    }
+
+    unsafe fn out_params(code: sys::kern_return_t, first: &mut u32, second: &mut u32) -> sys::kern_return_t {
+        *first = 1;
+        *second = 2;
+        code
+    }
+
+    #[test]
+    fn call_out_attaches_context() {
+        let values: io::Result<(u32, u32)> = unsafe { mach_call_out!(op: "out_params", port: 0x90b, out_params(0) -> (first, second)) };
+        assert_eq!(values.unwrap(), (1, 2));
+
+        let err = unsafe { mach_call_out!(op: "out_params", port: 0x90b, out_params(sys::KERN_INVALID_NAME as _) -> (first, second)) }.unwrap_err();
+        assert_eq!(ErrorContext::from_io(&err), Some(ErrorContext { operation: "out_params", port: Some(0x90b) }));
+        let err = unsafe { mach_call_out!(op: "out_params", out_params(sys::KERN_INVALID_NAME as _) -> (first, second)) }.unwrap_err();
+        assert_eq!(ErrorContext::from_io(&err), Some(ErrorContext { operation: "out_params", port: None }));
+    }
+}
@@ -0,0 +1,32 @@
+//! Human-readable labels for port names, used in diagnostics.
+//!
+//! Labels are stored in a process-wide table keyed by port name, so they appear in error messages (and in
+//! `mach-port`'s `Debug` output and leak reports) wherever the name is known, e.g. `service:com.example.broker`
+//! instead of `0xa07`. Nothing is sent to the kernel.
+
+use crate::RawPort;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+static LABELS: Mutex<Option<HashMap<RawPort, String>>> = Mutex::new(None);
+
+/// Labels `port`, replacing any previous label.
+pub fn set_label(port: RawPort, label: String) {
+    let mut labels = LABELS.lock().unwrap_or_else(|err| err.into_inner());
+    labels.get_or_insert_with(HashMap::new).insert(port, label);
+}
+
+/// Gets the label of `port`, if any.
+pub fn label(port: RawPort) -> Option<String> {
+    let labels = LABELS.lock().unwrap_or_else(|err| err.into_inner());
+    labels.as_ref().and_then(|labels| labels.get(&port).cloned())
+}
+
+/// Removes the label of `port`, e.g. because the name is being released and may be reused for another port.
+pub fn clear_label(port: RawPort) {
+    let mut labels = LABELS.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(labels) = labels.as_mut() {
+        labels.remove(&port);
+    }
+}
@@ -1,3 +1,18 @@
+//! The return codes, errors and port constants are available without std (disable the default `std` feature). Only
+//! `io::Error` conversions, the error-handling macros, port labels, timing and `thread_self` need it.
+//!
+//! On non-Apple targets (where mach-sys only provides the port and message types), the self-port accessors and timing
+//! are unavailable.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod code;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod label;
+#[cfg(target_vendor = "apple")]
+pub mod self_port;
+#[cfg(all(feature = "std", target_vendor = "apple"))]
+pub mod time;
 
-pub type RawPort = ::std::os::raw::c_uint;
\ No newline at end of file
+pub type RawPort = ::core::ffi::c_uint;
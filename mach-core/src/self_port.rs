@@ -0,0 +1,72 @@
+//! Access to the ports naming the current task, thread and host.
+//!
+//! `mach_thread_self` and `mach_host_self` hand out a new user reference on every call, which must be released with
+//! `mach_port_deallocate`. The accessors here cache those references (per thread and per process respectively) so
+//! callers can treat all three ports as borrowed.
+
+use crate::RawPort;
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use mach_sys as sys;
+
+/// Gets the current task's port.
+///
+/// This is a cached global maintained by libsystem; it must not be deallocated.
+#[inline]
+pub fn task_self() -> RawPort {
+    sys::mach_task_self()
+}
+
+static HOST_SELF: AtomicU32 = AtomicU32::new(sys::MACH_PORT_NULL);
+
+/// Gets the host name port.
+///
+/// The first call obtains a reference that is kept for the lifetime of the process; it must not be deallocated.
+pub fn host_self() -> RawPort {
+    let port = HOST_SELF.load(Ordering::Acquire);
+    if port != sys::MACH_PORT_NULL {
+        return port;
+    }
+    let port = unsafe { sys::mach_host_self() };
+    match HOST_SELF.compare_exchange(sys::MACH_PORT_NULL, port, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => port,
+        Err(existing) => {
+            // Another thread won the race, so drop our extra reference
+            unsafe {
+                let _ = sys::mach_port_deallocate(sys::mach_task_self(), port);
+            }
+            existing
+        },
+    }
+}
+
+#[cfg(feature = "std")]
+struct ThreadSelf(RawPort);
+
+#[cfg(feature = "std")]
+impl Drop for ThreadSelf {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = sys::mach_port_deallocate(sys::mach_task_self(), self.0);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static THREAD_SELF: ThreadSelf = ThreadSelf(unsafe { sys::mach_thread_self() });
+}
+
+/// Gets the calling thread's port.
+///
+/// The reference is cached for the lifetime of the thread and released when it exits; it must not be deallocated, and
+/// must not be used from other threads after the calling thread exits.
+#[cfg(feature = "std")]
+pub fn thread_self() -> RawPort {
+    THREAD_SELF.try_with(|port| port.0).unwrap_or_else(|_| {
+        // Thread-local storage is being torn down, so we can't cache the reference. Leaking a single reference on
+        // thread exit is preferable to handing out a port that may be deallocated under the caller.
+        unsafe { sys::mach_thread_self() }
+    })
+}
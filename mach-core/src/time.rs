@@ -0,0 +1,353 @@
+//! Mach absolute time and conversions between it and standard time units.
+//!
+//! `mach_absolute_time` counts in ticks whose length is given by `mach_timebase_info`. The ratio is 1/1 on Intel
+//! Macs but not on Apple silicon (125/3), so ticks must never be treated as nanoseconds.
+
+use std::{io, mem};
+use std::ops::{Add, Sub};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use mach_sys as sys;
+
+/// The ratio converting Mach absolute time ticks to nanoseconds.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Timebase {
+    pub numer: u32,
+    pub denom: u32,
+}
+
+// numer in the high half, denom in the low half; zero until first queried
+static TIMEBASE: AtomicU64 = AtomicU64::new(0);
+
+/// Gets the current value of the system's monotonic tick counter (which does not advance while asleep).
+#[inline]
+pub fn absolute_time() -> u64 {
+    unsafe { sys::mach_absolute_time() }
+}
+
+/// Gets the timebase, querying the kernel only on first use.
+pub fn timebase() -> Timebase {
+    let packed = TIMEBASE.load(Ordering::Relaxed);
+    if packed != 0 {
+        return Timebase { numer: (packed >> 32) as u32, denom: packed as u32 };
+    }
+    let timebase = unsafe {
+        let mut info: sys::mach_timebase_info_data_t = mem::zeroed();
+        if sys::mach_timebase_info(&mut info) != 0 || info.numer == 0 || info.denom == 0 {
+            // mach_timebase_info cannot fail in practice, but never divide by zero if it does
+            info.numer = 1;
+            info.denom = 1;
+        }
+        Timebase { numer: info.numer, denom: info.denom }
+    };
+    TIMEBASE.store((timebase.numer as u64) << 32 | timebase.denom as u64, Ordering::Relaxed);
+    timebase
+}
+
+impl Timebase {
+    /// Converts a tick count to nanoseconds, saturating on overflow.
+    pub fn abs_to_nanos(self, abs: u64) -> u64 {
+        if self.numer == self.denom {
+            return abs;
+        }
+        let nanos = abs as u128 * self.numer as u128 / self.denom as u128;
+        if nanos > u64::MAX as u128 { u64::MAX } else { nanos as u64 }
+    }
+
+    /// Converts nanoseconds to a tick count, rounding down and saturating on overflow.
+    pub fn nanos_to_abs(self, nanos: u64) -> u64 {
+        if self.numer == self.denom {
+            return nanos;
+        }
+        let abs = nanos as u128 * self.denom as u128 / self.numer as u128;
+        if abs > u64::MAX as u128 { u64::MAX } else { abs as u64 }
+    }
+
+    /// Converts a tick count to a [`Duration`].
+    pub fn abs_to_duration(self, abs: u64) -> Duration {
+        let nanos = self.abs_to_nanos(abs);
+        Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+    }
+
+    /// Converts a [`Duration`] to a tick count, saturating on overflow.
+    pub fn duration_to_abs(self, duration: Duration) -> u64 {
+        let nanos = duration.as_secs() as u128 * 1_000_000_000 + duration.subsec_nanos() as u128;
+        if nanos > u64::MAX as u128 {
+            return u64::MAX;
+        }
+        self.nanos_to_abs(nanos as u64)
+    }
+}
+
+/// Converts a tick count to nanoseconds, saturating on overflow.
+pub fn abs_to_nanos(abs: u64) -> u64 {
+    timebase().abs_to_nanos(abs)
+}
+
+/// Converts nanoseconds to a tick count, rounding down and saturating on overflow.
+pub fn nanos_to_abs(nanos: u64) -> u64 {
+    timebase().nanos_to_abs(nanos)
+}
+
+/// Converts a tick count to a [`Duration`].
+pub fn abs_to_duration(abs: u64) -> Duration {
+    timebase().abs_to_duration(abs)
+}
+
+/// Converts a [`Duration`] to a tick count, saturating on overflow.
+pub fn duration_to_abs(duration: Duration) -> u64 {
+    timebase().duration_to_abs(duration)
+}
+
+/// Converts an [`Instant`] to an absolute tick deadline, relative to the current time on both clocks.
+pub fn instant_to_abs(instant: Instant) -> u64 {
+    let now_abs = absolute_time();
+    let now = Instant::now();
+    if instant >= now {
+        now_abs.saturating_add(duration_to_abs(instant - now))
+    } else {
+        now_abs.saturating_sub(duration_to_abs(now - instant))
+    }
+}
+
+/// Blocks the calling thread until `mach_absolute_time` reaches `deadline`.
+///
+/// Unlike `std::thread::sleep`, the wakeup is not subject to timer coalescing slop beyond what the scheduler imposes,
+/// giving sub-millisecond precision. Returns an error of kind `Interrupted` if the wait is aborted (e.g. by
+/// `thread_abort`) before the deadline.
+pub fn wait_until_abs(deadline: u64) -> io::Result<()> {
+    let code = unsafe { sys::mach_wait_until(deadline) };
+    match code as u32 {
+        sys::KERN_ABORTED => Err(io::Error::new(io::ErrorKind::Interrupted, "mach_wait_until aborted")),
+        _ => crate::mach_call!(op: "mach_wait_until", code),
+    }
+}
+
+/// Blocks the calling thread until `deadline`. See [`wait_until_abs`].
+pub fn wait_until(deadline: Instant) -> io::Result<()> {
+    wait_until_abs(instant_to_abs(deadline))
+}
+
+/// Blocks the calling thread until `deadline`, resuming the wait if it is aborted early.
+pub fn wait_until_uninterrupted(deadline: Instant) -> io::Result<()> {
+    let deadline = instant_to_abs(deadline);
+    loop {
+        match wait_until_abs(deadline) {
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                if absolute_time() >= deadline {
+                    return Ok(());
+                }
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Blocks the calling thread for `duration`. See [`wait_until_abs`].
+pub fn sleep(duration: Duration) -> io::Result<()> {
+    wait_until_abs(absolute_time().saturating_add(duration_to_abs(duration)))
+}
+
+/// A reading of `mach_absolute_time`.
+///
+/// Stores raw ticks and only converts to standard units when a [`Duration`] is requested, which makes taking and
+/// comparing readings cheaper than with [`Instant`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct MachInstant(u64);
+
+impl MachInstant {
+    #[inline]
+    pub fn now() -> MachInstant {
+        MachInstant(absolute_time())
+    }
+
+    #[inline]
+    pub fn from_ticks(ticks: u64) -> MachInstant {
+        MachInstant(ticks)
+    }
+
+    #[inline]
+    pub fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// The ticks elapsed since `earlier`, or zero if `earlier` is later than `self`.
+    #[inline]
+    pub fn ticks_since(self, earlier: MachInstant) -> u64 {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// The time elapsed since `earlier`, or zero if `earlier` is later than `self`.
+    pub fn duration_since(self, earlier: MachInstant) -> Duration {
+        abs_to_duration(self.ticks_since(earlier))
+    }
+
+    pub fn elapsed(self) -> Duration {
+        MachInstant::now().duration_since(self)
+    }
+}
+
+impl Add<Duration> for MachInstant {
+    type Output = MachInstant;
+
+    fn add(self, rhs: Duration) -> MachInstant {
+        MachInstant(self.0.saturating_add(duration_to_abs(rhs)))
+    }
+}
+
+impl Sub<Duration> for MachInstant {
+    type Output = MachInstant;
+
+    fn sub(self, rhs: Duration) -> MachInstant {
+        MachInstant(self.0.saturating_sub(duration_to_abs(rhs)))
+    }
+}
+
+impl Sub for MachInstant {
+    type Output = Duration;
+
+    fn sub(self, rhs: MachInstant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+/// Accumulates elapsed time across start/stop intervals, measured in raw ticks.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Stopwatch {
+    accumulated: u64,
+    started: Option<u64>,
+}
+
+impl Stopwatch {
+    #[inline]
+    pub fn new() -> Stopwatch {
+        Stopwatch::default()
+    }
+
+    /// Creates a stopwatch that is already running.
+    #[inline]
+    pub fn start_new() -> Stopwatch {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.start();
+        stopwatch
+    }
+
+    /// Starts (or resumes) timing. Has no effect if already running.
+    #[inline]
+    pub fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(absolute_time());
+        }
+    }
+
+    /// Stops timing, adding the current interval to the total. Has no effect if not running.
+    #[inline]
+    pub fn stop(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.accumulated += absolute_time().saturating_sub(started);
+        }
+    }
+
+    /// Stops the stopwatch and clears the accumulated time.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.accumulated = 0;
+        self.started = None;
+    }
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// The total ticks accumulated, including the current interval if running.
+    #[inline]
+    pub fn elapsed_ticks(&self) -> u64 {
+        match self.started {
+            Some(started) => self.accumulated + absolute_time().saturating_sub(started),
+            None => self.accumulated,
+        }
+    }
+
+    /// The total time accumulated, including the current interval if running.
+    pub fn elapsed(&self) -> Duration {
+        abs_to_duration(self.elapsed_ticks())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The timebase on Apple silicon
+    const ARM: Timebase = Timebase { numer: 125, denom: 3 };
+    const IDENTITY: Timebase = Timebase { numer: 1, denom: 1 };
+
+    #[test]
+    fn conversions_round_trip() {
+        assert_eq!(ARM.abs_to_nanos(24_000_000), 1_000_000_000);
+        assert_eq!(ARM.nanos_to_abs(1_000_000_000), 24_000_000);
+        assert_eq!(ARM.abs_to_duration(24), Duration::from_micros(1));
+        assert_eq!(ARM.duration_to_abs(Duration::from_secs(1)), 24_000_000);
+        for &ticks in &[0, 3, 999_999, 123_456_789_012] {
+            // Ticks that are a whole number of nanoseconds survive the round trip exactly
+            assert_eq!(ARM.nanos_to_abs(ARM.abs_to_nanos(ticks)), ticks);
+        }
+        for &nanos in &[0, 1, 41, 42, 999_999_999, 123_456_789_012] {
+            // Rounding down loses less than one tick
+            let back = ARM.abs_to_nanos(ARM.nanos_to_abs(nanos));
+            assert!(back <= nanos && nanos - back < 42, "{} -> {}", nanos, back);
+        }
+        assert_eq!(IDENTITY.abs_to_nanos(12_345), 12_345);
+        assert_eq!(IDENTITY.duration_to_abs(Duration::new(1, 5)), 1_000_000_005);
+    }
+
+    #[test]
+    fn conversions_saturate() {
+        assert_eq!(ARM.abs_to_nanos(u64::MAX), u64::MAX);
+        assert_eq!(ARM.nanos_to_abs(u64::MAX), (u64::MAX as u128 * 3 / 125) as u64);
+        assert_eq!(ARM.duration_to_abs(Duration::new(u64::MAX, 0)), u64::MAX);
+        assert_eq!(IDENTITY.duration_to_abs(Duration::new(u64::MAX, 0)), u64::MAX);
+        assert_eq!(ARM.abs_to_duration(u64::MAX), Duration::from_nanos(u64::MAX));
+    }
+
+    #[test]
+    fn instant_arithmetic() {
+        let start = MachInstant::from_ticks(1_000_000);
+        let later = start + Duration::from_millis(5);
+        let elapsed = later - start;
+        // Converting to ticks rounds down by less than one tick
+        assert!(elapsed <= Duration::from_millis(5) && Duration::from_millis(5) - elapsed < Duration::from_micros(1));
+        assert_eq!(later - Duration::from_millis(5), start);
+        assert_eq!(start - later, Duration::from_secs(0));
+        assert_eq!(start.ticks_since(later), 0);
+        assert_eq!(MachInstant::from_ticks(u64::MAX) + Duration::from_secs(1), MachInstant::from_ticks(u64::MAX));
+        assert_eq!(MachInstant::from_ticks(0) - Duration::from_secs(1), MachInstant::from_ticks(0));
+    }
+
+    #[test]
+    fn stopwatch_accumulates_intervals() {
+        let mut stopwatch = Stopwatch::new();
+        assert!(!stopwatch.is_running());
+        assert_eq!(stopwatch.elapsed_ticks(), 0);
+
+        stopwatch.start();
+        sleep(Duration::from_millis(5)).unwrap();
+        stopwatch.stop();
+        let first = stopwatch.elapsed();
+        assert!(first >= Duration::from_millis(5));
+        // Stopped stopwatches don't advance
+        sleep(Duration::from_millis(5)).unwrap();
+        assert_eq!(stopwatch.elapsed(), first);
+
+        stopwatch.start();
+        sleep(Duration::from_millis(5)).unwrap();
+        assert!(stopwatch.is_running());
+        assert!(stopwatch.elapsed() >= first + Duration::from_millis(5));
+
+        stopwatch.reset();
+        assert!(!stopwatch.is_running());
+        assert_eq!(stopwatch.elapsed_ticks(), 0);
+    }
+}
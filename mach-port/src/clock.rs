@@ -0,0 +1,206 @@
+//! Mach clock services.
+//!
+//! Clocks are obtained from the host with [`Host::clock`] (or [`Clock::get`]), and are mostly of interest for the
+//! legacy interfaces that take a clock port. New code should generally prefer `mach_absolute_time` based timing.
+
+use crate::{RawPort, Port, Msg};
+use crate::host::Host;
+
+use std::{io, mem, ptr, fmt};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Identifies one of the host's clocks.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ClockId {
+    /// A monotonic clock counting from boot (`SYSTEM_CLOCK`).
+    System,
+    /// Wall clock time since the UNIX epoch (`CALENDAR_CLOCK`).
+    Calendar,
+}
+
+impl ClockId {
+    fn as_raw(self) -> sys::clock_id_t {
+        (match self {
+            ClockId::System => sys::SYSTEM_CLOCK,
+            ClockId::Calendar => sys::CALENDAR_CLOCK,
+        }) as sys::clock_id_t
+    }
+}
+
+/// An owned send right to a clock service port.
+pub struct Clock {
+    port: sys::clock_serv_t,
+}
+
+impl Drop for Clock {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_port_deallocate(clock port)", port: self.port, sys::mach_port_deallocate(mach_core::self_port::task_self(), self.port));
+        }
+    }
+}
+
+impl Host {
+    /// Obtains a send right to one of the host's clocks.
+    pub fn clock(&self, id: ClockId) -> io::Result<Clock> {
+        unsafe {
+            let mut port: sys::clock_serv_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "host_get_clock_service", port: self.as_raw_port(), sys::host_get_clock_service(self.as_raw_port(), id.as_raw(), &mut port))?;
+            Ok(Clock::from_raw_port(port))
+        }
+    }
+}
+
+impl Clock {
+    /// Obtains one of the current host's clocks.
+    pub fn get(id: ClockId) -> io::Result<Clock> {
+        Host::current().clock(id)
+    }
+
+    /// Takes ownership of a send right to a clock port.
+    pub unsafe fn from_raw_port(port: RawPort) -> Clock {
+        Clock {
+            port,
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        let port = self.port;
+        mem::forget(self);
+        port
+    }
+
+    /// Reads the clock, returning the time elapsed since its epoch (boot for the system clock, the UNIX epoch for
+    /// the calendar clock).
+    pub fn get_time(&self) -> io::Result<Duration> {
+        unsafe {
+            let mut time: sys::mach_timespec_t = mem::zeroed();
+            mach_call!(log op: "clock_get_time", port: self.port, sys::clock_get_time(self.port, &mut time))?;
+            Ok(timespec_to_duration(time))
+        }
+    }
+
+    /// Reads the clock as wall clock time. Only meaningful for the calendar clock.
+    pub fn get_system_time(&self) -> io::Result<SystemTime> {
+        Ok(UNIX_EPOCH + self.get_time()?)
+    }
+}
+
+/// When a clock alarm should fire, or a clock sleep should end.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AlarmTime {
+    /// At the given clock time (relative to the clock's epoch).
+    Absolute(Duration),
+    /// After the given delay from now.
+    Relative(Duration),
+}
+
+impl Clock {
+    /// Requests that an alarm message be sent to `reply_port` when the clock reaches the given time.
+    ///
+    /// `reply_port` must hold a receive right; the kernel is given a send-once right to it. Decode the message with
+    /// [`ClockAlarm::decode`] when it arrives.
+    pub fn alarm(&self, time: AlarmTime, reply_port: &Port) -> io::Result<()> {
+        let (alarm_type, duration) = match time {
+            AlarmTime::Absolute(duration) => (sys::TIME_ABSOLUTE, duration),
+            AlarmTime::Relative(duration) => (sys::TIME_RELATIVE, duration),
+        };
+        unsafe {
+            mach_call!(log op: "clock_alarm", port: self.port, sys::clock_alarm(self.port, alarm_type as _, duration_to_timespec(duration), reply_port.as_raw_port()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Clock {
+    /// Blocks the calling thread until the clock reaches the given time, returning the clock time at wakeup.
+    ///
+    /// The sleep can be ended early by calling [`Thread::abort`](crate::Thread::abort) on the sleeping thread from
+    /// another thread, in which case this fails with `Interrupted`. Only the system clock supports sleeping.
+    pub fn sleep(&self, time: AlarmTime) -> io::Result<Duration> {
+        let (sleep_type, duration) = match time {
+            AlarmTime::Absolute(duration) => (sys::TIME_ABSOLUTE, duration),
+            AlarmTime::Relative(duration) => (sys::TIME_RELATIVE, duration),
+        };
+        let time = duration_to_timespec(duration);
+        unsafe {
+            let mut wakeup: sys::mach_timespec_t = mem::zeroed();
+            let code = sys::clock_sleep_trap(self.port, sleep_type as _, time.tv_sec as _, time.tv_nsec as _, &mut wakeup);
+            match code as u32 {
+                sys::KERN_ABORTED => return Err(io::Error::new(io::ErrorKind::Interrupted, "clock sleep aborted")),
+                _ => mach_call!(log op: "clock_sleep_trap", port: self.port, code)?,
+            }
+            Ok(timespec_to_duration(wakeup))
+        }
+    }
+}
+
+// Message ID of clock_alarm_reply from clock_reply.defs
+const CLOCK_ALARM_REPLY_ID: sys::mach_msg_id_t = 3125107;
+
+#[repr(C)]
+struct ClockAlarmReply {
+    header: sys::mach_msg_header_t,
+    ndr: sys::NDR_record_t,
+    alarm_code: sys::kern_return_t,
+    alarm_type: sys::alarm_type_t,
+    alarm_time: sys::mach_timespec_t,
+}
+
+/// A decoded alarm message requested with [`Clock::alarm`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ClockAlarm {
+    /// The clock time at which the alarm fired.
+    pub time: Duration,
+}
+
+impl ClockAlarm {
+    /// Whether a received message is a clock alarm.
+    pub fn is_alarm_message(msg: &Msg) -> bool {
+        msg.header().msgh_id == CLOCK_ALARM_REPLY_ID
+    }
+
+    /// Decodes a clock alarm message, or returns `None` if `msg` is not one.
+    ///
+    /// If the kernel could not schedule the alarm (e.g. because the requested time was invalid), the error it
+    /// reported is returned.
+    pub fn decode(msg: &Msg) -> Option<io::Result<ClockAlarm>> {
+        if !ClockAlarm::is_alarm_message(msg) {
+            return None;
+        }
+        let bytes = msg.as_bytes();
+        if bytes.len() < mem::size_of::<ClockAlarmReply>() {
+            return Some(Err(io::Error::new(io::ErrorKind::InvalidData, "clock alarm message truncated")));
+        }
+        let reply = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const ClockAlarmReply) };
+        Some(mach_call!(reply.alarm_code).map(|()| ClockAlarm {
+            time: timespec_to_duration(reply.alarm_time),
+        }))
+    }
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Clock")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
+
+pub(crate) fn timespec_to_duration(time: sys::mach_timespec_t) -> Duration {
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+pub(crate) fn duration_to_timespec(duration: Duration) -> sys::mach_timespec_t {
+    sys::mach_timespec_t {
+        tv_sec: duration.as_secs() as _,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
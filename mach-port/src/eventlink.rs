@@ -0,0 +1,153 @@
+//! Bindings for Mach eventlinks (macOS 12 and later).
+//!
+//! An eventlink is a pair of connected ports, each with a monotonically increasing signal count. Signaling one end
+//! wakes the thread waiting on the other end; when both ends are associated with threads, the kernel can hand off the
+//! processor directly, giving the lowest wake latency available for ping-pong style communication.
+
+use crate::{RawPort, Thread};
+
+use std::{io, mem, fmt};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+use mach_core::time::MachInstant;
+
+// Declared in mach/mach_eventlink.h, which is missing from older SDKs
+extern "C" {
+    fn mach_eventlink_create(task: sys::task_t, option: u32, pair: *mut sys::mach_port_t) -> sys::kern_return_t;
+    fn mach_eventlink_destroy(eventlink: sys::mach_port_t) -> sys::kern_return_t;
+    fn mach_eventlink_associate(eventlink: sys::mach_port_t, thread: sys::thread_t, copyin_addr_wait: sys::mach_vm_address_t, copyin_mask_wait: u64, copyin_mask_signal: u64, option: u32) -> sys::kern_return_t;
+    fn mach_eventlink_disassociate(eventlink: sys::mach_port_t, option: u32) -> sys::kern_return_t;
+    fn mach_eventlink_signal(eventlink: sys::mach_port_t, signal_count: u64) -> sys::kern_return_t;
+    fn mach_eventlink_wait_until(eventlink: sys::mach_port_t, count: *mut u64, option: u32, clock_id: u32, deadline: u64) -> sys::kern_return_t;
+    fn mach_eventlink_signal_wait_until(eventlink: sys::mach_port_t, count: *mut u64, signal_count: u64, option: u32, clock_id: u32, deadline: u64) -> sys::kern_return_t;
+}
+
+// From mach/mach_eventlink_types.h
+const MELC_OPTION_NO_COPYIN: u32 = 0x1;
+const MELA_OPTION_NONE: u32 = 0x0;
+const MELA_OPTION_ASSOCIATE_ON_WAIT: u32 = 0x1;
+const MELD_OPTION_NONE: u32 = 0x0;
+const MELSW_OPTION_NONE: u32 = 0x0;
+const MELSW_OPTION_NO_WAIT: u32 = 0x1;
+const KERN_CLOCK_MACH_ABSOLUTE_TIME: u32 = 1;
+
+/// One end of an eventlink.
+pub struct Eventlink {
+    port: sys::mach_port_t,
+}
+
+impl Drop for Eventlink {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_eventlink_destroy", port: self.port, mach_eventlink_destroy(self.port));
+        }
+    }
+}
+
+impl Eventlink {
+    /// Creates a connected pair of eventlink ends.
+    pub fn pair() -> io::Result<(Eventlink, Eventlink)> {
+        unsafe {
+            let mut ports: [sys::mach_port_t; 2] = [sys::MACH_PORT_NULL; 2];
+            mach_call!(log op: "mach_eventlink_create", mach_eventlink_create(mach_core::self_port::task_self(), MELC_OPTION_NO_COPYIN, ports.as_mut_ptr()))?;
+            Ok((Eventlink::from_raw_port(ports[0]), Eventlink::from_raw_port(ports[1])))
+        }
+    }
+
+    /// Takes ownership of an eventlink port.
+    pub unsafe fn from_raw_port(port: RawPort) -> Eventlink {
+        Eventlink {
+            port,
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        let port = self.port;
+        mem::forget(self);
+        port
+    }
+
+    /// Associates this end with `thread`, which is then the only thread that may wait on it.
+    pub fn associate(&self, thread: &Thread) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "mach_eventlink_associate", port: self.port, mach_eventlink_associate(self.port, thread.as_raw_port(), 0, 0, 0, MELA_OPTION_NONE))?;
+        }
+        Ok(())
+    }
+
+    /// Associates this end with whichever thread waits on it next.
+    pub fn associate_on_wait(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "mach_eventlink_associate", port: self.port, mach_eventlink_associate(self.port, sys::MACH_PORT_NULL, 0, 0, 0, MELA_OPTION_ASSOCIATE_ON_WAIT))?;
+        }
+        Ok(())
+    }
+
+    pub fn disassociate(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "mach_eventlink_disassociate", port: self.port, mach_eventlink_disassociate(self.port, MELD_OPTION_NONE))?;
+        }
+        Ok(())
+    }
+
+    /// Signals the other end, waking its waiter.
+    pub fn signal(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "mach_eventlink_signal", port: self.port, mach_eventlink_signal(self.port, 0))?;
+        }
+        Ok(())
+    }
+
+    /// Waits until this end's signal count exceeds `count` (the count last returned), or `deadline` passes.
+    ///
+    /// Returns the new signal count, or fails with `TimedOut`.
+    pub fn wait(&self, count: u64, deadline: Option<MachInstant>) -> io::Result<u64> {
+        let mut count = count;
+        wait_result(unsafe { mach_eventlink_wait_until(self.port, &mut count, MELSW_OPTION_NONE, KERN_CLOCK_MACH_ABSOLUTE_TIME, deadline_ticks(deadline)) })?;
+        Ok(count)
+    }
+
+    /// Returns the new signal count if it exceeds `count`, without blocking.
+    pub fn try_wait(&self, count: u64) -> io::Result<Option<u64>> {
+        let mut count = count;
+        match wait_result(unsafe { mach_eventlink_wait_until(self.port, &mut count, MELSW_OPTION_NO_WAIT, KERN_CLOCK_MACH_ABSOLUTE_TIME, 0) }) {
+            Ok(()) => Ok(Some(count)),
+            Err(ref err) if err.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Atomically signals the other end and waits on this one. See [`wait`](Eventlink::wait).
+    pub fn signal_wait(&self, count: u64, deadline: Option<MachInstant>) -> io::Result<u64> {
+        let mut count = count;
+        wait_result(unsafe { mach_eventlink_signal_wait_until(self.port, &mut count, 0, MELSW_OPTION_NONE, KERN_CLOCK_MACH_ABSOLUTE_TIME, deadline_ticks(deadline)) })?;
+        Ok(count)
+    }
+}
+
+// A deadline of zero waits forever
+fn deadline_ticks(deadline: Option<MachInstant>) -> u64 {
+    deadline.map(|deadline| deadline.ticks().max(1)).unwrap_or(0)
+}
+
+fn wait_result(code: sys::kern_return_t) -> io::Result<()> {
+    match code as u32 {
+        sys::KERN_SUCCESS => Ok(()),
+        sys::KERN_OPERATION_TIMED_OUT => Err(io::Error::new(io::ErrorKind::TimedOut, "eventlink wait timed out")),
+        sys::KERN_ABORTED => Err(io::Error::new(io::ErrorKind::Interrupted, "eventlink wait aborted")),
+        _ => mach_call!(log op: "mach_eventlink_wait_until", code),
+    }
+}
+
+impl fmt::Debug for Eventlink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Eventlink")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{Port, Task, Thread};
+
+use std::{io, fmt};
+use std::ops::{BitOr, BitOrAssign};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// A set of exception types, as accepted by the `*_exception_ports` family of calls.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExceptionMask(pub sys::exception_mask_t);
+
+impl ExceptionMask {
+    pub const BAD_ACCESS: ExceptionMask = ExceptionMask(sys::EXC_MASK_BAD_ACCESS as _);
+    pub const BAD_INSTRUCTION: ExceptionMask = ExceptionMask(sys::EXC_MASK_BAD_INSTRUCTION as _);
+    pub const ARITHMETIC: ExceptionMask = ExceptionMask(sys::EXC_MASK_ARITHMETIC as _);
+    pub const EMULATION: ExceptionMask = ExceptionMask(sys::EXC_MASK_EMULATION as _);
+    pub const SOFTWARE: ExceptionMask = ExceptionMask(sys::EXC_MASK_SOFTWARE as _);
+    pub const BREAKPOINT: ExceptionMask = ExceptionMask(sys::EXC_MASK_BREAKPOINT as _);
+    pub const SYSCALL: ExceptionMask = ExceptionMask(sys::EXC_MASK_SYSCALL as _);
+    pub const MACH_SYSCALL: ExceptionMask = ExceptionMask(sys::EXC_MASK_MACH_SYSCALL as _);
+    pub const RPC_ALERT: ExceptionMask = ExceptionMask(sys::EXC_MASK_RPC_ALERT as _);
+    pub const CRASH: ExceptionMask = ExceptionMask(sys::EXC_MASK_CRASH as _);
+    pub const RESOURCE: ExceptionMask = ExceptionMask(sys::EXC_MASK_RESOURCE as _);
+    pub const GUARD: ExceptionMask = ExceptionMask(sys::EXC_MASK_GUARD as _);
+    pub const CORPSE_NOTIFY: ExceptionMask = ExceptionMask(sys::EXC_MASK_CORPSE_NOTIFY as _);
+    /// All exception types that can be handled by a user-space handler (`EXC_MASK_ALL`).
+    pub const ALL: ExceptionMask = ExceptionMask(sys::EXC_MASK_ALL as _);
+
+    #[inline]
+    pub fn empty() -> ExceptionMask {
+        ExceptionMask(0)
+    }
+
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub fn contains(self, other: ExceptionMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for ExceptionMask {
+    type Output = ExceptionMask;
+
+    #[inline]
+    fn bitor(self, rhs: ExceptionMask) -> ExceptionMask {
+        ExceptionMask(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ExceptionMask {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: ExceptionMask) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for ExceptionMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExceptionMask({:#x?})", self.0)
+    }
+}
+
+/// Determines the format of the messages an exception handler receives.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExceptionBehavior(pub sys::exception_behavior_t);
+
+impl ExceptionBehavior {
+    /// Sends the faulting thread and task along with the exception codes.
+    pub const DEFAULT: ExceptionBehavior = ExceptionBehavior(sys::EXCEPTION_DEFAULT as _);
+    /// Sends the thread state of the configured flavor along with the exception codes.
+    pub const STATE: ExceptionBehavior = ExceptionBehavior(sys::EXCEPTION_STATE as _);
+    /// Sends both the faulting thread and task and the thread state.
+    pub const STATE_IDENTITY: ExceptionBehavior = ExceptionBehavior(sys::EXCEPTION_STATE_IDENTITY as _);
+
+    /// Requests 64-bit exception codes (`MACH_EXCEPTION_CODES`), which should be used by all new handlers.
+    #[inline]
+    pub fn with_mach_exception_codes(self) -> ExceptionBehavior {
+        ExceptionBehavior(self.0 | sys::MACH_EXCEPTION_CODES as sys::exception_behavior_t)
+    }
+
+    #[inline]
+    pub fn mach_exception_codes(self) -> bool {
+        self.0 & sys::MACH_EXCEPTION_CODES as sys::exception_behavior_t != 0
+    }
+
+    /// Gets the behavior with any modifier flags stripped.
+    #[inline]
+    pub fn base(self) -> ExceptionBehavior {
+        ExceptionBehavior(self.0 & !(sys::MACH_EXCEPTION_CODES as sys::exception_behavior_t))
+    }
+}
+
+impl fmt::Debug for ExceptionBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let base = match self.base() {
+            ExceptionBehavior::DEFAULT => "DEFAULT",
+            ExceptionBehavior::STATE => "STATE",
+            ExceptionBehavior::STATE_IDENTITY => "STATE_IDENTITY",
+            _ => return write!(f, "ExceptionBehavior({:#x?})", self.0),
+        };
+        if self.mach_exception_codes() {
+            write!(f, "ExceptionBehavior::{} | MACH_EXCEPTION_CODES", base)
+        } else {
+            write!(f, "ExceptionBehavior::{}", base)
+        }
+    }
+}
+
+/// An exception handler registration retrieved from a task or thread.
+#[derive(Debug)]
+pub struct ExceptionHandler {
+    pub mask: ExceptionMask,
+    /// A send right to the handler's port, or `None` if no handler is registered for these exception types.
+    pub port: Option<Port>,
+    pub behavior: ExceptionBehavior,
+    pub flavor: sys::thread_state_flavor_t,
+}
+
+/// Restores previously registered exception handlers when dropped.
+///
+/// Returned by [`Task::swap_exception_ports`] and [`Thread::swap_exception_ports`].
+#[must_use]
+pub struct ExceptionPortsGuard<'a> {
+    target: ExceptionPortTarget<'a>,
+    previous: Vec<ExceptionHandler>,
+}
+
+impl<'a> ExceptionPortsGuard<'a> {
+    /// The handlers that were registered before the swap, and which will be restored when the guard is dropped.
+    ///
+    /// Handlers that wish to chain to their predecessor should forward exceptions to these ports.
+    #[inline]
+    pub fn previous(&self) -> &[ExceptionHandler] {
+        &self.previous
+    }
+}
+
+impl<'a> Drop for ExceptionPortsGuard<'a> {
+    fn drop(&mut self) {
+        for handler in self.previous.iter() {
+            // Failures are logged by set
+            let _ = self.target.set(handler.mask, handler.port.as_ref(), handler.behavior, handler.flavor);
+        }
+    }
+}
+
+impl<'a> fmt::Debug for ExceptionPortsGuard<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExceptionPortsGuard")
+            .field("previous", &self.previous)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum ExceptionPortTarget<'a> {
+    Task(&'a Task),
+    Thread(&'a Thread),
+}
+
+impl<'a> ExceptionPortTarget<'a> {
+    pub(crate) fn set(self, mask: ExceptionMask, port: Option<&Port>, behavior: ExceptionBehavior, flavor: sys::thread_state_flavor_t) -> io::Result<()> {
+        let port = port.map(|port| port.as_raw_port()).unwrap_or(sys::MACH_PORT_NULL);
+        unsafe {
+            match self {
+                ExceptionPortTarget::Task(task) => {
+                    mach_call!(log op: "task_set_exception_ports", port: task.as_raw_port(), sys::task_set_exception_ports(task.as_raw_port(), mask.0, port, behavior.0, flavor))?;
+                },
+                ExceptionPortTarget::Thread(thread) => {
+                    mach_call!(log op: "thread_set_exception_ports", port: thread.as_raw_port(), sys::thread_set_exception_ports(thread.as_raw_port(), mask.0, port, behavior.0, flavor))?;
+                },
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(self, mask: ExceptionMask) -> io::Result<Vec<ExceptionHandler>> {
+        unsafe {
+            let mut table = HandlerTable::new();
+            match self {
+                ExceptionPortTarget::Task(task) => {
+                    mach_call!(log op: "task_get_exception_ports", port: task.as_raw_port(), sys::task_get_exception_ports(
+                        task.as_raw_port(), mask.0,
+                        table.masks.as_mut_ptr(), &mut table.count, table.ports.as_mut_ptr(), table.behaviors.as_mut_ptr(), table.flavors.as_mut_ptr(),
+                    ))?;
+                },
+                ExceptionPortTarget::Thread(thread) => {
+                    mach_call!(log op: "thread_get_exception_ports", port: thread.as_raw_port(), sys::thread_get_exception_ports(
+                        thread.as_raw_port(), mask.0,
+                        table.masks.as_mut_ptr(), &mut table.count, table.ports.as_mut_ptr(), table.behaviors.as_mut_ptr(), table.flavors.as_mut_ptr(),
+                    ))?;
+                },
+            }
+            Ok(table.into_handlers())
+        }
+    }
+
+    pub(crate) fn swap(self, mask: ExceptionMask, port: Option<&Port>, behavior: ExceptionBehavior, flavor: sys::thread_state_flavor_t) -> io::Result<ExceptionPortsGuard<'a>> {
+        let port = port.map(|port| port.as_raw_port()).unwrap_or(sys::MACH_PORT_NULL);
+        unsafe {
+            let mut table = HandlerTable::new();
+            match self {
+                ExceptionPortTarget::Task(task) => {
+                    mach_call!(log op: "task_swap_exception_ports", port: task.as_raw_port(), sys::task_swap_exception_ports(
+                        task.as_raw_port(), mask.0, port, behavior.0, flavor,
+                        table.masks.as_mut_ptr(), &mut table.count, table.ports.as_mut_ptr(), table.behaviors.as_mut_ptr(), table.flavors.as_mut_ptr(),
+                    ))?;
+                },
+                ExceptionPortTarget::Thread(thread) => {
+                    mach_call!(log op: "thread_swap_exception_ports", port: thread.as_raw_port(), sys::thread_swap_exception_ports(
+                        thread.as_raw_port(), mask.0, port, behavior.0, flavor,
+                        table.masks.as_mut_ptr(), &mut table.count, table.ports.as_mut_ptr(), table.behaviors.as_mut_ptr(), table.flavors.as_mut_ptr(),
+                    ))?;
+                },
+            }
+            // Converting the table can't fail, so the previous handlers always end up in the guard to be restored
+            Ok(ExceptionPortsGuard {
+                target: self,
+                previous: table.into_handlers(),
+            })
+        }
+    }
+}
+
+const EXC_TYPES_COUNT: usize = sys::EXC_TYPES_COUNT as usize;
+
+// Output arrays of the *_get_exception_ports and *_swap_exception_ports calls
+struct HandlerTable {
+    count: sys::mach_msg_type_number_t,
+    masks: [sys::exception_mask_t; EXC_TYPES_COUNT],
+    ports: [sys::mach_port_t; EXC_TYPES_COUNT],
+    behaviors: [sys::exception_behavior_t; EXC_TYPES_COUNT],
+    flavors: [sys::thread_state_flavor_t; EXC_TYPES_COUNT],
+}
+
+impl HandlerTable {
+    fn new() -> HandlerTable {
+        HandlerTable {
+            count: EXC_TYPES_COUNT as _,
+            masks: [0; EXC_TYPES_COUNT],
+            ports: [sys::MACH_PORT_NULL; EXC_TYPES_COUNT],
+            behaviors: [0; EXC_TYPES_COUNT],
+            flavors: [0; EXC_TYPES_COUNT],
+        }
+    }
+
+    // Takes ownership of the returned send rights
+    unsafe fn into_handlers(self) -> Vec<ExceptionHandler> {
+        let mut handlers = Vec::with_capacity(self.count as usize);
+        for i in 0..(self.count as usize) {
+            let port = if self.ports[i] == sys::MACH_PORT_NULL || self.ports[i] == sys::MACH_PORT_DEAD {
+                None
+            } else {
+                Some(Port::from_raw_send_right(self.ports[i]))
+            };
+            handlers.push(ExceptionHandler {
+                mask: ExceptionMask(self.masks[i]),
+                port,
+                behavior: ExceptionBehavior(self.behaviors[i]),
+                flavor: self.flavors[i],
+            });
+        }
+        handlers
+    }
+}
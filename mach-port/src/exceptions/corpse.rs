@@ -0,0 +1,265 @@
+use crate::{Task, NativeThreadState};
+use crate::exceptions::{Exception, ExceptionType, KcdataIter};
+use crate::exceptions::kcdata::KCDATA_BUFFER_BEGIN_CRASHINFO;
+use crate::exceptions::kcdata::read_at;
+
+use std::{io, slice};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+// Crash info item types from kern/kcdata.h
+const TASK_CRASHINFO_TASKDYLD_INFO: u32 = 0x803;
+const TASK_CRASHINFO_UUID: u32 = 0x804;
+const TASK_CRASHINFO_PID: u32 = 0x805;
+const TASK_CRASHINFO_PPID: u32 = 0x806;
+const TASK_CRASHINFO_PROC_NAME: u32 = 0x809;
+const TASK_CRASHINFO_EXCEPTION_CODES: u32 = 0x80e;
+const TASK_CRASHINFO_PROC_PATH: u32 = 0x80f;
+const TASK_CRASHINFO_CRASHED_THREADID: u32 = 0x81a;
+const EXIT_REASON_SNAPSHOT: u32 = 0x1001;
+const EXIT_REASON_USER_DESC: u32 = 0x1002;
+const KCDATA_TYPE_LIBRARY_LOADINFO64: u32 = 0x31;
+
+/// Why a crashed process exited, as recorded by the kernel (`os_reason`).
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ExitReason {
+    pub namespace: u32,
+    pub code: u64,
+    pub flags: u64,
+    pub description: Option<String>,
+}
+
+/// A binary image loaded in the crashed process.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BinaryImage {
+    pub load_address: u64,
+    pub uuid: [u8; 16],
+}
+
+/// The crash information the kernel attaches to a corpse.
+///
+/// Fields are `None` or empty if the kernel did not record them.
+#[derive(Clone, Default, Debug)]
+pub struct CrashInfo {
+    pub pid: Option<i32>,
+    pub ppid: Option<i32>,
+    pub process_name: Option<String>,
+    pub process_path: Option<String>,
+    pub uuid: Option<[u8; 16]>,
+    pub crashed_thread_id: Option<u64>,
+    pub exception_codes: Vec<i64>,
+    pub exit_reason: Option<ExitReason>,
+    /// The address and size of `dyld_all_image_infos` in the corpse, for walking the full image list.
+    pub dyld_info: Option<(u64, u64)>,
+    pub images: Vec<BinaryImage>,
+}
+
+impl CrashInfo {
+    /// Parses a crash info `kcdata` buffer.
+    pub fn parse(buffer: &[u8]) -> io::Result<CrashInfo> {
+        let mut items = KcdataIter::new(buffer);
+        match items.next() {
+            Some(ref item) if item.raw_type() == KCDATA_BUFFER_BEGIN_CRASHINFO => (),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "not a crash info kcdata buffer")),
+        }
+
+        let mut info = CrashInfo::default();
+        for item in items {
+            match item.element_type() {
+                TASK_CRASHINFO_PID => info.pid = item.read(),
+                TASK_CRASHINFO_PPID => info.ppid = item.read(),
+                TASK_CRASHINFO_PROC_NAME => info.process_name = Some(item.string()),
+                TASK_CRASHINFO_PROC_PATH => info.process_path = Some(item.string()),
+                TASK_CRASHINFO_UUID => info.uuid = item.read(),
+                TASK_CRASHINFO_CRASHED_THREADID => info.crashed_thread_id = item.read(),
+                TASK_CRASHINFO_EXCEPTION_CODES => {
+                    let data = item.data();
+                    info.exception_codes = (0..(data.len() / 8)).filter_map(|i| read_at::<i64>(data, i * 8)).collect();
+                },
+                TASK_CRASHINFO_TASKDYLD_INFO => {
+                    if let (Some(address), Some(size)) = (read_at::<u64>(item.data(), 0), read_at::<u64>(item.data(), 8)) {
+                        info.dyld_info = Some((address, size));
+                    }
+                },
+                EXIT_REASON_SNAPSHOT => {
+                    // struct exit_reason_snapshot is packed
+                    let data = item.data();
+                    if let (Some(namespace), Some(code), Some(flags)) = (read_at::<u32>(data, 0), read_at::<u64>(data, 4), read_at::<u64>(data, 12)) {
+                        let description = info.exit_reason.take().and_then(|reason| reason.description);
+                        info.exit_reason = Some(ExitReason { namespace, code, flags, description });
+                    }
+                },
+                EXIT_REASON_USER_DESC => {
+                    let description = Some(item.string());
+                    match info.exit_reason {
+                        Some(ref mut reason) => reason.description = description,
+                        None => info.exit_reason = Some(ExitReason { namespace: 0, code: 0, flags: 0, description }),
+                    }
+                },
+                KCDATA_TYPE_LIBRARY_LOADINFO64 => {
+                    info.images.extend(item.elements().filter_map(parse_image));
+                },
+                _ => (),
+            }
+        }
+        Ok(info)
+    }
+}
+
+// struct dyld_uuid_info_64
+fn parse_image(data: &[u8]) -> Option<BinaryImage> {
+    Some(BinaryImage {
+        load_address: read_at(data, 0)?,
+        uuid: read_at(data, 8)?,
+    })
+}
+
+/// A corpse: the suspended remains of a crashed task, delivered via `EXC_CORPSE_NOTIFY`.
+///
+/// The corpse's threads and memory can be inspected like those of a live task, which lets a crash reporter gather a
+/// full report without the crashed process having to stay alive.
+#[derive(Debug)]
+pub struct Corpse {
+    task: Task,
+    info: CrashInfo,
+}
+
+impl Corpse {
+    /// Adopts a corpse task port and maps its crash info.
+    pub fn from_task(task: Task) -> io::Result<Corpse> {
+        unsafe {
+            let mut address: sys::mach_vm_address_t = 0;
+            let mut size: sys::mach_vm_size_t = 0;
+            mach_call!(log op: "task_map_corpse_info_64", port: task.as_raw_port(), sys::task_map_corpse_info_64(mach_core::self_port::task_self(), task.as_raw_port(), &mut address, &mut size))?;
+            let info = CrashInfo::parse(slice::from_raw_parts(address as *const u8, size as usize));
+            let _ = mach_call!(log op: "mach_vm_deallocate(corpse info)", sys::mach_vm_deallocate(mach_core::self_port::task_self(), address, size));
+            Ok(Corpse {
+                task,
+                info: info?,
+            })
+        }
+    }
+
+    /// The corpse task, whose threads and memory remain readable.
+    #[inline]
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+
+    #[inline]
+    pub fn info(&self) -> &CrashInfo {
+        &self.info
+    }
+
+    /// Captures the general purpose registers of every thread in the corpse, keyed by thread ID.
+    pub fn thread_states(&self) -> io::Result<Vec<(u64, NativeThreadState)>> {
+        self.task.threads()?.iter()
+            .map(|thread| -> io::Result<(u64, NativeThreadState)> { Ok((thread.id()?, thread.get_state()?)) })
+            .collect()
+    }
+
+    #[inline]
+    pub fn into_task(self) -> Task {
+        self.task
+    }
+}
+
+impl Exception {
+    /// Adopts the corpse carried by an `EXC_CORPSE_NOTIFY` exception.
+    ///
+    /// Returns `None` for other exception types, or if the task port has already been taken. Handlers receive these
+    /// exceptions by registering for [`ExceptionMask::CORPSE_NOTIFY`](crate::ExceptionMask::CORPSE_NOTIFY) with an
+    /// identity-carrying behavior.
+    pub fn take_corpse(&mut self) -> Option<io::Result<Corpse>> {
+        if self.exception_type() != ExceptionType::CorpseNotify {
+            return None;
+        }
+        self.take_task().map(Corpse::from_task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exceptions::kcdata::KCDATA_TYPE_ARRAY_PAD0;
+    use crate::exceptions::kcdata::tests::{push_item, push_end};
+
+    fn crash_info_buffer() -> Vec<u8> {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, KCDATA_BUFFER_BEGIN_CRASHINFO, 0, &[]);
+        push_item(&mut buffer, TASK_CRASHINFO_PID, 0, &123i32.to_ne_bytes());
+        push_item(&mut buffer, TASK_CRASHINFO_PPID, 0, &1i32.to_ne_bytes());
+        push_item(&mut buffer, TASK_CRASHINFO_PROC_NAME, 0, b"crasher\0\0\0\0\0\0\0\0\0");
+
+        let mut snapshot = Vec::new();
+        snapshot.extend_from_slice(&6u32.to_ne_bytes());
+        snapshot.extend_from_slice(&0x8badf00du64.to_ne_bytes());
+        snapshot.extend_from_slice(&2u64.to_ne_bytes());
+        push_item(&mut buffer, EXIT_REASON_SNAPSHOT, 0, &snapshot);
+        push_item(&mut buffer, EXIT_REASON_USER_DESC, 0, b"watchdog\0");
+
+        // Two dyld_uuid_info_64 elements followed by 8 bytes of padding
+        let mut images = Vec::new();
+        for (address, uuid) in &[(0x1_0000_0000u64, [1u8; 16]), (0x1_8000_0000u64, [2u8; 16])] {
+            images.extend_from_slice(&address.to_ne_bytes());
+            images.extend_from_slice(uuid);
+        }
+        images.extend_from_slice(&[0; 8]);
+        push_item(&mut buffer, KCDATA_TYPE_ARRAY_PAD0 | 8, (KCDATA_TYPE_LIBRARY_LOADINFO64 as u64) << 32 | 2, &images);
+        push_end(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn parses_crash_info() {
+        let info = CrashInfo::parse(&crash_info_buffer()).unwrap();
+        assert_eq!(info.pid, Some(123));
+        assert_eq!(info.ppid, Some(1));
+        assert_eq!(info.process_name.as_deref(), Some("crasher"));
+        assert_eq!(info.exit_reason, Some(ExitReason {
+            namespace: 6,
+            code: 0x8badf00d,
+            flags: 2,
+            description: Some("watchdog".to_owned()),
+        }));
+        assert_eq!(info.images, vec![
+            BinaryImage { load_address: 0x1_0000_0000, uuid: [1; 16] },
+            BinaryImage { load_address: 0x1_8000_0000, uuid: [2; 16] },
+        ]);
+        assert_eq!(info.crashed_thread_id, None);
+    }
+
+    #[test]
+    fn rejects_other_buffers() {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, TASK_CRASHINFO_PID, 0, &123i32.to_ne_bytes());
+        assert_eq!(CrashInfo::parse(&buffer).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(CrashInfo::parse(&[]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn keeps_items_before_truncation() {
+        let buffer = crash_info_buffer();
+        // Cut off in the middle of the exit reason snapshot
+        let cut = buffer.windows(4).position(|window| window == EXIT_REASON_SNAPSHOT.to_ne_bytes()).unwrap() + 20;
+        let info = CrashInfo::parse(&buffer[..cut]).unwrap();
+        assert_eq!(info.pid, Some(123));
+        assert_eq!(info.process_name.as_deref(), Some("crasher"));
+        assert_eq!(info.exit_reason, None);
+        assert!(info.images.is_empty());
+    }
+
+    #[test]
+    fn ignores_short_items() {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, KCDATA_BUFFER_BEGIN_CRASHINFO, 0, &[]);
+        push_item(&mut buffer, TASK_CRASHINFO_PID, 0, &[1, 2]);
+        push_item(&mut buffer, EXIT_REASON_SNAPSHOT, 0, &[0; 12]);
+        push_item(&mut buffer, TASK_CRASHINFO_CRASHED_THREADID, 0, &77u64.to_ne_bytes());
+        let info = CrashInfo::parse(&buffer).unwrap();
+        assert_eq!(info.pid, None);
+        assert_eq!(info.exit_reason, None);
+        assert_eq!(info.crashed_thread_id, Some(77));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{Port, Task, ThreadState, NativeThreadState, ExceptionMask, ExceptionBehavior, ExceptionPortsGuard};
+use crate::exceptions::{Exception, ExceptionResult, ExceptionServer, forward};
+
+use std::io;
+use std::time::Duration;
+
+use mach_core::error::ErrorContext;
+
+/// An exception handler for crash reporting that chains to whichever handler was registered before it.
+///
+/// After the report callback runs, each exception is re-delivered to the previously registered handler for its type
+/// (e.g. ReportCrash or Crashpad), translated to the behavior and flavor that handler expects, and the previous
+/// handler's verdict is passed back to the kernel. The previous handlers are restored when the catcher is dropped.
+///
+/// The faulting thread is suspended while its exception is handled, so the catcher must be run on a dedicated thread.
+pub struct CrashCatcher<'a> {
+    server: ExceptionServer,
+    reply_port: Port,
+    guard: ExceptionPortsGuard<'a>,
+}
+
+impl<'a> CrashCatcher<'a> {
+    /// The exception types that indicate a crash.
+    pub fn default_mask() -> ExceptionMask {
+        ExceptionMask::BAD_ACCESS | ExceptionMask::BAD_INSTRUCTION | ExceptionMask::ARITHMETIC | ExceptionMask::BREAKPOINT | ExceptionMask::GUARD
+    }
+
+    /// Registers a new exception port on `task` for the exception types in `mask`.
+    pub fn install(task: &'a Task, mask: ExceptionMask) -> io::Result<CrashCatcher<'a>> {
+        let server = ExceptionServer::create()?;
+        let reply_port = Port::new()?;
+        let sender = server.port().make_sender()?;
+        // Ask for everything, so we can translate to any behavior the previous handler wants
+        let guard = task.swap_exception_ports(
+            mask,
+            ExceptionBehavior::STATE_IDENTITY.with_mach_exception_codes(),
+            NativeThreadState::FLAVOR,
+            &sender,
+        )?;
+        Ok(CrashCatcher {
+            server,
+            reply_port,
+            guard,
+        })
+    }
+
+    /// Waits for a single exception, invokes `report`, and forwards the exception to the previous handler.
+    pub fn handle_one<F>(&mut self, timeout: Option<Duration>, report: F) -> io::Result<()> where
+        F: FnOnce(&Exception),
+    {
+        let previous = self.guard.previous();
+        let reply_port = &self.reply_port;
+        self.server.handle_one(timeout, |exception| {
+            report(exception);
+            let handler = exception.exception_type().mask()
+                .and_then(|mask| previous.iter().find(|handler| handler.mask.contains(mask)));
+            match handler {
+                Some(handler) => forward(exception, handler, reply_port).unwrap_or_else(|err| {
+                    // Failed kernel calls were already logged along with the operation
+                    if ErrorContext::from_io(&err).is_none() {
+                        error!("failed to forward exception to previous handler: {}", err);
+                    }
+                    ExceptionResult::Unhandled
+                }),
+                None => ExceptionResult::Unhandled,
+            }
+        })
+    }
+
+    /// Handles exceptions until an error occurs.
+    pub fn run<F>(&mut self, mut report: F) -> io::Result<()> where
+        F: FnMut(&Exception),
+    {
+        loop {
+            self.handle_one(None, &mut report)?;
+        }
+    }
+}
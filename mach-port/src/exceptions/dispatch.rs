@@ -0,0 +1,100 @@
+use crate::Msg;
+use crate::exceptions::{Exception, ExceptionResult};
+use crate::exceptions::message::{THREAD_STATE_MAX, REPLY_ID_OFFSET};
+
+use std::{io, mem};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Returns `true` if the message is an exception request that [`dispatch`] can handle.
+pub fn is_exception_request(msg: &Msg) -> bool {
+    Exception::request_behavior(msg.header().msgh_id).is_some()
+}
+
+/// Decodes an exception request received on an exception port, invokes `handler`, and sends the reply.
+///
+/// All combinations of `EXCEPTION_DEFAULT`, `EXCEPTION_STATE` and `EXCEPTION_STATE_IDENTITY`, with and without
+/// `MACH_EXCEPTION_CODES`, are supported. For the state behaviors, any changes the handler makes through
+/// [`Exception::state_mut`] are sent back to the kernel and applied to the faulting thread if the exception is handled.
+///
+/// Like `exc_server`, returns `Ok(false)` without replying if the message is not an exception request, so callers
+/// can multiplex exception ports with other traffic.
+pub fn dispatch<F>(request: &Msg, handler: F) -> io::Result<bool> where
+    F: FnOnce(&mut Exception) -> ExceptionResult,
+{
+    if !is_exception_request(request) {
+        return Ok(false);
+    }
+    let header = *request.header();
+    match Exception::decode(request) {
+        Ok(mut exception) => {
+            let ret_code = match handler(&mut exception) {
+                ExceptionResult::Handled => sys::KERN_SUCCESS as sys::kern_return_t,
+                ExceptionResult::Unhandled => sys::KERN_FAILURE as sys::kern_return_t,
+            };
+            let state = exception.state().map(|state| (state.flavor(), state.as_slice()));
+            send_reply(&header, ret_code, state)?;
+        },
+        Err(err) => {
+            warn!("received malformed exception message with ID {}: {:?}", header.msgh_id, err);
+            send_reply(&header, sys::MIG_BAD_ARGUMENTS, None)?;
+        },
+    }
+    Ok(true)
+}
+
+#[repr(C)]
+struct Reply {
+    header: sys::mach_msg_header_t,
+    ndr: sys::NDR_record_t,
+    ret_code: sys::kern_return_t,
+    // Only present in successful replies to the STATE variants
+    flavor: sys::thread_state_flavor_t,
+    new_state_count: sys::mach_msg_type_number_t,
+    new_state: [sys::natural_t; THREAD_STATE_MAX],
+}
+
+/// Sends a MIG-formatted reply to an exception request.
+pub(crate) fn send_reply(request: &sys::mach_msg_header_t, ret_code: sys::kern_return_t, state: Option<(sys::thread_state_flavor_t, &[sys::natural_t])>) -> io::Result<()> {
+    if request.msgh_remote_port == sys::MACH_PORT_NULL {
+        // Sent as a simple routine, no reply expected
+        return Ok(());
+    }
+    if let Some((_, data)) = state {
+        if data.len() > THREAD_STATE_MAX {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "thread state too large for exception reply"));
+        }
+    }
+    unsafe {
+        let mut reply: Reply = mem::zeroed();
+        // Reply on the send-once right from the request, with whatever disposition it arrived as
+        reply.header.msgh_bits = sys::MACH_MSGH_BITS_REMOTE(request.msgh_bits);
+        reply.header.msgh_remote_port = request.msgh_remote_port;
+        reply.header.msgh_local_port = sys::MACH_PORT_NULL;
+        reply.header.msgh_id = request.msgh_id + REPLY_ID_OFFSET;
+        reply.ndr = sys::NDR_record;
+        reply.ret_code = ret_code;
+
+        let mut size = mem::size_of::<sys::mach_msg_header_t>() + mem::size_of::<sys::NDR_record_t>() + mem::size_of::<sys::kern_return_t>();
+        // MIG omits everything but the return code from failed replies
+        if let (Some((flavor, data)), sys::KERN_SUCCESS) = (state, ret_code as u32) {
+            reply.flavor = flavor;
+            reply.new_state_count = data.len() as _;
+            reply.new_state[..data.len()].copy_from_slice(data);
+            size += mem::size_of::<sys::thread_state_flavor_t>() + mem::size_of::<sys::mach_msg_type_number_t>() + data.len() * mem::size_of::<sys::natural_t>();
+        }
+        reply.header.msgh_size = size as _;
+
+        mach_call!(log op: "mach_msg(exception reply)", sys::mach_msg(
+            &mut reply.header,
+            sys::MACH_SEND_MSG as _,
+            size as _,
+            0,
+            sys::MACH_PORT_NULL,
+            sys::MACH_MSG_TIMEOUT_NONE as _,
+            sys::MACH_PORT_NULL,
+        ))?;
+    }
+    Ok(())
+}
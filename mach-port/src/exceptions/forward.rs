@@ -0,0 +1,167 @@
+use crate::{Port, ExceptionBehavior, ExceptionHandler, RawThreadState};
+use crate::exceptions::{Exception, ExceptionResult};
+use crate::exceptions::message::{Reader, THREAD_STATE_MAX, REPLY_ID_OFFSET};
+use crate::exceptions::message::{EXCEPTION_RAISE, EXCEPTION_RAISE_STATE, EXCEPTION_RAISE_STATE_IDENTITY};
+use crate::exceptions::message::{MACH_EXCEPTION_RAISE, MACH_EXCEPTION_RAISE_STATE, MACH_EXCEPTION_RAISE_STATE_IDENTITY};
+
+use std::{io, mem, ptr, slice};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Re-delivers an exception to another handler (typically one that was registered before ours) and waits for its
+/// verdict.
+///
+/// The request is translated to the behavior and flavor the other handler registered with: thread state of a
+/// different flavor is fetched from the faulting thread, and any state the other handler returns is applied either
+/// to `exception`'s state (which the caller's reply will carry) or directly to the thread. Identity behaviors require
+/// that the exception still holds its thread and task ports.
+///
+/// `reply_port` must be a receive right that is not used for anything else while the call is in progress.
+pub fn forward(exception: &mut Exception, handler: &ExceptionHandler, reply_port: &Port) -> io::Result<ExceptionResult> {
+    let target = match handler.port {
+        Some(ref port) => port,
+        None => return Ok(ExceptionResult::Unhandled),
+    };
+    let behavior = handler.behavior;
+    let base = behavior.base();
+    let identity = base != ExceptionBehavior::STATE;
+    let has_state = base != ExceptionBehavior::DEFAULT;
+
+    let (thread, task) = match (exception.thread(), exception.task()) {
+        (Some(thread), Some(task)) => (thread.as_raw_port(), task.as_raw_port()),
+        _ if !identity => (sys::MACH_PORT_NULL, sys::MACH_PORT_NULL),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "forwarding to an identity handler requires the thread and task ports")),
+    };
+
+    // Obtain the state in the flavor the other handler wants
+    let mut state_from_thread = false;
+    let state = if has_state {
+        match exception.state() {
+            Some(state) if state.flavor() == handler.flavor => Some(state.clone()),
+            _ => match exception.thread() {
+                Some(thread) => {
+                    state_from_thread = true;
+                    Some(thread.get_raw_state(handler.flavor)?)
+                },
+                None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot obtain thread state of the requested flavor")),
+            },
+        }
+    } else {
+        None
+    };
+
+    let id = match (base, behavior.mach_exception_codes()) {
+        (ExceptionBehavior::DEFAULT, false) => EXCEPTION_RAISE,
+        (ExceptionBehavior::STATE, false) => EXCEPTION_RAISE_STATE,
+        (ExceptionBehavior::STATE_IDENTITY, false) => EXCEPTION_RAISE_STATE_IDENTITY,
+        (ExceptionBehavior::DEFAULT, true) => MACH_EXCEPTION_RAISE,
+        (ExceptionBehavior::STATE, true) => MACH_EXCEPTION_RAISE_STATE,
+        (ExceptionBehavior::STATE_IDENTITY, true) => MACH_EXCEPTION_RAISE_STATE_IDENTITY,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported exception behavior")),
+    };
+
+    // Encode the request in MIG's wire format
+    let mut request = Vec::new();
+    push(&mut request, unsafe { mem::zeroed::<sys::mach_msg_header_t>() });
+    if identity {
+        push(&mut request, 2 as sys::mach_msg_size_t);
+        push(&mut request, port_descriptor(thread));
+        push(&mut request, port_descriptor(task));
+    }
+    push(&mut request, unsafe { sys::NDR_record });
+    push(&mut request, exception.exception_type().into_raw());
+    push(&mut request, exception.codes().len() as sys::mach_msg_type_number_t);
+    for &code in exception.codes() {
+        if behavior.mach_exception_codes() {
+            push(&mut request, code);
+        } else {
+            push(&mut request, code as i32);
+        }
+    }
+    if let Some(ref state) = state {
+        push(&mut request, state.flavor());
+        push(&mut request, state.as_slice().len() as sys::mach_msg_type_number_t);
+        for &word in state.as_slice() {
+            push(&mut request, word);
+        }
+    }
+
+    // Use u64 storage so the header is suitably aligned, and leave room for the largest reply plus trailer
+    let reply_capacity = mem::size_of::<sys::mach_msg_header_t>() + mem::size_of::<sys::NDR_record_t>() + 3 * 4 + THREAD_STATE_MAX * 4 + mem::size_of::<sys::mach_msg_max_trailer_t>();
+    let mut storage = vec![0u64; (request.len().max(reply_capacity) + 7) / 8];
+    unsafe {
+        ptr::copy_nonoverlapping(request.as_ptr(), storage.as_mut_ptr() as *mut u8, request.len());
+        let header = &mut *(storage.as_mut_ptr() as *mut sys::mach_msg_header_t);
+        header.msgh_bits = sys::MACH_MSGH_BITS(sys::MACH_MSG_TYPE_COPY_SEND, sys::MACH_MSG_TYPE_MAKE_SEND_ONCE);
+        if identity {
+            header.msgh_bits |= sys::MACH_MSGH_BITS_COMPLEX;
+        }
+        header.msgh_size = request.len() as _;
+        header.msgh_remote_port = target.as_raw_port();
+        header.msgh_local_port = reply_port.as_raw_port();
+        header.msgh_id = id;
+
+        mach_call!(log op: "mach_msg(forward exception)", sys::mach_msg(
+            header,
+            (sys::MACH_SEND_MSG | sys::MACH_RCV_MSG) as _,
+            request.len() as _,
+            (storage.len() * 8) as _,
+            reply_port.as_raw_port(),
+            sys::MACH_MSG_TIMEOUT_NONE as _,
+            sys::MACH_PORT_NULL,
+        ))?;
+    }
+
+    // Decode the reply
+    let bytes = unsafe { slice::from_raw_parts(storage.as_ptr() as *const u8, storage.len() * 8) };
+    let header: sys::mach_msg_header_t = unsafe { ptr::read(storage.as_ptr() as *const sys::mach_msg_header_t) };
+    if header.msgh_id != id + REPLY_ID_OFFSET {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected reply to forwarded exception"));
+    }
+    let mut reader = Reader { bytes: &bytes[..(header.msgh_size as usize).min(bytes.len())], offset: mem::size_of::<sys::mach_msg_header_t>() };
+    reader.skip(mem::size_of::<sys::NDR_record_t>())?;
+    let ret_code = reader.read_u32()? as sys::kern_return_t;
+    if ret_code != sys::KERN_SUCCESS as sys::kern_return_t {
+        return Ok(ExceptionResult::Unhandled);
+    }
+
+    if has_state {
+        let flavor = reader.read_u32()? as sys::thread_state_flavor_t;
+        let count = reader.read_u32()? as usize;
+        if count > THREAD_STATE_MAX {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "thread state too large"));
+        }
+        let mut data = Vec::with_capacity(count);
+        for _ in 0..count {
+            data.push(reader.read_u32()?);
+        }
+        let new_state = RawThreadState::new(flavor, data);
+        match exception.state_mut() {
+            Some(ours) if !state_from_thread && ours.flavor() == flavor => *ours = new_state,
+            _ => if let Some(thread) = exception.thread() {
+                thread.set_raw_state(&new_state)?;
+            },
+        }
+    }
+
+    Ok(ExceptionResult::Handled)
+}
+
+fn port_descriptor(name: sys::mach_port_t) -> sys::mach_msg_port_descriptor_t {
+    unsafe {
+        let mut descriptor = sys::mach_msg_port_descriptor_t {
+            name,
+            pad1: 0,
+            _bitfield_1: mem::zeroed(),
+        };
+        descriptor.set_type(sys::MACH_MSG_PORT_DESCRIPTOR);
+        descriptor.set_disposition(sys::MACH_MSG_TYPE_COPY_SEND);
+        descriptor
+    }
+}
+
+fn push<T: Copy>(buffer: &mut Vec<u8>, value: T) {
+    let bytes = unsafe { slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>()) };
+    buffer.extend_from_slice(bytes);
+}
@@ -0,0 +1,242 @@
+use crate::exceptions::{Exception, ExceptionType};
+
+/// The kind of resource whose guard was violated in an `EXC_GUARD` exception.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GuardType {
+    MachPort,
+    Fd,
+    User,
+    Vnode,
+    VirtualMemory,
+    RejectedSyscall,
+    Other(u8),
+}
+
+impl GuardType {
+    fn from_raw(raw: u8) -> GuardType {
+        match raw {
+            1 => GuardType::MachPort,
+            2 => GuardType::Fd,
+            3 => GuardType::User,
+            4 => GuardType::Vnode,
+            5 => GuardType::VirtualMemory,
+            6 => GuardType::RejectedSyscall,
+            raw => GuardType::Other(raw),
+        }
+    }
+}
+
+/// The operation that violated a guarded Mach port (`kGUARD_EXC_*`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PortGuardFlavor {
+    Destroy,
+    ModRefs,
+    SetContext,
+    Unguarded,
+    IncorrectGuard,
+    Immovable,
+    StrictReply,
+    MsgFiltered,
+    InvalidRight,
+    InvalidName,
+    InvalidValue,
+    InvalidArgument,
+    RightExists,
+    KernNoSpace,
+    KernFailure,
+    KernResource,
+    SendInvalidReply,
+    SendInvalidVoucher,
+    SendInvalidRight,
+    RcvInvalidName,
+    RcvGuardedDesc,
+    ModRefsNonFatal,
+    ImmovableNonFatal,
+    Other(u32),
+}
+
+impl PortGuardFlavor {
+    fn from_raw(raw: u32) -> PortGuardFlavor {
+        match raw {
+            0x1 => PortGuardFlavor::Destroy,
+            0x2 => PortGuardFlavor::ModRefs,
+            0x4 => PortGuardFlavor::SetContext,
+            0x8 => PortGuardFlavor::Unguarded,
+            0x10 => PortGuardFlavor::IncorrectGuard,
+            0x20 => PortGuardFlavor::Immovable,
+            0x40 => PortGuardFlavor::StrictReply,
+            0x80 => PortGuardFlavor::MsgFiltered,
+            0x100 => PortGuardFlavor::InvalidRight,
+            0x200 => PortGuardFlavor::InvalidName,
+            0x400 => PortGuardFlavor::InvalidValue,
+            0x800 => PortGuardFlavor::InvalidArgument,
+            0x1000 => PortGuardFlavor::RightExists,
+            0x2000 => PortGuardFlavor::KernNoSpace,
+            0x4000 => PortGuardFlavor::KernFailure,
+            0x8000 => PortGuardFlavor::KernResource,
+            0x10000 => PortGuardFlavor::SendInvalidReply,
+            0x20000 => PortGuardFlavor::SendInvalidVoucher,
+            0x40000 => PortGuardFlavor::SendInvalidRight,
+            0x80000 => PortGuardFlavor::RcvInvalidName,
+            0x100000 => PortGuardFlavor::RcvGuardedDesc,
+            0x200000 => PortGuardFlavor::ModRefsNonFatal,
+            0x400000 => PortGuardFlavor::ImmovableNonFatal,
+            raw => PortGuardFlavor::Other(raw),
+        }
+    }
+}
+
+/// The operation that violated a guarded file descriptor (`kGUARD_EXC_*` from `sys/guarded.h`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FdGuardFlavor {
+    Close,
+    Dup,
+    NoCloexec,
+    SocketIpc,
+    Fileport,
+    Mismatch,
+    Write,
+    Other(u32),
+}
+
+impl FdGuardFlavor {
+    fn from_raw(raw: u32) -> FdGuardFlavor {
+        match raw {
+            0x1 => FdGuardFlavor::Close,
+            0x2 => FdGuardFlavor::Dup,
+            0x4 => FdGuardFlavor::NoCloexec,
+            0x8 => FdGuardFlavor::SocketIpc,
+            0x10 => FdGuardFlavor::Fileport,
+            0x20 => FdGuardFlavor::Mismatch,
+            0x40 => FdGuardFlavor::Write,
+            raw => FdGuardFlavor::Other(raw),
+        }
+    }
+}
+
+/// The decoded codes of an `EXC_GUARD` exception.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GuardException {
+    guard_type: GuardType,
+    flavor: u32,
+    target: u32,
+    identifier: u64,
+}
+
+impl GuardException {
+    /// Decodes the 64-bit codes of an `EXC_GUARD` exception, laid out as `EXC_GUARD_ENCODE_*` in `kern/exc_guard.h`
+    /// describes.
+    pub fn decode(codes: &[i64]) -> Option<GuardException> {
+        if codes.len() < 2 {
+            return None;
+        }
+        let code = codes[0] as u64;
+        Some(GuardException {
+            guard_type: GuardType::from_raw(((code >> 61) & 0x7) as u8),
+            flavor: ((code >> 32) & 0x1fff_ffff) as u32,
+            target: code as u32,
+            identifier: codes[1] as u64,
+        })
+    }
+
+    #[inline]
+    pub fn guard_type(&self) -> GuardType {
+        self.guard_type
+    }
+
+    /// The raw flavor of the violation, whose meaning depends on the guard type.
+    #[inline]
+    pub fn raw_flavor(&self) -> u32 {
+        self.flavor
+    }
+
+    /// The port name or file descriptor that was guarded, depending on the guard type.
+    #[inline]
+    pub fn target(&self) -> u32 {
+        self.target
+    }
+
+    /// The guard value associated with the resource, or other flavor-specific payload.
+    #[inline]
+    pub fn identifier(&self) -> u64 {
+        self.identifier
+    }
+
+    pub fn port_flavor(&self) -> Option<PortGuardFlavor> {
+        if self.guard_type == GuardType::MachPort {
+            Some(PortGuardFlavor::from_raw(self.flavor))
+        } else {
+            None
+        }
+    }
+
+    pub fn fd_flavor(&self) -> Option<FdGuardFlavor> {
+        if self.guard_type == GuardType::Fd {
+            Some(FdGuardFlavor::from_raw(self.flavor))
+        } else {
+            None
+        }
+    }
+}
+
+impl Exception {
+    /// Decodes the codes of an `EXC_GUARD` exception.
+    ///
+    /// Returns `None` for other exception types, or if the handler was not registered with `MACH_EXCEPTION_CODES`
+    /// (in which case the codes are truncated).
+    pub fn guard(&self) -> Option<GuardException> {
+        if self.exception_type() != ExceptionType::Guard || !self.behavior().mach_exception_codes() {
+            return None;
+        }
+        GuardException::decode(self.codes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // EXC_GUARD_ENCODE_TYPE, EXC_GUARD_ENCODE_FLAVOR and EXC_GUARD_ENCODE_TARGET from kern/exc_guard.h
+    fn encode(guard_type: u64, flavor: u64, target: u64) -> i64 {
+        (guard_type << 61 | flavor << 32 | target) as i64
+    }
+
+    #[test]
+    fn decodes_port_guard() {
+        let guard = GuardException::decode(&[encode(1, 0x100, 0x1303), 0x5eed]).unwrap();
+        assert_eq!(guard.guard_type(), GuardType::MachPort);
+        assert_eq!(guard.raw_flavor(), 0x100);
+        assert_eq!(guard.port_flavor(), Some(PortGuardFlavor::InvalidRight));
+        assert_eq!(guard.fd_flavor(), None);
+        assert_eq!(guard.target(), 0x1303);
+        assert_eq!(guard.identifier(), 0x5eed);
+    }
+
+    #[test]
+    fn decodes_fd_guard() {
+        let guard = GuardException::decode(&[encode(2, 0x1, 7), -1]).unwrap();
+        assert_eq!(guard.guard_type(), GuardType::Fd);
+        assert_eq!(guard.fd_flavor(), Some(FdGuardFlavor::Close));
+        assert_eq!(guard.port_flavor(), None);
+        assert_eq!(guard.target(), 7);
+        assert_eq!(guard.identifier(), u64::MAX);
+    }
+
+    #[test]
+    fn decodes_high_bits() {
+        // Types with the top bit set make the code negative, and the flavor uses all 29 of its bits
+        let guard = GuardException::decode(&[encode(7, 0x1fff_ffff, 0xffff_ffff), 0]).unwrap();
+        assert_eq!(guard.guard_type(), GuardType::Other(7));
+        assert_eq!(guard.raw_flavor(), 0x1fff_ffff);
+        assert_eq!(guard.target(), 0xffff_ffff);
+        let guard = GuardException::decode(&[encode(4, 0x3, 0), 0]).unwrap();
+        assert_eq!(guard.guard_type(), GuardType::Vnode);
+        assert_eq!(PortGuardFlavor::from_raw(0x3), PortGuardFlavor::Other(0x3));
+    }
+
+    #[test]
+    fn requires_both_codes() {
+        assert_eq!(GuardException::decode(&[encode(1, 0x1, 0x103)]), None);
+        assert_eq!(GuardException::decode(&[]), None);
+    }
+}
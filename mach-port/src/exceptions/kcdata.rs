@@ -0,0 +1,228 @@
+use std::{mem, ptr};
+
+// Item types from kern/kcdata.h
+pub(crate) const KCDATA_TYPE_ARRAY: u32 = 0x11;
+pub(crate) const KCDATA_TYPE_ARRAY_PAD0: u32 = 0x20;
+pub(crate) const KCDATA_TYPE_BUFFER_END: u32 = 0xf19158ed;
+pub(crate) const KCDATA_BUFFER_BEGIN_CRASHINFO: u32 = 0xdeadf00d;
+
+const KCDATA_FLAGS_STRUCT_HAS_PADDING: u64 = 0x80;
+const KCDATA_FLAGS_STRUCT_PADDING_MASK: u64 = 0xf;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct KcdataItemHeader {
+    ty: u32,
+    size: u32,
+    flags: u64,
+}
+
+/// A single item of a `kcdata` buffer, the self-describing format the kernel uses for crash info and stackshots.
+#[derive(Clone, Copy, Debug)]
+pub struct KcdataItem<'a> {
+    ty: u32,
+    flags: u64,
+    data: &'a [u8],
+}
+
+impl<'a> KcdataItem<'a> {
+    /// The raw item type. For arrays, this is `KCDATA_TYPE_ARRAY` or one of the `KCDATA_TYPE_ARRAY_PAD*` types.
+    #[inline]
+    pub fn raw_type(&self) -> u32 {
+        self.ty
+    }
+
+    #[inline]
+    pub fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    /// The payload of the item with any trailing padding removed.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    #[inline]
+    pub fn is_array(&self) -> bool {
+        self.ty == KCDATA_TYPE_ARRAY || (self.ty & !0xf) == KCDATA_TYPE_ARRAY_PAD0
+    }
+
+    /// The type of the item, or of its elements if the item is an array.
+    #[inline]
+    pub fn element_type(&self) -> u32 {
+        if self.is_array() {
+            (self.flags >> 32) as u32
+        } else {
+            self.ty
+        }
+    }
+
+    /// The number of elements in the item, which is 1 for non-array items.
+    ///
+    /// A malformed array claiming more elements than its payload has bytes is treated as empty.
+    #[inline]
+    pub fn element_count(&self) -> usize {
+        if self.is_array() {
+            let count = (self.flags & 0xffff_ffff) as usize;
+            if count > self.data.len() { 0 } else { count }
+        } else {
+            1
+        }
+    }
+
+    /// Iterates over the elements of an array item.
+    pub fn elements(&self) -> impl Iterator<Item=&'a [u8]> {
+        let count = self.element_count();
+        let element_size = self.data.len().checked_div(count).unwrap_or(0);
+        let data = self.data;
+        (0..count).map(move |i| &data[(i * element_size)..((i + 1) * element_size)])
+    }
+
+    /// Reads a value from the start of the payload, if it is large enough.
+    pub(crate) fn read<T: Copy>(&self) -> Option<T> {
+        read_at(self.data, 0)
+    }
+
+    /// Interprets the payload as a NUL-terminated string.
+    pub(crate) fn string(&self) -> String {
+        let len = self.data.iter().position(|&b| b == 0).unwrap_or(self.data.len());
+        String::from_utf8_lossy(&self.data[..len]).into_owned()
+    }
+}
+
+/// Iterates over the items of a `kcdata` buffer, stopping at the end marker or at the first malformed item.
+pub struct KcdataIter<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> KcdataIter<'a> {
+    pub fn new(buffer: &'a [u8]) -> KcdataIter<'a> {
+        KcdataIter {
+            buffer,
+            offset: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for KcdataIter<'a> {
+    type Item = KcdataItem<'a>;
+
+    fn next(&mut self) -> Option<KcdataItem<'a>> {
+        let header: KcdataItemHeader = read_at(self.buffer, self.offset)?;
+        if header.ty == KCDATA_TYPE_BUFFER_END {
+            return None;
+        }
+        let start = self.offset + mem::size_of::<KcdataItemHeader>();
+        let end = start.checked_add(header.size as usize)?;
+        if end > self.buffer.len() {
+            return None;
+        }
+        let padding = if (header.ty & !0xf) == KCDATA_TYPE_ARRAY_PAD0 {
+            (header.ty & 0xf) as usize
+        } else if header.flags & KCDATA_FLAGS_STRUCT_HAS_PADDING != 0 {
+            (header.flags & KCDATA_FLAGS_STRUCT_PADDING_MASK) as usize
+        } else {
+            0
+        };
+        self.offset = end;
+        Some(KcdataItem {
+            ty: header.ty,
+            flags: header.flags,
+            data: &self.buffer[start..(end - padding.min(header.size as usize))],
+        })
+    }
+}
+
+pub(crate) fn read_at<T: Copy>(bytes: &[u8], offset: usize) -> Option<T> {
+    if bytes.len() < offset || bytes.len() - offset < mem::size_of::<T>() {
+        return None;
+    }
+    Some(unsafe { ptr::read_unaligned(bytes.as_ptr().add(offset) as *const T) })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Appends an item to a kcdata buffer under construction.
+    pub(crate) fn push_item(buffer: &mut Vec<u8>, ty: u32, flags: u64, payload: &[u8]) {
+        buffer.extend_from_slice(&ty.to_ne_bytes());
+        buffer.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        buffer.extend_from_slice(&flags.to_ne_bytes());
+        buffer.extend_from_slice(payload);
+    }
+
+    pub(crate) fn push_end(buffer: &mut Vec<u8>) {
+        push_item(buffer, KCDATA_TYPE_BUFFER_END, 0, &[]);
+    }
+
+    #[test]
+    fn iterates_until_end_marker() {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, KCDATA_BUFFER_BEGIN_CRASHINFO, 0, &[]);
+        push_item(&mut buffer, 0x805, 0, &42u32.to_ne_bytes());
+        push_end(&mut buffer);
+        push_item(&mut buffer, 0x806, 0, &1u32.to_ne_bytes());
+
+        let items: Vec<_> = KcdataIter::new(&buffer).collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].raw_type(), KCDATA_BUFFER_BEGIN_CRASHINFO);
+        assert!(items[0].data().is_empty());
+        assert_eq!(items[1].element_type(), 0x805);
+        assert_eq!(items[1].element_count(), 1);
+        assert_eq!(items[1].read::<u32>(), Some(42));
+        assert_eq!(items[1].read::<u64>(), None);
+    }
+
+    #[test]
+    fn strips_struct_padding() {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, 0x809, KCDATA_FLAGS_STRUCT_HAS_PADDING | 3, b"proc\0\xff\xff\xff");
+        let item = KcdataIter::new(&buffer).next().unwrap();
+        assert_eq!(item.data(), b"proc\0");
+        assert_eq!(item.string(), "proc");
+    }
+
+    #[test]
+    fn splits_padded_arrays() {
+        let mut payload: Vec<u8> = (0..24).collect();
+        payload.extend(0..8);
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, KCDATA_TYPE_ARRAY_PAD0 | 8, 0x31 << 32 | 2, &payload);
+        let item = KcdataIter::new(&buffer).next().unwrap();
+        assert!(item.is_array());
+        assert_eq!(item.element_type(), 0x31);
+        assert_eq!(item.element_count(), 2);
+        let elements: Vec<_> = item.elements().collect();
+        assert_eq!(elements, vec![&payload[0..12], &payload[12..24]]);
+    }
+
+    #[test]
+    fn rejects_oversized_array_count() {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, KCDATA_TYPE_ARRAY, 0x31 << 32 | 0xffff_ffff, &[0; 16]);
+        let item = KcdataIter::new(&buffer).next().unwrap();
+        assert_eq!(item.element_count(), 0);
+        assert_eq!(item.elements().count(), 0);
+    }
+
+    #[test]
+    fn stops_at_truncated_items() {
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, 0x805, 0, &42u32.to_ne_bytes());
+        push_item(&mut buffer, 0x806, 0, &[0; 8]);
+        // The second item claims more bytes than remain
+        let truncated = &buffer[..buffer.len() - 1];
+        assert_eq!(KcdataIter::new(truncated).count(), 1);
+        // Not even a whole header
+        assert_eq!(KcdataIter::new(&buffer[..8]).count(), 0);
+
+        // A size larger than any buffer
+        let mut buffer = Vec::new();
+        push_item(&mut buffer, 0x805, 0, &[]);
+        buffer[4..8].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert_eq!(KcdataIter::new(&buffer).count(), 0);
+    }
+}
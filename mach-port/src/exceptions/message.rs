@@ -0,0 +1,303 @@
+use crate::{Msg, Task, Thread, RawThreadState, ExceptionBehavior, ExceptionMask};
+
+use std::{io, mem, ptr};
+
+use mach_sys as sys;
+
+// Message IDs from exc.defs, used by handlers registered without MACH_EXCEPTION_CODES
+pub(crate) const EXCEPTION_RAISE: sys::mach_msg_id_t = 2401;
+pub(crate) const EXCEPTION_RAISE_STATE: sys::mach_msg_id_t = 2402;
+pub(crate) const EXCEPTION_RAISE_STATE_IDENTITY: sys::mach_msg_id_t = 2403;
+
+// Message IDs from mach_exc.defs
+pub(crate) const MACH_EXCEPTION_RAISE: sys::mach_msg_id_t = 2405;
+pub(crate) const MACH_EXCEPTION_RAISE_STATE: sys::mach_msg_id_t = 2406;
+pub(crate) const MACH_EXCEPTION_RAISE_STATE_IDENTITY: sys::mach_msg_id_t = 2407;
+
+// MIG replies use the request ID plus 100
+pub(crate) const REPLY_ID_OFFSET: sys::mach_msg_id_t = 100;
+
+pub(crate) const THREAD_STATE_MAX: usize = sys::THREAD_STATE_MAX as usize;
+
+/// The largest request any of the exception message formats can produce.
+#[repr(C)]
+pub(crate) struct MaxRequest {
+    header: sys::mach_msg_header_t,
+    body: sys::mach_msg_body_t,
+    thread: sys::mach_msg_port_descriptor_t,
+    task: sys::mach_msg_port_descriptor_t,
+    ndr: sys::NDR_record_t,
+    exception: sys::exception_type_t,
+    code_count: sys::mach_msg_type_number_t,
+    codes: [i64; 2],
+    flavor: sys::thread_state_flavor_t,
+    old_state_count: sys::mach_msg_type_number_t,
+    old_state: [sys::natural_t; THREAD_STATE_MAX],
+}
+
+/// The type of a Mach exception (`EXC_*`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ExceptionType {
+    BadAccess,
+    BadInstruction,
+    Arithmetic,
+    Emulation,
+    Software,
+    Breakpoint,
+    Syscall,
+    MachSyscall,
+    RpcAlert,
+    Crash,
+    Resource,
+    Guard,
+    CorpseNotify,
+    Other(sys::exception_type_t),
+}
+
+impl ExceptionType {
+    pub fn from_raw(raw: sys::exception_type_t) -> ExceptionType {
+        match raw as u32 {
+            sys::EXC_BAD_ACCESS => ExceptionType::BadAccess,
+            sys::EXC_BAD_INSTRUCTION => ExceptionType::BadInstruction,
+            sys::EXC_ARITHMETIC => ExceptionType::Arithmetic,
+            sys::EXC_EMULATION => ExceptionType::Emulation,
+            sys::EXC_SOFTWARE => ExceptionType::Software,
+            sys::EXC_BREAKPOINT => ExceptionType::Breakpoint,
+            sys::EXC_SYSCALL => ExceptionType::Syscall,
+            sys::EXC_MACH_SYSCALL => ExceptionType::MachSyscall,
+            sys::EXC_RPC_ALERT => ExceptionType::RpcAlert,
+            sys::EXC_CRASH => ExceptionType::Crash,
+            sys::EXC_RESOURCE => ExceptionType::Resource,
+            sys::EXC_GUARD => ExceptionType::Guard,
+            sys::EXC_CORPSE_NOTIFY => ExceptionType::CorpseNotify,
+            _ => ExceptionType::Other(raw),
+        }
+    }
+
+    /// The mask selecting this exception type when registering exception ports.
+    ///
+    /// Returns `None` for [`Other`](ExceptionType::Other) types that are negative or too large to have a bit in a mask.
+    #[inline]
+    pub fn mask(self) -> Option<ExceptionMask> {
+        sys::EXC_MASK(self.into_raw()).map(ExceptionMask)
+    }
+
+    pub fn into_raw(self) -> sys::exception_type_t {
+        (match self {
+            ExceptionType::BadAccess => sys::EXC_BAD_ACCESS,
+            ExceptionType::BadInstruction => sys::EXC_BAD_INSTRUCTION,
+            ExceptionType::Arithmetic => sys::EXC_ARITHMETIC,
+            ExceptionType::Emulation => sys::EXC_EMULATION,
+            ExceptionType::Software => sys::EXC_SOFTWARE,
+            ExceptionType::Breakpoint => sys::EXC_BREAKPOINT,
+            ExceptionType::Syscall => sys::EXC_SYSCALL,
+            ExceptionType::MachSyscall => sys::EXC_MACH_SYSCALL,
+            ExceptionType::RpcAlert => sys::EXC_RPC_ALERT,
+            ExceptionType::Crash => sys::EXC_CRASH,
+            ExceptionType::Resource => sys::EXC_RESOURCE,
+            ExceptionType::Guard => sys::EXC_GUARD,
+            ExceptionType::CorpseNotify => sys::EXC_CORPSE_NOTIFY,
+            ExceptionType::Other(raw) => return raw,
+        }) as sys::exception_type_t
+    }
+}
+
+/// A decoded exception message.
+#[derive(Debug)]
+pub struct Exception {
+    behavior: ExceptionBehavior,
+    exception: ExceptionType,
+    codes: Vec<i64>,
+    thread: Option<Thread>,
+    task: Option<Task>,
+    state: Option<RawThreadState>,
+}
+
+impl Exception {
+    /// The behavior the exception port was registered with, which determines which parts of the exception are present.
+    #[inline]
+    pub fn behavior(&self) -> ExceptionBehavior {
+        self.behavior
+    }
+
+    #[inline]
+    pub fn exception_type(&self) -> ExceptionType {
+        self.exception
+    }
+
+    /// The exception codes, whose meaning depends on the exception type. Usually contains two elements.
+    #[inline]
+    pub fn codes(&self) -> &[i64] {
+        &self.codes
+    }
+
+    /// The faulting thread. Only present for `DEFAULT` and `STATE_IDENTITY` behaviors.
+    #[inline]
+    pub fn thread(&self) -> Option<&Thread> {
+        self.thread.as_ref()
+    }
+
+    /// The task containing the faulting thread. Only present for `DEFAULT` and `STATE_IDENTITY` behaviors.
+    #[inline]
+    pub fn task(&self) -> Option<&Task> {
+        self.task.as_ref()
+    }
+
+    /// Takes ownership of the faulting thread's port, so it can outlive the handler.
+    #[inline]
+    pub fn take_thread(&mut self) -> Option<Thread> {
+        self.thread.take()
+    }
+
+    /// Takes ownership of the faulting task's port, so it can outlive the handler.
+    #[inline]
+    pub fn take_task(&mut self) -> Option<Task> {
+        self.task.take()
+    }
+
+    /// The thread state of the flavor the exception port was registered with. Only present for `STATE` and
+    /// `STATE_IDENTITY` behaviors.
+    #[inline]
+    pub fn state(&self) -> Option<&RawThreadState> {
+        self.state.as_ref()
+    }
+
+    /// Mutable access to the thread state, which will be applied to the faulting thread if the exception is handled.
+    ///
+    /// Only present for `STATE` and `STATE_IDENTITY` behaviors.
+    #[inline]
+    pub fn state_mut(&mut self) -> Option<&mut RawThreadState> {
+        self.state.as_mut()
+    }
+
+    /// Determines the behavior of an exception request from its message ID, or `None` if it is not an exception
+    /// request.
+    pub(crate) fn request_behavior(id: sys::mach_msg_id_t) -> Option<ExceptionBehavior> {
+        Some(match id {
+            EXCEPTION_RAISE => ExceptionBehavior::DEFAULT,
+            EXCEPTION_RAISE_STATE => ExceptionBehavior::STATE,
+            EXCEPTION_RAISE_STATE_IDENTITY => ExceptionBehavior::STATE_IDENTITY,
+            MACH_EXCEPTION_RAISE => ExceptionBehavior::DEFAULT.with_mach_exception_codes(),
+            MACH_EXCEPTION_RAISE_STATE => ExceptionBehavior::STATE.with_mach_exception_codes(),
+            MACH_EXCEPTION_RAISE_STATE_IDENTITY => ExceptionBehavior::STATE_IDENTITY.with_mach_exception_codes(),
+            _ => return None,
+        })
+    }
+
+    /// Decodes an exception request message, taking ownership of any port rights it carries.
+    pub(crate) fn decode(msg: &Msg) -> io::Result<Exception> {
+        let bytes = msg.as_bytes();
+        let behavior = match Exception::request_behavior(msg.header().msgh_id) {
+            Some(behavior) => behavior,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "unrecognized exception message ID")),
+        };
+        let identity = behavior.base() != ExceptionBehavior::STATE;
+        let has_state = behavior.base() != ExceptionBehavior::DEFAULT;
+        let mut reader = Reader { bytes, offset: mem::size_of::<sys::mach_msg_header_t>() };
+
+        let (mut thread, mut task) = (None, None);
+        if identity {
+            if !msg.complex() || reader.read_u32()? < 2 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "exception message is missing port descriptors"));
+            }
+            unsafe {
+                thread = Some(Thread::from_raw_port(reader.read_port_descriptor()?));
+                task = Some(Task::from_raw_port(reader.read_port_descriptor()?));
+            }
+        }
+
+        reader.skip(mem::size_of::<sys::NDR_record_t>())?;
+        let exception = ExceptionType::from_raw(reader.read_u32()? as sys::exception_type_t);
+        let code_count = reader.read_u32()? as usize;
+        if code_count > 2 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too many exception codes"));
+        }
+        let mut codes = Vec::with_capacity(code_count);
+        for _ in 0..code_count {
+            if behavior.mach_exception_codes() {
+                codes.push(reader.read_u64()? as i64);
+            } else {
+                // exc.defs codes are integer_t, which must be sign extended
+                codes.push(reader.read_u32()? as i32 as i64);
+            }
+        }
+
+        let state = if has_state {
+            let flavor = reader.read_u32()? as sys::thread_state_flavor_t;
+            let count = reader.read_u32()? as usize;
+            if count > THREAD_STATE_MAX {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "thread state too large"));
+            }
+            let mut data = Vec::with_capacity(count);
+            for _ in 0..count {
+                data.push(reader.read_u32()?);
+            }
+            Some(RawThreadState::new(flavor, data))
+        } else {
+            None
+        };
+
+        Ok(Exception {
+            behavior,
+            exception,
+            codes,
+            thread,
+            task,
+            state,
+        })
+    }
+}
+
+// Bounds-checked cursor over received message bytes. MIG packs its messages to 4 bytes, so reads are unaligned.
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() - self.offset < len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "exception message truncated"));
+        }
+        let taken = &self.bytes[self.offset..(self.offset + len)];
+        self.offset += len;
+        Ok(taken)
+    }
+
+    pub(crate) fn skip(&mut self, len: usize) -> io::Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    pub(crate) fn read_u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const u32) })
+    }
+
+    pub(crate) fn read_u64(&mut self) -> io::Result<u64> {
+        let bytes = self.take(8)?;
+        Ok(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const u64) })
+    }
+
+    pub(crate) fn read_port_descriptor(&mut self) -> io::Result<sys::mach_port_t> {
+        let bytes = self.take(mem::size_of::<sys::mach_msg_port_descriptor_t>())?;
+        let descriptor = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const sys::mach_msg_port_descriptor_t) };
+        if descriptor.type_() != sys::MACH_MSG_PORT_DESCRIPTOR {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "expected port descriptor"));
+        }
+        Ok(descriptor.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_out_of_range() {
+        assert_eq!(ExceptionType::BadAccess.mask(), Some(ExceptionMask::BAD_ACCESS));
+        assert_eq!(ExceptionType::Other(31).mask(), Some(ExceptionMask(1 << 31)));
+        assert_eq!(ExceptionType::Other(32).mask(), None);
+        assert_eq!(ExceptionType::Other(-1).mask(), None);
+        assert_eq!(ExceptionType::Other(sys::exception_type_t::MIN).mask(), None);
+    }
+}
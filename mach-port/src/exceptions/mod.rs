@@ -0,0 +1,30 @@
+//! Receiving, decoding and replying to Mach exception messages.
+//!
+//! Exception ports are registered with [`Task::swap_exception_ports`](crate::Task::swap_exception_ports) or
+//! [`Thread::swap_exception_ports`](crate::Thread::swap_exception_ports), after which the kernel delivers a message to
+//! the port for every matching exception and suspends the faulting thread until a reply arrives. [`ExceptionServer`]
+//! receives those messages, hands them to a handler as an [`Exception`], and sends the reply. Programs with their own
+//! receive loop can use [`dispatch`] instead, which plays the role of the MIG-generated `exc_server`/`mach_exc_server`
+//! without needing to link any generated C code.
+
+mod message;
+mod dispatch;
+mod guard;
+mod resource;
+mod kcdata;
+mod corpse;
+mod forward;
+mod crash_catcher;
+mod signal;
+mod server;
+
+pub use self::message::*;
+pub use self::dispatch::*;
+pub use self::guard::*;
+pub use self::resource::*;
+pub use self::kcdata::{KcdataItem, KcdataIter};
+pub use self::corpse::*;
+pub use self::forward::*;
+pub use self::crash_catcher::*;
+pub use self::signal::*;
+pub use self::server::*;
@@ -0,0 +1,194 @@
+use crate::exceptions::{Exception, ExceptionType};
+
+use std::time::Duration;
+
+// Resource types and flavors from kern/exc_resource.h
+const RESOURCE_TYPE_CPU: u8 = 1;
+const RESOURCE_TYPE_WAKEUPS: u8 = 2;
+const RESOURCE_TYPE_MEMORY: u8 = 3;
+const RESOURCE_TYPE_IO: u8 = 4;
+
+const FLAVOR_CPU_MONITOR_FATAL: u8 = 2;
+const FLAVOR_DIAG_MEMLIMIT: u8 = 2;
+const FLAVOR_IO_LOGICAL_WRITES: u8 = 2;
+
+/// Which memory limit was exceeded in an `EXC_RESOURCE` memory exception.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MemoryLimitKind {
+    /// The task's footprint high watermark (`FLAVOR_HIGH_WATERMARK`).
+    HighWatermark,
+    /// A diagnostic memory limit (`FLAVOR_DIAG_MEMLIMIT`).
+    Diagnostic,
+}
+
+/// The decoded codes of an `EXC_RESOURCE` exception.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ResourceException {
+    /// The task exceeded its CPU usage limit.
+    Cpu {
+        /// Whether the monitor was configured to kill the task (`FLAVOR_CPU_MONITOR_FATAL`).
+        fatal: bool,
+        limit_percent: u8,
+        interval: Duration,
+        observed_percent: u8,
+    },
+    /// The task woke up the CPU more often than permitted.
+    Wakeups {
+        limit_per_second: u32,
+        interval: Duration,
+        observed_per_second: u32,
+    },
+    /// The task's physical footprint exceeded a limit.
+    Memory {
+        kind: MemoryLimitKind,
+        limit_mb: u32,
+    },
+    /// The task wrote more data to storage than permitted.
+    Io {
+        /// Whether the limit applies to logical rather than physical writes.
+        logical: bool,
+        limit_mb: u32,
+        interval: Duration,
+        observed_mb: u32,
+    },
+    /// A resource type this crate does not know how to decode.
+    Other {
+        resource_type: u8,
+        flavor: u8,
+        code: u64,
+        subcode: u64,
+    },
+}
+
+impl ResourceException {
+    /// Decodes the 64-bit codes of an `EXC_RESOURCE` exception.
+    pub fn decode(codes: &[i64]) -> Option<ResourceException> {
+        if codes.len() < 2 {
+            return None;
+        }
+        let code = codes[0] as u64;
+        let subcode = codes[1] as u64;
+        let resource_type = ((code >> 61) & 0x7) as u8;
+        let flavor = ((code >> 58) & 0x7) as u8;
+
+        Some(match resource_type {
+            RESOURCE_TYPE_CPU => ResourceException::Cpu {
+                fatal: flavor == FLAVOR_CPU_MONITOR_FATAL,
+                limit_percent: (code & 0x7f) as u8,
+                interval: Duration::from_secs((code >> 7) & 0x1ff_ffff),
+                observed_percent: (subcode & 0x7f) as u8,
+            },
+            RESOURCE_TYPE_WAKEUPS => ResourceException::Wakeups {
+                limit_per_second: (code & 0xfff) as u32,
+                interval: Duration::from_secs((code >> 20) & 0xf_ffff),
+                observed_per_second: (subcode & 0xf_ffff) as u32,
+            },
+            RESOURCE_TYPE_MEMORY => ResourceException::Memory {
+                kind: if flavor == FLAVOR_DIAG_MEMLIMIT { MemoryLimitKind::Diagnostic } else { MemoryLimitKind::HighWatermark },
+                limit_mb: (code & 0x1fff) as u32,
+            },
+            RESOURCE_TYPE_IO => ResourceException::Io {
+                logical: flavor == FLAVOR_IO_LOGICAL_WRITES,
+                limit_mb: (code & 0x7fff) as u32,
+                interval: Duration::from_secs((code >> 15) & 0x1_ffff),
+                observed_mb: (subcode & 0x7fff) as u32,
+            },
+            _ => ResourceException::Other {
+                resource_type,
+                flavor,
+                code,
+                subcode,
+            },
+        })
+    }
+}
+
+impl Exception {
+    /// Decodes the codes of an `EXC_RESOURCE` exception.
+    ///
+    /// Returns `None` for other exception types, or if the handler was not registered with `MACH_EXCEPTION_CODES`
+    /// (in which case the codes are truncated).
+    pub fn resource(&self) -> Option<ResourceException> {
+        if self.exception_type() != ExceptionType::Resource || !self.behavior().mach_exception_codes() {
+            return None;
+        }
+        ResourceException::decode(self.codes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // EXC_RESOURCE_ENCODE_TYPE and EXC_RESOURCE_ENCODE_FLAVOR from kern/exc_resource.h
+    fn encode(resource_type: u64, flavor: u64, fields: u64) -> i64 {
+        (resource_type << 61 | flavor << 58 | fields) as i64
+    }
+
+    #[test]
+    fn decodes_cpu_monitor() {
+        // EXC_RESOURCE_CPUMONITOR_ENCODE_INTERVAL and EXC_RESOURCE_CPUMONITOR_ENCODE_PERCENTAGE
+        let code = encode(1, 2, 180 << 7 | 50);
+        assert_eq!(ResourceException::decode(&[code, 97]), Some(ResourceException::Cpu {
+            fatal: true,
+            limit_percent: 50,
+            interval: Duration::from_secs(180),
+            observed_percent: 97,
+        }));
+        let code = encode(1, 1, 0x1ff_ffff << 7 | 0x7f);
+        assert_eq!(ResourceException::decode(&[code, 0]), Some(ResourceException::Cpu {
+            fatal: false,
+            limit_percent: 0x7f,
+            interval: Duration::from_secs(0x1ff_ffff),
+            observed_percent: 0,
+        }));
+    }
+
+    #[test]
+    fn decodes_wakeups() {
+        // EXC_RESOURCE_CPUMONITOR_ENCODE_WAKEUPS_PERMITTED and EXC_RESOURCE_CPUMONITOR_ENCODE_OBSERVATION_INTERVAL
+        let code = encode(2, 1, 300 << 20 | 150);
+        assert_eq!(ResourceException::decode(&[code, 45_000]), Some(ResourceException::Wakeups {
+            limit_per_second: 150,
+            interval: Duration::from_secs(300),
+            observed_per_second: 45_000,
+        }));
+    }
+
+    #[test]
+    fn decodes_memory() {
+        // EXC_RESOURCE_HWM_ENCODE_LIMIT
+        assert_eq!(ResourceException::decode(&[encode(3, 1, 850), 0]), Some(ResourceException::Memory {
+            kind: MemoryLimitKind::HighWatermark,
+            limit_mb: 850,
+        }));
+        assert_eq!(ResourceException::decode(&[encode(3, 2, 3072), 0]), Some(ResourceException::Memory {
+            kind: MemoryLimitKind::Diagnostic,
+            limit_mb: 3072,
+        }));
+    }
+
+    #[test]
+    fn decodes_io() {
+        // EXC_RESOURCE_IO_ENCODE_LIMIT and EXC_RESOURCE_IO_ENCODE_INTERVAL
+        let code = encode(4, 2, 86_400 << 15 | 2048);
+        assert_eq!(ResourceException::decode(&[code, 3000]), Some(ResourceException::Io {
+            logical: true,
+            limit_mb: 2048,
+            interval: Duration::from_secs(86_400),
+            observed_mb: 3000,
+        }));
+    }
+
+    #[test]
+    fn keeps_unknown_types_raw() {
+        let code = encode(5, 3, 0x1234);
+        assert_eq!(ResourceException::decode(&[code, 7]), Some(ResourceException::Other {
+            resource_type: 5,
+            flavor: 3,
+            code: code as u64,
+            subcode: 7,
+        }));
+        assert_eq!(ResourceException::decode(&[code]), None);
+    }
+}
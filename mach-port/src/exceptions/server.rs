@@ -0,0 +1,73 @@
+use crate::{Port, MsgBuffer};
+use crate::exceptions::{Exception, dispatch};
+use crate::exceptions::message::MaxRequest;
+use crate::exceptions::dispatch::send_reply;
+
+use std::{io, mem};
+use std::time::Duration;
+
+use mach_sys as sys;
+
+/// The outcome of handling an exception, which determines what the kernel does with the faulting thread.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ExceptionResult {
+    /// The thread is resumed.
+    Handled,
+    /// The kernel tries the next handler in line (thread, task, then host), eventually translating the exception
+    /// into a signal.
+    Unhandled,
+}
+
+/// Receives exception messages on a port and replies to them on behalf of a handler.
+pub struct ExceptionServer {
+    port: Port,
+    buffer: MsgBuffer,
+}
+
+impl ExceptionServer {
+    /// Creates a server listening on `port`, which must hold a receive right.
+    pub fn new(port: Port) -> ExceptionServer {
+        let mut buffer = MsgBuffer::new();
+        buffer.reserve_inline_data(mem::size_of::<MaxRequest>());
+        ExceptionServer {
+            port,
+            buffer,
+        }
+    }
+
+    /// Creates a server listening on a newly allocated port.
+    ///
+    /// Use [`Port::make_sender`] on [`port`](ExceptionServer::port) to obtain the send right to register.
+    pub fn create() -> io::Result<ExceptionServer> {
+        Ok(ExceptionServer::new(Port::new()?))
+    }
+
+    #[inline]
+    pub fn port(&self) -> &Port {
+        &self.port
+    }
+
+    /// Waits for a single exception message, invokes `handler` on it, and replies.
+    pub fn handle_one<F>(&mut self, timeout: Option<Duration>, handler: F) -> io::Result<()> where
+        F: FnOnce(&mut Exception) -> ExceptionResult,
+    {
+        self.buffer.reset();
+        self.port.recv(&mut self.buffer, timeout)?;
+
+        if !dispatch(&self.buffer, handler)? {
+            let header = *self.buffer.header();
+            warn!("received non-exception message with ID {} on exception port", header.msgh_id);
+            send_reply(&header, sys::MIG_BAD_ID, None)?;
+        }
+        Ok(())
+    }
+
+    /// Handles exception messages until an error occurs.
+    pub fn run<F>(&mut self, mut handler: F) -> io::Result<()> where
+        F: FnMut(&mut Exception) -> ExceptionResult,
+    {
+        loop {
+            self.handle_one(None, &mut handler)?;
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::ExceptionMask;
+use crate::exceptions::{Exception, ExceptionType, ExceptionResult};
+
+use std::io;
+use std::os::raw::{c_int, c_void};
+
+use mach_sys as sys;
+
+// Darwin signal numbers
+const SIGILL: c_int = 4;
+const SIGTRAP: c_int = 5;
+const SIGABRT: c_int = 6;
+const SIGEMT: c_int = 7;
+const SIGFPE: c_int = 8;
+const SIGBUS: c_int = 10;
+const SIGSEGV: c_int = 11;
+const SIGSYS: c_int = 12;
+const SIGPIPE: c_int = 13;
+
+// The pthread has exited (sys/errno.h)
+const ESRCH: c_int = 3;
+
+// EXC_SOFTWARE codes generated by the BSD layer (sys/ux_exception.h)
+const EXC_UNIX_BAD_SYSCALL: i64 = 0x10000;
+const EXC_UNIX_BAD_PIPE: i64 = 0x10001;
+const EXC_UNIX_ABORT: i64 = 0x10002;
+const EXC_SOFT_SIGNAL: i64 = 0x10003;
+
+extern "C" {
+    fn pthread_from_mach_thread_np(thread: sys::mach_port_t) -> *mut c_void;
+    fn pthread_kill(thread: *mut c_void, sig: c_int) -> c_int;
+}
+
+/// Computes the signal the kernel would deliver for an exception if no Mach handler claimed it, mirroring
+/// `ux_exception` in the BSD layer.
+pub fn signal_for_exception(exception: &Exception) -> Option<c_int> {
+    let code = exception.codes().first().cloned().unwrap_or(0);
+    Some(match exception.exception_type() {
+        ExceptionType::BadAccess => if code == sys::KERN_INVALID_ADDRESS as i64 { SIGSEGV } else { SIGBUS },
+        ExceptionType::BadInstruction => SIGILL,
+        ExceptionType::Arithmetic => SIGFPE,
+        ExceptionType::Emulation => SIGEMT,
+        ExceptionType::Breakpoint => SIGTRAP,
+        ExceptionType::Software => match code {
+            EXC_UNIX_BAD_SYSCALL => SIGSYS,
+            EXC_UNIX_BAD_PIPE => SIGPIPE,
+            EXC_UNIX_ABORT => SIGABRT,
+            EXC_SOFT_SIGNAL => return exception.codes().get(1).map(|&signal| signal as c_int),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+/// How [`SignalBridge`] turns exceptions into signals.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SignalMode {
+    /// Report the exception as unhandled, so the kernel performs its usual translation into a signal (assuming no
+    /// other Mach handler claims it first).
+    FallThrough,
+    /// Send the corresponding signal to the faulting thread directly with `pthread_kill` and report the exception as
+    /// handled. Only possible for exceptions raised by threads in the current task; others fall through.
+    Raise,
+}
+
+/// Hands selected exception types over to signal-based handling, for programs that mix Mach exception handlers with
+/// signal handlers.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SignalBridge {
+    mask: ExceptionMask,
+    mode: SignalMode,
+}
+
+impl SignalBridge {
+    pub fn new(mask: ExceptionMask, mode: SignalMode) -> SignalBridge {
+        SignalBridge { mask, mode }
+    }
+
+    /// Handles `exception` if its type is covered by the bridge, returning the result to reply with. Returns `None`
+    /// if the exception should be handled some other way.
+    pub fn handle(&self, exception: &Exception) -> Option<ExceptionResult> {
+        match exception.exception_type().mask() {
+            Some(mask) if self.mask.contains(mask) => (),
+            _ => return None,
+        }
+        if self.mode == SignalMode::Raise {
+            let local_thread = match (exception.task(), exception.thread()) {
+                (Some(task), Some(thread)) if task.as_raw_port() == mach_core::self_port::task_self() => Some(thread),
+                _ => None,
+            };
+            if let (Some(thread), Some(signal)) = (local_thread, signal_for_exception(exception)) {
+                let code = unsafe {
+                    let pthread = pthread_from_mach_thread_np(thread.as_raw_port());
+                    if pthread.is_null() { ESRCH } else { pthread_kill(pthread, signal) }
+                };
+                if code == 0 {
+                    return Some(ExceptionResult::Handled);
+                }
+                warn!("raising signal {} for exception {:?} failed, falling through: {}", signal, exception.exception_type(), io::Error::from_raw_os_error(code));
+            }
+        }
+        Some(ExceptionResult::Unhandled)
+    }
+}
@@ -0,0 +1,266 @@
+//! Querying information about the host the current task is running on.
+
+use crate::RawPort;
+
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+pub use crate::host_priv::HostPriv;
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+pub use crate::host_special_ports::HostSpecialPort;
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+pub use crate::zone_info::{ZoneInfo, MemoryInfo};
+
+use std::{io, mem, slice, fmt};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// A send right to a host name port, which allows querying (but not changing) host-wide information.
+pub struct Host {
+    port: sys::host_t,
+    owned: bool,
+}
+
+impl Drop for Host {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                let _ = mach_call!(log op: "mach_port_deallocate(host port)", port: self.port, sys::mach_port_deallocate(mach_core::self_port::task_self(), self.port));
+            }
+        }
+    }
+}
+
+impl Host {
+    /// Gets a handle to the host the current task is running on.
+    ///
+    /// The port is borrowed from a process-wide cache and will not be deallocated when the [`Host`] is dropped.
+    pub fn current() -> Host {
+        Host {
+            port: mach_core::self_port::host_self(),
+            owned: false,
+        }
+    }
+
+    /// Takes ownership of a send right to a host port.
+    pub unsafe fn from_raw_port(port: RawPort) -> Host {
+        Host {
+            port,
+            owned: true,
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        let port = self.port;
+        mem::forget(self);
+        port
+    }
+
+    /// Retrieves the host's processor and memory configuration (`HOST_BASIC_INFO`).
+    pub fn basic_info(&self) -> io::Result<HostBasicInfo> {
+        unsafe {
+            let info: sys::host_basic_info_data_t = self.info(sys::HOST_BASIC_INFO)?;
+            Ok(HostBasicInfo {
+                max_cpus: info.max_cpus as u32,
+                avail_cpus: info.avail_cpus as u32,
+                cpu_type: info.cpu_type,
+                cpu_subtype: info.cpu_subtype,
+                cpu_threadtype: info.cpu_threadtype,
+                physical_cpu: info.physical_cpu as u32,
+                physical_cpu_max: info.physical_cpu_max as u32,
+                logical_cpu: info.logical_cpu as u32,
+                logical_cpu_max: info.logical_cpu_max as u32,
+                max_mem: info.max_mem,
+            })
+        }
+    }
+
+    /// Gets the kernel's version string (e.g. `Darwin Kernel Version 23.1.0: ...; root:xnu-10002.41.9~6/RELEASE_ARM64_T6000`).
+    pub fn kernel_version(&self) -> io::Result<String> {
+        unsafe {
+            let mut version: sys::kernel_version_t = mem::zeroed();
+            mach_call!(log op: "host_kernel_version", port: self.port, sys::host_kernel_version(self.port, version.as_mut_ptr()))?;
+            // The buffer is NUL-terminated unless the kernel filled it completely
+            let bytes = slice::from_raw_parts(version.as_ptr() as *const u8, version.len());
+            let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            Ok(String::from_utf8_lossy(&bytes[..len]).trim_end().to_owned())
+        }
+    }
+
+    /// Gets the size of a virtual memory page, in bytes.
+    pub fn page_size(&self) -> io::Result<u64> {
+        unsafe {
+            let mut page_size: sys::vm_size_t = 0;
+            mach_call!(log op: "host_page_size", port: self.port, sys::host_page_size(self.port, &mut page_size))?;
+            Ok(page_size as u64)
+        }
+    }
+
+    /// Retrieves system-wide virtual memory statistics (`HOST_VM_INFO64`).
+    pub fn vm_statistics(&self) -> io::Result<VmStatistics> {
+        unsafe {
+            let page_size = self.page_size()?;
+            let mut info: sys::vm_statistics64_data_t = mem::zeroed();
+            let mut count = (mem::size_of::<sys::vm_statistics64_data_t>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+            mach_call!(log op: "host_statistics64", port: self.port, sys::host_statistics64(self.port, sys::HOST_VM_INFO64 as _, &mut info as *mut _ as sys::host_info64_t, &mut count))?;
+            Ok(VmStatistics {
+                page_size,
+                free_count: info.free_count as u64,
+                active_count: info.active_count as u64,
+                inactive_count: info.inactive_count as u64,
+                wire_count: info.wire_count as u64,
+                speculative_count: info.speculative_count as u64,
+                throttled_count: info.throttled_count as u64,
+                purgeable_count: info.purgeable_count as u64,
+                external_page_count: info.external_page_count as u64,
+                internal_page_count: info.internal_page_count as u64,
+                compressor_page_count: info.compressor_page_count as u64,
+                total_uncompressed_pages_in_compressor: info.total_uncompressed_pages_in_compressor,
+                zero_fill_count: info.zero_fill_count,
+                reactivations: info.reactivations,
+                pageins: info.pageins,
+                pageouts: info.pageouts,
+                faults: info.faults,
+                cow_faults: info.cow_faults,
+                lookups: info.lookups,
+                hits: info.hits,
+                purges: info.purges,
+                decompressions: info.decompressions,
+                compressions: info.compressions,
+                swapins: info.swapins,
+                swapouts: info.swapouts,
+            })
+        }
+    }
+
+    /// Retrieves the number of scheduler ticks all processors have spent in each state (`HOST_CPU_LOAD_INFO`).
+    pub fn cpu_load(&self) -> io::Result<CpuLoadInfo> {
+        unsafe {
+            let info: sys::host_cpu_load_info_data_t = self.statistics(sys::HOST_CPU_LOAD_INFO)?;
+            Ok(CpuLoadInfo {
+                user: info.cpu_ticks[sys::CPU_STATE_USER as usize] as u64,
+                system: info.cpu_ticks[sys::CPU_STATE_SYSTEM as usize] as u64,
+                idle: info.cpu_ticks[sys::CPU_STATE_IDLE as usize] as u64,
+                nice: info.cpu_ticks[sys::CPU_STATE_NICE as usize] as u64,
+            })
+        }
+    }
+
+    /// Retrieves the load averages and Mach factors over the last 5, 30 and 60 seconds (`HOST_LOAD_INFO`).
+    pub fn load_info(&self) -> io::Result<LoadInfo> {
+        unsafe {
+            let info: sys::host_load_info_data_t = self.statistics(sys::HOST_LOAD_INFO)?;
+            let scale = sys::LOAD_SCALE as f64;
+            Ok(LoadInfo {
+                avenrun: [info.avenrun[0] as f64 / scale, info.avenrun[1] as f64 / scale, info.avenrun[2] as f64 / scale],
+                mach_factor: [info.mach_factor[0] as f64 / scale, info.mach_factor[1] as f64 / scale, info.mach_factor[2] as f64 / scale],
+            })
+        }
+    }
+
+    /// Retrieves a `host_info` structure of the given flavor.
+    ///
+    /// The caller must ensure `T` is the structure corresponding to `flavor`.
+    pub(crate) unsafe fn info<T>(&self, flavor: u32) -> io::Result<T> {
+        let mut info: T = mem::zeroed();
+        let mut count = (mem::size_of::<T>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+        mach_call!(log op: "host_info", port: self.port, sys::host_info(self.port, flavor as _, &mut info as *mut T as sys::host_info_t, &mut count))?;
+        Ok(info)
+    }
+
+    /// Retrieves a `host_statistics` structure of the given flavor.
+    ///
+    /// The caller must ensure `T` is the structure corresponding to `flavor`.
+    pub(crate) unsafe fn statistics<T>(&self, flavor: u32) -> io::Result<T> {
+        let mut info: T = mem::zeroed();
+        let mut count = (mem::size_of::<T>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+        mach_call!(log op: "host_statistics", port: self.port, sys::host_statistics(self.port, flavor as _, &mut info as *mut T as sys::host_info_t, &mut count))?;
+        Ok(info)
+    }
+}
+
+impl fmt::Debug for Host {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Host")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
+
+/// The host's processor and memory configuration.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HostBasicInfo {
+    pub max_cpus: u32,
+    pub avail_cpus: u32,
+    pub cpu_type: sys::cpu_type_t,
+    pub cpu_subtype: sys::cpu_subtype_t,
+    pub cpu_threadtype: sys::cpu_threadtype_t,
+    pub physical_cpu: u32,
+    pub physical_cpu_max: u32,
+    pub logical_cpu: u32,
+    pub logical_cpu_max: u32,
+    /// The amount of physical memory, in bytes.
+    pub max_mem: u64,
+}
+
+/// System-wide virtual memory statistics. Counts are in pages; cumulative event counters are since boot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct VmStatistics {
+    pub page_size: u64,
+    pub free_count: u64,
+    pub active_count: u64,
+    pub inactive_count: u64,
+    pub wire_count: u64,
+    pub speculative_count: u64,
+    pub throttled_count: u64,
+    pub purgeable_count: u64,
+    /// Pages backed by files.
+    pub external_page_count: u64,
+    /// Anonymous pages.
+    pub internal_page_count: u64,
+    /// Pages occupied by the compressor.
+    pub compressor_page_count: u64,
+    /// Pages that have been compressed into the compressor's pages.
+    pub total_uncompressed_pages_in_compressor: u64,
+    pub zero_fill_count: u64,
+    pub reactivations: u64,
+    pub pageins: u64,
+    pub pageouts: u64,
+    pub faults: u64,
+    pub cow_faults: u64,
+    pub lookups: u64,
+    pub hits: u64,
+    pub purges: u64,
+    pub decompressions: u64,
+    pub compressions: u64,
+    pub swapins: u64,
+    pub swapouts: u64,
+}
+
+/// Scheduler ticks spent in each processor state, summed over all processors since boot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CpuLoadInfo {
+    pub user: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub nice: u64,
+}
+
+impl CpuLoadInfo {
+    #[inline]
+    pub fn total(&self) -> u64 {
+        self.user + self.system + self.idle + self.nice
+    }
+}
+
+/// The scheduler's view of system load.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LoadInfo {
+    /// The average number of runnable threads over the last 5, 30 and 60 seconds.
+    pub avenrun: [f64; 3],
+    /// The average amount of idle processing capacity (in processors) over the same periods.
+    pub mach_factor: [f64; 3],
+}
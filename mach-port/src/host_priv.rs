@@ -0,0 +1,60 @@
+use crate::RawPort;
+use crate::host::Host;
+
+use std::{io, fmt};
+use std::ops::Deref;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// An owned send right to the host privileged port.
+///
+/// Operations that need the privileged port (enumerating all tasks, kernel zone diagnostics, host special ports) are
+/// only available on this type. It also dereferences to [`Host`], as the privileged port can be used wherever the
+/// host name port can.
+pub struct HostPriv {
+    host: Host,
+}
+
+impl HostPriv {
+    /// Obtains the host privileged port, which is only available when running as root.
+    pub fn get() -> io::Result<HostPriv> {
+        unsafe {
+            let mut port: sys::host_priv_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "host_get_host_priv_port", sys::host_get_host_priv_port(mach_core::self_port::host_self(), &mut port))?;
+            Ok(HostPriv::from_raw_port(port))
+        }
+    }
+
+    /// Takes ownership of a send right to the host privileged port.
+    pub unsafe fn from_raw_port(port: RawPort) -> HostPriv {
+        HostPriv {
+            host: Host::from_raw_port(port),
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.host.as_raw_port()
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        self.host.into_raw_port()
+    }
+}
+
+impl Deref for HostPriv {
+    type Target = Host;
+
+    #[inline]
+    fn deref(&self) -> &Host {
+        &self.host
+    }
+}
+
+impl fmt::Debug for HostPriv {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HostPriv")
+            .field("port", &format_args!("{:#x?}", self.as_raw_port()))
+            .finish()
+    }
+}
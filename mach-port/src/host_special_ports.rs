@@ -0,0 +1,93 @@
+use crate::Port;
+use crate::host::HostPriv;
+
+use std::io;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Identifies one of the host's special ports, through which system daemons publish host-level services.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HostSpecialPort {
+    Host,
+    HostPriv,
+    IoMaster,
+    DynamicPager,
+    AuditControl,
+    UserNotification,
+    Automountd,
+    Lockd,
+    KtraceBackground,
+    Seatbelt,
+    Kextd,
+    Launchctl,
+    Unfreed,
+    Amfid,
+    Gssd,
+    Telemetry,
+    AtmNotification,
+    Coalition,
+    Sysdiagnose,
+    XpcException,
+    Containerd,
+    Node,
+    ResourceNotify,
+    Closured,
+    Syspolicyd,
+    Other(i32),
+}
+
+impl HostSpecialPort {
+    fn as_raw(self) -> i32 {
+        (match self {
+            HostSpecialPort::Host => sys::HOST_PORT,
+            HostSpecialPort::HostPriv => sys::HOST_PRIV_PORT,
+            HostSpecialPort::IoMaster => sys::HOST_IO_MASTER_PORT,
+            HostSpecialPort::DynamicPager => sys::HOST_DYNAMIC_PAGER_PORT,
+            HostSpecialPort::AuditControl => sys::HOST_AUDIT_CONTROL_PORT,
+            HostSpecialPort::UserNotification => sys::HOST_USER_NOTIFICATION_PORT,
+            HostSpecialPort::Automountd => sys::HOST_AUTOMOUNTD_PORT,
+            HostSpecialPort::Lockd => sys::HOST_LOCKD_PORT,
+            HostSpecialPort::KtraceBackground => sys::HOST_KTRACE_BACKGROUND_PORT,
+            HostSpecialPort::Seatbelt => sys::HOST_SEATBELT_PORT,
+            HostSpecialPort::Kextd => sys::HOST_KEXTD_PORT,
+            HostSpecialPort::Launchctl => sys::HOST_LAUNCHCTL_PORT,
+            HostSpecialPort::Unfreed => sys::HOST_UNFREED_PORT,
+            HostSpecialPort::Amfid => sys::HOST_AMFID_PORT,
+            HostSpecialPort::Gssd => sys::HOST_GSSD_PORT,
+            HostSpecialPort::Telemetry => sys::HOST_TELEMETRY_PORT,
+            HostSpecialPort::AtmNotification => sys::HOST_ATM_NOTIFICATION_PORT,
+            HostSpecialPort::Coalition => sys::HOST_COALITION_PORT,
+            HostSpecialPort::Sysdiagnose => sys::HOST_SYSDIAGNOSE_PORT,
+            HostSpecialPort::XpcException => sys::HOST_XPC_EXCEPTION_PORT,
+            HostSpecialPort::Containerd => sys::HOST_CONTAINERD_PORT,
+            HostSpecialPort::Node => sys::HOST_NODE_PORT,
+            HostSpecialPort::ResourceNotify => sys::HOST_RESOURCE_NOTIFY_PORT,
+            HostSpecialPort::Closured => sys::HOST_CLOSURED_PORT,
+            HostSpecialPort::Syspolicyd => sys::HOST_SYSPOLICYD_PORT,
+            HostSpecialPort::Other(raw) => return raw,
+        }) as i32
+    }
+}
+
+impl HostPriv {
+    /// Retrieves a send right to one of the host's special ports, or `None` if it is not set.
+    pub fn special_port(&self, which: HostSpecialPort) -> io::Result<Option<Port>> {
+        unsafe {
+            let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "host_get_special_port", port: self.as_raw_port(), sys::host_get_special_port(self.as_raw_port(), sys::HOST_LOCAL_NODE, which.as_raw(), &mut port))?;
+            if port == sys::MACH_PORT_NULL {
+                return Ok(None);
+            }
+            Ok(Some(Port::from_raw_send_right(port)))
+        }
+    }
+
+    /// Registers a copy of the send right `port` as one of the host's special ports.
+    pub fn set_special_port(&self, which: HostSpecialPort, port: &Port) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "host_set_special_port", port: self.as_raw_port(), sys::host_set_special_port(self.as_raw_port(), which.as_raw(), port.as_raw_port()))?;
+        }
+        Ok(())
+    }
+}
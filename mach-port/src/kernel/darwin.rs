@@ -0,0 +1,46 @@
+use crate::RawPort;
+use crate::kernel::Kernel;
+
+use mach_sys as sys;
+
+pub(crate) struct Darwin;
+
+impl Kernel for Darwin {
+    fn port_allocate_receive(&self, name: &mut RawPort) -> sys::kern_return_t {
+        unsafe { sys::mach_port_allocate(mach_core::self_port::task_self(), sys::MACH_PORT_RIGHT_RECEIVE, name) }
+    }
+
+    fn port_mod_refs(&self, name: RawPort, right: sys::mach_port_right_t, delta: sys::mach_port_delta_t) -> sys::kern_return_t {
+        unsafe { sys::mach_port_mod_refs(mach_core::self_port::task_self(), name, right, delta) }
+    }
+
+    fn port_deallocate(&self, name: RawPort) -> sys::kern_return_t {
+        unsafe { sys::mach_port_deallocate(mach_core::self_port::task_self(), name) }
+    }
+
+    fn port_type(&self, name: RawPort, ty: &mut sys::mach_port_type_t) -> sys::kern_return_t {
+        unsafe { sys::mach_port_type(mach_core::self_port::task_self(), name, ty) }
+    }
+
+    fn port_make_send(&self, name: RawPort, port: &mut RawPort, right: &mut sys::mach_msg_type_name_t) -> sys::kern_return_t {
+        unsafe { sys::mach_port_extract_right(mach_core::self_port::task_self(), name, sys::MACH_MSG_TYPE_MAKE_SEND, port, right) }
+    }
+
+    fn port_request_notification(&self, name: RawPort, id: sys::mach_msg_id_t, notify: RawPort, notify_disposition: sys::mach_msg_type_name_t, previous: &mut RawPort) -> sys::kern_return_t {
+        unsafe { sys::mach_port_request_notification(mach_core::self_port::task_self(), name, id, 0, notify, notify_disposition, previous) }
+    }
+
+    #[inline]
+    unsafe fn msg(
+        &self,
+        msg: *mut sys::mach_msg_header_t,
+        option: sys::mach_msg_option_t,
+        send_size: sys::mach_msg_size_t,
+        rcv_size: sys::mach_msg_size_t,
+        rcv_name: RawPort,
+        timeout: sys::mach_msg_timeout_t,
+        notify: RawPort,
+    ) -> sys::mach_msg_return_t {
+        sys::mach_msg(msg, option, send_size, rcv_size, rcv_name, timeout, notify)
+    }
+}
@@ -0,0 +1,697 @@
+use crate::RawPort;
+use crate::kernel::Kernel;
+
+use std::{mem, ptr, slice};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use mach_sys as sys;
+
+// Matches MACH_PORT_QLIMIT_DEFAULT
+const QUEUE_LIMIT: usize = 5;
+
+pub(crate) struct Fake;
+
+// The whole fake namespace sits behind one lock, with a single condition variable signalled whenever a queue changes
+// or a port dies. Tests don't need anything finer grained.
+static SPACE: Mutex<Option<Space>> = Mutex::new(None);
+static CHANGED: Condvar = Condvar::new();
+
+struct Space {
+    next_name: RawPort,
+    entries: HashMap<RawPort, Entry>,
+}
+
+// Every right to a port shares a single name within a space, so one entry tracks all of them
+#[derive(Default)]
+struct Entry {
+    receive: bool,
+    send_refs: u32,
+    send_once_refs: u32,
+    dead_refs: u32,
+    queue: VecDeque<Vec<u8>>,
+    // Send-once rights to the ports that asked to be told when this one dies
+    dead_name_requests: Vec<RawPort>,
+}
+
+impl Entry {
+    fn is_unused(&self) -> bool {
+        !self.receive && self.send_refs == 0 && self.send_once_refs == 0 && self.dead_refs == 0
+    }
+}
+
+#[repr(C)]
+struct DeadNameNotification {
+    header: sys::mach_msg_header_t,
+    ndr: sys::NDR_record_t,
+    name: sys::mach_port_name_t,
+}
+
+fn lock() -> MutexGuard<'static, Option<Space>> {
+    let mut space = SPACE.lock().unwrap();
+    if space.is_none() {
+        *space = Some(Space {
+            next_name: 0x103,
+            entries: HashMap::new(),
+        });
+    }
+    space
+}
+
+impl Space {
+    fn entry(&mut self, name: RawPort) -> Option<&mut Entry> {
+        self.entries.get_mut(&name)
+    }
+
+    fn remove_if_unused(&mut self, name: RawPort) {
+        if self.entries.get(&name).map(Entry::is_unused).unwrap_or(false) {
+            self.entries.remove(&name);
+        }
+    }
+
+    // Destroys the receive right for `name`, turning any remaining send rights into a dead name
+    fn destroy_receive(&mut self, name: RawPort) {
+        let requests = {
+            let entry = self.entries.get_mut(&name).unwrap();
+            entry.receive = false;
+            entry.queue.clear();
+            entry.dead_refs += entry.send_refs + entry.send_once_refs;
+            entry.send_refs = 0;
+            entry.send_once_refs = 0;
+            mem::take(&mut entry.dead_name_requests)
+        };
+        for notify in requests {
+            let mut notification: DeadNameNotification = unsafe { mem::zeroed() };
+            notification.header.msgh_bits = sys::MACH_MSGH_BITS(0, sys::MACH_MSG_TYPE_PORT_SEND_ONCE);
+            notification.header.msgh_size = mem::size_of::<DeadNameNotification>() as _;
+            notification.header.msgh_local_port = notify;
+            notification.header.msgh_id = sys::MACH_NOTIFY_DEAD_NAME as _;
+            notification.name = name;
+            if let Some(entry) = self.entry(notify) {
+                // Delivering the notification consumes the send-once right
+                entry.send_once_refs = entry.send_once_refs.saturating_sub(1);
+                if entry.receive {
+                    let bytes = unsafe { slice::from_raw_parts(&notification as *const _ as *const u8, mem::size_of::<DeadNameNotification>()) };
+                    entry.queue.push_back(bytes.to_vec());
+                }
+            }
+            self.remove_if_unused(notify);
+        }
+        self.remove_if_unused(name);
+        CHANGED.notify_all();
+    }
+
+    // Checks that `name` holds the right needed to send it with `disposition`
+    fn check_copyin(&mut self, name: RawPort, disposition: sys::mach_msg_type_name_t) -> bool {
+        let entry = match self.entry(name) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        match disposition {
+            sys::MACH_MSG_TYPE_MOVE_RECEIVE | sys::MACH_MSG_TYPE_MAKE_SEND | sys::MACH_MSG_TYPE_MAKE_SEND_ONCE => entry.receive,
+            sys::MACH_MSG_TYPE_MOVE_SEND | sys::MACH_MSG_TYPE_COPY_SEND => entry.send_refs > 0,
+            sys::MACH_MSG_TYPE_MOVE_SEND_ONCE => entry.send_once_refs > 0,
+            _ => false,
+        }
+    }
+
+    // Transfers a right carried by a message. Since sender and receiver share the namespace, moved rights stay where
+    // they are and copied or made rights gain a reference. Returns the disposition the receiver sees.
+    fn copyin_copyout(&mut self, name: RawPort, disposition: sys::mach_msg_type_name_t) -> sys::mach_msg_type_name_t {
+        let entry = self.entry(name).unwrap();
+        match disposition {
+            sys::MACH_MSG_TYPE_COPY_SEND | sys::MACH_MSG_TYPE_MAKE_SEND => entry.send_refs += 1,
+            sys::MACH_MSG_TYPE_MAKE_SEND_ONCE => entry.send_once_refs += 1,
+            _ => (),
+        }
+        match disposition {
+            sys::MACH_MSG_TYPE_MOVE_RECEIVE => sys::MACH_MSG_TYPE_PORT_RECEIVE,
+            sys::MACH_MSG_TYPE_MOVE_SEND_ONCE | sys::MACH_MSG_TYPE_MAKE_SEND_ONCE => sys::MACH_MSG_TYPE_PORT_SEND_ONCE,
+            _ => sys::MACH_MSG_TYPE_PORT_SEND,
+        }
+    }
+}
+
+// Walks the port descriptors of a message held in `bytes`, which must be complex
+fn port_descriptors(bytes: &mut [u8]) -> Option<Vec<*mut sys::mach_msg_port_descriptor_t>> {
+    let header_len = mem::size_of::<sys::mach_msg_header_t>();
+    if bytes.len() < header_len + mem::size_of::<sys::mach_msg_body_t>() {
+        return None;
+    }
+    let count = unsafe { ptr::read_unaligned(bytes.as_ptr().add(header_len) as *const sys::mach_msg_body_t) }.msgh_descriptor_count as usize;
+    let mut offset = header_len + mem::size_of::<sys::mach_msg_body_t>();
+    let mut descriptors = Vec::with_capacity(count);
+    for _ in 0..count {
+        if bytes.len() - offset < mem::size_of::<sys::mach_msg_port_descriptor_t>() {
+            return None;
+        }
+        let descriptor = unsafe { bytes.as_mut_ptr().add(offset) as *mut sys::mach_msg_port_descriptor_t };
+        let descriptor_type = unsafe { ptr::read_unaligned(descriptor) }.type_();
+        if descriptor_type != sys::MACH_MSG_PORT_DESCRIPTOR {
+            // Out-of-line memory and port arrays are not supported
+            return None;
+        }
+        descriptors.push(descriptor);
+        offset += mem::size_of::<sys::mach_msg_port_descriptor_t>();
+    }
+    Some(descriptors)
+}
+
+impl Fake {
+    unsafe fn send(&self, msg: *mut sys::mach_msg_header_t, option: sys::mach_msg_option_t, send_size: sys::mach_msg_size_t, timeout: sys::mach_msg_timeout_t) -> sys::mach_msg_return_t {
+        let header = *msg;
+        if (send_size as usize) < mem::size_of::<sys::mach_msg_header_t>() || header.msgh_size != send_size {
+            return sys::MACH_SEND_MSG_TOO_SMALL as _;
+        }
+        let mut bytes = slice::from_raw_parts(msg as *const u8, send_size as usize).to_vec();
+        let complex = header.msgh_bits & sys::MACH_MSGH_BITS_COMPLEX != 0;
+        let remote_disposition = sys::MACH_MSGH_BITS_REMOTE(header.msgh_bits);
+        let local_disposition = sys::MACH_MSGH_BITS_LOCAL(header.msgh_bits);
+        let dest = header.msgh_remote_port;
+        let reply = header.msgh_local_port;
+
+        let deadline = if option & sys::MACH_SEND_TIMEOUT as sys::mach_msg_option_t != 0 {
+            Some(Instant::now() + Duration::from_millis(timeout as u64))
+        } else {
+            None
+        };
+        let mut space_guard = lock();
+        loop {
+            let space = space_guard.as_mut().unwrap();
+            match space.entry(dest) {
+                Some(entry) if entry.receive && entry.queue.len() >= QUEUE_LIMIT => (),
+                Some(_) => break,
+                None => return sys::MACH_SEND_INVALID_DEST as _,
+            }
+            // The destination's queue is full
+            space_guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return sys::MACH_SEND_TIMED_OUT as _;
+                    }
+                    CHANGED.wait_timeout(space_guard, deadline - now).unwrap().0
+                },
+                None => CHANGED.wait(space_guard).unwrap(),
+            };
+        }
+        let space = space_guard.as_mut().unwrap();
+
+        // Validate every right before transferring any of them
+        if !space.check_copyin(dest, remote_disposition) {
+            return sys::MACH_SEND_INVALID_DEST as _;
+        }
+        if reply != sys::MACH_PORT_NULL && !space.check_copyin(reply, local_disposition) {
+            return sys::MACH_SEND_INVALID_REPLY as _;
+        }
+        let descriptors = if complex {
+            match port_descriptors(&mut bytes) {
+                Some(descriptors) => descriptors,
+                None => return sys::MACH_SEND_INVALID_TYPE as _,
+            }
+        } else {
+            Vec::new()
+        };
+        for &descriptor in descriptors.iter() {
+            let descriptor = ptr::read_unaligned(descriptor);
+            if descriptor.name != sys::MACH_PORT_NULL && !space.check_copyin(descriptor.name, descriptor.disposition()) {
+                return sys::MACH_SEND_INVALID_RIGHT as _;
+            }
+        }
+
+        match remote_disposition {
+            sys::MACH_MSG_TYPE_MOVE_SEND => space.entry(dest).unwrap().send_refs -= 1,
+            sys::MACH_MSG_TYPE_MOVE_SEND_ONCE => space.entry(dest).unwrap().send_once_refs -= 1,
+            _ => (),
+        }
+        let reply_type = if reply != sys::MACH_PORT_NULL {
+            space.copyin_copyout(reply, local_disposition)
+        } else {
+            0
+        };
+        for &descriptor_ptr in descriptors.iter() {
+            let mut descriptor = ptr::read_unaligned(descriptor_ptr);
+            if descriptor.name != sys::MACH_PORT_NULL {
+                let disposition = space.copyin_copyout(descriptor.name, descriptor.disposition());
+                descriptor.set_disposition(disposition);
+                ptr::write_unaligned(descriptor_ptr, descriptor);
+            }
+        }
+
+        // Rewrite the header the way the receiver sees it
+        let mut received = header;
+        received.msgh_bits = sys::MACH_MSGH_BITS(reply_type, sys::MACH_MSG_TYPE_PORT_SEND) | (header.msgh_bits & sys::MACH_MSGH_BITS_COMPLEX);
+        received.msgh_remote_port = reply;
+        received.msgh_local_port = dest;
+        ptr::write_unaligned(bytes.as_mut_ptr() as *mut sys::mach_msg_header_t, received);
+
+        space.remove_if_unused(dest);
+        if let Some(entry) = space.entry(dest) {
+            if entry.receive {
+                entry.queue.push_back(bytes);
+            }
+        }
+        CHANGED.notify_all();
+        sys::MACH_MSG_SUCCESS as _
+    }
+
+    unsafe fn receive(&self, msg: *mut sys::mach_msg_header_t, option: sys::mach_msg_option_t, rcv_size: sys::mach_msg_size_t, rcv_name: RawPort, timeout: sys::mach_msg_timeout_t) -> sys::mach_msg_return_t {
+        let trailer_size = mem::size_of::<sys::mach_msg_trailer_t>();
+        let deadline = if option & sys::MACH_RCV_TIMEOUT as sys::mach_msg_option_t != 0 {
+            Some(Instant::now() + Duration::from_millis(timeout as u64))
+        } else {
+            None
+        };
+        let mut space_guard = lock();
+        loop {
+            let space = space_guard.as_mut().unwrap();
+            let entry = match space.entry(rcv_name) {
+                Some(entry) if entry.receive => entry,
+                // The receive right was destroyed while waiting
+                Some(_) | None => return sys::MACH_RCV_INVALID_NAME as _,
+            };
+            if let Some(len) = entry.queue.front().map(Vec::len) {
+                if (rcv_size as usize) < len + trailer_size {
+                    if option & sys::MACH_RCV_LARGE as sys::mach_msg_option_t != 0 {
+                        // The message stays queued, and the caller learns how much space it needs
+                        (*msg).msgh_size = (len + trailer_size) as _;
+                    } else {
+                        entry.queue.pop_front();
+                        CHANGED.notify_all();
+                    }
+                    return sys::MACH_RCV_TOO_LARGE as _;
+                }
+                let bytes = entry.queue.pop_front().unwrap();
+                ptr::copy_nonoverlapping(bytes.as_ptr(), msg as *mut u8, len);
+                let trailer = sys::mach_msg_trailer_t {
+                    msgh_trailer_type: sys::MACH_MSG_TRAILER_FORMAT_0 as _,
+                    msgh_trailer_size: trailer_size as _,
+                };
+                ptr::write_unaligned((msg as *mut u8).add(len) as *mut sys::mach_msg_trailer_t, trailer);
+                CHANGED.notify_all();
+                return sys::MACH_MSG_SUCCESS as _;
+            }
+            space_guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return sys::MACH_RCV_TIMED_OUT as _;
+                    }
+                    CHANGED.wait_timeout(space_guard, deadline - now).unwrap().0
+                },
+                None => CHANGED.wait(space_guard).unwrap(),
+            };
+        }
+    }
+}
+
+impl Kernel for Fake {
+    fn port_allocate_receive(&self, name: &mut RawPort) -> sys::kern_return_t {
+        let mut space = lock();
+        let space = space.as_mut().unwrap();
+        *name = space.next_name;
+        space.next_name += 1;
+        space.entries.insert(*name, Entry {
+            receive: true,
+            ..Entry::default()
+        });
+        sys::KERN_SUCCESS as _
+    }
+
+    fn port_mod_refs(&self, name: RawPort, right: sys::mach_port_right_t, delta: sys::mach_port_delta_t) -> sys::kern_return_t {
+        let mut space = lock();
+        let space = space.as_mut().unwrap();
+        let entry = match space.entry(name) {
+            Some(entry) => entry,
+            None => return sys::KERN_INVALID_NAME as _,
+        };
+        let refs = match right {
+            sys::MACH_PORT_RIGHT_RECEIVE => {
+                if !entry.receive {
+                    return sys::KERN_INVALID_RIGHT as _;
+                }
+                match delta {
+                    0 => (),
+                    -1 => space.destroy_receive(name),
+                    _ => return sys::KERN_INVALID_VALUE as _,
+                }
+                return sys::KERN_SUCCESS as _;
+            },
+            sys::MACH_PORT_RIGHT_SEND => &mut entry.send_refs,
+            sys::MACH_PORT_RIGHT_SEND_ONCE => &mut entry.send_once_refs,
+            sys::MACH_PORT_RIGHT_DEAD_NAME => &mut entry.dead_refs,
+            _ => return sys::KERN_INVALID_VALUE as _,
+        };
+        if *refs == 0 {
+            return sys::KERN_INVALID_RIGHT as _;
+        }
+        let updated = *refs as i64 + delta as i64;
+        if updated < 0 {
+            return sys::KERN_INVALID_VALUE as _;
+        }
+        *refs = updated as u32;
+        space.remove_if_unused(name);
+        sys::KERN_SUCCESS as _
+    }
+
+    fn port_deallocate(&self, name: RawPort) -> sys::kern_return_t {
+        if name == sys::MACH_PORT_NULL || name == sys::MACH_PORT_DEAD {
+            return sys::KERN_SUCCESS as _;
+        }
+        let right = {
+            let mut space = lock();
+            match space.as_mut().unwrap().entry(name) {
+                Some(entry) if entry.send_once_refs > 0 => sys::MACH_PORT_RIGHT_SEND_ONCE,
+                Some(entry) if entry.send_refs > 0 => sys::MACH_PORT_RIGHT_SEND,
+                Some(entry) if entry.dead_refs > 0 => sys::MACH_PORT_RIGHT_DEAD_NAME,
+                Some(_) => return sys::KERN_INVALID_RIGHT as _,
+                None => return sys::KERN_INVALID_NAME as _,
+            }
+        };
+        self.port_mod_refs(name, right, -1)
+    }
+
+    fn port_type(&self, name: RawPort, ty: &mut sys::mach_port_type_t) -> sys::kern_return_t {
+        let mut space = lock();
+        let entry = match space.as_mut().unwrap().entry(name) {
+            Some(entry) => entry,
+            None => return sys::KERN_INVALID_NAME as _,
+        };
+        *ty = 0;
+        if entry.receive {
+            *ty |= sys::MACH_PORT_TYPE_RECEIVE;
+        }
+        if entry.send_refs > 0 {
+            *ty |= sys::MACH_PORT_TYPE_SEND;
+        }
+        if entry.send_once_refs > 0 {
+            *ty |= sys::MACH_PORT_TYPE_SEND_ONCE;
+        }
+        if entry.dead_refs > 0 {
+            *ty |= sys::MACH_PORT_TYPE_DEAD_NAME;
+        }
+        sys::KERN_SUCCESS as _
+    }
+
+    fn port_make_send(&self, name: RawPort, port: &mut RawPort, right: &mut sys::mach_msg_type_name_t) -> sys::kern_return_t {
+        let mut space = lock();
+        match space.as_mut().unwrap().entry(name) {
+            Some(entry) if entry.receive => {
+                entry.send_refs += 1;
+                *port = name;
+                *right = sys::MACH_MSG_TYPE_PORT_SEND;
+                sys::KERN_SUCCESS as _
+            },
+            Some(_) => sys::KERN_INVALID_RIGHT as _,
+            None => sys::KERN_INVALID_NAME as _,
+        }
+    }
+
+    fn port_request_notification(&self, name: RawPort, id: sys::mach_msg_id_t, notify: RawPort, notify_disposition: sys::mach_msg_type_name_t, previous: &mut RawPort) -> sys::kern_return_t {
+        if id != sys::MACH_NOTIFY_DEAD_NAME as sys::mach_msg_id_t || notify_disposition != sys::MACH_MSG_TYPE_MAKE_SEND_ONCE {
+            return sys::KERN_INVALID_VALUE as _;
+        }
+        let mut space = lock();
+        let space = space.as_mut().unwrap();
+        match space.entry(notify) {
+            Some(entry) if entry.receive => (),
+            _ => return sys::KERN_INVALID_CAPABILITY as _,
+        }
+        match space.entry(name) {
+            Some(entry) if entry.send_refs > 0 => {
+                entry.dead_name_requests.push(notify);
+            },
+            Some(_) => return sys::KERN_INVALID_RIGHT as _,
+            None => return sys::KERN_INVALID_NAME as _,
+        }
+        space.entry(notify).unwrap().send_once_refs += 1;
+        *previous = sys::MACH_PORT_NULL;
+        sys::KERN_SUCCESS as _
+    }
+
+    unsafe fn msg(
+        &self,
+        msg: *mut sys::mach_msg_header_t,
+        option: sys::mach_msg_option_t,
+        send_size: sys::mach_msg_size_t,
+        rcv_size: sys::mach_msg_size_t,
+        rcv_name: RawPort,
+        timeout: sys::mach_msg_timeout_t,
+        _notify: RawPort,
+    ) -> sys::mach_msg_return_t {
+        if option & sys::MACH_SEND_MSG as sys::mach_msg_option_t != 0 {
+            let ret = self.send(msg, option, send_size, timeout);
+            if ret != sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t {
+                return ret;
+            }
+        }
+        if option & sys::MACH_RCV_MSG as sys::mach_msg_option_t != 0 {
+            return self.receive(msg, option, rcv_size, rcv_name, timeout);
+        }
+        sys::MACH_MSG_SUCCESS as _
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    const PAYLOAD: usize = 64;
+
+    #[repr(C)]
+    struct SimpleMsg {
+        header: sys::mach_msg_header_t,
+        payload: [u8; PAYLOAD],
+        trailer: sys::mach_msg_trailer_t,
+    }
+
+    #[repr(C)]
+    struct PortMsg {
+        header: sys::mach_msg_header_t,
+        body: sys::mach_msg_body_t,
+        port: sys::mach_msg_port_descriptor_t,
+        trailer: sys::mach_msg_trailer_t,
+    }
+
+    #[repr(C)]
+    struct DeadNameNotificationBuffer {
+        notification: DeadNameNotification,
+        trailer: sys::mach_msg_trailer_t,
+    }
+
+    fn allocate() -> RawPort {
+        let mut name = sys::MACH_PORT_NULL;
+        assert_eq!(Fake.port_allocate_receive(&mut name), sys::KERN_SUCCESS as sys::kern_return_t);
+        name
+    }
+
+    fn make_send(name: RawPort) {
+        let (mut port, mut right) = (sys::MACH_PORT_NULL, 0);
+        assert_eq!(Fake.port_make_send(name, &mut port, &mut right), sys::KERN_SUCCESS as sys::kern_return_t);
+        assert_eq!((port, right), (name, sys::MACH_MSG_TYPE_PORT_SEND));
+    }
+
+    fn refs(name: RawPort) -> Option<(bool, u32, u32, u32)> {
+        let mut space = lock();
+        space.as_mut().unwrap().entry(name).map(|entry| (entry.receive, entry.send_refs, entry.send_once_refs, entry.dead_refs))
+    }
+
+    fn timeout_option(timeout: Option<u32>, flag: u32) -> (sys::mach_msg_option_t, sys::mach_msg_timeout_t) {
+        match timeout {
+            Some(timeout) => (flag as sys::mach_msg_option_t, timeout),
+            None => (0, 0),
+        }
+    }
+
+    fn send(dest: RawPort, disposition: sys::mach_msg_type_name_t, id: sys::mach_msg_id_t, timeout: Option<u32>) -> sys::mach_msg_return_t {
+        let mut msg: SimpleMsg = unsafe { mem::zeroed() };
+        msg.header.msgh_bits = sys::MACH_MSGH_BITS(disposition, 0);
+        msg.header.msgh_size = (mem::size_of::<SimpleMsg>() - mem::size_of::<sys::mach_msg_trailer_t>()) as _;
+        msg.header.msgh_remote_port = dest;
+        msg.header.msgh_id = id;
+        let (option, timeout) = timeout_option(timeout, sys::MACH_SEND_TIMEOUT);
+        unsafe { Fake.msg(&mut msg.header, sys::MACH_SEND_MSG as sys::mach_msg_option_t | option, msg.header.msgh_size, 0, sys::MACH_PORT_NULL, timeout, sys::MACH_PORT_NULL) }
+    }
+
+    fn send_port(dest: RawPort, port: RawPort, disposition: sys::mach_msg_type_name_t) -> sys::mach_msg_return_t {
+        let mut msg: PortMsg = unsafe { mem::zeroed() };
+        msg.header.msgh_bits = sys::MACH_MSGH_BITS(sys::MACH_MSG_TYPE_COPY_SEND, 0) | sys::MACH_MSGH_BITS_COMPLEX;
+        msg.header.msgh_size = (mem::size_of::<PortMsg>() - mem::size_of::<sys::mach_msg_trailer_t>()) as _;
+        msg.header.msgh_remote_port = dest;
+        msg.body.msgh_descriptor_count = 1;
+        msg.port.name = port;
+        msg.port.set_disposition(disposition);
+        msg.port.set_type(sys::MACH_MSG_PORT_DESCRIPTOR);
+        unsafe { Fake.msg(&mut msg.header, sys::MACH_SEND_MSG as sys::mach_msg_option_t, msg.header.msgh_size, 0, sys::MACH_PORT_NULL, 0, sys::MACH_PORT_NULL) }
+    }
+
+    unsafe fn receive<T>(name: RawPort, msg: &mut T, size: usize, option: sys::mach_msg_option_t, timeout: Option<u32>) -> sys::mach_msg_return_t {
+        let (timeout_option, timeout) = timeout_option(timeout, sys::MACH_RCV_TIMEOUT);
+        Fake.msg(msg as *mut T as *mut sys::mach_msg_header_t, sys::MACH_RCV_MSG as sys::mach_msg_option_t | option | timeout_option, 0, size as _, name, timeout, sys::MACH_PORT_NULL)
+    }
+
+    fn receive_simple(name: RawPort, timeout: Option<u32>) -> Result<SimpleMsg, sys::mach_msg_return_t> {
+        let mut msg: SimpleMsg = unsafe { mem::zeroed() };
+        match unsafe { receive(name, &mut msg, mem::size_of::<SimpleMsg>(), 0, timeout) } {
+            ret if ret == sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t => Ok(msg),
+            ret => Err(ret),
+        }
+    }
+
+    #[test]
+    fn allocate_make_send_mod_refs() {
+        let name = allocate();
+        assert_eq!(refs(name), Some((true, 0, 0, 0)));
+        make_send(name);
+        assert_eq!(Fake.port_mod_refs(name, sys::MACH_PORT_RIGHT_SEND, 2), sys::KERN_SUCCESS as sys::kern_return_t);
+        assert_eq!(refs(name), Some((true, 3, 0, 0)));
+        let mut ty = 0;
+        assert_eq!(Fake.port_type(name, &mut ty), sys::KERN_SUCCESS as sys::kern_return_t);
+        assert_eq!(ty, sys::MACH_PORT_TYPE_RECEIVE | sys::MACH_PORT_TYPE_SEND);
+
+        // Refs can't go negative, and there are no send-once rights to adjust
+        assert_eq!(Fake.port_mod_refs(name, sys::MACH_PORT_RIGHT_SEND, -4), sys::KERN_INVALID_VALUE as sys::kern_return_t);
+        assert_eq!(Fake.port_mod_refs(name, sys::MACH_PORT_RIGHT_SEND_ONCE, 1), sys::KERN_INVALID_RIGHT as sys::kern_return_t);
+        assert_eq!(Fake.port_mod_refs(name, sys::MACH_PORT_RIGHT_RECEIVE, -2), sys::KERN_INVALID_VALUE as sys::kern_return_t);
+
+        // Destroying the receive right leaves the send rights as a dead name
+        assert_eq!(Fake.port_mod_refs(name, sys::MACH_PORT_RIGHT_RECEIVE, -1), sys::KERN_SUCCESS as sys::kern_return_t);
+        assert_eq!(refs(name), Some((false, 0, 0, 3)));
+        assert_eq!(Fake.port_type(name, &mut ty), sys::KERN_SUCCESS as sys::kern_return_t);
+        assert_eq!(ty, sys::MACH_PORT_TYPE_DEAD_NAME);
+        for _ in 0..3 {
+            assert_eq!(Fake.port_deallocate(name), sys::KERN_SUCCESS as sys::kern_return_t);
+        }
+        assert_eq!(refs(name), None);
+        assert_eq!(Fake.port_deallocate(name), sys::KERN_INVALID_NAME as sys::kern_return_t);
+        assert_eq!(Fake.port_type(name, &mut ty), sys::KERN_INVALID_NAME as sys::kern_return_t);
+    }
+
+    #[test]
+    fn full_queue_times_out_send() {
+        let name = allocate();
+        make_send(name);
+        for id in 0..QUEUE_LIMIT {
+            assert_eq!(send(name, sys::MACH_MSG_TYPE_COPY_SEND, id as _, Some(0)), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        }
+        assert_eq!(send(name, sys::MACH_MSG_TYPE_COPY_SEND, 100, Some(10)), sys::MACH_SEND_TIMED_OUT as sys::mach_msg_return_t);
+
+        // Draining one message makes room for a blocked sender
+        let sender = thread::spawn(move || send(name, sys::MACH_MSG_TYPE_COPY_SEND, 100, None));
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(receive_simple(name, Some(0)).ok().map(|msg| msg.header.msgh_id), Some(0));
+        assert_eq!(sender.join().unwrap(), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+
+        // Messages arrive in order, with the one that timed out missing
+        let ids: Vec<_> = (0..QUEUE_LIMIT).map(|_| receive_simple(name, Some(0)).unwrap().header.msgh_id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 100]);
+    }
+
+    #[test]
+    fn receive_times_out_and_wakes() {
+        let name = allocate();
+        make_send(name);
+        let start = Instant::now();
+        assert_eq!(receive_simple(name, Some(20)).err(), Some(sys::MACH_RCV_TIMED_OUT as sys::mach_msg_return_t));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let receiver = thread::spawn(move || receive_simple(name, None).map(|msg| msg.header.msgh_id));
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(send(name, sys::MACH_MSG_TYPE_COPY_SEND, 7, None), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        assert_eq!(receiver.join().unwrap(), Ok(7));
+    }
+
+    #[test]
+    fn receive_fails_when_right_destroyed() {
+        let name = allocate();
+        let receiver = thread::spawn(move || receive_simple(name, None).err());
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(Fake.port_mod_refs(name, sys::MACH_PORT_RIGHT_RECEIVE, -1), sys::KERN_SUCCESS as sys::kern_return_t);
+        assert_eq!(receiver.join().unwrap(), Some(sys::MACH_RCV_INVALID_NAME as sys::mach_msg_return_t));
+    }
+
+    #[test]
+    fn move_and_copy_dispositions() {
+        let dest = allocate();
+        make_send(dest);
+        assert_eq!(Fake.port_mod_refs(dest, sys::MACH_PORT_RIGHT_SEND, 1), sys::KERN_SUCCESS as sys::kern_return_t);
+
+        // Sending with COPY_SEND keeps the sender's right, MOVE_SEND consumes it
+        assert_eq!(send(dest, sys::MACH_MSG_TYPE_COPY_SEND, 0, None), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        assert_eq!(refs(dest), Some((true, 2, 0, 0)));
+        assert_eq!(send(dest, sys::MACH_MSG_TYPE_MOVE_SEND, 0, None), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        assert_eq!(refs(dest), Some((true, 1, 0, 0)));
+        assert_eq!(send(dest, sys::MACH_MSG_TYPE_MOVE_SEND_ONCE, 0, None), sys::MACH_SEND_INVALID_DEST as sys::mach_msg_return_t);
+        for _ in 0..2 {
+            receive_simple(dest, Some(0)).unwrap();
+        }
+
+        // Carried rights: copied and made rights gain a reference, moved rights keep their count
+        let carried = allocate();
+        make_send(carried);
+        let mut msg: PortMsg = unsafe { mem::zeroed() };
+        for &(disposition, received, send_refs) in &[
+            (sys::MACH_MSG_TYPE_COPY_SEND, sys::MACH_MSG_TYPE_PORT_SEND, 2),
+            (sys::MACH_MSG_TYPE_MAKE_SEND, sys::MACH_MSG_TYPE_PORT_SEND, 3),
+            (sys::MACH_MSG_TYPE_MOVE_SEND, sys::MACH_MSG_TYPE_PORT_SEND, 3),
+        ] {
+            assert_eq!(send_port(dest, carried, disposition), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+            assert_eq!(unsafe { receive(dest, &mut msg, mem::size_of::<PortMsg>(), 0, Some(0)) }, sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+            let port = msg.port;
+            assert_eq!((port.name, port.disposition()), (carried, received));
+            assert_eq!(refs(carried), Some((true, send_refs, 0, 0)));
+        }
+
+        // A right the sender doesn't hold is rejected without transferring anything
+        let unrelated = allocate();
+        assert_eq!(send_port(dest, unrelated, sys::MACH_MSG_TYPE_COPY_SEND), sys::MACH_SEND_INVALID_RIGHT as sys::mach_msg_return_t);
+        assert_eq!(refs(dest), Some((true, 1, 0, 0)));
+        assert_eq!(receive_simple(dest, Some(0)).err(), Some(sys::MACH_RCV_TIMED_OUT as sys::mach_msg_return_t));
+    }
+
+    #[test]
+    fn dead_name_notification() {
+        let watched = allocate();
+        make_send(watched);
+        let notify = allocate();
+        let mut previous = sys::MACH_PORT_NULL;
+        assert_eq!(
+            Fake.port_request_notification(watched, sys::MACH_NOTIFY_DEAD_NAME as _, notify, sys::MACH_MSG_TYPE_MAKE_SEND_ONCE, &mut previous),
+            sys::KERN_SUCCESS as sys::kern_return_t,
+        );
+        assert_eq!(previous, sys::MACH_PORT_NULL);
+        assert_eq!(refs(notify), Some((true, 0, 1, 0)));
+
+        assert_eq!(Fake.port_mod_refs(watched, sys::MACH_PORT_RIGHT_RECEIVE, -1), sys::KERN_SUCCESS as sys::kern_return_t);
+        let mut notification: DeadNameNotificationBuffer = unsafe { mem::zeroed() };
+        assert_eq!(unsafe { receive(notify, &mut notification, mem::size_of::<DeadNameNotificationBuffer>(), 0, Some(0)) }, sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        assert_eq!(notification.notification.header.msgh_id, sys::MACH_NOTIFY_DEAD_NAME as sys::mach_msg_id_t);
+        assert_eq!(notification.notification.name, watched);
+        // Delivery consumed the send-once right
+        assert_eq!(refs(notify), Some((true, 0, 0, 0)));
+        assert_eq!(refs(watched), Some((false, 0, 0, 1)));
+    }
+
+    #[test]
+    fn receive_too_large() {
+        let name = allocate();
+        make_send(name);
+        let needed = mem::size_of::<SimpleMsg>();
+        let mut small: SimpleMsg = unsafe { mem::zeroed() };
+        let small_size = mem::size_of::<sys::mach_msg_header_t>() + mem::size_of::<sys::mach_msg_trailer_t>();
+
+        // With RCV_LARGE the message stays queued and the required size is reported
+        assert_eq!(send(name, sys::MACH_MSG_TYPE_COPY_SEND, 1, None), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        assert_eq!(unsafe { receive(name, &mut small, small_size, sys::MACH_RCV_LARGE as _, Some(0)) }, sys::MACH_RCV_TOO_LARGE as sys::mach_msg_return_t);
+        assert_eq!(small.header.msgh_size as usize, needed);
+        assert_eq!(receive_simple(name, Some(0)).unwrap().header.msgh_id, 1);
+
+        // Without it the message is dropped
+        assert_eq!(send(name, sys::MACH_MSG_TYPE_COPY_SEND, 2, None), sys::MACH_MSG_SUCCESS as sys::mach_msg_return_t);
+        assert_eq!(unsafe { receive(name, &mut small, small_size, 0, Some(0)) }, sys::MACH_RCV_TOO_LARGE as sys::mach_msg_return_t);
+        assert_eq!(receive_simple(name, Some(0)).err(), Some(sys::MACH_RCV_TIMED_OUT as sys::mach_msg_return_t));
+    }
+}
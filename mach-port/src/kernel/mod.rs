@@ -0,0 +1,59 @@
+//! The Mach calls underlying [`Port`](crate::Port) and [`Msg`](crate::Msg), behind a trait so they can be served by
+//! an in-memory fake.
+//!
+//! On Apple targets the calls go straight to the kernel. Elsewhere they are handled by a single in-process port
+//! namespace that implements enough of the real semantics (send and send-once rights in headers and port descriptors,
+//! queueing, receive timeouts, dead names and dead-name notifications) for libraries to unit-test their IPC logic on CI
+//! machines without Darwin. Out-of-line memory and port sets are not supported by the fake, and only ports and messages
+//! are available on those targets.
+//!
+//! The fake can also be selected on Apple targets by building with `RUSTFLAGS="--cfg mach_port_fake_kernel"`. This is a
+//! cfg rather than a cargo feature because it isn't additive: tasks, threads, vouchers and so on still hand out real
+//! port names, which the fake doesn't know about, so it should only be used by test builds that stick to ports and
+//! messages.
+
+use crate::RawPort;
+
+use mach_sys as sys;
+
+#[cfg(all(target_vendor = "apple", not(mach_port_fake_kernel)))]
+mod darwin;
+#[cfg(any(not(target_vendor = "apple"), mach_port_fake_kernel))]
+mod fake;
+
+/// Mach port and message calls, always applied to the current task's namespace.
+///
+/// Methods mirror the Mach calls of the same name and return their raw codes, so callers can keep using
+/// [`mach_call!`](mach_core::mach_call).
+// Some calls are only made by modules that need the real kernel anyway
+#[cfg_attr(not(target_vendor = "apple"), allow(dead_code))]
+pub(crate) trait Kernel: Sync {
+    fn port_allocate_receive(&self, name: &mut RawPort) -> sys::kern_return_t;
+    fn port_mod_refs(&self, name: RawPort, right: sys::mach_port_right_t, delta: sys::mach_port_delta_t) -> sys::kern_return_t;
+    fn port_deallocate(&self, name: RawPort) -> sys::kern_return_t;
+    fn port_type(&self, name: RawPort, ty: &mut sys::mach_port_type_t) -> sys::kern_return_t;
+    /// `mach_port_extract_right` with `MACH_MSG_TYPE_MAKE_SEND` on the current task.
+    fn port_make_send(&self, name: RawPort, port: &mut RawPort, right: &mut sys::mach_msg_type_name_t) -> sys::kern_return_t;
+    fn port_request_notification(&self, name: RawPort, id: sys::mach_msg_id_t, notify: RawPort, notify_disposition: sys::mach_msg_type_name_t, previous: &mut RawPort) -> sys::kern_return_t;
+    // Mirrors mach_msg, so each argument keeps its familiar position
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn msg(
+        &self,
+        msg: *mut sys::mach_msg_header_t,
+        option: sys::mach_msg_option_t,
+        send_size: sys::mach_msg_size_t,
+        rcv_size: sys::mach_msg_size_t,
+        rcv_name: RawPort,
+        timeout: sys::mach_msg_timeout_t,
+        notify: RawPort,
+    ) -> sys::mach_msg_return_t;
+}
+
+/// Gets the kernel serving the current process.
+#[inline]
+pub(crate) fn kernel() -> &'static dyn Kernel {
+    #[cfg(all(target_vendor = "apple", not(mach_port_fake_kernel)))]
+    { &darwin::Darwin }
+    #[cfg(any(not(target_vendor = "apple"), mach_port_fake_kernel))]
+    { &fake::Fake }
+}
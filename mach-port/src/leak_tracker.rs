@@ -0,0 +1,127 @@
+//! A registry of live [`Port`](crate::Port)s and where they were created, for hunting down leaked rights.
+//!
+//! Enabled by the `leak-tracker` feature. Every `Port` created by this crate, including those taken from received
+//! message descriptors, records a backtrace when it is created and removes it when dropped or converted into a raw
+//! port. Calling [`report`] at a point where no rights should remain (e.g. at the end of a test) lists the ones that
+//! are still alive. Unlike `EXC_GUARD`, which only catches misuse of guarded ports, this catches plain leaks.
+//!
+//! Capturing a backtrace for every port is slow, so this is only intended for debug builds.
+
+use crate::RawPort;
+
+use std::sync::Mutex;
+use std::backtrace::Backtrace;
+
+/// A port right that has not been released.
+#[derive(Debug)]
+pub struct LiveRight {
+    pub name: RawPort,
+    pub receive: bool,
+    pub send: bool,
+    /// The port's [label](crate::Port::set_label), if it has one.
+    pub label: Option<String>,
+    /// The formatted backtrace of where the `Port` was created.
+    pub backtrace: String,
+}
+
+struct Entry {
+    // Several `Port`s can hold rights under the same name (e.g. a receive right and a sender made from it), so each
+    // one is told apart by an ID handed out when it is tracked
+    id: u64,
+    name: RawPort,
+    receive: bool,
+    send: bool,
+    backtrace: Backtrace,
+}
+
+struct Registry {
+    next_id: u64,
+    // Sorted by ID, since IDs are handed out in order under the lock
+    entries: Vec<Entry>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    next_id: 0,
+    entries: Vec::new(),
+});
+
+/// Records a new `Port`, returning the ID to release it with.
+pub(crate) fn track(name: RawPort, receive: bool, send: bool) -> u64 {
+    let backtrace = Backtrace::force_capture();
+    let mut registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.entries.push(Entry {
+        id,
+        name,
+        receive,
+        send,
+        backtrace,
+    });
+    id
+}
+
+pub(crate) fn untrack(id: u64) {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+    if let Ok(index) = registry.entries.binary_search_by_key(&id, |entry| entry.id) {
+        registry.entries.remove(index);
+    }
+}
+
+/// Lists the rights that are still alive, oldest first.
+pub fn report() -> Vec<LiveRight> {
+    let registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+    registry.entries.iter()
+        .map(|entry| LiveRight {
+            name: entry.name,
+            receive: entry.receive,
+            send: entry.send,
+            label: mach_core::label::label(entry.name),
+            backtrace: entry.backtrace.to_string(),
+        })
+        .collect()
+}
+
+/// The number of rights that are still alive.
+pub fn live_count() -> usize {
+    REGISTRY.lock().unwrap_or_else(|err| err.into_inner()).entries.len()
+}
+
+/// Logs every right that is still alive as a warning, returning how many there were.
+pub fn log_report() -> usize {
+    let live = report();
+    for right in live.iter() {
+        warn!("port {:#x} {:?} (receive: {}, send: {}) is still alive, created at:\n{}", right.name, right.label, right.receive, right.send, right.backtrace);
+    }
+    live.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Port;
+
+    fn live(name: RawPort) -> Vec<(bool, bool)> {
+        report().into_iter()
+            .filter(|right| right.name == name)
+            .map(|right| (right.receive, right.send))
+            .collect()
+    }
+
+    #[test]
+    fn ports_sharing_a_name_are_tracked_separately() {
+        let port = Port::new().unwrap();
+        let name = port.as_raw_port();
+        let sender = port.make_sender().unwrap();
+        assert_eq!(sender.as_raw_port(), name);
+        assert_eq!(live(name), vec![(true, false), (false, true)]);
+
+        // Dropping the older port releases its own entry, not the most recent one under the name
+        drop(port);
+        assert_eq!(live(name), vec![(false, true)]);
+        let raw = sender.into_raw_port();
+        assert_eq!(live(name), vec![]);
+        crate::kernel::kernel().port_deallocate(raw);
+    }
+}
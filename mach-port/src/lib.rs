@@ -1,9 +1,110 @@
 #[macro_use] extern crate log;
 
+// Everything builds for all Apple platforms, except APIs third-party code can only use on macOS (obtaining other
+// tasks' ports by PID, and the host privileged port), which are gated on `target_os = "macos"`. Other targets only get
+// ports and messages, served by the fake kernel, and the modules built purely on them.
+
+mod kernel;
 mod port;
+#[cfg(target_vendor = "apple")]
+mod port_space;
+#[cfg(target_vendor = "apple")]
+mod port_status;
 mod msg;
+#[cfg(target_vendor = "apple")]
+mod semaphore;
+#[cfg(target_vendor = "apple")]
+mod task;
+#[cfg(target_vendor = "apple")]
+mod task_policy;
+#[cfg(target_vendor = "apple")]
+mod task_info;
+#[cfg(target_vendor = "apple")]
+mod task_identity;
+#[cfg(target_vendor = "apple")]
+mod task_rights;
+#[cfg(target_vendor = "apple")]
+mod exception_ports;
+#[cfg(target_vendor = "apple")]
+mod thread;
+#[cfg(target_vendor = "apple")]
+mod thread_policy;
+#[cfg(target_vendor = "apple")]
+mod thread_state;
+#[cfg(target_vendor = "apple")]
+mod thread_switch;
+#[cfg(target_vendor = "apple")]
+mod timer;
+#[cfg(target_vendor = "apple")]
+mod usage;
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+mod host_priv;
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+mod host_special_ports;
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+mod processor_set;
+#[cfg(all(feature = "host-priv", target_os = "macos"))]
+mod zone_info;
+
+#[cfg(target_vendor = "apple")]
+pub mod clock;
+#[cfg(all(target_vendor = "apple", feature = "eventlink"))]
+pub mod eventlink;
+#[cfg(target_vendor = "apple")]
+pub mod exceptions;
+#[cfg(target_vendor = "apple")]
+pub mod host;
+#[cfg(feature = "leak-tracker")]
+pub mod leak_tracker;
+#[cfg(target_vendor = "apple")]
+pub mod memory_pressure;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(target_vendor = "apple")]
+pub mod profiler;
+#[cfg(feature = "record")]
+pub mod record;
+#[cfg(all(target_vendor = "apple", feature = "signpost"))]
+pub mod signpost;
+#[cfg(target_vendor = "apple")]
+pub mod snapshot;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(target_vendor = "apple")]
+pub mod vm;
+#[cfg(target_vendor = "apple")]
+pub mod voucher;
 
 pub use self::port::*;
+#[cfg(target_vendor = "apple")]
+pub use self::port_space::*;
+#[cfg(target_vendor = "apple")]
+pub use self::port_status::*;
 pub use self::msg::*;
+#[cfg(target_vendor = "apple")]
+pub use self::semaphore::*;
+#[cfg(target_vendor = "apple")]
+pub use self::task::*;
+#[cfg(target_vendor = "apple")]
+pub use self::task_policy::*;
+#[cfg(target_vendor = "apple")]
+pub use self::task_info::*;
+#[cfg(target_vendor = "apple")]
+pub use self::task_identity::*;
+#[cfg(target_vendor = "apple")]
+pub use self::exception_ports::*;
+#[cfg(target_vendor = "apple")]
+pub use self::thread::*;
+#[cfg(target_vendor = "apple")]
+pub use self::thread_policy::*;
+#[cfg(target_vendor = "apple")]
+pub use self::thread_state::*;
+#[cfg(target_vendor = "apple")]
+pub use self::thread_switch::*;
+#[cfg(target_vendor = "apple")]
+pub use self::timer::*;
+#[cfg(target_vendor = "apple")]
+pub use self::usage::*;
 
 pub use mach_core::RawPort;
+pub use mach_core::error::{MachError, MachErrorExt};
@@ -0,0 +1,131 @@
+//! Detecting system memory pressure by polling host virtual memory statistics.
+//!
+//! The kernel's own pressure notifications are only delivered through dispatch sources and `kevent`. For programs
+//! without either, [`MemoryPressureMonitor`] approximates them from `host_statistics64`: the level rises as the
+//! fraction of reclaimable memory falls, and whenever the system started swapping since the previous sample.
+
+use crate::host::{Host, VmStatistics};
+
+use std::{io, thread};
+use std::time::Duration;
+
+/// How constrained the system is for memory.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum PressureLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+/// The result of a single sampling pass.
+#[derive(Clone, Copy, Debug)]
+pub struct PressureSample {
+    pub level: PressureLevel,
+    /// Whether `level` differs from the previous sample's.
+    pub changed: bool,
+    /// The fraction of physical memory that is free or can be reclaimed without swapping or compressing.
+    pub available_fraction: f64,
+    /// Pages compressed since the previous sample.
+    pub compressions: u64,
+    /// Pages swapped out since the previous sample.
+    pub swapouts: u64,
+    pub statistics: VmStatistics,
+}
+
+/// Periodically samples host virtual memory statistics and reports memory pressure level changes.
+pub struct MemoryPressureMonitor {
+    host: Host,
+    interval: Duration,
+    warning_threshold: f64,
+    critical_threshold: f64,
+    total_pages: u64,
+    level: PressureLevel,
+    previous: Option<VmStatistics>,
+}
+
+impl MemoryPressureMonitor {
+    pub fn new() -> io::Result<MemoryPressureMonitor> {
+        let host = Host::current();
+        let basic_info = host.basic_info()?;
+        let page_size = host.page_size()?;
+        Ok(MemoryPressureMonitor {
+            host,
+            interval: Duration::from_secs(1),
+            warning_threshold: 0.15,
+            critical_threshold: 0.05,
+            total_pages: basic_info.max_mem / page_size,
+            level: PressureLevel::Normal,
+            previous: None,
+        })
+    }
+
+    /// Sets the delay between samples taken by [`run`](MemoryPressureMonitor::run). Defaults to one second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the available memory fractions below which the level becomes `Warning` and `Critical`. Defaults to 15% and
+    /// 5%.
+    pub fn thresholds(mut self, warning: f64, critical: f64) -> Self {
+        self.warning_threshold = warning;
+        self.critical_threshold = critical;
+        self
+    }
+
+    /// The level determined by the most recent sample.
+    #[inline]
+    pub fn level(&self) -> PressureLevel {
+        self.level
+    }
+
+    /// Takes a single sample and updates the current level.
+    pub fn sample(&mut self) -> io::Result<PressureSample> {
+        let statistics = self.host.vm_statistics()?;
+        let available = statistics.free_count + statistics.speculative_count + statistics.purgeable_count + statistics.external_page_count;
+        let available_fraction = if self.total_pages == 0 { 1.0 } else { available as f64 / self.total_pages as f64 };
+        let (compressions, swapouts) = match self.previous {
+            Some(ref previous) => (
+                statistics.compressions.wrapping_sub(previous.compressions),
+                statistics.swapouts.wrapping_sub(previous.swapouts),
+            ),
+            None => (0, 0),
+        };
+
+        let mut level = if available_fraction < self.critical_threshold {
+            PressureLevel::Critical
+        } else if available_fraction < self.warning_threshold {
+            PressureLevel::Warning
+        } else {
+            PressureLevel::Normal
+        };
+        if swapouts > 0 && level < PressureLevel::Warning {
+            level = PressureLevel::Warning;
+        }
+
+        let changed = level != self.level;
+        self.level = level;
+        self.previous = Some(statistics);
+        Ok(PressureSample {
+            level,
+            changed,
+            available_fraction,
+            compressions,
+            swapouts,
+            statistics,
+        })
+    }
+
+    /// Samples repeatedly, invoking `on_change` whenever the level changes, until it returns `false`.
+    pub fn run<F>(&mut self, mut on_change: F) -> io::Result<()> where
+        F: FnMut(&PressureSample) -> bool,
+    {
+        loop {
+            let sample = self.sample()?;
+            if sample.changed && !on_change(&sample) {
+                return Ok(());
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
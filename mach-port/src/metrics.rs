@@ -0,0 +1,117 @@
+//! Process-wide counters of message traffic, for exporting IPC health to monitoring.
+//!
+//! Enabled by the `metrics` feature. Every [`Port::send`](crate::Port::send) and [`Port::recv`](crate::Port::recv)
+//! updates the counters, which can be read at any time with [`snapshot`].
+
+use crate::Msg;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use mach_sys as sys;
+
+static MESSAGES_SENT: AtomicU64 = AtomicU64::new(0);
+static MESSAGES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static INLINE_BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static INLINE_BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static OOL_BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static OOL_BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static DESCRIPTORS_SENT: AtomicU64 = AtomicU64::new(0);
+static DESCRIPTORS_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static QUEUE_FULL: AtomicU64 = AtomicU64::new(0);
+static SEND_FAILURES: Mutex<Option<HashMap<sys::mach_error_t, u64>>> = Mutex::new(None);
+
+/// The values of the counters at a point in time.
+#[derive(Clone, Debug, Default)]
+pub struct MetricsSnapshot {
+    pub messages_sent: u64,
+    pub messages_received: u64,
+    pub inline_bytes_sent: u64,
+    pub inline_bytes_received: u64,
+    pub ool_bytes_sent: u64,
+    pub ool_bytes_received: u64,
+    pub descriptors_sent: u64,
+    pub descriptors_received: u64,
+    /// Sends that failed because the destination's queue was full (timeouts and `MACH_SEND_NO_BUFFER`).
+    pub queue_full: u64,
+    /// Failed sends, keyed by `mach_msg_return_t`.
+    pub send_failures: HashMap<sys::mach_error_t, u64>,
+}
+
+impl MetricsSnapshot {
+    /// The total number of failed sends.
+    pub fn total_send_failures(&self) -> u64 {
+        self.send_failures.values().sum()
+    }
+}
+
+/// Reads the current value of every counter.
+pub fn snapshot() -> MetricsSnapshot {
+    MetricsSnapshot {
+        messages_sent: MESSAGES_SENT.load(Ordering::Relaxed),
+        messages_received: MESSAGES_RECEIVED.load(Ordering::Relaxed),
+        inline_bytes_sent: INLINE_BYTES_SENT.load(Ordering::Relaxed),
+        inline_bytes_received: INLINE_BYTES_RECEIVED.load(Ordering::Relaxed),
+        ool_bytes_sent: OOL_BYTES_SENT.load(Ordering::Relaxed),
+        ool_bytes_received: OOL_BYTES_RECEIVED.load(Ordering::Relaxed),
+        descriptors_sent: DESCRIPTORS_SENT.load(Ordering::Relaxed),
+        descriptors_received: DESCRIPTORS_RECEIVED.load(Ordering::Relaxed),
+        queue_full: QUEUE_FULL.load(Ordering::Relaxed),
+        send_failures: SEND_FAILURES.lock().unwrap_or_else(|err| err.into_inner()).clone().unwrap_or_default(),
+    }
+}
+
+/// Resets every counter to zero.
+pub fn reset() {
+    for counter in [
+        &MESSAGES_SENT, &MESSAGES_RECEIVED, &INLINE_BYTES_SENT, &INLINE_BYTES_RECEIVED, &OOL_BYTES_SENT,
+        &OOL_BYTES_RECEIVED, &DESCRIPTORS_SENT, &DESCRIPTORS_RECEIVED, &QUEUE_FULL,
+    ].iter() {
+        counter.store(0, Ordering::Relaxed);
+    }
+    *SEND_FAILURES.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+// Sizes of a message, captured before sending since the buffer is reset afterwards
+pub(crate) struct MsgStats {
+    inline_bytes: u64,
+    ool_bytes: u64,
+    descriptors: u64,
+}
+
+impl MsgStats {
+    pub(crate) fn of(msg: &Msg) -> MsgStats {
+        MsgStats {
+            inline_bytes: msg.inline_data().len() as u64,
+            ool_bytes: msg.ool_bytes(),
+            descriptors: msg.descriptor_count() as u64,
+        }
+    }
+}
+
+pub(crate) fn record_send(stats: &MsgStats) {
+    MESSAGES_SENT.fetch_add(1, Ordering::Relaxed);
+    INLINE_BYTES_SENT.fetch_add(stats.inline_bytes, Ordering::Relaxed);
+    OOL_BYTES_SENT.fetch_add(stats.ool_bytes, Ordering::Relaxed);
+    DESCRIPTORS_SENT.fetch_add(stats.descriptors, Ordering::Relaxed);
+}
+
+pub(crate) fn record_send_failure(code: sys::mach_error_t) {
+    match code as u32 {
+        sys::MACH_SEND_TIMED_OUT | sys::MACH_SEND_NO_BUFFER => {
+            QUEUE_FULL.fetch_add(1, Ordering::Relaxed);
+        },
+        _ => (),
+    }
+    let mut failures = SEND_FAILURES.lock().unwrap_or_else(|err| err.into_inner());
+    *failures.get_or_insert_with(HashMap::new).entry(code).or_insert(0) += 1;
+}
+
+pub(crate) fn record_receive(msg: &Msg) {
+    let stats = MsgStats::of(msg);
+    MESSAGES_RECEIVED.fetch_add(1, Ordering::Relaxed);
+    INLINE_BYTES_RECEIVED.fetch_add(stats.inline_bytes, Ordering::Relaxed);
+    OOL_BYTES_RECEIVED.fetch_add(stats.ool_bytes, Ordering::Relaxed);
+    DESCRIPTORS_RECEIVED.fetch_add(stats.descriptors, Ordering::Relaxed);
+}
@@ -1,4 +1,8 @@
 use crate::{Port, RawPort};
+#[cfg(target_vendor = "apple")]
+use crate::vm::VmAllocation;
+#[cfg(target_vendor = "apple")]
+use crate::voucher::Voucher;
 
 use std::{io, mem, ptr, slice, fmt};
 use std::marker::PhantomData;
@@ -103,6 +107,23 @@ impl MsgBuffer {
         }
     }
 
+    /// Creates a buffer holding a copy of a complete message in the format produced by [`Port::recv`].
+    ///
+    /// The bytes must start with a header whose `msgh_size` matches their length.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> MsgBuffer {
+        debug_assert!(bytes.len() >= mem::size_of::<MessageStart>());
+        let mut buffer = Vec::with_capacity(bytes.len() + mem::size_of::<sys::mach_msg_trailer_t>());
+        buffer.extend_from_slice(bytes);
+        let mut msg = MsgBuffer {
+            buffer,
+            capacity_inline: 0,
+            capacity_descriptors: 0,
+        };
+        msg.capacity_descriptors = msg.descriptor_count();
+        msg.capacity_inline = msg.inline_data().len();
+        msg
+    }
+
     /// Resets the [`MsgBuffer`], deallocating any owned resources contained within.
     pub fn reset(&mut self) {
         debug_assert!(self.buffer.len() >= mem::size_of::<MessageStart>());
@@ -158,14 +179,16 @@ impl MsgBuffer {
         }
         unsafe {
             debug_assert!(self.buffer.capacity() - self.buffer.len() >= data.len());
-            ptr::copy_nonoverlapping(data.as_ptr(), self.buffer.as_mut_ptr().offset(self.buffer.len() as isize), data.len());
+            ptr::copy_nonoverlapping(data.as_ptr(), self.buffer.as_mut_ptr().add(self.buffer.len()), data.len());
             self.header_mut().msgh_size += data.len() as sys::mach_msg_size_t;
             self.buffer.set_len(self.buffer.len() + data.len());
         }
     }
 
     /// Attaches a port to a message, marking for the designated right to be copied on transmission.
-    /// 
+    ///
+    /// # Safety
+    ///
     /// It is the responsibility of the caller to ensure that the port lives until the message is sent or the port is removed
     /// from the message.
     pub unsafe fn copy_right(&mut self, mode: PortCopyMode, port: &Port) {
@@ -173,7 +196,9 @@ impl MsgBuffer {
     }
 
     /// Attaches a port to a message, marking for the designated right to be copied on transmission.
-    /// 
+    ///
+    /// # Safety
+    ///
     /// It is the responsibility of the caller to ensure that the port lives until the message is sent or the port is removed
     /// from the message.
     pub unsafe fn copy_right_raw(&mut self, mode: PortCopyMode, port: RawPort) {
@@ -197,6 +222,10 @@ impl MsgBuffer {
     }
 
     /// Attaches a port to a message, marking for the designated right to be moved on transmission.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own the right, which now belongs to the message.
     pub unsafe fn move_right_raw(&mut self, mode: PortMoveMode, port: RawPort) {
         let mut descriptor = sys::mach_msg_port_descriptor_t {
             name: port,
@@ -212,6 +241,56 @@ impl MsgBuffer {
         self.append_descriptor(descriptor);
     }
 
+    /// Attaches an allocation to a message as out-of-line memory, which is moved out of the current task on
+    /// transmission.
+    ///
+    /// The kernel transfers the pages themselves rather than copying their contents, so this is the cheapest way to
+    /// send large buffers.
+    #[cfg(target_vendor = "apple")]
+    pub fn move_allocation(&mut self, allocation: VmAllocation) -> io::Result<()> {
+        unsafe { self.append_ool_raw(allocation.address(), allocation.size(), true)?; }
+        // The message now owns the memory
+        allocation.into_raw();
+        Ok(())
+    }
+
+    /// Attaches an allocation to a message as out-of-line memory, which is shared copy-on-write with the receiver on
+    /// transmission.
+    ///
+    /// # Safety
+    ///
+    /// It is the responsibility of the caller to ensure that the allocation lives until the message is sent or the
+    /// memory is removed from the message.
+    #[cfg(target_vendor = "apple")]
+    pub unsafe fn copy_allocation(&mut self, allocation: &VmAllocation) -> io::Result<()> {
+        self.append_ool_raw(allocation.address(), allocation.size(), false)
+    }
+
+    /// Attaches a range of memory in the current task to a message as out-of-line memory, optionally deallocating it
+    /// from the current task on transmission.
+    ///
+    /// Page-aligned ranges are transferred without copying their contents.
+    ///
+    /// # Safety
+    ///
+    /// It is the responsibility of the caller to ensure that the memory lives until the message is sent or the memory
+    /// is removed from the message.
+    pub unsafe fn append_ool_raw(&mut self, address: u64, size: u64, deallocate: bool) -> io::Result<()> {
+        if size > sys::mach_msg_size_t::MAX as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "out-of-line memory too large for a single descriptor"));
+        }
+        let mut descriptor = sys::mach_msg_ool_descriptor_t {
+            address: address as *mut _,
+            _bitfield_1: mem::zeroed(),
+            size: size as sys::mach_msg_size_t,
+        };
+        descriptor.set_type(sys::MACH_MSG_OOL_DESCRIPTOR);
+        descriptor.set_deallocate(deallocate as _);
+        descriptor.set_copy(sys::MACH_MSG_VIRTUAL_COPY);
+        self.append_descriptor(descriptor);
+        Ok(())
+    }
+
     unsafe fn append_descriptor<T>(&mut self, descriptor: T) {
         // TODO: special case when there is no inline data to be shuffled?
         debug_assert!(mem::size_of::<T>() <= mem::size_of::<sys::mach_msg_descriptor_t>());
@@ -222,30 +301,94 @@ impl MsgBuffer {
         self.header_mut().msgh_bits |= sys::MACH_MSGH_BITS_COMPLEX;
         self.header_mut().msgh_size += mem::size_of::<T>() as sys::mach_msg_size_t;
         // Update reservations
-        if self.descriptor_count() as usize > self.capacity_descriptors {
-            self.capacity_descriptors = self.descriptor_count() as usize;
+        if self.descriptor_count() > self.capacity_descriptors {
+            self.capacity_descriptors = self.descriptor_count();
             self.update_reservation();
         }
     }
 }
 
+impl Default for MsgBuffer {
+    fn default() -> MsgBuffer {
+        MsgBuffer::new()
+    }
+}
+
 impl Msg {
+    /// Validates a message received from an untrusted source and copies it into a [`MsgBuffer`].
+    ///
+    /// The header size and every descriptor are checked against the bounds of `bytes` before any of them are used, so
+    /// this is safe to call on arbitrary input (e.g. from a fuzzer or a hostile client). Like messages built with
+    /// [`MsgBuffer`], the message must have a body even if it is not complex. Bytes past `msgh_size` (such as the
+    /// trailer) are ignored.
+    ///
+    /// Port names and out-of-line addresses in untrusted bytes do not refer to anything the caller owns, so they are
+    /// replaced with null; dispositions, sizes and descriptor types are kept.
+    pub fn parse_untrusted(bytes: &[u8]) -> io::Result<MsgBuffer> {
+        fn invalid(message: &'static str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        if bytes.len() < mem::size_of::<MessageStart>() {
+            return Err(invalid("message is shorter than its header"));
+        }
+        let start = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const MessageStart) };
+        let size = start.header.msgh_size as usize;
+        if size < mem::size_of::<MessageStart>() {
+            return Err(invalid("msgh_size is smaller than the message header"));
+        }
+        if size > bytes.len() {
+            return Err(invalid("msgh_size is larger than the message"));
+        }
+        if !size.is_multiple_of(4) {
+            return Err(invalid("msgh_size is not a multiple of 4"));
+        }
+
+        let complex = start.header.msgh_bits & sys::MACH_MSGH_BITS_COMPLEX != 0;
+        let count = start.body.msgh_descriptor_count as usize;
+        if !complex && count != 0 {
+            return Err(invalid("non-complex message has descriptors"));
+        }
+        // Every descriptor is at least as large as the common prefix, so this loop is bounded by the message size
+        let mut offset = mem::size_of::<MessageStart>();
+        for _ in 0..count {
+            if size - offset < mem::size_of::<sys::mach_msg_type_descriptor_t>() {
+                return Err(invalid("descriptor extends past the end of the message"));
+            }
+            let descriptor = unsafe { ptr::read_unaligned(bytes.as_ptr().add(offset) as *const sys::mach_msg_type_descriptor_t) };
+            let len = match descriptor.type_() {
+                sys::MACH_MSG_PORT_DESCRIPTOR => mem::size_of::<sys::mach_msg_port_descriptor_t>(),
+                sys::MACH_MSG_OOL_DESCRIPTOR | sys::MACH_MSG_OOL_VOLATILE_DESCRIPTOR => mem::size_of::<sys::mach_msg_ool_descriptor_t>(),
+                sys::MACH_MSG_OOL_PORTS_DESCRIPTOR => mem::size_of::<sys::mach_msg_ool_ports_descriptor_t>(),
+                _ => return Err(invalid("unknown descriptor type")),
+            };
+            if size - offset < len {
+                return Err(invalid("descriptor extends past the end of the message"));
+            }
+            offset += len;
+        }
+
+        let mut msg = MsgBuffer::from_bytes(&bytes[..size]);
+        msg.redact_rights();
+        Ok(msg)
+    }
+
     #[inline]
     pub fn inline_data(&self) -> &[u8] {
         debug_assert!(self.0.len() >= self.header().msgh_size as usize);
         let offset = mem::size_of::<MessageStart>() + self.descriptors_byte_len();
-        unsafe { slice::from_raw_parts(self.0.as_ptr().offset(offset as isize), self.header().msgh_size as usize - offset) }
+        unsafe { slice::from_raw_parts(self.0.as_ptr().add(offset), self.header().msgh_size as usize - offset) }
     }
 
     #[inline]
     pub fn inline_data_mut(&mut self) -> &mut [u8] {
         debug_assert!(self.0.len() >= self.header().msgh_size as usize);
         let offset = mem::size_of::<MessageStart>() + self.descriptors_byte_len();
-        unsafe { slice::from_raw_parts_mut(self.0.as_mut_ptr().offset(offset as isize), self.header().msgh_size as usize - offset) }
+        unsafe { slice::from_raw_parts_mut(self.0.as_mut_ptr().add(offset), self.header().msgh_size as usize - offset) }
     }
 
     #[inline]
-    pub fn descriptors(&self) -> MsgDescriptorIter {
+    pub fn descriptors(&self) -> MsgDescriptorIter<'_> {
         MsgDescriptorIter {
            rem_count: self.descriptor_count(),
            ptr: unsafe { self.0.as_ptr().add(mem::size_of::<MessageStart>()) as *const MsgDescriptor },
@@ -254,7 +397,7 @@ impl Msg {
     }
 
     #[inline]
-    pub fn descriptors_mut(&mut self) -> MsgDescriptorIterMut {
+    pub fn descriptors_mut(&mut self) -> MsgDescriptorIterMut<'_> {
         MsgDescriptorIterMut {
            rem_count: self.descriptor_count(),
            ptr: unsafe { self.0.as_mut_ptr().add(mem::size_of::<MessageStart>()) as *mut MsgDescriptor },
@@ -276,16 +419,100 @@ impl Msg {
     fn descriptors_byte_len(&self) -> usize {
         let mut iter = self.descriptors();
         let start_ptr = iter.ptr;
-        while let Some(_) = iter.next() {
+        for _ in &mut iter {
         }
         iter.ptr as usize - start_ptr as usize
     }
 
+    /// The total size of the out-of-line memory attached to the message.
+    pub fn ool_bytes(&self) -> u64 {
+        self.descriptors()
+            .map(|descriptor| match descriptor.kind() {
+                MsgDescriptorKind::Ool(descriptor) | MsgDescriptorKind::OolVolatile(descriptor) => {
+                    let ool = unsafe { &*(descriptor as *const MsgDescriptor as *const sys::mach_msg_ool_descriptor_t) };
+                    ool.size as u64
+                },
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// The message ID (`msgh_id`), which identifies the operation or notification the message carries.
+    #[inline]
+    pub fn id(&self) -> sys::mach_msg_id_t {
+        self.header().msgh_id
+    }
+
+    #[inline]
+    pub fn set_id(&mut self, id: sys::mach_msg_id_t) {
+        self.header_mut().msgh_id = id;
+    }
+
+    /// Attaches a copy of the send right to `voucher` to the message, or removes any attached voucher.
+    ///
+    /// Like the rest of the header, the voucher is cleared after a successful send, so it must be set again for each
+    /// message.
+    ///
+    /// # Safety
+    ///
+    /// It is the responsibility of the caller to ensure that the voucher lives until the message is sent or the voucher is
+    /// removed from the message.
+    #[cfg(target_vendor = "apple")]
+    pub unsafe fn set_voucher(&mut self, voucher: Option<&Voucher>) {
+        let header = self.header_mut();
+        let (port, disposition) = match voucher {
+            Some(voucher) => (voucher.as_raw_port(), sys::MACH_MSG_TYPE_COPY_SEND),
+            None => (sys::MACH_PORT_NULL, 0),
+        };
+        header.msgh_voucher_port = port;
+        header.msgh_bits = sys::MACH_MSGH_BITS_SET(
+            sys::MACH_MSGH_BITS_REMOTE(header.msgh_bits),
+            sys::MACH_MSGH_BITS_LOCAL(header.msgh_bits),
+            disposition,
+            header.msgh_bits,
+        );
+    }
+
     #[inline]
     pub fn complex(&self) -> bool {
         self.header().msgh_bits & sys::MACH_MSGH_BITS_COMPLEX != 0
     }
 
+    /// Replaces every port name and out-of-line address in the message with null, keeping only the metadata
+    /// (dispositions, sizes and descriptor types).
+    ///
+    /// The rights and memory themselves are not released, so this should only be applied to copies.
+    pub(crate) fn redact_rights(&mut self) {
+        {
+            let header = self.header_mut();
+            header.msgh_remote_port = sys::MACH_PORT_NULL;
+            header.msgh_local_port = sys::MACH_PORT_NULL;
+            header.msgh_voucher_port = sys::MACH_PORT_NULL;
+        }
+        if !self.complex() {
+            return;
+        }
+        for descriptor in self.descriptors_mut() {
+            match descriptor.kind_mut() {
+                MsgDescriptorKindMut::Port(descriptor) => descriptor.0.name = sys::MACH_PORT_NULL,
+                MsgDescriptorKindMut::Ool(descriptor) | MsgDescriptorKindMut::OolVolatile(descriptor) => unsafe {
+                    (*(descriptor as *mut MsgDescriptor as *mut sys::mach_msg_ool_descriptor_t)).address = ptr::null_mut();
+                },
+                MsgDescriptorKindMut::OolPorts(descriptor) => unsafe {
+                    (*(descriptor as *mut MsgDescriptor as *mut sys::mach_msg_ool_ports_descriptor_t)).address = ptr::null_mut();
+                },
+            }
+        }
+    }
+
+    /// The raw bytes of the message, as described by the header's `msgh_size`.
+    #[inline]
+    #[cfg_attr(not(any(target_vendor = "apple", feature = "record")), allow(dead_code))]
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        debug_assert!(self.0.len() >= self.header().msgh_size as usize);
+        unsafe { slice::from_raw_parts(self.0.as_ptr(), self.header().msgh_size as usize) }
+    }
+
     #[inline]
     pub(crate) fn header(&self) -> &sys::mach_msg_header_t {
         debug_assert!(self.0.len() >= mem::size_of::<sys::mach_msg_header_t>());
@@ -301,23 +528,23 @@ impl Msg {
 
 impl MsgDescriptor {
     #[inline]
-    pub fn kind(&self) -> MsgDescriptorKind {
+    pub fn kind(&self) -> MsgDescriptorKind<'_> {
         match self.0.type_() {
             sys::MACH_MSG_PORT_DESCRIPTOR => MsgDescriptorKind::Port(unsafe { &*(self as *const _ as *const MsgPortDescriptor) }),
-            sys::MACH_MSG_OOL_DESCRIPTOR => MsgDescriptorKind::Ool(unsafe { &*(self as *const _ as *const MsgDescriptor) }),
-            sys::MACH_MSG_OOL_PORTS_DESCRIPTOR => MsgDescriptorKind::OolPorts(unsafe { &*(self as *const _ as *const MsgDescriptor) }),
-            sys::MACH_MSG_OOL_VOLATILE_DESCRIPTOR => MsgDescriptorKind::OolVolatile(unsafe { &*(self as *const _ as *const MsgDescriptor) }),
+            sys::MACH_MSG_OOL_DESCRIPTOR => MsgDescriptorKind::Ool(self),
+            sys::MACH_MSG_OOL_PORTS_DESCRIPTOR => MsgDescriptorKind::OolPorts(self),
+            sys::MACH_MSG_OOL_VOLATILE_DESCRIPTOR => MsgDescriptorKind::OolVolatile(self),
             _ => unreachable!(), 
         }
     }
 
     #[inline]
-    pub fn kind_mut(&mut self) -> MsgDescriptorKindMut {
+    pub fn kind_mut(&mut self) -> MsgDescriptorKindMut<'_> {
         match self.0.type_() {
             sys::MACH_MSG_PORT_DESCRIPTOR => MsgDescriptorKindMut::Port(unsafe { &mut *(self as *mut _ as *mut MsgPortDescriptor) }),
-            sys::MACH_MSG_OOL_DESCRIPTOR => MsgDescriptorKindMut::Ool(unsafe { &mut *(self as *mut _ as *mut MsgDescriptor) }),
-            sys::MACH_MSG_OOL_PORTS_DESCRIPTOR => MsgDescriptorKindMut::OolPorts(unsafe { &mut *(self as *mut _ as *mut MsgDescriptor) }),
-            sys::MACH_MSG_OOL_VOLATILE_DESCRIPTOR => MsgDescriptorKindMut::OolVolatile(unsafe { &mut *(self as *mut _ as *mut MsgDescriptor) }),
+            sys::MACH_MSG_OOL_DESCRIPTOR => MsgDescriptorKindMut::Ool(self),
+            sys::MACH_MSG_OOL_PORTS_DESCRIPTOR => MsgDescriptorKindMut::OolPorts(self),
+            sys::MACH_MSG_OOL_VOLATILE_DESCRIPTOR => MsgDescriptorKindMut::OolVolatile(self),
             _ => unreachable!(), 
         }
     }
@@ -505,7 +732,7 @@ impl fmt::Debug for Msg {
 
 impl fmt::Debug for MsgBuffer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        (&**self).fmt(f)
+        (**self).fmt(f)
     }
 }
 
@@ -513,14 +740,106 @@ impl Deref for MsgBuffer {
     type Target = Msg;
 
     fn deref(&self) -> &Msg {
-        let gen: &MsgImpl = self;
+        let gen: &dyn MsgImpl = self;
         unsafe { mem::transmute(gen) }
     }
 }
 
 impl DerefMut for MsgBuffer {
     fn deref_mut(&mut self) -> &mut Msg {
-        let gen: &mut MsgImpl = self;
+        let gen: &mut dyn MsgImpl = self;
         unsafe { mem::transmute(gen) }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER_SIZE: usize = mem::size_of::<MessageStart>();
+    const DESCRIPTOR_TYPE_OFFSET: usize = HEADER_SIZE + 11;
+
+    // A complex message carrying a port descriptor and 8 bytes of inline data
+    fn message() -> Vec<u8> {
+        let mut msg = MsgBuffer::new();
+        msg.set_id(42);
+        unsafe { msg.copy_right_raw(PortCopyMode::Send, 0x1234); }
+        msg.extend_inline_data(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        msg.as_bytes().to_vec()
+    }
+
+    fn set_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+    }
+
+    fn get_u32(bytes: &[u8], offset: usize) -> u32 {
+        let mut value = [0u8; 4];
+        value.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_ne_bytes(value)
+    }
+
+    fn parse_error(bytes: &[u8]) -> String {
+        let err = Msg::parse_untrusted(bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.to_string()
+    }
+
+    #[test]
+    fn parse_untrusted_valid() {
+        let mut bytes = message();
+        // Trailing bytes past msgh_size are ignored
+        bytes.extend_from_slice(&[0xff; 8]);
+        let msg = Msg::parse_untrusted(&bytes).unwrap();
+        assert_eq!(msg.id(), 42);
+        assert!(msg.complex());
+        assert_eq!(msg.inline_data(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(msg.descriptor_count(), 1);
+        // The port name doesn't refer to anything the caller owns
+        match msg.descriptors().next().unwrap().kind() {
+            MsgDescriptorKind::Port(port) => assert_eq!(port.0.name, sys::MACH_PORT_NULL),
+            _ => panic!("expected a port descriptor"),
+        }
+    }
+
+    #[test]
+    fn parse_untrusted_truncated_header() {
+        let bytes = message();
+        assert_eq!(parse_error(&bytes[..HEADER_SIZE - 1]), "message is shorter than its header");
+        assert_eq!(parse_error(&[]), "message is shorter than its header");
+    }
+
+    #[test]
+    fn parse_untrusted_bad_size() {
+        let mut bytes = message();
+        let size = get_u32(&bytes, 4);
+        set_u32(&mut bytes, 4, size + 4);
+        assert_eq!(parse_error(&bytes), "msgh_size is larger than the message");
+        set_u32(&mut bytes, 4, HEADER_SIZE as u32 - 4);
+        assert_eq!(parse_error(&bytes), "msgh_size is smaller than the message header");
+        set_u32(&mut bytes, 4, size - 2);
+        assert_eq!(parse_error(&bytes), "msgh_size is not a multiple of 4");
+    }
+
+    #[test]
+    fn parse_untrusted_descriptor_count_past_end() {
+        let mut bytes = message();
+        set_u32(&mut bytes, HEADER_SIZE - 4, 2);
+        assert_eq!(parse_error(&bytes), "descriptor extends past the end of the message");
+        set_u32(&mut bytes, HEADER_SIZE - 4, u32::MAX);
+        assert_eq!(parse_error(&bytes), "descriptor extends past the end of the message");
+    }
+
+    #[test]
+    fn parse_untrusted_unknown_descriptor_type() {
+        let mut bytes = message();
+        bytes[DESCRIPTOR_TYPE_OFFSET] = 0x7f;
+        assert_eq!(parse_error(&bytes), "unknown descriptor type");
+    }
+
+    #[test]
+    fn parse_untrusted_descriptors_without_complex() {
+        let mut bytes = message();
+        let bits = get_u32(&bytes, 0);
+        set_u32(&mut bytes, 0, bits & !sys::MACH_MSGH_BITS_COMPLEX);
+        assert_eq!(parse_error(&bytes), "non-complex message has descriptors");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{RawPort, Msg};
+use crate::kernel::kernel;
 
 use std::{io, mem, fmt};
 use std::time::Duration;
@@ -10,53 +11,72 @@ pub struct Port {
     port: sys::mach_port_name_t,
     has_receive: bool,
     has_send: bool,
+    #[cfg(feature = "leak-tracker")]
+    tracker_id: u64,
 }
 
 impl Drop for Port {
     fn drop(&mut self) {
-        unsafe {
-            if self.has_receive {
-                let _ = mach_call!(log: sys::mach_port_mod_refs(sys::mach_task_self(), self.port, sys::MACH_PORT_RIGHT_RECEIVE, -1), "freeing receive right with mach_port_mod_refs failed: {:?}");
-            }
-            if self.has_send {
-                // If the receive right is already dead, this returns
-                match sys::mach_port_mod_refs(sys::mach_task_self(), self.port, sys::MACH_PORT_RIGHT_SEND, -1) as u32 {
-                    sys::KERN_SUCCESS | sys::KERN_INVALID_RIGHT => (),
-                    code => {
-                        let err = mach_core::error::rust_from_mach_error(code as _);
-                        error!("freeing send right with mach_port_mod_refs failed: {:?}", err);
-                    },
-                }
+        #[cfg(feature = "leak-tracker")]
+        crate::leak_tracker::untrack(self.tracker_id);
+        trace!("releasing {:?}", self);
+        if self.has_receive {
+            let _ = mach_call!(log op: "mach_port_mod_refs(receive)", port: self.port, kernel().port_mod_refs(self.port, sys::MACH_PORT_RIGHT_RECEIVE, -1));
+        }
+        if self.has_send {
+            // If the receive right is already dead, the send right has become a dead name
+            let result = match kernel().port_mod_refs(self.port, sys::MACH_PORT_RIGHT_SEND, -1) as u32 {
+                sys::KERN_INVALID_RIGHT => mach_call!(op: "mach_port_mod_refs(dead name)", port: self.port, kernel().port_mod_refs(self.port, sys::MACH_PORT_RIGHT_DEAD_NAME, -1)),
+                code => mach_call!(op: "mach_port_mod_refs(send)", port: self.port, code as sys::kern_return_t),
+            };
+            if let Err(err) = result {
+                error!("{}", err);
             }
         }
+        // Other `Port`s may still hold rights under the name, so the label stays until the name itself is gone (after
+        // which the kernel may reuse it for an unrelated port)
+        let mut ty: sys::mach_port_type_t = 0;
+        if kernel().port_type(self.port, &mut ty) as u32 == sys::KERN_INVALID_NAME {
+            mach_core::label::clear_label(self.port);
+        }
     }
 }
 
 impl Port {
     pub fn new() -> io::Result<Port> {
-        unsafe {
-            let mut port: sys::mach_port_t = 0;
-            mach_call!(log: sys::mach_port_allocate(sys::mach_task_self(), sys::MACH_PORT_RIGHT_RECEIVE, &mut port), "mach_port_allocate failed: {:?}")?;
-            let port = Port {
-                port,
-                has_receive: true,
-                has_send: false,
-            };
-            Ok(port)
-        }
+        let mut port: sys::mach_port_t = 0;
+        mach_call!(log op: "mach_port_allocate", kernel().port_allocate_receive(&mut port))?;
+        Ok(Port::with_rights(port, true, false))
     }
 
+    /// Takes ownership of the rights held under `port`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own the rights, and nothing else may release them.
     // TODO: rename has_{send,receive} to own_{send,receive}, and make this function specify (current method cannot always roundtrip a Port)
     pub unsafe fn from_raw_port(port: RawPort) -> io::Result<Self> {
         let mut ty: sys::mach_port_type_t = 0;
-        mach_call!(log: sys::mach_port_type(sys::mach_task_self(), port, &mut ty), "mach_port_type failed: {:?}")?;
+        mach_call!(log op: "mach_port_type", port: port, kernel().port_type(port, &mut ty))?;
         // TODO: support send-once
 
-        Ok(Port {
-            port,
-            has_send: ty & sys::MACH_PORT_TYPE_SEND != 0,
-            has_receive: ty & sys::MACH_PORT_TYPE_RECEIVE != 0,
-        })
+        Ok(Port::with_rights(port, ty & sys::MACH_PORT_TYPE_RECEIVE != 0, ty & sys::MACH_PORT_TYPE_SEND != 0))
+    }
+
+    /// Takes ownership of a single send right, e.g. one the kernel just handed over in an out parameter.
+    ///
+    /// Unlike [`from_raw_port`](Port::from_raw_port) this doesn't ask the kernel which rights the name holds, so it
+    /// can't fail, and doesn't claim a receive right the caller happens to hold under the same name.
+    #[cfg(target_vendor = "apple")]
+    pub(crate) unsafe fn from_raw_send_right(port: RawPort) -> Port {
+        Port::with_rights(port, false, true)
+    }
+
+    /// Takes ownership of a receive right, e.g. one just extracted from another task, without claiming any send
+    /// rights held under the same name.
+    #[cfg(target_vendor = "apple")]
+    pub(crate) unsafe fn from_raw_receive_right(port: RawPort) -> Port {
+        Port::with_rights(port, true, false)
     }
 
     pub fn as_raw_port(&self) -> RawPort {
@@ -64,26 +84,47 @@ impl Port {
     }
 
     pub fn into_raw_port(self) -> RawPort {
+        #[cfg(feature = "leak-tracker")]
+        crate::leak_tracker::untrack(self.tracker_id);
         let port = self.port;
         mem::forget(self);
         port
     }
 
+    /// Attaches a human-readable label to the port's name, which is included in `Debug` output, error messages and
+    /// leak reports.
+    ///
+    /// Labels are keyed by name, so they are shared by every `Port` holding rights under the same name. The label is
+    /// removed when the last of those rights is released.
+    pub fn set_label<S: Into<String>>(&self, label: S) {
+        mach_core::label::set_label(self.port, label.into());
+    }
+
+    #[inline]
+    pub fn label(&self) -> Option<String> {
+        mach_core::label::label(self.port)
+    }
+
+    // Takes ownership of newly created rights, registering them with the leak tracker
+    #[inline]
+    fn with_rights(port: RawPort, has_receive: bool, has_send: bool) -> Port {
+        Port {
+            port,
+            has_receive,
+            has_send,
+            #[cfg(feature = "leak-tracker")]
+            tracker_id: crate::leak_tracker::track(port, has_receive, has_send),
+        }
+    }
+
     pub fn make_sender(&self) -> io::Result<Port> {
-        unsafe {
-            let mut port: sys::mach_port_t = 0;
-            let mut right: sys::mach_msg_type_name_t = 0;
-            mach_call!(log: sys::mach_port_extract_right(sys::mach_task_self(), self.port, sys::MACH_MSG_TYPE_MAKE_SEND, &mut port, &mut right), "mach_port_extract_right failed: {:?}")?;
-            if right != sys::MACH_MSG_TYPE_PORT_SEND {
-                return Err(io::Error::new(io::ErrorKind::Other, "mach_port_extract_right did not return requested right type"));
-            }
-            let port = Port {
-                port,
-                has_receive: false,
-                has_send: true,
-            };
-            Ok(port)
+        let mut port: sys::mach_port_t = 0;
+        let mut right: sys::mach_msg_type_name_t = 0;
+        mach_call!(log op: "mach_port_extract_right", port: self.port, kernel().port_make_send(self.port, &mut port, &mut right))?;
+        if right != sys::MACH_MSG_TYPE_PORT_SEND {
+            return Err(io::Error::other("mach_port_extract_right did not return requested right type"));
         }
+        Ok(Port::with_rights(port, false, true))
     }
 
     pub fn send(&self, msg: &mut Msg, timeout: Option<Duration>) -> io::Result<()> {
@@ -91,11 +132,15 @@ impl Port {
             let mut flags = sys::MACH_SEND_MSG;
             let mut timeout_arg = sys::MACH_MSG_TIMEOUT_NONE as sys::mach_msg_timeout_t;
             if let Some(duration) = timeout {
-                flags |= sys::MACH_RCV_TIMEOUT;
+                flags |= sys::MACH_SEND_TIMEOUT;
                 timeout_arg = convert_timeout(duration);
             }
             msg.header_mut().msgh_remote_port = self.port;
-            let result = mach_call!(sys::mach_msg(
+            #[cfg(feature = "metrics")]
+            let stats = crate::metrics::MsgStats::of(msg);
+            #[cfg(all(target_vendor = "apple", feature = "signpost"))]
+            let _interval = crate::signpost::Interval::begin(crate::signpost::SignpostCode::SEND, [self.port as usize, msg.header().msgh_id as usize, 0, 0]);
+            let result = mach_call!(op: "mach_msg(send)", port: self.port, kernel().msg(
                 msg.0.as_ptr() as *mut _,
                 flags as _,
                 msg.header().msgh_size,
@@ -105,7 +150,17 @@ impl Port {
                 sys::MACH_PORT_NULL,
             ));
             msg.header_mut().msgh_remote_port = sys::MACH_PORT_NULL;
+            #[cfg(feature = "metrics")]
+            match result {
+                Ok(()) => crate::metrics::record_send(&stats),
+                Err(ref err) => if let Some(err) = mach_core::error::MachError::from_io(err) {
+                    crate::metrics::record_send_failure(err.code());
+                },
+            }
             result?;
+            trace!("sent message {} to {:?}", msg.header().msgh_id, self);
+            #[cfg(feature = "record")]
+            crate::record::record(crate::record::Direction::Sent, self.port, msg);
             msg.0.reset_on_send();
             Ok(())
         }
@@ -119,7 +174,9 @@ impl Port {
                 flags |= sys::MACH_RCV_TIMEOUT;
                 timeout_arg = convert_timeout(duration);
             }
-            mach_call!(sys::mach_msg(
+            #[cfg(all(target_vendor = "apple", feature = "signpost"))]
+            let _interval = crate::signpost::Interval::begin(crate::signpost::SignpostCode::RECEIVE, [self.port as usize, 0, 0, 0]);
+            mach_call!(op: "mach_msg(receive)", port: self.port, kernel().msg(
                 msg.0.as_mut_ptr() as *mut _,
                 flags as _,
                 0,
@@ -131,6 +188,11 @@ impl Port {
 
             let size = msg.header().msgh_size;
             msg.0.set_len(size as usize);
+            trace!("received message {} on {:?}", msg.header().msgh_id, self);
+            #[cfg(feature = "metrics")]
+            crate::metrics::record_receive(msg);
+            #[cfg(feature = "record")]
+            crate::record::record(crate::record::Direction::Received, self.port, msg);
 
             Ok(())
         }
@@ -141,18 +203,66 @@ impl fmt::Debug for Port {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Port")
             .field("port", &format_args!("{:#x?}", self.port))
+            .field("label", &self.label())
             .field("has_receive", &self.has_receive)
             .field("has_send", &self.has_send)
             .finish()
     }
 }
 
-fn convert_timeout(duration: Duration) -> sys::mach_msg_timeout_t {
+pub(crate) fn convert_timeout(duration: Duration) -> sys::mach_msg_timeout_t {
     duration
         .as_secs()
         .checked_mul(1000)
         .and_then(|x| x.checked_add(duration.subsec_millis() as u64))
-        .filter(|&x| x <= std::i32::MAX as u64)
+        .filter(|&x| x <= i32::MAX as u64)
         .map(|x| x as i32)
-        .unwrap_or(std::i32::MAX) as sys::mach_msg_timeout_t
-}
\ No newline at end of file
+        .unwrap_or(i32::MAX) as sys::mach_msg_timeout_t
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MsgBuffer;
+
+    #[test]
+    fn label_outlives_receive_right_while_sender_alive() {
+        let port = Port::new().unwrap();
+        let name = port.as_raw_port();
+        let sender = port.make_sender().unwrap();
+        port.set_label("test:receive-first");
+        drop(port);
+        assert_eq!(sender.label().as_deref(), Some("test:receive-first"));
+        drop(sender);
+        assert_eq!(mach_core::label::label(name), None);
+        let mut ty = 0;
+        assert_eq!(kernel().port_type(name, &mut ty) as u32, sys::KERN_INVALID_NAME);
+    }
+
+    #[test]
+    fn label_outlives_sender_while_receive_right_alive() {
+        let port = Port::new().unwrap();
+        let name = port.as_raw_port();
+        let sender = port.make_sender().unwrap();
+        sender.set_label("test:sender-first");
+        drop(sender);
+        assert_eq!(port.label().as_deref(), Some("test:sender-first"));
+        drop(port);
+        assert_eq!(mach_core::label::label(name), None);
+    }
+
+    #[test]
+    fn send_times_out_when_queue_is_full() {
+        let port = Port::new().unwrap();
+        let sender = port.make_sender().unwrap();
+        let timeout = Duration::from_millis(10);
+        let err = (0..64)
+            .map(|id| {
+                let mut msg = MsgBuffer::new();
+                msg.set_id(id);
+                sender.send(&mut msg, Some(timeout))
+            })
+            .find_map(Result::err)
+            .expect("queue never filled");
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
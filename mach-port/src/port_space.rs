@@ -0,0 +1,155 @@
+use crate::{RawPort, Task, TaskFlavor};
+
+use std::{io, mem, ptr, slice};
+use std::collections::HashMap;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// An entry in a task's port name table.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PortNameInfo {
+    pub name: RawPort,
+    /// The rights held under the name (`MACH_PORT_TYPE_*` bits).
+    pub port_type: sys::mach_port_type_t,
+    /// The number of user references to the name's send or dead name right.
+    pub urefs: u32,
+    /// An opaque (and per-boot obfuscated) identifier for the kernel port object, which is the same in every task
+    /// holding rights to the port.
+    pub object: u32,
+}
+
+impl PortNameInfo {
+    #[inline]
+    pub fn has_receive(&self) -> bool {
+        self.port_type & sys::MACH_PORT_TYPE_RECEIVE != 0
+    }
+
+    #[inline]
+    pub fn has_send(&self) -> bool {
+        self.port_type & sys::MACH_PORT_TYPE_SEND != 0
+    }
+
+    #[inline]
+    pub fn has_send_once(&self) -> bool {
+        self.port_type & sys::MACH_PORT_TYPE_SEND_ONCE != 0
+    }
+
+    #[inline]
+    pub fn is_port_set(&self) -> bool {
+        self.port_type & sys::MACH_PORT_TYPE_PORT_SET != 0
+    }
+
+    #[inline]
+    pub fn is_dead_name(&self) -> bool {
+        self.port_type & sys::MACH_PORT_TYPE_DEAD_NAME != 0
+    }
+}
+
+/// A snapshot of a task's port name space, as reported by `mach_port_space_info`.
+#[derive(Clone, Debug)]
+pub struct PortSpaceSnapshot {
+    /// The number of slots in the name table, which grows as needed and never shrinks.
+    pub table_size: u32,
+    pub names: Vec<PortNameInfo>,
+}
+
+/// The changes between two [`PortSpaceSnapshot`]s.
+#[derive(Clone, Debug, Default)]
+pub struct PortSpaceDiff {
+    /// Names present only in the later snapshot.
+    pub added: Vec<PortNameInfo>,
+    /// Names present only in the earlier snapshot.
+    pub removed: Vec<PortNameInfo>,
+    /// Names present in both snapshots whose rights or reference counts changed, as (earlier, later) pairs.
+    pub changed: Vec<(PortNameInfo, PortNameInfo)>,
+}
+
+impl PortSpaceDiff {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// The net change in the number of names in use.
+    #[inline]
+    pub fn net_growth(&self) -> isize {
+        self.added.len() as isize - self.removed.len() as isize
+    }
+}
+
+impl PortSpaceSnapshot {
+    /// The number of names holding any of the rights in `port_type`.
+    pub fn count(&self, port_type: sys::mach_port_type_t) -> usize {
+        self.names.iter().filter(|info| info.port_type & port_type != 0).count()
+    }
+
+    /// Compares this snapshot against an `earlier` one, e.g. to assert that an operation did not leak rights.
+    pub fn diff(&self, earlier: &PortSpaceSnapshot) -> PortSpaceDiff {
+        let earlier_names: HashMap<RawPort, &PortNameInfo> = earlier.names.iter().map(|info| (info.name, info)).collect();
+        let later_names: HashMap<RawPort, &PortNameInfo> = self.names.iter().map(|info| (info.name, info)).collect();
+        let mut diff = PortSpaceDiff::default();
+        for info in self.names.iter() {
+            match earlier_names.get(&info.name) {
+                None => diff.added.push(*info),
+                Some(old) if old.port_type != info.port_type || old.urefs != info.urefs || old.object != info.object => {
+                    diff.changed.push((**old, *info));
+                },
+                Some(_) => (),
+            }
+        }
+        for info in earlier.names.iter() {
+            if !later_names.contains_key(&info.name) {
+                diff.removed.push(*info);
+            }
+        }
+        diff
+    }
+}
+
+impl Task {
+    /// Takes a snapshot of the task's port name space.
+    pub fn port_space(&self) -> io::Result<PortSpaceSnapshot> {
+        self.require(TaskFlavor::Read)?;
+        unsafe {
+            let mut space_info: sys::ipc_info_space_t = mem::zeroed();
+            let mut table: sys::ipc_info_name_array_t = ptr::null_mut();
+            let mut table_count: sys::mach_msg_type_number_t = 0;
+            let mut tree: sys::ipc_info_tree_name_array_t = ptr::null_mut();
+            let mut tree_count: sys::mach_msg_type_number_t = 0;
+            mach_call!(log op: "mach_port_space_info", port: self.as_raw_port(), sys::mach_port_space_info(
+                self.as_raw_port(), &mut space_info, &mut table, &mut table_count, &mut tree, &mut tree_count,
+            ))?;
+
+            // An empty table may come back as a null pointer, which from_raw_parts doesn't accept
+            let table_entries: &[sys::ipc_info_name_t] = if table.is_null() || table_count == 0 {
+                &[]
+            } else {
+                slice::from_raw_parts(table, table_count as usize)
+            };
+            let names = table_entries.iter()
+                // Free slots in the table have no rights
+                .filter(|entry| entry.iin_type != 0)
+                .map(|entry| PortNameInfo {
+                    name: entry.iin_name,
+                    port_type: entry.iin_type,
+                    urefs: entry.iin_urefs,
+                    object: entry.iin_object,
+                })
+                .collect();
+
+            // Both arrays are returned out-of-line
+            if !table.is_null() {
+                let _ = mach_call!(log op: "mach_vm_deallocate(port name table)", sys::mach_vm_deallocate(mach_core::self_port::task_self(), table as _, (table_count as usize * mem::size_of::<sys::ipc_info_name_t>()) as _));
+            }
+            if !tree.is_null() && tree_count != 0 {
+                let _ = mach_call!(log op: "mach_vm_deallocate(port name tree)", sys::mach_vm_deallocate(mach_core::self_port::task_self(), tree as _, (tree_count as usize * mem::size_of::<sys::ipc_info_tree_name_t>()) as _));
+            }
+
+            Ok(PortSpaceSnapshot {
+                table_size: space_info.iis_table_size,
+                names,
+            })
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{RawPort, Port, Task, TaskFlavor};
+use crate::kernel::kernel;
+
+use std::{io, mem};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// The state of a receive right (`MACH_PORT_RECEIVE_STATUS`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ReceiveStatus {
+    /// The port set containing the port, or `MACH_PORT_NULL`.
+    pub port_set: RawPort,
+    /// The sequence number the next received message will carry.
+    pub seqno: u32,
+    /// The make-send count.
+    pub mscount: u32,
+    /// The maximum number of queued messages before senders block.
+    pub qlimit: u32,
+    /// The number of messages currently queued.
+    pub msgcount: u32,
+    /// The number of outstanding send-once rights.
+    pub sorights: u32,
+    /// Whether any send rights exist.
+    pub srights: bool,
+    /// Whether a port-destroyed notification has been requested.
+    pub pdrequest: bool,
+    /// Whether a no-senders notification has been requested.
+    pub nsrequest: bool,
+    pub flags: u32,
+}
+
+/// The metadata of a queued message, as returned by [`Task::peek_message`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PeekedMessage {
+    pub seqno: u32,
+    pub size: u32,
+    pub id: sys::mach_msg_id_t,
+}
+
+impl Task {
+    /// Retrieves the state of the receive right named `name` in the task's namespace.
+    pub fn receive_status(&self, name: RawPort) -> io::Result<ReceiveStatus> {
+        self.require(TaskFlavor::Read)?;
+        unsafe {
+            let mut status: sys::mach_port_status_t = mem::zeroed();
+            let mut count = (mem::size_of::<sys::mach_port_status_t>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+            mach_call!(log op: "mach_port_get_attributes", port: self.as_raw_port(), sys::mach_port_get_attributes(
+                self.as_raw_port(), name, sys::MACH_PORT_RECEIVE_STATUS as _, &mut status as *mut _ as sys::mach_port_info_t, &mut count,
+            ))?;
+            Ok(ReceiveStatus {
+                port_set: status.mps_pset,
+                seqno: status.mps_seqno,
+                mscount: status.mps_mscount,
+                qlimit: status.mps_qlimit,
+                msgcount: status.mps_msgcount,
+                sorights: status.mps_sorights,
+                srights: status.mps_srights != 0,
+                pdrequest: status.mps_pdrequest != 0,
+                nsrequest: status.mps_nsrequest != 0,
+                flags: status.mps_flags,
+            })
+        }
+    }
+
+    /// Inspects a message queued on the receive right named `name` without dequeuing it.
+    ///
+    /// If `seqno` is `None`, the message at the head of the queue is returned. Returns `None` if there is no such
+    /// message.
+    pub fn peek_message(&self, name: RawPort, seqno: Option<u32>) -> io::Result<Option<PeekedMessage>> {
+        self.require(TaskFlavor::Read)?;
+        unsafe {
+            let mut seqno = seqno.unwrap_or(0);
+            let mut size: sys::mach_msg_size_t = 0;
+            let mut id: sys::mach_msg_id_t = 0;
+            let mut trailer: sys::mach_msg_max_trailer_t = mem::zeroed();
+            let mut trailer_size = mem::size_of::<sys::mach_msg_max_trailer_t>() as sys::mach_msg_type_number_t;
+            let code = sys::mach_port_peek(
+                self.as_raw_port(), name, (sys::MACH_RCV_TRAILER_TYPE(sys::MACH_MSG_TRAILER_FORMAT_0) | sys::MACH_RCV_TRAILER_ELEMENTS(sys::MACH_RCV_TRAILER_NULL)) as _,
+                &mut seqno, &mut size, &mut id, &mut trailer as *mut _ as *mut _, &mut trailer_size,
+            );
+            match code as u32 {
+                // Returned when the queue is empty or has no message with the requested sequence number
+                sys::KERN_FAILURE => return Ok(None),
+                _ => mach_call!(log op: "mach_port_peek", port: self.as_raw_port(), code)?,
+            }
+            Ok(Some(PeekedMessage { seqno, size, id }))
+        }
+    }
+}
+
+impl Port {
+    /// Retrieves the state of the port's receive right.
+    pub fn receive_status(&self) -> io::Result<ReceiveStatus> {
+        Task::current().receive_status(self.as_raw_port())
+    }
+
+    /// Requests that a dead-name notification (`MACH_NOTIFY_DEAD_NAME`) be sent to `notify` when the receive right
+    /// for this port is destroyed.
+    ///
+    /// The port must hold a send right.
+    pub fn request_dead_name_notification(&self, notify: &Port) -> io::Result<()> {
+        let mut previous: sys::mach_port_t = sys::MACH_PORT_NULL;
+        mach_call!(log op: "mach_port_request_notification", port: self.as_raw_port(), kernel().port_request_notification(
+            self.as_raw_port(), sys::MACH_NOTIFY_DEAD_NAME as _, notify.as_raw_port(), sys::MACH_MSG_TYPE_MAKE_SEND_ONCE, &mut previous,
+        ))?;
+        // Any previously registered notification port is returned as a send-once right
+        if previous != sys::MACH_PORT_NULL {
+            let _ = mach_call!(log op: "mach_port_deallocate(previous notification right)", port: previous, kernel().port_deallocate(previous));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::Task;
+use crate::host::HostPriv;
+
+use std::{io, mem, ptr, slice};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+impl HostPriv {
+    /// Enumerates the tasks of every process on the system through the default processor set.
+    ///
+    /// With System Integrity Protection enabled, tasks of protected processes are still omitted.
+    pub fn all_tasks(&self) -> io::Result<Vec<Task>> {
+        unsafe {
+            let mut pset_name: sys::processor_set_name_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "processor_set_default", port: self.as_raw_port(), sys::processor_set_default(self.as_raw_port(), &mut pset_name))?;
+            let pset_name = OwnedName(pset_name);
+
+            let mut pset: sys::processor_set_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "host_processor_set_priv", port: self.as_raw_port(), sys::host_processor_set_priv(self.as_raw_port(), pset_name.0, &mut pset))?;
+            let pset = OwnedName(pset);
+
+            let mut list: sys::task_array_t = ptr::null_mut();
+            let mut count: sys::mach_msg_type_number_t = 0;
+            mach_call!(log op: "processor_set_tasks", port: pset.0, sys::processor_set_tasks(pset.0, &mut list, &mut count))?;
+            // Take ownership of the task ports before anything else can fail
+            let tasks = slice::from_raw_parts(list, count as usize).iter()
+                .map(|&port| Task::from_raw_port(port))
+                .collect();
+            let _ = mach_call!(log op: "mach_vm_deallocate(task list)", sys::mach_vm_deallocate(
+                mach_core::self_port::task_self(),
+                list as sys::mach_vm_address_t,
+                (count as usize * mem::size_of::<sys::task_t>()) as sys::mach_vm_size_t,
+            ));
+            Ok(tasks)
+        }
+    }
+}
+
+// Send right released when the enumeration is done
+struct OwnedName(sys::mach_port_t);
+
+impl Drop for OwnedName {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_port_deallocate", port: self.0, sys::mach_port_deallocate(mach_core::self_port::task_self(), self.0));
+        }
+    }
+}
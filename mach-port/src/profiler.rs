@@ -0,0 +1,167 @@
+//! A sampling profiler built on thread suspension and register capture.
+//!
+//! Each sampling pass suspends every thread of the target task in turn (except the sampling thread itself), reads
+//! its program counter and frame pointer, optionally walks the frame pointer chain through memory reads, and resumes
+//! it. Frame walking relies on the target being compiled with frame pointers, which is the default on Darwin.
+
+use crate::{RawPort, Task, TaskFlavor, Thread, ThreadState, NativeThreadState};
+
+use std::{io, mem, thread};
+use std::time::{Duration, Instant};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// A single stack sample of one thread.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub thread_id: u64,
+    pub timestamp: Instant,
+    pub pc: u64,
+    pub fp: u64,
+    /// Return addresses recovered by walking the frame pointer chain, innermost first. Empty if frame walking is
+    /// disabled.
+    pub frames: Vec<u64>,
+}
+
+/// Periodically samples the threads of a task.
+pub struct Profiler<'a> {
+    task: &'a Task,
+    interval: Duration,
+    max_frames: usize,
+}
+
+impl<'a> Profiler<'a> {
+    pub fn new(task: &'a Task) -> Profiler<'a> {
+        Profiler {
+            task,
+            interval: Duration::from_millis(1),
+            max_frames: 0,
+        }
+    }
+
+    /// Sets the delay between sampling passes. Defaults to one millisecond.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Walks up to `max_frames` stack frames for each sample. Defaults to zero (only PC and FP are captured).
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Takes one sample of every thread in the task.
+    ///
+    /// Threads that exit during the pass are skipped.
+    pub fn sample_once(&self) -> io::Result<Vec<Sample>> {
+        if self.max_frames > 0 {
+            self.task.require(TaskFlavor::Read)?;
+        }
+        let current = Thread::current();
+        let threads = self.task.threads()?;
+        let mut samples = Vec::with_capacity(threads.len());
+        for thread in threads.iter() {
+            if thread.as_raw_port() == current.as_raw_port() {
+                continue;
+            }
+            if let Some(sample) = self.sample_thread(thread)? {
+                samples.push(sample);
+            }
+        }
+        Ok(samples)
+    }
+
+    /// Samples the task repeatedly, passing each pass's samples to `sink` until it returns `false`.
+    pub fn run<F>(&self, mut sink: F) -> io::Result<()> where
+        F: FnMut(&[Sample]) -> bool,
+    {
+        loop {
+            let samples = self.sample_once()?;
+            if !sink(&samples) {
+                return Ok(());
+            }
+            thread::sleep(self.interval);
+        }
+    }
+
+    fn sample_thread(&self, thread: &Thread) -> io::Result<Option<Sample>> {
+        let thread_id = match thread.id() {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+        let port = thread.as_raw_port();
+        let mut frames = Vec::with_capacity(self.max_frames);
+        let mut state: NativeThreadState = unsafe { mem::zeroed() };
+        let mut count = NativeThreadState::COUNT;
+        // Nothing may allocate while the thread is suspended: if we are sampling our own task, it may hold the
+        // allocator's lock. Even building an io::Error allocates, so the calls are made directly and their codes are
+        // only turned into errors once the thread has been resumed.
+        let (timestamp, get_state_result, resume_result) = unsafe {
+            if sys::thread_suspend(port) != sys::KERN_SUCCESS as sys::kern_return_t {
+                return Ok(None);
+            }
+            let timestamp = Instant::now();
+            let get_state_result = sys::thread_get_state(port, NativeThreadState::FLAVOR, &mut state as *mut NativeThreadState as sys::thread_state_t, &mut count);
+            if get_state_result == sys::KERN_SUCCESS as sys::kern_return_t {
+                walk_frames(self.task.as_raw_port(), state.fp(), &mut frames);
+            }
+            (timestamp, get_state_result, sys::thread_resume(port))
+        };
+        mach_call!(log op: "thread_resume", port: port, resume_result)?;
+        mach_call!(log op: "thread_get_state", port: port, get_state_result)?;
+        Ok(Some(Sample {
+            thread_id,
+            timestamp,
+            pc: state.pc(),
+            fp: state.fp(),
+            frames,
+        }))
+    }
+}
+
+// Follows the frame pointer chain, pushing return addresses until `frames` reaches its capacity. Runs while the thread
+// is suspended, so it must not allocate.
+fn walk_frames(task: RawPort, mut fp: u64, frames: &mut Vec<u64>) {
+    while frames.len() < frames.capacity() {
+        if fp == 0 || !fp.is_multiple_of(8) {
+            break;
+        }
+        // Each frame record is the caller's frame pointer followed by the return address
+        let mut record = [0u64; 2];
+        if !read_record(task, fp, &mut record) {
+            break;
+        }
+        let (next_fp, return_address) = (record[0], strip_pointer_auth(record[1]));
+        if return_address == 0 {
+            break;
+        }
+        frames.push(return_address);
+        // Stacks grow down, so callers' frames must be at higher addresses
+        if next_fp <= fp {
+            break;
+        }
+        fp = next_fp;
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn strip_pointer_auth(address: u64) -> u64 {
+    address & 0x0000_7fff_ffff_ffff
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn strip_pointer_auth(address: u64) -> u64 {
+    address
+}
+
+// Reads with mach_vm_read_overwrite directly, since Task::read_memory allocates its errors
+fn read_record(task: RawPort, address: u64, record: &mut [u64; 2]) -> bool {
+    let size = mem::size_of_val(record);
+    let mut out_size: sys::mach_vm_size_t = 0;
+    let ret = unsafe {
+        sys::mach_vm_read_overwrite(task, address, size as _, record.as_mut_ptr() as sys::mach_vm_address_t, &mut out_size)
+    };
+    ret == sys::KERN_SUCCESS as sys::kern_return_t && out_size as usize == size
+}
@@ -0,0 +1,272 @@
+//! Recording message traffic to a file and replaying it, for reproducing protocol bugs deterministically.
+//!
+//! Enabled by the `record` feature. While a recording is active (between [`start`] and [`stop`]), every message
+//! successfully sent with [`Port::send`](crate::Port::send) or received with [`Port::recv`](crate::Port::recv) is
+//! appended to the file. Rights and out-of-line memory cannot be meaningfully persisted, so port names and
+//! out-of-line addresses are replaced with null; dispositions, sizes and descriptor types are kept.
+//!
+//! [`Replay`] reads a recording back. [`Replay::feed_incoming`] passes each received message to a server's dispatch
+//! function (e.g. [`exceptions::dispatch`](crate::exceptions::dispatch)) in the order it originally arrived. Since
+//! the reply ports are null, dispatchers that skip replies to simple routines will not attempt to send anything.
+//!
+//! The file format is a sequence of records in native byte order, and is only meant to be read back on the same
+//! architecture that wrote it.
+
+use crate::{RawPort, Msg, MsgBuffer};
+
+use std::io;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"MACHREC1";
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+struct Recorder {
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+/// Whether a message was sent or received by the recording process.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+impl Direction {
+    fn to_byte(self) -> u8 {
+        match self {
+            Direction::Sent => 0,
+            Direction::Received => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Direction> {
+        match byte {
+            0 => Ok(Direction::Sent),
+            1 => Ok(Direction::Received),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid message direction in recording")),
+        }
+    }
+}
+
+/// Starts recording message traffic to a new file at `path`, replacing any recording in progress.
+pub fn start<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    let previous = RECORDER.lock().unwrap_or_else(|err| err.into_inner()).replace(Recorder {
+        file,
+        started: Instant::now(),
+    });
+    if let Some(mut previous) = previous {
+        previous.file.flush()?;
+    }
+    Ok(())
+}
+
+/// Stops the recording in progress, if any, and flushes it to disk.
+pub fn stop() -> io::Result<()> {
+    if let Some(mut recorder) = RECORDER.lock().unwrap_or_else(|err| err.into_inner()).take() {
+        recorder.file.flush()?;
+    }
+    Ok(())
+}
+
+/// Whether a recording is in progress.
+pub fn is_recording() -> bool {
+    RECORDER.lock().unwrap_or_else(|err| err.into_inner()).is_some()
+}
+
+pub(crate) fn record(direction: Direction, port: RawPort, msg: &Msg) {
+    let mut recorder = RECORDER.lock().unwrap_or_else(|err| err.into_inner());
+    let result = match recorder.as_mut() {
+        Some(recorder) => recorder.write(direction, port, msg),
+        None => return,
+    };
+    if let Err(err) = result {
+        error!("failed to write message to recording, stopping: {:?}", err);
+        *recorder = None;
+    }
+}
+
+impl Recorder {
+    fn write(&mut self, direction: Direction, port: RawPort, msg: &Msg) -> io::Result<()> {
+        let mut copy = MsgBuffer::from_bytes(msg.as_bytes());
+        copy.redact_rights();
+        let bytes = copy.as_bytes();
+        self.file.write_all(&[direction.to_byte()])?;
+        self.file.write_all(&port.to_ne_bytes())?;
+        self.file.write_all(&(self.started.elapsed().as_nanos() as u64).to_ne_bytes())?;
+        self.file.write_all(&(bytes.len() as u32).to_ne_bytes())?;
+        self.file.write_all(bytes)
+    }
+}
+
+/// A message read back from a recording.
+#[derive(Debug)]
+pub struct RecordedMessage {
+    pub direction: Direction,
+    /// The name of the port the message was sent to or received on, in the recording process.
+    pub port: RawPort,
+    /// The time since the recording started.
+    pub elapsed: Duration,
+    msg: MsgBuffer,
+}
+
+impl RecordedMessage {
+    /// The message, with port names and out-of-line addresses redacted.
+    #[inline]
+    pub fn msg(&self) -> &Msg {
+        &self.msg
+    }
+
+    #[inline]
+    pub fn into_msg(self) -> MsgBuffer {
+        self.msg
+    }
+}
+
+/// Reads the messages of a recording in order.
+pub struct Replay {
+    file: BufReader<File>,
+}
+
+impl Replay {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a message recording"));
+        }
+        Ok(Replay { file })
+    }
+
+    /// Passes every received message in the recording to `dispatch`, stopping at the first error.
+    ///
+    /// Returns the number of messages dispatched.
+    pub fn feed_incoming<F>(self, mut dispatch: F) -> io::Result<usize> where
+        F: FnMut(&Msg) -> io::Result<()>,
+    {
+        let mut count = 0;
+        for message in self {
+            let message = message?;
+            if message.direction == Direction::Received {
+                dispatch(message.msg())?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn read_message(&mut self) -> io::Result<Option<RecordedMessage>> {
+        let mut direction = [0u8; 1];
+        if self.file.read(&mut direction)? == 0 {
+            return Ok(None);
+        }
+        let direction = Direction::from_byte(direction[0])?;
+        let mut port = [0u8; 4];
+        self.file.read_exact(&mut port)?;
+        let mut elapsed = [0u8; 8];
+        self.file.read_exact(&mut elapsed)?;
+        let mut len = [0u8; 4];
+        self.file.read_exact(&mut len)?;
+        let len = u32::from_ne_bytes(len) as usize;
+        let mut bytes = vec![0u8; len];
+        self.file.read_exact(&mut bytes)?;
+        // Recordings are files like any other, so don't trust them any more than a message from another process
+        let msg = Msg::parse_untrusted(&bytes)?;
+        if msg.as_bytes().len() != len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "recorded message size does not match its header"));
+        }
+        Ok(Some(RecordedMessage {
+            direction,
+            port: u32::from_ne_bytes(port) as RawPort,
+            elapsed: Duration::from_nanos(u64::from_ne_bytes(elapsed)),
+            msg,
+        }))
+    }
+}
+
+impl Iterator for Replay {
+    type Item = io::Result<RecordedMessage>;
+
+    fn next(&mut self) -> Option<io::Result<RecordedMessage>> {
+        self.read_message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Port;
+
+    use std::{fs, process};
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mach-port-record-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let path = temp_path("roundtrip");
+        let port = Port::new().unwrap();
+        let sender = port.make_sender().unwrap();
+
+        start(&path).unwrap();
+        assert!(is_recording());
+        let mut msg = MsgBuffer::new();
+        msg.set_id(9);
+        msg.extend_inline_data(b"recorded");
+        sender.send(&mut msg, None).unwrap();
+        let mut received = MsgBuffer::new();
+        received.reserve_inline_data(64);
+        port.recv(&mut received, None).unwrap();
+        stop().unwrap();
+        assert!(!is_recording());
+
+        // Other tests may send while the recording is active, so only look at this port's messages
+        let messages: Vec<_> = Replay::open(&path).unwrap()
+            .map(Result::unwrap)
+            .filter(|message| message.port == port.as_raw_port())
+            .collect();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(messages.iter().map(|message| message.direction).collect::<Vec<_>>(), vec![Direction::Sent, Direction::Received]);
+        for message in messages {
+            assert_eq!(message.msg().id(), 9);
+            assert_eq!(message.msg().inline_data(), b"recorded");
+            assert_eq!(message.msg().header().msgh_remote_port, 0);
+            assert_eq!(message.msg().header().msgh_local_port, 0);
+        }
+    }
+
+    #[test]
+    fn replay_rejects_malformed_message() {
+        let path = temp_path("malformed");
+        let mut msg = MsgBuffer::new();
+        msg.extend_inline_data(&[0; 8]);
+        let mut bytes = msg.as_bytes().to_vec();
+        // A msgh_size past the end of the recorded bytes
+        let size = bytes.len() as u32 + 64;
+        bytes[4..8].copy_from_slice(&size.to_ne_bytes());
+
+        let mut file = Vec::new();
+        file.extend_from_slice(MAGIC);
+        file.push(Direction::Received.to_byte());
+        file.extend_from_slice(&0x103u32.to_ne_bytes());
+        file.extend_from_slice(&0u64.to_ne_bytes());
+        file.extend_from_slice(&(bytes.len() as u32).to_ne_bytes());
+        file.extend_from_slice(&bytes);
+        fs::write(&path, file).unwrap();
+
+        let result = Replay::open(&path).unwrap().feed_incoming(|_| panic!("malformed message was dispatched"));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
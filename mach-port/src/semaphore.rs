@@ -0,0 +1,97 @@
+use crate::RawPort;
+use crate::clock::duration_to_timespec;
+
+use std::{io, fmt};
+use std::time::Duration;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// A Mach semaphore owned by the current task.
+pub struct Semaphore {
+    port: sys::semaphore_t,
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "semaphore_destroy", port: self.port, sys::semaphore_destroy(mach_core::self_port::task_self(), self.port));
+        }
+    }
+}
+
+impl Semaphore {
+    /// Creates a semaphore with the given initial count, woken in FIFO order.
+    pub fn new(value: i32) -> io::Result<Semaphore> {
+        unsafe {
+            let mut port: sys::semaphore_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "semaphore_create", sys::semaphore_create(mach_core::self_port::task_self(), &mut port, sys::SYNC_POLICY_FIFO as _, value))?;
+            Ok(Semaphore {
+                port,
+            })
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    /// Increments the semaphore, waking one waiter if there are any.
+    pub fn signal(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "semaphore_signal", port: self.port, sys::semaphore_signal(self.port))?;
+        }
+        Ok(())
+    }
+
+    /// Wakes all waiters without changing the count.
+    pub fn signal_all(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "semaphore_signal_all", port: self.port, sys::semaphore_signal_all(self.port))?;
+        }
+        Ok(())
+    }
+
+    /// Decrements the semaphore, blocking while it is zero.
+    ///
+    /// Fails with `Interrupted` if the wait is aborted.
+    pub fn wait(&self) -> io::Result<()> {
+        wait_result(unsafe { sys::semaphore_wait(self.port) })
+    }
+
+    /// Decrements the semaphore, blocking for at most `timeout` while it is zero.
+    ///
+    /// Fails with `TimedOut` if the timeout expires, or `Interrupted` if the wait is aborted.
+    pub fn timed_wait(&self, timeout: Duration) -> io::Result<()> {
+        wait_result(unsafe { sys::semaphore_timedwait(self.port, duration_to_timespec(timeout)) })
+    }
+
+    /// Atomically signals `other` and waits on this semaphore, so that no wakeup of `other`'s waiter can be observed
+    /// before this thread is waiting.
+    pub fn wait_signal(&self, other: &Semaphore) -> io::Result<()> {
+        wait_result(unsafe { sys::semaphore_wait_signal(self.port, other.port) })
+    }
+
+    /// Atomically signals `other` and waits on this semaphore for at most `timeout`. See
+    /// [`wait_signal`](Semaphore::wait_signal).
+    pub fn timed_wait_signal(&self, other: &Semaphore, timeout: Duration) -> io::Result<()> {
+        wait_result(unsafe { sys::semaphore_timedwait_signal(self.port, other.port, duration_to_timespec(timeout)) })
+    }
+}
+
+fn wait_result(code: sys::kern_return_t) -> io::Result<()> {
+    match code as u32 {
+        sys::KERN_SUCCESS => Ok(()),
+        sys::KERN_OPERATION_TIMED_OUT => Err(io::Error::new(io::ErrorKind::TimedOut, "semaphore wait timed out")),
+        sys::KERN_ABORTED => Err(io::Error::new(io::ErrorKind::Interrupted, "semaphore wait aborted")),
+        _ => mach_call!(log op: "semaphore_wait", code),
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
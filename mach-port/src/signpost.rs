@@ -0,0 +1,62 @@
+//! Instrumentation of IPC with kdebug signposts, for viewing in Instruments.
+//!
+//! When the `signpost` feature is enabled, every [`Port::send`](crate::Port::send) and
+//! [`Port::recv`](crate::Port::recv) is wrapped in a signpost interval, which shows up in the "Points of Interest"
+//! track of Instruments and alongside kernel events in System Trace. The first argument of each interval is the port
+//! name, and the second the message ID (for sends) or zero (for receives).
+//!
+//! `os_signpost` itself is not used, since its format strings must be compiled into a special section of the binary
+//! by the C compiler; kdebug signposts are what it records under the hood.
+
+use std::os::raw::c_int;
+
+extern "C" {
+    // Declared in sys/kdebug_signpost.h
+    fn kdebug_signpost(code: u32, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> c_int;
+    fn kdebug_signpost_start(code: u32, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> c_int;
+    fn kdebug_signpost_end(code: u32, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> c_int;
+}
+
+/// The code identifying a signpost, which Instruments uses to group and label intervals.
+///
+/// Only the low 14 bits are significant.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SignpostCode(pub u32);
+
+impl SignpostCode {
+    /// Emitted by [`Port::send`](crate::Port::send).
+    pub const SEND: SignpostCode = SignpostCode(0x3a00);
+    /// Emitted by [`Port::recv`](crate::Port::recv).
+    pub const RECEIVE: SignpostCode = SignpostCode(0x3a01);
+}
+
+/// Emits a single signpost event.
+#[inline]
+pub fn point(code: SignpostCode, args: [usize; 4]) {
+    unsafe { kdebug_signpost(code.0, args[0], args[1], args[2], args[3]); }
+}
+
+/// A signpost interval, which ends when dropped.
+#[must_use]
+#[derive(Debug)]
+pub struct Interval {
+    code: SignpostCode,
+    // Instruments pairs the start and end of an interval by code and first argument
+    arg1: usize,
+}
+
+impl Interval {
+    pub fn begin(code: SignpostCode, args: [usize; 4]) -> Interval {
+        unsafe { kdebug_signpost_start(code.0, args[0], args[1], args[2], args[3]); }
+        Interval {
+            code,
+            arg1: args[0],
+        }
+    }
+}
+
+impl Drop for Interval {
+    fn drop(&mut self) {
+        unsafe { kdebug_signpost_end(self.code.0, self.arg1, 0, 0, 0); }
+    }
+}
@@ -0,0 +1,185 @@
+//! Capturing the threads and memory of a task for crash and hang reports.
+//!
+//! The target task should be suspended (see [`Task::suspend`](crate::Task::suspend)) for the snapshot to be
+//! consistent. Snapshotting the current task is not supported, since its threads cannot be stopped without stopping
+//! the snapshot itself.
+
+use crate::{Task, NativeThreadState, ThreadState, RawThreadState};
+
+use std::{io, mem};
+use std::io::Write;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"MACHSNAP";
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// The captured state of a single thread.
+#[derive(Clone, Debug)]
+pub struct ThreadSnapshot {
+    pub id: u64,
+    pub state: RawThreadState,
+}
+
+impl ThreadSnapshot {
+    /// The general purpose registers of the thread.
+    pub fn registers(&self) -> Option<NativeThreadState> {
+        self.state.get()
+    }
+}
+
+/// A captured region of the task's address space.
+#[derive(Clone, Debug)]
+pub struct RegionSnapshot {
+    pub address: u64,
+    pub size: u64,
+    pub protection: sys::vm_prot_t,
+    /// The contents of the region, if memory capture was requested and the region was readable and small enough.
+    pub contents: Option<Vec<u8>>,
+}
+
+/// Controls what [`TaskSnapshot::capture`] records.
+#[derive(Clone, Debug)]
+pub struct SnapshotOptions {
+    capture_memory: bool,
+    max_region_size: u64,
+}
+
+impl Default for SnapshotOptions {
+    fn default() -> SnapshotOptions {
+        SnapshotOptions {
+            capture_memory: false,
+            max_region_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl SnapshotOptions {
+    pub fn new() -> SnapshotOptions {
+        SnapshotOptions::default()
+    }
+
+    /// Whether to copy the contents of readable regions. Defaults to `false`.
+    pub fn capture_memory(mut self, capture_memory: bool) -> Self {
+        self.capture_memory = capture_memory;
+        self
+    }
+
+    /// Regions larger than this are recorded without their contents. Defaults to 16MiB.
+    pub fn max_region_size(mut self, max_region_size: u64) -> Self {
+        self.max_region_size = max_region_size;
+        self
+    }
+}
+
+/// An in-memory representation of a task's threads and address space.
+#[derive(Clone, Debug)]
+pub struct TaskSnapshot {
+    pub threads: Vec<ThreadSnapshot>,
+    pub regions: Vec<RegionSnapshot>,
+}
+
+impl TaskSnapshot {
+    pub fn capture(task: &Task, options: &SnapshotOptions) -> io::Result<TaskSnapshot> {
+        if task.as_raw_port() == mach_core::self_port::task_self() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot snapshot the current task"));
+        }
+
+        let mut threads = Vec::new();
+        for thread in task.threads()?.iter() {
+            threads.push(ThreadSnapshot {
+                id: thread.id()?,
+                state: thread.get_raw_state(NativeThreadState::FLAVOR)?,
+            });
+        }
+
+        let mut regions = Vec::new();
+        let mut address: sys::mach_vm_address_t = 0;
+        loop {
+            let mut size: sys::mach_vm_size_t = 0;
+            let mut info: sys::vm_region_basic_info_data_64_t = unsafe { mem::zeroed() };
+            let mut count = (mem::size_of::<sys::vm_region_basic_info_data_64_t>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+            let mut object_name: sys::mach_port_t = sys::MACH_PORT_NULL;
+            let result = unsafe {
+                sys::mach_vm_region(
+                    task.as_raw_port(),
+                    &mut address,
+                    &mut size,
+                    sys::VM_REGION_BASIC_INFO_64 as _,
+                    &mut info as *mut _ as sys::vm_region_info_t,
+                    &mut count,
+                    &mut object_name,
+                )
+            };
+            if result as u32 == sys::KERN_INVALID_ADDRESS {
+                // No regions past this address
+                break;
+            }
+            mach_call!(log op: "mach_vm_region", port: task.as_raw_port(), result)?;
+
+            let contents = if options.capture_memory && info.protection & sys::VM_PROT_READ as sys::vm_prot_t != 0 && size <= options.max_region_size {
+                read_region(task, address, size).ok()
+            } else {
+                None
+            };
+            regions.push(RegionSnapshot {
+                address,
+                size,
+                protection: info.protection,
+                contents,
+            });
+            address += size;
+        }
+
+        Ok(TaskSnapshot {
+            threads,
+            regions,
+        })
+    }
+
+    /// Streams the snapshot to `writer` in a simple little-endian binary format:
+    ///
+    /// * the magic `MACHSNAP` and a `u32` format version
+    /// * a `u32` thread count, followed by each thread's `u64` ID, `i32` state flavor, `u32` state word count and
+    ///   `u32` state words
+    /// * a `u32` region count, followed by each region's `u64` address, `u64` size, `i32` protection, `u8` flag
+    ///   indicating whether contents follow, and if so a `u64` length and the contents
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+
+        writer.write_all(&(self.threads.len() as u32).to_le_bytes())?;
+        for thread in self.threads.iter() {
+            writer.write_all(&thread.id.to_le_bytes())?;
+            writer.write_all(&thread.state.flavor().to_le_bytes())?;
+            writer.write_all(&(thread.state.as_slice().len() as u32).to_le_bytes())?;
+            for word in thread.state.as_slice() {
+                writer.write_all(&word.to_le_bytes())?;
+            }
+        }
+
+        writer.write_all(&(self.regions.len() as u32).to_le_bytes())?;
+        for region in self.regions.iter() {
+            writer.write_all(&region.address.to_le_bytes())?;
+            writer.write_all(&region.size.to_le_bytes())?;
+            writer.write_all(&region.protection.to_le_bytes())?;
+            match region.contents {
+                Some(ref contents) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&(contents.len() as u64).to_le_bytes())?;
+                    writer.write_all(contents)?;
+                },
+                None => writer.write_all(&[0])?,
+            }
+        }
+        writer.flush()
+    }
+}
+
+fn read_region(task: &Task, address: u64, size: u64) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0u8; size as usize];
+    let read = task.read_memory(address, &mut buffer)?;
+    buffer.truncate(read);
+    Ok(buffer)
+}
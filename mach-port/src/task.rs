@@ -0,0 +1,325 @@
+use crate::{RawPort, Port, Thread, ThreadState, ExceptionMask, ExceptionBehavior, ExceptionHandler, ExceptionPortsGuard};
+use crate::exception_ports::ExceptionPortTarget;
+
+use std::{io, mem, ptr, slice, fmt};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+pub struct Task {
+    port: sys::task_t,
+    owned: bool,
+    flavor: TaskFlavor,
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                let _ = mach_call!(log op: "mach_port_deallocate(task port)", port: self.port, sys::mach_port_deallocate(mach_core::self_port::task_self(), self.port));
+            }
+        }
+    }
+}
+
+impl Task {
+    /// Gets a handle to the current task.
+    ///
+    /// The port is borrowed from `mach_task_self` and will not be deallocated when the [`Task`] is dropped.
+    pub fn current() -> Task {
+        Task {
+            port: mach_core::self_port::task_self(),
+            owned: false,
+            flavor: TaskFlavor::Control,
+        }
+    }
+
+    /// Takes ownership of a send right to a task control port.
+    pub unsafe fn from_raw_port(port: RawPort) -> Task {
+        Task::from_raw_port_with_flavor(port, TaskFlavor::Control)
+    }
+
+    /// Takes ownership of a send right to a task port of the given flavor.
+    ///
+    /// The flavor is used to fail early when calling methods the port does not permit; passing a more privileged
+    /// flavor than the port actually has only defers those errors to the kernel.
+    pub unsafe fn from_raw_port_with_flavor(port: RawPort, flavor: TaskFlavor) -> Task {
+        Task {
+            port,
+            owned: true,
+            flavor,
+        }
+    }
+
+    /// Obtains a port of the given flavor for the task of process `pid`, using the matching `task_*_for_pid` call.
+    ///
+    /// Less privileged flavors are subject to less restrictive access checks, so callers should request the least
+    /// privileged flavor sufficient for the operations they need.
+    ///
+    /// Only available on macOS; the `task_*_for_pid` calls are reserved for the system on iOS, tvOS and watchOS.
+    #[cfg(target_os = "macos")]
+    pub fn for_pid(pid: i32, flavor: TaskFlavor) -> io::Result<Task> {
+        unsafe {
+            let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+            match flavor {
+                TaskFlavor::Control => mach_call!(log op: "task_for_pid", sys::task_for_pid(mach_core::self_port::task_self(), pid, &mut port))?,
+                TaskFlavor::Read => mach_call!(log op: "task_read_for_pid", sys::task_read_for_pid(mach_core::self_port::task_self(), pid, &mut port))?,
+                TaskFlavor::Inspect => mach_call!(log op: "task_inspect_for_pid", sys::task_inspect_for_pid(mach_core::self_port::task_self(), pid, &mut port))?,
+                TaskFlavor::Name => mach_call!(log op: "task_name_for_pid", sys::task_name_for_pid(mach_core::self_port::task_self(), pid, &mut port))?,
+            }
+            Ok(Task::from_raw_port_with_flavor(port, flavor))
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        let port = self.port;
+        mem::forget(self);
+        port
+    }
+
+    #[inline]
+    pub fn flavor(&self) -> TaskFlavor {
+        self.flavor
+    }
+
+    /// Obtains a less privileged port for the same task, e.g. to hand to a helper that only needs to inspect it.
+    pub fn downgrade(&self, flavor: TaskFlavor) -> io::Result<Task> {
+        self.require(flavor)?;
+        let which = match flavor {
+            TaskFlavor::Control => {
+                unsafe {
+                    mach_call!(log op: "mach_port_mod_refs", port: self.port, sys::mach_port_mod_refs(mach_core::self_port::task_self(), self.port, sys::MACH_PORT_RIGHT_SEND, 1))?;
+                    return Ok(Task::from_raw_port_with_flavor(self.port, flavor));
+                }
+            },
+            TaskFlavor::Read => TaskSpecialPort::Read,
+            TaskFlavor::Inspect => TaskSpecialPort::Inspect,
+            TaskFlavor::Name => TaskSpecialPort::Name,
+        };
+        unsafe {
+            let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "task_get_special_port", port: self.port, sys::task_get_special_port(self.port, which.as_raw(), &mut port))?;
+            Ok(Task::from_raw_port_with_flavor(port, flavor))
+        }
+    }
+
+    /// Fails with `PermissionDenied` if the port's flavor is less privileged than `required`.
+    pub(crate) fn require(&self, required: TaskFlavor) -> io::Result<()> {
+        if self.flavor.allows(required) {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("operation requires a {:?} task port, but this port is {:?}", required, self.flavor)))
+        }
+    }
+
+    /// Increments the suspend count of the task, stopping all of its threads until the returned guard is dropped.
+    ///
+    /// Suspending the current task will never return.
+    pub fn suspend(&self) -> io::Result<TaskSuspendGuard> {
+        self.require(TaskFlavor::Control)?;
+        unsafe {
+            mach_call!(log op: "task_suspend", port: self.port, sys::task_suspend(self.port))?;
+        }
+        Ok(TaskSuspendGuard { task: self })
+    }
+
+    /// Enumerates the threads currently running in the task.
+    ///
+    /// Inspect ports yield thread inspect ports, which only permit querying thread information.
+    pub fn threads(&self) -> io::Result<Vec<Thread>> {
+        self.require(TaskFlavor::Inspect)?;
+        unsafe {
+            let mut list: sys::thread_act_array_t = ptr::null_mut();
+            let mut count: sys::mach_msg_type_number_t = 0;
+            mach_call!(log op: "task_threads", port: self.port, sys::task_threads(self.port, &mut list, &mut count))?;
+            // Take ownership of the thread ports before anything else can fail
+            let threads = slice::from_raw_parts(list, count as usize).iter()
+                .map(|&port| Thread::from_raw_port(port))
+                .collect();
+            let _ = mach_call!(log op: "mach_vm_deallocate(thread list)", sys::mach_vm_deallocate(
+                mach_core::self_port::task_self(),
+                list as sys::mach_vm_address_t,
+                (count as usize * mem::size_of::<sys::thread_act_t>()) as sys::mach_vm_size_t,
+            ));
+            Ok(threads)
+        }
+    }
+
+    /// Retrieves a send right to one of the task's special ports, or `None` if it is not set.
+    pub fn special_port(&self, which: TaskSpecialPort) -> io::Result<Option<Port>> {
+        unsafe {
+            let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "task_get_special_port", port: self.port, sys::task_get_special_port(self.port, which.as_raw(), &mut port))?;
+            if port == sys::MACH_PORT_NULL {
+                return Ok(None);
+            }
+            Ok(Some(Port::from_raw_send_right(port)))
+        }
+    }
+
+    /// Registers a copy of the send right `port` as one of the task's special ports.
+    pub fn set_special_port(&self, which: TaskSpecialPort, port: &Port) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        unsafe {
+            mach_call!(log op: "task_set_special_port", port: self.port, sys::task_set_special_port(self.port, which.as_raw(), port.as_raw_port()))?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the exception handlers registered on the task for the given exception types.
+    pub fn exception_ports(&self, mask: ExceptionMask) -> io::Result<Vec<ExceptionHandler>> {
+        ExceptionPortTarget::Task(self).get(mask)
+    }
+
+    /// Registers `port` as the handler for the given exception types, or removes the handler if `port` is `None`.
+    ///
+    /// The port must hold a send right (e.g. one created with [`Port::make_sender`]), which will be copied.
+    pub fn set_exception_ports(&self, mask: ExceptionMask, port: Option<&Port>, behavior: ExceptionBehavior, flavor: sys::thread_state_flavor_t) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        ExceptionPortTarget::Task(self).set(mask, port, behavior, flavor)
+    }
+
+    /// Registers `port` as the handler for the given exception types, restoring the previously registered handlers when
+    /// the returned guard is dropped.
+    ///
+    /// The port must hold a send right (e.g. one created with [`Port::make_sender`]), which will be copied.
+    pub fn swap_exception_ports(&self, mask: ExceptionMask, behavior: ExceptionBehavior, flavor: sys::thread_state_flavor_t, port: &Port) -> io::Result<ExceptionPortsGuard> {
+        self.require(TaskFlavor::Control)?;
+        ExceptionPortTarget::Task(self).swap(mask, Some(port), behavior, flavor)
+    }
+
+    /// Creates a new thread in the task that begins executing immediately with the given register state.
+    ///
+    /// The caller is responsible for providing a state (stack, program counter, etc.) that is valid in the target task.
+    pub unsafe fn create_thread<S: ThreadState>(&self, state: &S) -> io::Result<Thread> {
+        self.require(TaskFlavor::Control)?;
+        let mut thread: sys::thread_act_t = 0;
+        mach_call!(log op: "thread_create_running", port: self.port, sys::thread_create_running(self.port, S::FLAVOR, state as *const S as sys::thread_state_t, S::COUNT, &mut thread))?;
+        Ok(Thread::from_raw_port(thread))
+    }
+
+    /// Retrieves a `task_info` structure of the given flavor.
+    ///
+    /// The caller must ensure `T` is the structure corresponding to `flavor`.
+    pub(crate) unsafe fn info<T>(&self, flavor: u32) -> io::Result<T> {
+        let mut info: T = mem::zeroed();
+        let mut count = (mem::size_of::<T>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+        mach_call!(log op: "task_info", port: self.port, sys::task_info(self.port, flavor as _, &mut info as *mut T as sys::task_info_t, &mut count))?;
+        Ok(info)
+    }
+}
+
+/// Resumes a [`Task`] suspended with [`Task::suspend`] when dropped.
+#[must_use]
+pub struct TaskSuspendGuard<'a> {
+    task: &'a Task,
+}
+
+impl<'a> TaskSuspendGuard<'a> {
+    #[inline]
+    pub fn task(&self) -> &'a Task {
+        self.task
+    }
+
+    /// Resumes the task, reporting any failure instead of logging it.
+    pub fn resume(self) -> io::Result<()> {
+        let port = self.task.port;
+        mem::forget(self);
+        unsafe {
+            mach_call!(op: "task_resume", port: port, sys::task_resume(port))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Drop for TaskSuspendGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "task_resume", port: self.task.port, sys::task_resume(self.task.port));
+        }
+    }
+}
+
+/// The privilege level of a task port, from most to least capable.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TaskFlavor {
+    /// Full control of the task (the port returned by `task_for_pid`).
+    Control,
+    /// Allows reading the task's memory and thread state, but not modifying them.
+    Read,
+    /// Allows querying information about the task, but not reading its memory.
+    Inspect,
+    /// Only identifies the task.
+    Name,
+}
+
+impl TaskFlavor {
+    pub(crate) fn as_raw(self) -> sys::mach_task_flavor_t {
+        (match self {
+            TaskFlavor::Control => sys::TASK_FLAVOR_CONTROL,
+            TaskFlavor::Read => sys::TASK_FLAVOR_READ,
+            TaskFlavor::Inspect => sys::TASK_FLAVOR_INSPECT,
+            TaskFlavor::Name => sys::TASK_FLAVOR_NAME,
+        }) as sys::mach_task_flavor_t
+    }
+}
+
+impl TaskFlavor {
+    /// Whether a port of this flavor can be used for operations requiring `required`.
+    #[inline]
+    pub fn allows(self, required: TaskFlavor) -> bool {
+        self.rank() <= required.rank()
+    }
+
+    fn rank(self) -> u32 {
+        match self {
+            TaskFlavor::Control => 0,
+            TaskFlavor::Read => 1,
+            TaskFlavor::Inspect => 2,
+            TaskFlavor::Name => 3,
+        }
+    }
+}
+
+/// Identifies one of the special ports registered on a task.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TaskSpecialPort {
+    Kernel,
+    Host,
+    Name,
+    Bootstrap,
+    Inspect,
+    Read,
+    Access,
+    DebugControl,
+    ResourceNotify,
+}
+
+impl TaskSpecialPort {
+    fn as_raw(self) -> i32 {
+        (match self {
+            TaskSpecialPort::Kernel => sys::TASK_KERNEL_PORT,
+            TaskSpecialPort::Host => sys::TASK_HOST_PORT,
+            TaskSpecialPort::Name => sys::TASK_NAME_PORT,
+            TaskSpecialPort::Bootstrap => sys::TASK_BOOTSTRAP_PORT,
+            TaskSpecialPort::Inspect => sys::TASK_INSPECT_PORT,
+            TaskSpecialPort::Read => sys::TASK_READ_PORT,
+            TaskSpecialPort::Access => sys::TASK_ACCESS_PORT,
+            TaskSpecialPort::DebugControl => sys::TASK_DEBUG_CONTROL_PORT,
+            TaskSpecialPort::ResourceNotify => sys::TASK_RESOURCE_NOTIFY_PORT,
+        }) as i32
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .field("flavor", &self.flavor)
+            .finish()
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{RawPort, Task, TaskFlavor};
+
+use std::{io, mem, fmt};
+
+use mach_sys as sys;
+use mach_core::{mach_call, mach_call_out};
+
+impl Task {
+    /// Gets the BSD process ID of the task.
+    pub fn pid(&self) -> io::Result<i32> {
+        unsafe { mach_call_out!(log op: "pid_for_task", port: self.as_raw_port(), sys::pid_for_task(self.as_raw_port()) -> pid) }
+    }
+
+    /// Creates an identity token for the task, which can be sent to other processes and later converted back into a
+    /// task port of a chosen flavor (macOS 11 and later).
+    pub fn identity_token(&self) -> io::Result<TaskIdentityToken> {
+        unsafe {
+            let token = mach_call_out!(log op: "task_create_identity_token", port: self.as_raw_port(), sys::task_create_identity_token(self.as_raw_port()) -> token)?;
+            Ok(TaskIdentityToken::from_raw_port(token))
+        }
+    }
+}
+
+/// An owned send right to a task identity token.
+///
+/// Unlike a task port, a token does not grant any access to the task by itself. The holder must convert it with
+/// [`task_port`](TaskIdentityToken::task_port), which is subject to the same access checks as obtaining the task
+/// port directly.
+pub struct TaskIdentityToken {
+    port: sys::task_id_token_t,
+}
+
+impl Drop for TaskIdentityToken {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_port_deallocate(task identity token)", port: self.port, sys::mach_port_deallocate(mach_core::self_port::task_self(), self.port));
+        }
+    }
+}
+
+impl TaskIdentityToken {
+    /// Takes ownership of a send right to a task identity token.
+    pub unsafe fn from_raw_port(port: RawPort) -> TaskIdentityToken {
+        TaskIdentityToken {
+            port,
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        let port = self.port;
+        mem::forget(self);
+        port
+    }
+
+    /// Obtains a port of the given flavor for the task the token identifies.
+    pub fn task_port(&self, flavor: TaskFlavor) -> io::Result<Task> {
+        unsafe {
+            let port = mach_call_out!(log op: "task_identity_token_get_task_port", port: self.port, sys::task_identity_token_get_task_port(self.port, flavor.as_raw()) -> port)?;
+            Ok(Task::from_raw_port_with_flavor(port, flavor))
+        }
+    }
+}
+
+impl fmt::Debug for TaskIdentityToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskIdentityToken")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
@@ -0,0 +1,138 @@
+use crate::Task;
+
+use std::io;
+use std::time::Duration;
+
+use mach_sys as sys;
+use mach_core::time;
+
+/// Virtual memory statistics for a task (`TASK_VM_INFO`). All sizes are in bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TaskVmInfo {
+    pub virtual_size: u64,
+    pub region_count: u32,
+    pub page_size: u32,
+    pub resident_size: u64,
+    pub resident_size_peak: u64,
+    pub device: u64,
+    pub device_peak: u64,
+    /// Anonymous memory resident in the task.
+    pub internal: u64,
+    pub internal_peak: u64,
+    /// File-backed memory resident in the task.
+    pub external: u64,
+    pub external_peak: u64,
+    pub reusable: u64,
+    pub reusable_peak: u64,
+    pub purgeable_volatile_pmap: u64,
+    pub purgeable_volatile_resident: u64,
+    pub purgeable_volatile_virtual: u64,
+    /// Memory held by the task in the compressor.
+    pub compressed: u64,
+    pub compressed_peak: u64,
+    pub compressed_lifetime: u64,
+    /// The memory footprint as reported by Activity Monitor and used for jetsam limits.
+    pub phys_footprint: u64,
+    pub min_address: u64,
+    pub max_address: u64,
+}
+
+impl Task {
+    /// Retrieves virtual memory statistics for the task, including its physical footprint.
+    pub fn vm_info(&self) -> io::Result<TaskVmInfo> {
+        unsafe {
+            let info: sys::task_vm_info_data_t = self.info(sys::TASK_VM_INFO)?;
+            Ok(TaskVmInfo {
+                virtual_size: info.virtual_size,
+                region_count: info.region_count as u32,
+                page_size: info.page_size as u32,
+                resident_size: info.resident_size,
+                resident_size_peak: info.resident_size_peak,
+                device: info.device,
+                device_peak: info.device_peak,
+                internal: info.internal,
+                internal_peak: info.internal_peak,
+                external: info.external,
+                external_peak: info.external_peak,
+                reusable: info.reusable,
+                reusable_peak: info.reusable_peak,
+                purgeable_volatile_pmap: info.purgeable_volatile_pmap,
+                purgeable_volatile_resident: info.purgeable_volatile_resident,
+                purgeable_volatile_virtual: info.purgeable_volatile_virtual,
+                compressed: info.compressed,
+                compressed_peak: info.compressed_peak,
+                compressed_lifetime: info.compressed_lifetime,
+                phys_footprint: info.phys_footprint,
+                min_address: info.min_address,
+                max_address: info.max_address,
+            })
+        }
+    }
+}
+
+/// CPU time and wakeup counts for a task (`TASK_POWER_INFO`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TaskPowerInfo {
+    pub total_user: Duration,
+    pub total_system: Duration,
+    pub interrupt_wakeups: u64,
+    /// Wakeups that brought the whole package out of idle.
+    pub platform_idle_wakeups: u64,
+    /// Timer wakeups at less than 5ms intervals.
+    pub timer_wakeups_bin_1: u64,
+    /// Timer wakeups at less than 1ms intervals.
+    pub timer_wakeups_bin_2: u64,
+}
+
+/// Energy statistics for a task (`TASK_POWER_INFO_V2`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TaskPowerInfoV2 {
+    pub cpu: TaskPowerInfo,
+    /// Time the task has spent using the GPU.
+    pub gpu_time: Duration,
+    /// The energy consumed by the task, in nanojoules. Only reported on Apple silicon.
+    pub energy: Option<u64>,
+    /// CPU time spent on performance cores.
+    pub p_time: Duration,
+    pub pset_switches: u64,
+}
+
+impl Task {
+    /// Retrieves CPU time and wakeup counts for the task.
+    pub fn power_info(&self) -> io::Result<TaskPowerInfo> {
+        unsafe {
+            let info: sys::task_power_info_data_t = self.info(sys::TASK_POWER_INFO)?;
+            Ok(convert_power_info(&info))
+        }
+    }
+
+    /// Retrieves CPU, GPU and energy statistics for the task.
+    pub fn power_info_v2(&self) -> io::Result<TaskPowerInfoV2> {
+        unsafe {
+            let info: sys::task_power_info_v2_data_t = self.info(sys::TASK_POWER_INFO_V2)?;
+            #[cfg(target_arch = "aarch64")]
+            let energy = Some(info.task_energy);
+            #[cfg(not(target_arch = "aarch64"))]
+            let energy = None;
+            Ok(TaskPowerInfoV2 {
+                cpu: convert_power_info(&info.cpu_energy),
+                gpu_time: Duration::from_nanos(info.gpu_energy.task_gpu_utilisation),
+                energy,
+                p_time: time::abs_to_duration(info.task_ptime),
+                pset_switches: info.task_pset_switches,
+            })
+        }
+    }
+}
+
+// The CPU times are accumulated in Mach absolute time units
+fn convert_power_info(info: &sys::task_power_info_data_t) -> TaskPowerInfo {
+    TaskPowerInfo {
+        total_user: time::abs_to_duration(info.total_user),
+        total_system: time::abs_to_duration(info.total_system),
+        interrupt_wakeups: info.task_interrupt_wakeups,
+        platform_idle_wakeups: info.task_platform_idle_wakeups,
+        timer_wakeups_bin_1: info.task_timer_wakeups_bin_1,
+        timer_wakeups_bin_2: info.task_timer_wakeups_bin_2,
+    }
+}
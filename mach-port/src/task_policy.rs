@@ -0,0 +1,249 @@
+use crate::{Task, TaskFlavor};
+
+use std::{io, mem};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// The role of a task, which the scheduler uses to classify its threads (`TASK_CATEGORY_POLICY`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TaskRole {
+    Reniced,
+    Unspecified,
+    ForegroundApplication,
+    BackgroundApplication,
+    ControlApplication,
+    GraphicsServer,
+    ThrottleApplication,
+    NonUiApplication,
+    DefaultApplication,
+    DarwinBgApplication,
+    Other(sys::task_role_t),
+}
+
+impl TaskRole {
+    fn from_raw(raw: sys::task_role_t) -> TaskRole {
+        match raw {
+            -1 => TaskRole::Reniced,
+            0 => TaskRole::Unspecified,
+            1 => TaskRole::ForegroundApplication,
+            2 => TaskRole::BackgroundApplication,
+            3 => TaskRole::ControlApplication,
+            4 => TaskRole::GraphicsServer,
+            5 => TaskRole::ThrottleApplication,
+            6 => TaskRole::NonUiApplication,
+            7 => TaskRole::DefaultApplication,
+            8 => TaskRole::DarwinBgApplication,
+            raw => TaskRole::Other(raw),
+        }
+    }
+
+    fn into_raw(self) -> sys::task_role_t {
+        match self {
+            TaskRole::Reniced => -1,
+            TaskRole::Unspecified => 0,
+            TaskRole::ForegroundApplication => 1,
+            TaskRole::BackgroundApplication => 2,
+            TaskRole::ControlApplication => 3,
+            TaskRole::GraphicsServer => 4,
+            TaskRole::ThrottleApplication => 5,
+            TaskRole::NonUiApplication => 6,
+            TaskRole::DefaultApplication => 7,
+            TaskRole::DarwinBgApplication => 8,
+            TaskRole::Other(raw) => raw,
+        }
+    }
+}
+
+/// A latency QoS tier; lower tiers request more responsive timer and I/O coalescing behavior.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LatencyQosTier {
+    Unspecified,
+    Tier0,
+    Tier1,
+    Tier2,
+    Tier3,
+    Tier4,
+    Tier5,
+}
+
+/// A throughput QoS tier; lower tiers request more CPU and I/O throughput.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ThroughputQosTier {
+    Unspecified,
+    Tier0,
+    Tier1,
+    Tier2,
+    Tier3,
+    Tier4,
+    Tier5,
+}
+
+// The tiers are encoded with a type tag in the upper bits (see LATENCY_QOS_TIER_0 in mach/thread_policy.h)
+const LATENCY_QOS_TAG: u32 = 0xff << 16;
+const THROUGHPUT_QOS_TAG: u32 = 0xfe << 16;
+
+fn tier_index(raw: u32, tag: u32) -> Option<u32> {
+    if raw == 0 {
+        Some(0)
+    } else if raw & !0xffff == tag && (raw & 0xffff) >= 1 && (raw & 0xffff) <= 6 {
+        Some(raw & 0xffff)
+    } else {
+        None
+    }
+}
+
+impl LatencyQosTier {
+    pub(crate) fn from_raw(raw: u32) -> LatencyQosTier {
+        match tier_index(raw, LATENCY_QOS_TAG) {
+            Some(1) => LatencyQosTier::Tier0,
+            Some(2) => LatencyQosTier::Tier1,
+            Some(3) => LatencyQosTier::Tier2,
+            Some(4) => LatencyQosTier::Tier3,
+            Some(5) => LatencyQosTier::Tier4,
+            Some(6) => LatencyQosTier::Tier5,
+            _ => LatencyQosTier::Unspecified,
+        }
+    }
+
+    pub(crate) fn into_raw(self) -> u32 {
+        match self {
+            LatencyQosTier::Unspecified => 0,
+            LatencyQosTier::Tier0 => LATENCY_QOS_TAG | 1,
+            LatencyQosTier::Tier1 => LATENCY_QOS_TAG | 2,
+            LatencyQosTier::Tier2 => LATENCY_QOS_TAG | 3,
+            LatencyQosTier::Tier3 => LATENCY_QOS_TAG | 4,
+            LatencyQosTier::Tier4 => LATENCY_QOS_TAG | 5,
+            LatencyQosTier::Tier5 => LATENCY_QOS_TAG | 6,
+        }
+    }
+}
+
+impl ThroughputQosTier {
+    pub(crate) fn from_raw(raw: u32) -> ThroughputQosTier {
+        match tier_index(raw, THROUGHPUT_QOS_TAG) {
+            Some(1) => ThroughputQosTier::Tier0,
+            Some(2) => ThroughputQosTier::Tier1,
+            Some(3) => ThroughputQosTier::Tier2,
+            Some(4) => ThroughputQosTier::Tier3,
+            Some(5) => ThroughputQosTier::Tier4,
+            Some(6) => ThroughputQosTier::Tier5,
+            _ => ThroughputQosTier::Unspecified,
+        }
+    }
+
+    pub(crate) fn into_raw(self) -> u32 {
+        match self {
+            ThroughputQosTier::Unspecified => 0,
+            ThroughputQosTier::Tier0 => THROUGHPUT_QOS_TAG | 1,
+            ThroughputQosTier::Tier1 => THROUGHPUT_QOS_TAG | 2,
+            ThroughputQosTier::Tier2 => THROUGHPUT_QOS_TAG | 3,
+            ThroughputQosTier::Tier3 => THROUGHPUT_QOS_TAG | 4,
+            ThroughputQosTier::Tier4 => THROUGHPUT_QOS_TAG | 5,
+            ThroughputQosTier::Tier5 => THROUGHPUT_QOS_TAG | 6,
+        }
+    }
+}
+
+/// Restrictions applied to a suppressed (e.g. App Napped) task (`TASK_SUPPRESSION_POLICY`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TaskSuppressionPolicy {
+    pub active: bool,
+    pub lowpri_cpu: bool,
+    pub timer_throttle: LatencyQosTier,
+    pub system_timer_throttle: bool,
+    pub disk_throttle: bool,
+    pub cpu_limit: bool,
+    pub suspend: bool,
+    pub throughput_qos: ThroughputQosTier,
+    pub suppressed_cpu: bool,
+    pub background_sockets: bool,
+}
+
+impl Task {
+    /// Gets the role the scheduler currently assigns to the task.
+    pub fn role(&self) -> io::Result<TaskRole> {
+        unsafe {
+            let policy: sys::task_category_policy_data_t = self.get_policy(sys::TASK_CATEGORY_POLICY)?;
+            Ok(TaskRole::from_raw(policy.role))
+        }
+    }
+
+    /// Classifies the task, e.g. as a background or non-UI application.
+    pub fn set_role(&self, role: TaskRole) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::task_category_policy_data_t = mem::zeroed();
+            policy.role = role.into_raw();
+            self.set_policy(sys::TASK_CATEGORY_POLICY, &policy)
+        }
+    }
+
+    pub fn suppression_policy(&self) -> io::Result<TaskSuppressionPolicy> {
+        unsafe {
+            let policy: sys::task_suppression_policy_data_t = self.get_policy(sys::TASK_SUPPRESSION_POLICY)?;
+            Ok(TaskSuppressionPolicy {
+                active: policy.active != 0,
+                lowpri_cpu: policy.lowpri_cpu != 0,
+                timer_throttle: LatencyQosTier::from_raw(policy.timer_throttle as u32),
+                system_timer_throttle: policy.system_timer_throttle != 0,
+                disk_throttle: policy.disk_throttle != 0,
+                cpu_limit: policy.cpu_limit != 0,
+                suspend: policy.suspend != 0,
+                throughput_qos: ThroughputQosTier::from_raw(policy.throughput_qos as u32),
+                suppressed_cpu: policy.suppressed_cpu != 0,
+                background_sockets: policy.background_sockets != 0,
+            })
+        }
+    }
+
+    pub fn set_suppression_policy(&self, suppression: &TaskSuppressionPolicy) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::task_suppression_policy_data_t = mem::zeroed();
+            policy.active = suppression.active as _;
+            policy.lowpri_cpu = suppression.lowpri_cpu as _;
+            policy.timer_throttle = suppression.timer_throttle.into_raw() as _;
+            policy.system_timer_throttle = suppression.system_timer_throttle as _;
+            policy.disk_throttle = suppression.disk_throttle as _;
+            policy.cpu_limit = suppression.cpu_limit as _;
+            policy.suspend = suppression.suspend as _;
+            policy.throughput_qos = suppression.throughput_qos.into_raw() as _;
+            policy.suppressed_cpu = suppression.suppressed_cpu as _;
+            policy.background_sockets = suppression.background_sockets as _;
+            self.set_policy(sys::TASK_SUPPRESSION_POLICY, &policy)
+        }
+    }
+
+    /// Gets the task's base latency and throughput QoS tiers.
+    pub fn qos(&self) -> io::Result<(LatencyQosTier, ThroughputQosTier)> {
+        unsafe {
+            let policy: sys::task_qos_policy = self.get_policy(sys::TASK_BASE_QOS_POLICY)?;
+            Ok((LatencyQosTier::from_raw(policy.task_latency_qos_tier as u32), ThroughputQosTier::from_raw(policy.task_throughput_qos_tier as u32)))
+        }
+    }
+
+    /// Sets the task's base latency and throughput QoS tiers.
+    pub fn set_qos(&self, latency: LatencyQosTier, throughput: ThroughputQosTier) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::task_qos_policy = mem::zeroed();
+            policy.task_latency_qos_tier = latency.into_raw() as _;
+            policy.task_throughput_qos_tier = throughput.into_raw() as _;
+            self.set_policy(sys::TASK_BASE_QOS_POLICY, &policy)
+        }
+    }
+
+    unsafe fn get_policy<T>(&self, flavor: u32) -> io::Result<T> {
+        self.require(TaskFlavor::Inspect)?;
+        let mut policy: T = mem::zeroed();
+        let mut count = (mem::size_of::<T>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+        let mut get_default: sys::boolean_t = 0;
+        mach_call!(log op: "task_policy_get", port: self.as_raw_port(), sys::task_policy_get(self.as_raw_port(), flavor as _, &mut policy as *mut T as sys::task_policy_t, &mut count, &mut get_default))?;
+        Ok(policy)
+    }
+
+    unsafe fn set_policy<T>(&self, flavor: u32, policy: &T) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        let count = (mem::size_of::<T>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+        mach_call!(log op: "task_policy_set", port: self.as_raw_port(), sys::task_policy_set(self.as_raw_port(), flavor as _, policy as *const T as sys::task_policy_t, count))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{RawPort, Port, Task, TaskFlavor, PortCopyMode, PortMoveMode};
+
+use std::io;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+impl Task {
+    /// Copies (or makes) a right to the port named `name` in the task's namespace into the current task.
+    ///
+    /// Send-once rights are not supported, as [`Port`] cannot manage them.
+    pub fn copy_right_out(&self, name: RawPort, mode: PortCopyMode) -> io::Result<Port> {
+        let disposition = match mode {
+            PortCopyMode::Send => sys::MACH_MSG_TYPE_COPY_SEND,
+            PortCopyMode::MakeSend => sys::MACH_MSG_TYPE_MAKE_SEND,
+            PortCopyMode::MakeSendOnce => return Err(send_once_unsupported()),
+        };
+        unsafe { self.extract_right(name, disposition) }
+    }
+
+    /// Moves a right to the port named `name` out of the task's namespace into the current task.
+    ///
+    /// Send-once rights are not supported, as [`Port`] cannot manage them.
+    pub fn move_right_out(&self, name: RawPort, mode: PortMoveMode) -> io::Result<Port> {
+        let disposition = match mode {
+            PortMoveMode::Receive => sys::MACH_MSG_TYPE_MOVE_RECEIVE,
+            PortMoveMode::Send => sys::MACH_MSG_TYPE_MOVE_SEND,
+            PortMoveMode::SendOnce => return Err(send_once_unsupported()),
+        };
+        unsafe { self.extract_right(name, disposition) }
+    }
+
+    /// Places a copy of a right to `port` into the task's namespace under `name`.
+    ///
+    /// `name` must either be unused in the task, or already name the same port (in which case a send right's user
+    /// reference count is incremented).
+    pub fn insert_right(&self, name: RawPort, port: &Port, mode: PortCopyMode) -> io::Result<()> {
+        let disposition = match mode {
+            PortCopyMode::Send => sys::MACH_MSG_TYPE_COPY_SEND,
+            PortCopyMode::MakeSend => sys::MACH_MSG_TYPE_MAKE_SEND,
+            PortCopyMode::MakeSendOnce => sys::MACH_MSG_TYPE_MAKE_SEND_ONCE,
+        };
+        unsafe { self.insert_raw_right(name, port.as_raw_port(), disposition) }
+    }
+
+    /// Moves a right to `port` into the task's namespace under `name`. See [`insert_right`](Task::insert_right).
+    pub fn insert_moved_right(&self, name: RawPort, port: Port, mode: PortMoveMode) -> io::Result<()> {
+        let disposition = match mode {
+            PortMoveMode::Receive => sys::MACH_MSG_TYPE_MOVE_RECEIVE,
+            PortMoveMode::Send => sys::MACH_MSG_TYPE_MOVE_SEND,
+            PortMoveMode::SendOnce => sys::MACH_MSG_TYPE_MOVE_SEND_ONCE,
+        };
+        // On failure the kernel leaves the right untouched, so it is released with the Port
+        unsafe { self.insert_raw_right(name, port.as_raw_port(), disposition)?; }
+        port.into_raw_port();
+        Ok(())
+    }
+
+    unsafe fn extract_right(&self, name: RawPort, disposition: sys::mach_msg_type_name_t) -> io::Result<Port> {
+        self.require(TaskFlavor::Control)?;
+        let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+        let mut right: sys::mach_msg_type_name_t = 0;
+        mach_call!(log op: "mach_port_extract_right", port: self.as_raw_port(), sys::mach_port_extract_right(self.as_raw_port(), name, disposition, &mut port, &mut right))?;
+        // The name may also hold rights we already own (e.g. extracting a send right to one of our own receive
+        // rights), so only the right that was actually transferred is taken
+        match right {
+            sys::MACH_MSG_TYPE_PORT_RECEIVE => Ok(Port::from_raw_receive_right(port)),
+            sys::MACH_MSG_TYPE_PORT_SEND => Ok(Port::from_raw_send_right(port)),
+            // Send-once rights are rejected before the call
+            _ => unreachable!("mach_port_extract_right returned unexpected right {}", right),
+        }
+    }
+
+    unsafe fn insert_raw_right(&self, name: RawPort, port: RawPort, disposition: sys::mach_msg_type_name_t) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        mach_call!(log op: "mach_port_insert_right", port: self.as_raw_port(), sys::mach_port_insert_right(self.as_raw_port(), name, port, disposition))?;
+        Ok(())
+    }
+}
+
+fn send_once_unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "extracting send-once rights is not supported")
+}
@@ -0,0 +1,233 @@
+//! Scaffolding for testing protocols built on [`Port`] without a second process.
+//!
+//! Enabled by the `testing` feature. [`pair`] creates two connected [`Endpoint`]s, each holding a receive right and a
+//! send right to its peer's. Faults can be queued on an endpoint with [`Endpoint::inject`] to make its next sends
+//! stall or fail the way they would against a busy or crashed peer, and [`Endpoint::expect_ids`] checks the sequence
+//! of messages that arrived.
+//!
+//! On non-Apple targets (or with `--cfg mach_port_fake_kernel`), ports are served by the in-memory fake kernel, so these
+//! tests run without a Darwin kernel.
+
+use crate::{Port, Msg, MsgBuffer, RawPort};
+
+use std::{io, thread};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use mach_sys as sys;
+use mach_core::error::MachError;
+
+/// A failure or delay applied to a single send from an [`Endpoint`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Fault {
+    /// Sleeps before sending, to simulate a slow transport or a descheduled sender.
+    Delay(Duration),
+    /// Fails the send with `MACH_SEND_TIMED_OUT`, as happens when the peer's queue stays full for the whole timeout.
+    QueueFull,
+    /// Fails the send with `MACH_SEND_INVALID_DEST`, as happens once the peer's receive right is destroyed.
+    PeerDead,
+}
+
+/// One side of an in-process connection created by [`pair`].
+pub struct Endpoint {
+    receive: Port,
+    peer: Port,
+    faults: Mutex<VecDeque<Fault>>,
+}
+
+/// Creates two endpoints, each able to send to the other.
+pub fn pair() -> io::Result<(Endpoint, Endpoint)> {
+    let a = Port::new()?;
+    let b = Port::new()?;
+    let a_sender = a.make_sender()?;
+    let b_sender = b.make_sender()?;
+    a.set_label("testing::pair(a)");
+    b.set_label("testing::pair(b)");
+    Ok((
+        Endpoint { receive: a, peer: b_sender, faults: Mutex::new(VecDeque::new()) },
+        Endpoint { receive: b, peer: a_sender, faults: Mutex::new(VecDeque::new()) },
+    ))
+}
+
+impl Endpoint {
+    /// The port this endpoint receives on.
+    #[inline]
+    pub fn port(&self) -> &Port {
+        &self.receive
+    }
+
+    /// The send right to the peer's port.
+    #[inline]
+    pub fn peer(&self) -> &Port {
+        &self.peer
+    }
+
+    /// Queues a fault to apply to the next send that has not already consumed one.
+    pub fn inject(&self, fault: Fault) {
+        self.faults.lock().unwrap().push_back(fault);
+    }
+
+    /// Discards any faults that have not been applied yet.
+    pub fn clear_faults(&self) {
+        self.faults.lock().unwrap().clear();
+    }
+
+    /// Sends a message to the peer, applying the next injected fault, if any.
+    pub fn send(&self, msg: &mut Msg, timeout: Option<Duration>) -> io::Result<()> {
+        let fault = self.faults.lock().unwrap().pop_front();
+        match fault {
+            Some(Fault::Delay(duration)) => thread::sleep(duration),
+            Some(Fault::QueueFull) => return Err(send_error(sys::MACH_SEND_TIMED_OUT, self.peer.as_raw_port())),
+            Some(Fault::PeerDead) => return Err(send_error(sys::MACH_SEND_INVALID_DEST, self.peer.as_raw_port())),
+            None => (),
+        }
+        self.peer.send(msg, timeout)
+    }
+
+    #[inline]
+    pub fn recv(&self, msg: &mut Msg, timeout: Option<Duration>) -> io::Result<()> {
+        self.receive.recv(msg, timeout)
+    }
+
+    /// Receives messages until `ids.len()` have arrived, and panics unless their IDs match `ids` in order.
+    ///
+    /// Waits at most `timeout` for each message. Returns the received messages so their contents can be checked too.
+    pub fn expect_ids(&self, ids: &[sys::mach_msg_id_t], timeout: Duration) -> Vec<MsgBuffer> {
+        let mut received = Vec::with_capacity(ids.len());
+        for (index, &expected) in ids.iter().enumerate() {
+            let mut buffer = receive_buffer();
+            if let Err(err) = self.receive.recv(&mut buffer, Some(timeout)) {
+                panic!("expected message {} with ID {} of sequence {:?}, but receive failed: {}", index, expected, ids, err);
+            }
+            if buffer.id() != expected {
+                panic!("expected message {} of sequence {:?} to have ID {}, but it had ID {}", index, ids, expected, buffer.id());
+            }
+            received.push(buffer);
+        }
+        received
+    }
+
+    /// Panics if a message arrives within `timeout`.
+    pub fn expect_empty(&self, timeout: Duration) {
+        let mut buffer = receive_buffer();
+        match self.receive.recv(&mut buffer, Some(timeout)) {
+            Ok(()) => panic!("expected no messages, but received one with ID {}", buffer.id()),
+            Err(ref err) if MachError::from_io(err).map(|err| err.is_timeout()).unwrap_or(false) => (),
+            Err(err) => panic!("expected no messages, but receive failed: {}", err),
+        }
+    }
+}
+
+// Large enough for any message a test is likely to send
+fn receive_buffer() -> MsgBuffer {
+    let mut buffer = MsgBuffer::new();
+    buffer.reserve_descriptors(16);
+    buffer.reserve_inline_data(64 * 1024);
+    buffer
+}
+
+fn send_error(code: u32, port: RawPort) -> io::Error {
+    MachError::from_mach_error(code as _).with_context("mach_msg(send)", Some(port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn message(id: sys::mach_msg_id_t) -> MsgBuffer {
+        let mut msg = MsgBuffer::new();
+        msg.set_id(id);
+        msg
+    }
+
+    fn send_error(endpoint: &Endpoint, fault: Fault) -> MachError {
+        endpoint.inject(fault);
+        let err = endpoint.send(&mut message(1), Some(TIMEOUT)).unwrap_err();
+        MachError::from_io(&err).expect("injected faults should fail with a MachError")
+    }
+
+    #[test]
+    fn pair_connects_both_ways() {
+        let (a, b) = pair().unwrap();
+        a.send(&mut message(1), None).unwrap();
+        b.send(&mut message(2), None).unwrap();
+        b.expect_ids(&[1], TIMEOUT);
+        a.expect_ids(&[2], TIMEOUT);
+        assert_eq!(a.port().label().as_deref(), Some("testing::pair(a)"));
+        assert_eq!(a.peer().as_raw_port(), b.port().as_raw_port());
+    }
+
+    #[test]
+    fn delay_fault() {
+        let (a, b) = pair().unwrap();
+        a.inject(Fault::Delay(Duration::from_millis(20)));
+        let start = Instant::now();
+        a.send(&mut message(1), None).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        b.expect_ids(&[1], TIMEOUT);
+    }
+
+    #[test]
+    fn queue_full_fault() {
+        let (a, b) = pair().unwrap();
+        assert!(send_error(&a, Fault::QueueFull).is_timeout());
+        b.expect_empty(Duration::from_millis(10));
+    }
+
+    #[test]
+    fn peer_dead_fault() {
+        let (a, b) = pair().unwrap();
+        assert!(send_error(&a, Fault::PeerDead).is_peer_gone());
+        b.expect_empty(Duration::from_millis(10));
+    }
+
+    #[test]
+    fn faults_apply_in_order() {
+        let (a, b) = pair().unwrap();
+        a.inject(Fault::PeerDead);
+        a.inject(Fault::QueueFull);
+        assert!(a.send(&mut message(1), None).is_err());
+        assert!(a.send(&mut message(2), None).is_err());
+        a.send(&mut message(3), None).unwrap();
+
+        a.inject(Fault::PeerDead);
+        a.clear_faults();
+        a.send(&mut message(4), None).unwrap();
+        b.expect_ids(&[3, 4], TIMEOUT);
+    }
+
+    #[test]
+    #[should_panic(expected = "to have ID 2, but it had ID 1")]
+    fn expect_ids_order_mismatch() {
+        let (a, b) = pair().unwrap();
+        a.send(&mut message(1), None).unwrap();
+        a.send(&mut message(2), None).unwrap();
+        b.expect_ids(&[2, 1], TIMEOUT);
+    }
+
+    #[test]
+    #[should_panic(expected = "but receive failed")]
+    fn expect_ids_missing() {
+        let (_a, b) = pair().unwrap();
+        b.expect_ids(&[1], Duration::from_millis(10));
+    }
+
+    #[test]
+    fn expect_empty() {
+        let (_a, b) = pair().unwrap();
+        b.expect_empty(Duration::from_millis(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected no messages, but received one with ID 5")]
+    fn expect_empty_with_message() {
+        let (a, b) = pair().unwrap();
+        a.send(&mut message(5), None).unwrap();
+        b.expect_empty(TIMEOUT);
+    }
+}
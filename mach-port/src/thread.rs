@@ -0,0 +1,269 @@
+use crate::{RawPort, Port, ThreadState, RawThreadState, NativeDebugState, WatchpointKind, ExceptionMask, ExceptionBehavior, ExceptionHandler, ExceptionPortsGuard};
+use crate::exception_ports::ExceptionPortTarget;
+
+use std::{io, mem, fmt};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// An owned send right to a thread port.
+pub struct Thread {
+    port: sys::thread_act_t,
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_port_deallocate(thread port)", port: self.port, sys::mach_port_deallocate(mach_core::self_port::task_self(), self.port));
+        }
+    }
+}
+
+impl Thread {
+    /// Gets a handle to the calling thread.
+    pub fn current() -> Thread {
+        // The cached reference is only borrowed, so take one of our own for the Thread to release. This can only fail
+        // if the name's user references overflow.
+        let port = mach_core::self_port::thread_self();
+        unsafe {
+            mach_call!(log op: "mach_port_mod_refs(send)", port: port, sys::mach_port_mod_refs(mach_core::self_port::task_self(), port, sys::MACH_PORT_RIGHT_SEND, 1))
+                .expect("failed to add a reference to the current thread's port");
+            Thread::from_raw_port(port)
+        }
+    }
+
+    /// Takes ownership of a send right to a thread port.
+    pub unsafe fn from_raw_port(port: RawPort) -> Thread {
+        Thread {
+            port,
+        }
+    }
+
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        let port = self.port;
+        mem::forget(self);
+        port
+    }
+
+    /// Gets the system-wide unique identifier of the thread.
+    pub fn id(&self) -> io::Result<u64> {
+        unsafe {
+            let info: sys::thread_identifier_info_data_t = self.info(sys::THREAD_IDENTIFIER_INFO)?;
+            Ok(info.thread_id)
+        }
+    }
+
+    /// Retrieves a send right to one of the thread's special ports, or `None` if it is not set.
+    pub fn special_port(&self, which: ThreadSpecialPort) -> io::Result<Option<Port>> {
+        unsafe {
+            let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "thread_get_special_port", port: self.port, sys::thread_get_special_port(self.port, which.as_raw(), &mut port))?;
+            if port == sys::MACH_PORT_NULL {
+                return Ok(None);
+            }
+            Ok(Some(Port::from_raw_send_right(port)))
+        }
+    }
+
+    /// Registers a copy of the send right `port` as one of the thread's special ports.
+    pub fn set_special_port(&self, which: ThreadSpecialPort, port: &Port) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "thread_set_special_port", port: self.port, sys::thread_set_special_port(self.port, which.as_raw(), port.as_raw_port()))?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the exception handlers registered on the thread for the given exception types.
+    ///
+    /// Thread-level handlers take precedence over those registered on the task.
+    pub fn exception_ports(&self, mask: ExceptionMask) -> io::Result<Vec<ExceptionHandler>> {
+        ExceptionPortTarget::Thread(self).get(mask)
+    }
+
+    /// Registers `port` as the thread's handler for the given exception types, or removes the handler if `port` is `None`.
+    ///
+    /// The port must hold a send right, which will be copied.
+    pub fn set_exception_ports(&self, mask: ExceptionMask, port: Option<&Port>, behavior: ExceptionBehavior, flavor: sys::thread_state_flavor_t) -> io::Result<()> {
+        ExceptionPortTarget::Thread(self).set(mask, port, behavior, flavor)
+    }
+
+    /// Registers `port` as the thread's handler for the given exception types, restoring the previously registered
+    /// handlers when the returned guard is dropped.
+    ///
+    /// The port must hold a send right, which will be copied.
+    pub fn swap_exception_ports(&self, mask: ExceptionMask, behavior: ExceptionBehavior, flavor: sys::thread_state_flavor_t, port: &Port) -> io::Result<ExceptionPortsGuard> {
+        ExceptionPortTarget::Thread(self).swap(mask, Some(port), behavior, flavor)
+    }
+
+    /// Increments the suspend count of the thread, stopping it until the returned guard is dropped.
+    ///
+    /// Suspending the calling thread will never return.
+    pub fn suspend(&self) -> io::Result<ThreadSuspendGuard> {
+        unsafe {
+            mach_call!(log op: "thread_suspend", port: self.port, sys::thread_suspend(self.port))?;
+        }
+        Ok(ThreadSuspendGuard { thread: self })
+    }
+
+    /// Aborts any system call or page fault the thread is blocked in, causing it to return an interrupted error.
+    ///
+    /// The thread should be suspended.
+    pub fn abort(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "thread_abort", port: self.port, sys::thread_abort(self.port))?;
+        }
+        Ok(())
+    }
+
+    /// Like [`abort`](Thread::abort), but fails rather than interrupting the thread at a point where its state cannot
+    /// be cleanly resumed.
+    pub fn abort_safely(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "thread_abort_safely", port: self.port, sys::thread_abort_safely(self.port))?;
+        }
+        Ok(())
+    }
+
+    /// Reads the register state of the given flavor from the thread.
+    ///
+    /// Unless the thread is the calling thread, it should be suspended for the result to be meaningful.
+    pub fn get_state<S: ThreadState>(&self) -> io::Result<S> {
+        unsafe {
+            let mut state: S = mem::zeroed();
+            let mut count = S::COUNT;
+            mach_call!(log op: "thread_get_state", port: self.port, sys::thread_get_state(self.port, S::FLAVOR, &mut state as *mut S as sys::thread_state_t, &mut count))?;
+            debug_assert_eq!(count, S::COUNT);
+            Ok(state)
+        }
+    }
+
+    /// Overwrites the register state of the given flavor on the thread.
+    ///
+    /// The thread should be suspended, otherwise the state will be clobbered at an arbitrary point in its execution.
+    pub fn set_state<S: ThreadState>(&self, state: &S) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "thread_set_state", port: self.port, sys::thread_set_state(self.port, S::FLAVOR, state as *const S as sys::thread_state_t, S::COUNT))?;
+            Ok(())
+        }
+    }
+
+    /// Reads the register state of a flavor only known at runtime.
+    pub fn get_raw_state(&self, flavor: sys::thread_state_flavor_t) -> io::Result<RawThreadState> {
+        unsafe {
+            let mut data = vec![0 as sys::natural_t; sys::THREAD_STATE_MAX as usize];
+            let mut count = data.len() as sys::mach_msg_type_number_t;
+            mach_call!(log op: "thread_get_state", port: self.port, sys::thread_get_state(self.port, flavor, data.as_mut_ptr(), &mut count))?;
+            data.truncate(count as usize);
+            Ok(RawThreadState::new(flavor, data))
+        }
+    }
+
+    pub fn set_raw_state(&self, state: &RawThreadState) -> io::Result<()> {
+        unsafe {
+            let data = state.as_slice();
+            mach_call!(log op: "thread_set_state", port: self.port, sys::thread_set_state(self.port, state.flavor(), data.as_ptr() as sys::thread_state_t, data.len() as _))?;
+            Ok(())
+        }
+    }
+
+    /// Reads the register state of the given flavor, applies `f` to it, and writes it back.
+    ///
+    /// If `f` returns an error, the thread's state is left untouched.
+    pub fn modify_state<S, F>(&self, f: F) -> io::Result<()> where
+        S: ThreadState,
+        F: FnOnce(&mut S) -> io::Result<()>,
+    {
+        let mut state = self.get_state::<S>()?;
+        f(&mut state)?;
+        self.set_state(&state)
+    }
+
+    /// Installs a hardware execution breakpoint in the given debug register slot.
+    pub fn set_hardware_breakpoint(&self, slot: usize, address: u64) -> io::Result<()> {
+        self.modify_state(|state: &mut NativeDebugState| state.set_breakpoint(slot, address))
+    }
+
+    pub fn clear_hardware_breakpoint(&self, slot: usize) -> io::Result<()> {
+        self.modify_state(|state: &mut NativeDebugState| state.clear_breakpoint(slot))
+    }
+
+    /// Installs a hardware watchpoint covering `len` bytes at `address` in the given debug register slot.
+    pub fn set_hardware_watchpoint(&self, slot: usize, address: u64, len: usize, kind: WatchpointKind) -> io::Result<()> {
+        self.modify_state(|state: &mut NativeDebugState| state.set_watchpoint(slot, address, len, kind))
+    }
+
+    pub fn clear_hardware_watchpoint(&self, slot: usize) -> io::Result<()> {
+        self.modify_state(|state: &mut NativeDebugState| state.clear_watchpoint(slot))
+    }
+
+    /// Retrieves a `thread_info` structure of the given flavor.
+    ///
+    /// The caller must ensure `T` is the structure corresponding to `flavor`.
+    pub(crate) unsafe fn info<T>(&self, flavor: u32) -> io::Result<T> {
+        let mut info: T = mem::zeroed();
+        let mut count = (mem::size_of::<T>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+        mach_call!(log op: "thread_info", port: self.port, sys::thread_info(self.port, flavor as _, &mut info as *mut T as sys::thread_info_t, &mut count))?;
+        Ok(info)
+    }
+}
+
+impl fmt::Debug for Thread {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Thread")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
+
+/// Identifies one of the special ports registered on a thread.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ThreadSpecialPort {
+    Kernel,
+    Inspect,
+    Read,
+}
+
+impl ThreadSpecialPort {
+    fn as_raw(self) -> i32 {
+        (match self {
+            ThreadSpecialPort::Kernel => sys::THREAD_KERNEL_PORT,
+            ThreadSpecialPort::Inspect => sys::THREAD_INSPECT_PORT,
+            ThreadSpecialPort::Read => sys::THREAD_READ_PORT,
+        }) as i32
+    }
+}
+
+/// Resumes a [`Thread`] suspended with [`Thread::suspend`] when dropped.
+#[must_use]
+pub struct ThreadSuspendGuard<'a> {
+    thread: &'a Thread,
+}
+
+impl<'a> ThreadSuspendGuard<'a> {
+    #[inline]
+    pub fn thread(&self) -> &'a Thread {
+        self.thread
+    }
+
+    /// Resumes the thread, reporting any failure instead of logging it.
+    pub fn resume(self) -> io::Result<()> {
+        let port = self.thread.port;
+        mem::forget(self);
+        unsafe {
+            mach_call!(op: "thread_resume", port: port, sys::thread_resume(port))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Drop for ThreadSuspendGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "thread_resume", port: self.thread.port, sys::thread_resume(self.thread.port));
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{Thread, LatencyQosTier, ThroughputQosTier};
+
+use std::{io, mem};
+use std::time::Duration;
+
+use mach_sys as sys;
+use mach_core::{mach_call, time};
+
+/// Scheduling parameters for a real-time thread (`THREAD_TIME_CONSTRAINT_POLICY`).
+///
+/// A thread with this policy asks for `computation` of CPU time within `constraint` of the start of each `period`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TimeConstraintPolicy {
+    /// The nominal interval between the starts of successive computations, or zero if there is no inherent period.
+    pub period: Duration,
+    /// The CPU time needed for each computation.
+    pub computation: Duration,
+    /// The maximum real time from the start of a computation to its end. Must not be less than `computation`.
+    pub constraint: Duration,
+    /// Whether the computation may be interrupted, which allows `constraint` to exceed `computation` by more.
+    pub preemptible: bool,
+}
+
+/// A thread quality-of-service class, as used by `pthread_set_qos_class_self_np` and dispatch queues.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum QosClass {
+    Unspecified,
+    Maintenance,
+    Background,
+    Utility,
+    Legacy,
+    UserInitiated,
+    UserInteractive,
+}
+
+// THREAD_QOS_POLICY and its tiers live in the private thread_policy_private.h, so they don't come through bindgen
+const THREAD_QOS_POLICY: u32 = 9;
+const THREAD_QOS_MIN_TIER_IMPORTANCE: i32 = -15;
+
+#[repr(C)]
+struct ThreadQosPolicy {
+    qos_tier: sys::integer_t,
+    tier_importance: sys::integer_t,
+}
+
+impl QosClass {
+    fn from_raw(raw: sys::integer_t) -> QosClass {
+        match raw {
+            1 => QosClass::Maintenance,
+            2 => QosClass::Background,
+            3 => QosClass::Utility,
+            4 => QosClass::Legacy,
+            5 => QosClass::UserInitiated,
+            6 => QosClass::UserInteractive,
+            _ => QosClass::Unspecified,
+        }
+    }
+
+    fn into_raw(self) -> sys::integer_t {
+        match self {
+            QosClass::Unspecified => 0,
+            QosClass::Maintenance => 1,
+            QosClass::Background => 2,
+            QosClass::Utility => 3,
+            QosClass::Legacy => 4,
+            QosClass::UserInitiated => 5,
+            QosClass::UserInteractive => 6,
+        }
+    }
+}
+
+impl Thread {
+    /// Gets the thread's real-time parameters, or `None` if it is scheduled with the standard timeshare policy.
+    pub fn time_constraint_policy(&self) -> io::Result<Option<TimeConstraintPolicy>> {
+        unsafe {
+            let (policy, is_default): (sys::thread_time_constraint_policy_data_t, bool) = self.get_policy(sys::THREAD_TIME_CONSTRAINT_POLICY)?;
+            if is_default {
+                return Ok(None);
+            }
+            Ok(Some(TimeConstraintPolicy {
+                period: time::abs_to_duration(policy.period as u64),
+                computation: time::abs_to_duration(policy.computation as u64),
+                constraint: time::abs_to_duration(policy.constraint as u64),
+                preemptible: policy.preemptible != 0,
+            }))
+        }
+    }
+
+    /// Makes the thread a real-time thread with the given parameters, converting them to Mach absolute time units.
+    ///
+    /// The kernel may demote threads that consistently exceed their computation budget back to timeshare.
+    pub fn set_time_constraint_policy(&self, params: &TimeConstraintPolicy) -> io::Result<()> {
+        if params.computation > params.constraint {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "time constraint policy computation exceeds constraint"));
+        }
+        unsafe {
+            let mut policy: sys::thread_time_constraint_policy_data_t = mem::zeroed();
+            policy.period = duration_to_abs(params.period)?;
+            policy.computation = duration_to_abs(params.computation)?;
+            policy.constraint = duration_to_abs(params.constraint)?;
+            policy.preemptible = params.preemptible as _;
+            self.set_policy(sys::THREAD_TIME_CONSTRAINT_POLICY, &policy)
+        }
+    }
+
+    /// Makes the calling thread a real-time thread. See [`set_time_constraint_policy`](Thread::set_time_constraint_policy).
+    pub fn set_current_time_constraint_policy(params: &TimeConstraintPolicy) -> io::Result<()> {
+        Thread::current().set_time_constraint_policy(params)
+    }
+
+    /// Returns the thread to the standard timeshare policy, undoing [`set_time_constraint_policy`](Thread::set_time_constraint_policy).
+    pub fn set_timeshare(&self) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::thread_extended_policy_data_t = mem::zeroed();
+            policy.timeshare = 1;
+            self.set_policy(sys::THREAD_EXTENDED_POLICY, &policy)
+        }
+    }
+
+    /// Gets the thread's affinity tag, or `None` if it has not been assigned one.
+    pub fn affinity_tag(&self) -> io::Result<Option<i32>> {
+        unsafe {
+            let (policy, is_default): (sys::thread_affinity_policy_data_t, bool) = self.get_policy(sys::THREAD_AFFINITY_POLICY)?;
+            if is_default || policy.affinity_tag == sys::THREAD_AFFINITY_TAG_NULL as i32 {
+                Ok(None)
+            } else {
+                Ok(Some(policy.affinity_tag))
+            }
+        }
+    }
+
+    /// Assigns the thread to an affinity set. Threads in the same task with the same tag are scheduled to share an L2
+    /// cache where possible, while threads with different tags are spread across caches. `None` removes the thread
+    /// from its affinity set.
+    ///
+    /// Affinity sets are only a hint, and are not supported at all on Apple silicon (which fails with
+    /// `KERN_NOT_SUPPORTED`).
+    pub fn set_affinity_tag(&self, tag: Option<i32>) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::thread_affinity_policy_data_t = mem::zeroed();
+            policy.affinity_tag = tag.unwrap_or(sys::THREAD_AFFINITY_TAG_NULL as i32);
+            self.set_policy(sys::THREAD_AFFINITY_POLICY, &policy)
+        }
+    }
+
+    /// Assigns the calling thread to an affinity set. See [`set_affinity_tag`](Thread::set_affinity_tag).
+    pub fn set_current_affinity_tag(tag: Option<i32>) -> io::Result<()> {
+        Thread::current().set_affinity_tag(tag)
+    }
+
+    /// Gets the thread's importance relative to other threads in the same task.
+    pub fn precedence(&self) -> io::Result<i32> {
+        unsafe {
+            let (policy, _): (sys::thread_precedence_policy_data_t, bool) = self.get_policy(sys::THREAD_PRECEDENCE_POLICY)?;
+            Ok(policy.importance)
+        }
+    }
+
+    /// Sets the thread's importance relative to other threads in the same task, which is added to the task's base
+    /// priority.
+    pub fn set_precedence(&self, importance: i32) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::thread_precedence_policy_data_t = mem::zeroed();
+            policy.importance = importance;
+            self.set_policy(sys::THREAD_PRECEDENCE_POLICY, &policy)
+        }
+    }
+
+    /// Sets the calling thread's importance. See [`set_precedence`](Thread::set_precedence).
+    pub fn set_current_precedence(importance: i32) -> io::Result<()> {
+        Thread::current().set_precedence(importance)
+    }
+
+    /// Gets the thread's QoS class and its relative priority within that class.
+    pub fn qos_class(&self) -> io::Result<(QosClass, i32)> {
+        unsafe {
+            let (policy, _): (ThreadQosPolicy, bool) = self.get_policy(THREAD_QOS_POLICY)?;
+            Ok((QosClass::from_raw(policy.qos_tier), policy.tier_importance))
+        }
+    }
+
+    /// Assigns the thread a QoS class. `relative_priority` lowers the thread's importance within the class, and must be
+    /// between -15 and 0.
+    pub fn set_qos_class(&self, class: QosClass, relative_priority: i32) -> io::Result<()> {
+        if relative_priority > 0 || relative_priority < THREAD_QOS_MIN_TIER_IMPORTANCE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "QoS relative priority must be between -15 and 0"));
+        }
+        unsafe {
+            let policy = ThreadQosPolicy {
+                qos_tier: class.into_raw(),
+                tier_importance: relative_priority,
+            };
+            self.set_policy(THREAD_QOS_POLICY, &policy)
+        }
+    }
+
+    /// Assigns the calling thread a QoS class. See [`set_qos_class`](Thread::set_qos_class).
+    pub fn set_current_qos_class(class: QosClass, relative_priority: i32) -> io::Result<()> {
+        Thread::current().set_qos_class(class, relative_priority)
+    }
+
+    pub fn latency_qos(&self) -> io::Result<LatencyQosTier> {
+        unsafe {
+            let (policy, _): (sys::thread_latency_qos_policy_data_t, bool) = self.get_policy(sys::THREAD_LATENCY_QOS_POLICY)?;
+            Ok(LatencyQosTier::from_raw(policy.thread_latency_qos_tier as u32))
+        }
+    }
+
+    /// Overrides the latency QoS tier the thread inherits from its task.
+    pub fn set_latency_qos(&self, tier: LatencyQosTier) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::thread_latency_qos_policy_data_t = mem::zeroed();
+            policy.thread_latency_qos_tier = tier.into_raw() as _;
+            self.set_policy(sys::THREAD_LATENCY_QOS_POLICY, &policy)
+        }
+    }
+
+    pub fn throughput_qos(&self) -> io::Result<ThroughputQosTier> {
+        unsafe {
+            let (policy, _): (sys::thread_throughput_qos_policy_data_t, bool) = self.get_policy(sys::THREAD_THROUGHPUT_QOS_POLICY)?;
+            Ok(ThroughputQosTier::from_raw(policy.thread_throughput_qos_tier as u32))
+        }
+    }
+
+    /// Overrides the throughput QoS tier the thread inherits from its task.
+    pub fn set_throughput_qos(&self, tier: ThroughputQosTier) -> io::Result<()> {
+        unsafe {
+            let mut policy: sys::thread_throughput_qos_policy_data_t = mem::zeroed();
+            policy.thread_throughput_qos_tier = tier.into_raw() as _;
+            self.set_policy(sys::THREAD_THROUGHPUT_QOS_POLICY, &policy)
+        }
+    }
+
+    // Also reports whether the kernel returned the default policy because none was set explicitly
+    unsafe fn get_policy<T>(&self, flavor: u32) -> io::Result<(T, bool)> {
+        let mut policy: T = mem::zeroed();
+        let mut count = (mem::size_of::<T>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+        let mut get_default: sys::boolean_t = 0;
+        mach_call!(log op: "thread_policy_get", port: self.as_raw_port(), sys::thread_policy_get(self.as_raw_port(), flavor as _, &mut policy as *mut T as sys::thread_policy_t, &mut count, &mut get_default))?;
+        Ok((policy, get_default != 0))
+    }
+
+    unsafe fn set_policy<T>(&self, flavor: u32, policy: &T) -> io::Result<()> {
+        let count = (mem::size_of::<T>() / mem::size_of::<sys::integer_t>()) as sys::mach_msg_type_number_t;
+        mach_call!(log op: "thread_policy_set", port: self.as_raw_port(), sys::thread_policy_set(self.as_raw_port(), flavor as _, policy as *const T as sys::thread_policy_t, count))?;
+        Ok(())
+    }
+}
+
+// Time constraint parameters are 32-bit counts of Mach absolute time units
+fn duration_to_abs(duration: Duration) -> io::Result<u32> {
+    let abs = time::duration_to_abs(duration);
+    if abs > u32::MAX as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "time constraint policy duration too large"));
+    }
+    Ok(abs as u32)
+}
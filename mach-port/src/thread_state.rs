@@ -0,0 +1,346 @@
+use std::{io, mem, ptr, slice, fmt};
+use std::ops::{Deref, DerefMut};
+
+use mach_sys as sys;
+
+/// A register state structure that can be read from or written to a [`Thread`](crate::Thread).
+///
+/// Implementors must be `repr(C)` wrappers around the structure the kernel expects for `FLAVOR`. Values are always in
+/// the native byte order of the target, since thread state never crosses a machine boundary.
+pub unsafe trait ThreadState: Sized {
+    const FLAVOR: sys::thread_state_flavor_t;
+
+    /// The size of the structure in `natural_t` units, as the `*_COUNT` macros in the headers compute it.
+    const COUNT: sys::mach_msg_type_number_t = (mem::size_of::<Self>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+}
+
+/// Thread state of a flavor only known at runtime, such as the state carried in exception messages.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RawThreadState {
+    flavor: sys::thread_state_flavor_t,
+    data: Vec<sys::natural_t>,
+}
+
+impl RawThreadState {
+    pub fn new(flavor: sys::thread_state_flavor_t, data: Vec<sys::natural_t>) -> RawThreadState {
+        RawThreadState { flavor, data }
+    }
+
+    pub fn from_state<S: ThreadState>(state: &S) -> RawThreadState {
+        let data = unsafe { slice::from_raw_parts(state as *const S as *const sys::natural_t, S::COUNT as usize) };
+        RawThreadState {
+            flavor: S::FLAVOR,
+            data: data.to_vec(),
+        }
+    }
+
+    #[inline]
+    pub fn flavor(&self) -> sys::thread_state_flavor_t {
+        self.flavor
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[sys::natural_t] {
+        &self.data
+    }
+
+    /// Interprets the state as the typed state `S`, if the flavor and size match.
+    pub fn get<S: ThreadState>(&self) -> Option<S> {
+        if self.flavor != S::FLAVOR || self.data.len() < S::COUNT as usize {
+            return None;
+        }
+        unsafe { Some(ptr::read_unaligned(self.data.as_ptr() as *const S)) }
+    }
+
+    /// Replaces the contents with the typed state `S`. Fails if the flavor does not match.
+    pub fn set<S: ThreadState>(&mut self, state: &S) -> bool {
+        if self.flavor != S::FLAVOR {
+            return false;
+        }
+        *self = RawThreadState::from_state(state);
+        true
+    }
+}
+
+impl fmt::Debug for RawThreadState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RawThreadState")
+            .field("flavor", &self.flavor)
+            .field("count", &self.data.len())
+            .finish()
+    }
+}
+
+macro_rules! thread_state {
+    ($(#[$attr:meta])* $name:ident, $raw:ty, $flavor:expr) => {
+        $(#[$attr])*
+        #[repr(C)]
+        #[derive(Clone, Copy)]
+        pub struct $name(pub $raw);
+
+        unsafe impl ThreadState for $name {
+            const FLAVOR: sys::thread_state_flavor_t = $flavor as sys::thread_state_flavor_t;
+        }
+
+        impl Deref for $name {
+            type Target = $raw;
+
+            #[inline]
+            fn deref(&self) -> &$raw {
+                &self.0
+            }
+        }
+
+        impl DerefMut for $name {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut $raw {
+                &mut self.0
+            }
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+thread_state!(
+    /// General purpose registers (`x86_THREAD_STATE64`).
+    X86ThreadState64, sys::x86_thread_state64_t, sys::x86_THREAD_STATE64
+);
+#[cfg(target_arch = "x86_64")]
+thread_state!(
+    /// Floating point and vector registers (`x86_FLOAT_STATE64`).
+    X86FloatState64, sys::x86_float_state64_t, sys::x86_FLOAT_STATE64
+);
+#[cfg(target_arch = "x86_64")]
+thread_state!(
+    /// Information about the last exception taken by the thread (`x86_EXCEPTION_STATE64`).
+    X86ExceptionState64, sys::x86_exception_state64_t, sys::x86_EXCEPTION_STATE64
+);
+
+#[cfg(target_arch = "aarch64")]
+thread_state!(
+    /// General purpose registers (`ARM_THREAD_STATE64`).
+    ArmThreadState64, sys::arm_thread_state64_t, sys::ARM_THREAD_STATE64
+);
+#[cfg(target_arch = "aarch64")]
+thread_state!(
+    /// Floating point and vector registers (`ARM_NEON_STATE64`).
+    ArmNeonState64, sys::arm_neon_state64_t, sys::ARM_NEON_STATE64
+);
+#[cfg(target_arch = "aarch64")]
+thread_state!(
+    /// Information about the last exception taken by the thread (`ARM_EXCEPTION_STATE64`).
+    ArmExceptionState64, sys::arm_exception_state64_t, sys::ARM_EXCEPTION_STATE64
+);
+
+/// The general purpose register state of the architecture the crate was compiled for.
+#[cfg(target_arch = "x86_64")]
+pub type NativeThreadState = X86ThreadState64;
+/// The general purpose register state of the architecture the crate was compiled for.
+#[cfg(target_arch = "aarch64")]
+pub type NativeThreadState = ArmThreadState64;
+
+#[cfg(target_arch = "x86_64")]
+impl X86ThreadState64 {
+    #[inline]
+    pub fn pc(&self) -> u64 { self.0.__rip }
+    #[inline]
+    pub fn set_pc(&mut self, pc: u64) { self.0.__rip = pc }
+    #[inline]
+    pub fn sp(&self) -> u64 { self.0.__rsp }
+    #[inline]
+    pub fn set_sp(&mut self, sp: u64) { self.0.__rsp = sp }
+    #[inline]
+    pub fn fp(&self) -> u64 { self.0.__rbp }
+    #[inline]
+    pub fn set_fp(&mut self, fp: u64) { self.0.__rbp = fp }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl ArmThreadState64 {
+    #[inline]
+    pub fn pc(&self) -> u64 { self.0.__pc }
+    #[inline]
+    pub fn set_pc(&mut self, pc: u64) { self.0.__pc = pc }
+    #[inline]
+    pub fn sp(&self) -> u64 { self.0.__sp }
+    #[inline]
+    pub fn set_sp(&mut self, sp: u64) { self.0.__sp = sp }
+    #[inline]
+    pub fn fp(&self) -> u64 { self.0.__fp }
+    #[inline]
+    pub fn set_fp(&mut self, fp: u64) { self.0.__fp = fp }
+    #[inline]
+    pub fn lr(&self) -> u64 { self.0.__lr }
+    #[inline]
+    pub fn set_lr(&mut self, lr: u64) { self.0.__lr = lr }
+}
+
+impl fmt::Debug for NativeThreadState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeThreadState")
+            .field("pc", &format_args!("{:#x?}", self.pc()))
+            .field("sp", &format_args!("{:#x?}", self.sp()))
+            .field("fp", &format_args!("{:#x?}", self.fp()))
+            .finish()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+thread_state!(
+    /// Hardware debug registers (`x86_DEBUG_STATE64`).
+    ///
+    /// Breakpoints and watchpoints share the four slots provided by `DR0`-`DR3`.
+    X86DebugState64, sys::x86_debug_state64_t, sys::x86_DEBUG_STATE64
+);
+#[cfg(target_arch = "aarch64")]
+thread_state!(
+    /// Hardware breakpoint and watchpoint registers (`ARM_DEBUG_STATE64`).
+    ///
+    /// Breakpoints and watchpoints use separate slots. The number of slots actually implemented depends on the CPU;
+    /// setting a slot the hardware lacks is silently ignored by the kernel.
+    ArmDebugState64, sys::arm_debug_state64_t, sys::ARM_DEBUG_STATE64
+);
+
+/// The debug register state of the architecture the crate was compiled for.
+#[cfg(target_arch = "x86_64")]
+pub type NativeDebugState = X86DebugState64;
+/// The debug register state of the architecture the crate was compiled for.
+#[cfg(target_arch = "aarch64")]
+pub type NativeDebugState = ArmDebugState64;
+
+/// The kinds of access that trigger a hardware watchpoint.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum WatchpointKind {
+    /// Not supported on x86_64, which can only trap on writes or on any access.
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl X86DebugState64 {
+    pub const SLOT_COUNT: usize = 4;
+
+    /// Sets an execution breakpoint in the given slot.
+    pub fn set_breakpoint(&mut self, slot: usize, address: u64) -> io::Result<()> {
+        // Execution breakpoints must use RW = 00 and LEN = 00
+        self.set_slot(slot, address, 0b00, 0b00)
+    }
+
+    pub fn clear_breakpoint(&mut self, slot: usize) -> io::Result<()> {
+        self.clear_slot(slot)
+    }
+
+    /// Sets a data watchpoint in the given slot. `len` must be 1, 2, 4 or 8 and `address` must be aligned to it.
+    pub fn set_watchpoint(&mut self, slot: usize, address: u64, len: usize, kind: WatchpointKind) -> io::Result<()> {
+        let rw = match kind {
+            WatchpointKind::Write => 0b01,
+            WatchpointKind::ReadWrite => 0b11,
+            WatchpointKind::Read => return Err(io::Error::new(io::ErrorKind::InvalidInput, "x86_64 does not support read-only watchpoints")),
+        };
+        let len_bits = match len {
+            1 => 0b00,
+            2 => 0b01,
+            4 => 0b11,
+            8 => 0b10,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "watchpoint length must be 1, 2, 4 or 8")),
+        };
+        if address % len as u64 != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "watchpoint address must be aligned to its length"));
+        }
+        self.set_slot(slot, address, rw, len_bits)
+    }
+
+    pub fn clear_watchpoint(&mut self, slot: usize) -> io::Result<()> {
+        self.clear_slot(slot)
+    }
+
+    fn address_mut(&mut self, slot: usize) -> io::Result<&mut u64> {
+        match slot {
+            0 => Ok(&mut self.0.__dr0),
+            1 => Ok(&mut self.0.__dr1),
+            2 => Ok(&mut self.0.__dr2),
+            3 => Ok(&mut self.0.__dr3),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "debug register slot out of range")),
+        }
+    }
+
+    fn set_slot(&mut self, slot: usize, address: u64, rw: u64, len: u64) -> io::Result<()> {
+        *self.address_mut(slot)? = address;
+        let control_shift = 16 + 4 * slot as u64;
+        let mut dr7 = self.0.__dr7;
+        dr7 &= !(0b1111 << control_shift);
+        dr7 |= ((len << 2) | rw) << control_shift;
+        // Local enable bit for the slot
+        dr7 |= 1 << (2 * slot as u64);
+        self.0.__dr7 = dr7;
+        Ok(())
+    }
+
+    fn clear_slot(&mut self, slot: usize) -> io::Result<()> {
+        *self.address_mut(slot)? = 0;
+        let control_shift = 16 + 4 * slot as u64;
+        self.0.__dr7 &= !((0b1111 << control_shift) | (0b11 << (2 * slot as u64)));
+        Ok(())
+    }
+}
+
+// Control register fields shared by BCR and WCR
+#[cfg(target_arch = "aarch64")]
+const ARM_DBG_ENABLE: u64 = 1;
+#[cfg(target_arch = "aarch64")]
+const ARM_DBG_EL0: u64 = 0b10 << 1;
+
+#[cfg(target_arch = "aarch64")]
+impl ArmDebugState64 {
+    /// Sets an execution breakpoint in the given slot.
+    pub fn set_breakpoint(&mut self, slot: usize, address: u64) -> io::Result<()> {
+        if slot >= self.0.__bvr.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "breakpoint slot out of range"));
+        }
+        // Match all four bytes of the instruction at an unlinked address
+        const BAS_ALL: u64 = 0xf << 5;
+        self.0.__bvr[slot] = address & !0b11;
+        self.0.__bcr[slot] = BAS_ALL | ARM_DBG_EL0 | ARM_DBG_ENABLE;
+        Ok(())
+    }
+
+    pub fn clear_breakpoint(&mut self, slot: usize) -> io::Result<()> {
+        if slot >= self.0.__bvr.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "breakpoint slot out of range"));
+        }
+        self.0.__bvr[slot] = 0;
+        self.0.__bcr[slot] = 0;
+        Ok(())
+    }
+
+    /// Sets a data watchpoint in the given slot. The watched range must be between 1 and 8 bytes and must not cross
+    /// an 8-byte boundary.
+    pub fn set_watchpoint(&mut self, slot: usize, address: u64, len: usize, kind: WatchpointKind) -> io::Result<()> {
+        if slot >= self.0.__wvr.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "watchpoint slot out of range"));
+        }
+        let offset = (address & 0b111) as usize;
+        if len == 0 || offset + len > 8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "watchpoint range must lie within an aligned 8-byte word"));
+        }
+        let lsc: u64 = match kind {
+            WatchpointKind::Read => 0b01,
+            WatchpointKind::Write => 0b10,
+            WatchpointKind::ReadWrite => 0b11,
+        };
+        let bas = ((1u64 << len) - 1) << offset;
+        self.0.__wvr[slot] = address & !0b111;
+        self.0.__wcr[slot] = (bas << 5) | (lsc << 3) | ARM_DBG_EL0 | ARM_DBG_ENABLE;
+        Ok(())
+    }
+
+    pub fn clear_watchpoint(&mut self, slot: usize) -> io::Result<()> {
+        if slot >= self.0.__wvr.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "watchpoint slot out of range"));
+        }
+        self.0.__wvr[slot] = 0;
+        self.0.__wcr[slot] = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use crate::Thread;
+use crate::port::convert_timeout;
+
+use std::io;
+use std::time::Duration;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// What the calling thread should do with its priority when yielding with [`Thread::switch`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SwitchOption {
+    /// Simply yield the processor.
+    None,
+    /// Depress the thread to the lowest priority for the given time, or until aborted with
+    /// [`Thread::depress_abort`], so that any other runnable thread gets to run first.
+    Depress,
+    /// Block the thread for the given time, or until aborted.
+    Wait,
+}
+
+impl SwitchOption {
+    fn as_raw(self) -> i32 {
+        (match self {
+            SwitchOption::None => sys::SWITCH_OPTION_NONE,
+            SwitchOption::Depress => sys::SWITCH_OPTION_DEPRESS,
+            SwitchOption::Wait => sys::SWITCH_OPTION_WAIT,
+        }) as i32
+    }
+}
+
+impl Thread {
+    /// Yields the processor, optionally handing it directly to `hint` (e.g. the owner of a contended lock).
+    ///
+    /// `time` is the duration of the depression or wait, and is rounded to milliseconds. It is ignored for
+    /// [`SwitchOption::None`].
+    pub fn switch(hint: Option<&Thread>, option: SwitchOption, time: Duration) -> io::Result<()> {
+        let hint = hint.map(|thread| thread.as_raw_port()).unwrap_or(sys::MACH_PORT_NULL);
+        unsafe {
+            mach_call!(log op: "thread_switch", port: hint, sys::thread_switch(hint, option.as_raw(), convert_timeout(time)))?;
+        }
+        Ok(())
+    }
+
+    /// Ends a priority depression started with [`SwitchOption::Depress`] before its time expires.
+    pub fn depress_abort(&self) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "thread_depress_abort", port: self.as_raw_port(), sys::thread_depress_abort(self.as_raw_port()))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{RawPort, Msg};
+use crate::port::convert_timeout;
+
+use std::{io, mem, fmt};
+use std::time::{Duration, Instant};
+
+use mach_sys as sys;
+use mach_core::{mach_call, time};
+
+// mk_timer.h is not part of the public SDK, but these are exported by libsystem_kernel
+extern "C" {
+    fn mk_timer_create() -> sys::mach_port_name_t;
+    fn mk_timer_destroy(name: sys::mach_port_name_t) -> sys::kern_return_t;
+    fn mk_timer_arm(name: sys::mach_port_name_t, expire_time: u64) -> sys::kern_return_t;
+    fn mk_timer_cancel(name: sys::mach_port_name_t, result_time: *mut u64) -> sys::kern_return_t;
+}
+
+// The message delivered on expiration (mk_timer_expire_msg_t)
+#[repr(C)]
+struct ExpireMsg {
+    header: sys::mach_msg_header_t,
+    unused: [u64; 3],
+    trailer: sys::mach_msg_trailer_t,
+}
+
+/// A kernel timer whose expirations are delivered as messages on its port.
+///
+/// The timer's port holds a receive right, so it can be added to a port set and multiplexed with other IPC traffic;
+/// use [`is_expiration`](Timer::is_expiration) to recognize its messages.
+pub struct Timer {
+    port: sys::mach_port_name_t,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mk_timer_destroy", port: self.port, mk_timer_destroy(self.port));
+        }
+    }
+}
+
+impl Timer {
+    pub fn new() -> io::Result<Timer> {
+        let port = unsafe { mk_timer_create() };
+        if port == sys::MACH_PORT_NULL {
+            return Err(io::Error::new(io::ErrorKind::Other, "mk_timer_create failed"));
+        }
+        Ok(Timer {
+            port,
+        })
+    }
+
+    /// The name of the timer's port, which holds a receive right.
+    ///
+    /// The port is destroyed along with the timer, and must not be deallocated by other means.
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port
+    }
+
+    /// Arms the timer to fire when `mach_absolute_time` reaches `deadline`, replacing any previous deadline.
+    pub fn arm_abs(&self, deadline: u64) -> io::Result<()> {
+        unsafe {
+            mach_call!(log op: "mk_timer_arm", port: self.port, mk_timer_arm(self.port, deadline))?;
+        }
+        Ok(())
+    }
+
+    /// Arms the timer to fire at `deadline`, replacing any previous deadline.
+    pub fn arm(&self, deadline: Instant) -> io::Result<()> {
+        self.arm_abs(time::instant_to_abs(deadline))
+    }
+
+    /// Arms the timer to fire after `delay`, replacing any previous deadline.
+    pub fn arm_after(&self, delay: Duration) -> io::Result<()> {
+        self.arm_abs(time::absolute_time().saturating_add(time::duration_to_abs(delay)))
+    }
+
+    /// Disarms the timer, returning the absolute time it was armed for, or `None` if it was not armed.
+    pub fn cancel(&self) -> io::Result<Option<u64>> {
+        unsafe {
+            let mut armed: u64 = 0;
+            mach_call!(log op: "mk_timer_cancel", port: self.port, mk_timer_cancel(self.port, &mut armed))?;
+            Ok(if armed == 0 { None } else { Some(armed) })
+        }
+    }
+
+    /// Waits for the timer to fire, consuming its expiration message.
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<()> {
+        unsafe {
+            let mut msg: ExpireMsg = mem::zeroed();
+            let mut flags = sys::MACH_RCV_MSG;
+            let mut timeout_arg = sys::MACH_MSG_TIMEOUT_NONE as sys::mach_msg_timeout_t;
+            if let Some(duration) = timeout {
+                flags |= sys::MACH_RCV_TIMEOUT;
+                timeout_arg = convert_timeout(duration);
+            }
+            mach_call!(sys::mach_msg(
+                &mut msg.header,
+                flags as _,
+                0,
+                mem::size_of::<ExpireMsg>() as _,
+                self.port,
+                timeout_arg,
+                sys::MACH_PORT_NULL,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Whether a message received (e.g. through a port set) is an expiration of this timer.
+    pub fn is_expiration(&self, msg: &Msg) -> bool {
+        msg.header().msgh_local_port == self.port
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("port", &format_args!("{:#x?}", self.port))
+            .finish()
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{Task, Thread};
+
+use std::io;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use mach_sys as sys;
+
+/// The scheduling state of a thread, as reported by `thread_info`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RunState {
+    Running,
+    Stopped,
+    Waiting,
+    Uninterruptible,
+    Halted,
+    Other(sys::integer_t),
+}
+
+impl RunState {
+    fn from_raw(raw: sys::integer_t) -> RunState {
+        match raw as u32 {
+            sys::TH_STATE_RUNNING => RunState::Running,
+            sys::TH_STATE_STOPPED => RunState::Stopped,
+            sys::TH_STATE_WAITING => RunState::Waiting,
+            sys::TH_STATE_UNINTERRUPTIBLE => RunState::Uninterruptible,
+            sys::TH_STATE_HALTED => RunState::Halted,
+            _ => RunState::Other(raw),
+        }
+    }
+}
+
+/// CPU usage and scheduling information for a single thread.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ThreadUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// The scheduler's decaying estimate of recent CPU usage, as a fraction of one CPU.
+    pub cpu_usage: f64,
+    pub run_state: RunState,
+    /// Whether the thread is an idle thread (only applies to kernel threads).
+    pub idle: bool,
+    pub suspend_count: i32,
+    /// How long the thread has been sleeping, for threads in the `Waiting` state.
+    pub sleep_time: Duration,
+}
+
+impl ThreadUsage {
+    /// The total CPU time consumed by the thread in both user and kernel mode.
+    #[inline]
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+}
+
+impl Thread {
+    /// Retrieves the thread's CPU usage and run state (`THREAD_BASIC_INFO`).
+    pub fn usage(&self) -> io::Result<ThreadUsage> {
+        unsafe {
+            let info: sys::thread_basic_info_data_t = self.info(sys::THREAD_BASIC_INFO)?;
+            Ok(ThreadUsage {
+                user_time: time_value_to_duration(info.user_time),
+                system_time: time_value_to_duration(info.system_time),
+                cpu_usage: info.cpu_usage as f64 / sys::TH_USAGE_SCALE as f64,
+                run_state: RunState::from_raw(info.run_state),
+                idle: info.flags as u32 & sys::TH_FLAGS_IDLE != 0,
+                suspend_count: info.suspend_count,
+                sleep_time: Duration::from_secs(info.sleep_time as u64),
+            })
+        }
+    }
+}
+
+/// A snapshot of the CPU time consumed by each thread of a task, which can be diffed against an earlier snapshot to
+/// compute CPU usage over an interval.
+#[derive(Clone, Debug)]
+pub struct TaskUsageSample {
+    timestamp: Instant,
+    threads: HashMap<u64, ThreadUsage>,
+}
+
+impl Task {
+    /// Collects the usage of every thread in the task.
+    ///
+    /// Threads that exit while the sample is being collected are omitted.
+    pub fn usage_sample(&self) -> io::Result<TaskUsageSample> {
+        let mut threads = HashMap::new();
+        for thread in self.threads()? {
+            // The thread may have terminated since we enumerated it
+            let (id, usage) = match (thread.id(), thread.usage()) {
+                (Ok(id), Ok(usage)) => (id, usage),
+                _ => continue,
+            };
+            threads.insert(id, usage);
+        }
+        Ok(TaskUsageSample {
+            timestamp: Instant::now(),
+            threads,
+        })
+    }
+}
+
+impl TaskUsageSample {
+    #[inline]
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+
+    /// The usage of each thread, keyed by thread ID.
+    #[inline]
+    pub fn threads(&self) -> &HashMap<u64, ThreadUsage> {
+        &self.threads
+    }
+
+    /// The CPU time consumed by all threads alive at the time of the sample.
+    pub fn cpu_time(&self) -> Duration {
+        self.threads.values().map(ThreadUsage::cpu_time).fold(Duration::from_secs(0), |acc, x| acc + x)
+    }
+
+    /// The CPU time consumed by each thread since `earlier`, keyed by thread ID. Threads created since `earlier` are
+    /// counted from zero; CPU time of threads that have since exited is lost.
+    pub fn cpu_time_since(&self, earlier: &TaskUsageSample) -> HashMap<u64, Duration> {
+        self.threads.iter()
+            .map(|(&id, usage)| {
+                let before = earlier.threads.get(&id).map(ThreadUsage::cpu_time).unwrap_or_default();
+                (id, usage.cpu_time().checked_sub(before).unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// The average number of CPUs the task kept busy between `earlier` and this sample (e.g. `1.5` for one and a half
+    /// cores).
+    pub fn cpu_usage_since(&self, earlier: &TaskUsageSample) -> f64 {
+        let elapsed = duration_secs(self.timestamp.duration_since(earlier.timestamp));
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        let busy: f64 = self.cpu_time_since(earlier).values().map(|&x| duration_secs(x)).sum();
+        busy / elapsed
+    }
+}
+
+fn time_value_to_duration(value: sys::time_value_t) -> Duration {
+    Duration::new(value.seconds as u64, value.microseconds as u32 * 1000)
+}
+
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
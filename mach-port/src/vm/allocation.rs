@@ -0,0 +1,127 @@
+use crate::Task;
+use crate::vm::{VmTag, RemoteAllocation, round_page, tag_flags};
+
+use std::{io, mem, slice, fmt};
+use std::ops::{Deref, DerefMut, Range};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// An owned range of zero-filled virtual memory in the current task, allocated with `mach_vm_allocate`.
+///
+/// The size is always rounded up to a whole number of pages.
+pub struct VmAllocation {
+    address: sys::mach_vm_address_t,
+    size: sys::mach_vm_size_t,
+}
+
+impl Drop for VmAllocation {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_vm_deallocate", sys::mach_vm_deallocate(mach_core::self_port::task_self(), self.address, self.size));
+        }
+    }
+}
+
+impl VmAllocation {
+    /// Allocates at least `size` bytes anywhere in the address space, attributed to the
+    /// [default tag](crate::vm::set_default_tag) if one is set.
+    pub fn new(size: usize) -> io::Result<VmAllocation> {
+        VmAllocation::allocate(0, size, sys::VM_FLAGS_ANYWHERE as i32, None)
+    }
+
+    /// Allocates at least `size` bytes anywhere in the address space, attributed to `tag`.
+    pub fn with_tag(size: usize, tag: VmTag) -> io::Result<VmAllocation> {
+        VmAllocation::allocate(0, size, sys::VM_FLAGS_ANYWHERE as i32, Some(tag))
+    }
+
+    /// Allocates at least `size` bytes anywhere in another task's address space.
+    ///
+    /// See [`RemoteAllocation`] for details.
+    pub fn new_in(task: &Task, size: usize) -> io::Result<RemoteAllocation> {
+        RemoteAllocation::new(task, size, None)
+    }
+
+    /// Allocates memory at exactly `address`, which must be page aligned. Fails if any part of the range is already
+    /// allocated.
+    ///
+    /// Uses the [default tag](crate::vm::set_default_tag) if `tag` is `None`.
+    pub fn new_fixed(address: u64, size: usize, tag: Option<VmTag>) -> io::Result<VmAllocation> {
+        VmAllocation::allocate(address, size, sys::VM_FLAGS_FIXED as i32, tag)
+    }
+
+    fn allocate(address: u64, size: usize, flags: i32, tag: Option<VmTag>) -> io::Result<VmAllocation> {
+        if size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot allocate zero bytes of virtual memory"));
+        }
+        let size = round_page(size as u64);
+        let flags = flags | tag_flags(tag);
+        unsafe {
+            let mut address = address;
+            mach_call!(log op: "mach_vm_allocate", sys::mach_vm_allocate(mach_core::self_port::task_self(), &mut address, size, flags))?;
+            Ok(VmAllocation::from_raw_parts(address, size))
+        }
+    }
+
+    /// Takes ownership of a page-aligned range of memory in the current task.
+    pub unsafe fn from_raw_parts(address: u64, size: u64) -> VmAllocation {
+        VmAllocation {
+            address,
+            size,
+        }
+    }
+
+    #[inline]
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// The size of the allocation in bytes, which is a multiple of the page size.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    #[inline]
+    pub(crate) fn range(&self) -> Range<u64> {
+        self.address..(self.address + self.size)
+    }
+
+    /// Releases ownership of the memory, returning its address and size.
+    pub fn into_raw(self) -> (u64, u64) {
+        let parts = (self.address, self.size);
+        mem::forget(self);
+        parts
+    }
+
+    /// Releases ownership of the memory, which will never be deallocated.
+    pub fn leak(self) -> &'static mut [u8] {
+        let (address, size) = self.into_raw();
+        unsafe { slice::from_raw_parts_mut(address as *mut u8, size as usize) }
+    }
+}
+
+impl Deref for VmAllocation {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.address as *const u8, self.size as usize) }
+    }
+}
+
+impl DerefMut for VmAllocation {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.address as *mut u8, self.size as usize) }
+    }
+}
+
+impl fmt::Debug for VmAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VmAllocation")
+            .field("address", &format_args!("{:#x?}", self.address))
+            .field("size", &format_args!("{:#x?}", self.size))
+            .finish()
+    }
+}
@@ -0,0 +1,71 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::VmAllocation;
+
+use std::io;
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Advice about how a range of memory will be used (`VM_BEHAVIOR_*`), the Mach equivalent of `madvise`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Behavior {
+    Default,
+    Random,
+    Sequential,
+    ReverseSequential,
+    /// Start paging in the range, as it will be needed soon.
+    WillNeed,
+    /// The range will not be needed soon, so its pages can be reclaimed first.
+    DontNeed,
+    /// Discard the contents of the range; reads may return either the old data or zeroes.
+    Free,
+    ZeroWiredPages,
+    /// The pages may be reclaimed without being paged out, and no longer count towards the task's footprint.
+    Reusable,
+    /// Reverses [`Behavior::Reusable`] before the memory is used again.
+    Reuse,
+    CanReuse,
+    /// Page out the range immediately.
+    PageOut,
+}
+
+impl Behavior {
+    fn as_raw(self) -> sys::vm_behavior_t {
+        (match self {
+            Behavior::Default => sys::VM_BEHAVIOR_DEFAULT,
+            Behavior::Random => sys::VM_BEHAVIOR_RANDOM,
+            Behavior::Sequential => sys::VM_BEHAVIOR_SEQUENTIAL,
+            Behavior::ReverseSequential => sys::VM_BEHAVIOR_RSEQNTL,
+            Behavior::WillNeed => sys::VM_BEHAVIOR_WILLNEED,
+            Behavior::DontNeed => sys::VM_BEHAVIOR_DONTNEED,
+            Behavior::Free => sys::VM_BEHAVIOR_FREE,
+            Behavior::ZeroWiredPages => sys::VM_BEHAVIOR_ZERO_WIRED_PAGES,
+            Behavior::Reusable => sys::VM_BEHAVIOR_REUSABLE,
+            Behavior::Reuse => sys::VM_BEHAVIOR_REUSE,
+            Behavior::CanReuse => sys::VM_BEHAVIOR_CAN_REUSE,
+            Behavior::PageOut => sys::VM_BEHAVIOR_PAGEOUT,
+        }) as sys::vm_behavior_t
+    }
+}
+
+impl Task {
+    /// Advises the kernel how the pages overlapping `range` will be used.
+    pub fn set_behavior(&self, range: Range<u64>, behavior: Behavior) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        if range.end < range.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+        }
+        unsafe {
+            mach_call!(log op: "mach_vm_behavior_set", port: self.as_raw_port(), sys::mach_vm_behavior_set(self.as_raw_port(), range.start, range.end - range.start, behavior.as_raw()))?;
+        }
+        Ok(())
+    }
+}
+
+impl VmAllocation {
+    /// Advises the kernel how the whole allocation will be used.
+    pub fn set_behavior(&self, behavior: Behavior) -> io::Result<()> {
+        Task::current().set_behavior(self.range(), behavior)
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::VmAllocation;
+
+use std::io;
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+impl Task {
+    /// Copies the memory in `source` to `destination` within this task.
+    ///
+    /// Whole pages are shared copy-on-write rather than copied, which makes this much faster than `memcpy` for large
+    /// page-aligned ranges. Both ranges must already be mapped, and the destination must be writable.
+    ///
+    /// # Safety
+    ///
+    /// If this is the current task, the destination is overwritten as if through a raw pointer, so the caller must
+    /// ensure it doesn't overlap memory that Rust code holds a reference to or otherwise relies on.
+    pub unsafe fn copy_memory(&self, source: Range<u64>, destination: u64) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        let size = range_size(&source)?;
+        mach_call!(log op: "mach_vm_copy", port: self.as_raw_port(), sys::mach_vm_copy(self.as_raw_port(), source.start, size, destination))?;
+        Ok(())
+    }
+
+    /// Copies the memory in `source` in this task to `destination` in another task.
+    ///
+    /// The data passes through the current task as a copy-on-write mapping, so pages are only copied when either side
+    /// modifies them.
+    ///
+    /// # Safety
+    ///
+    /// If `target` is the current task, the same obligation as for [`copy_memory`](Task::copy_memory) applies to
+    /// `destination`.
+    pub unsafe fn copy_memory_to(&self, source: Range<u64>, target: &Task, destination: u64) -> io::Result<()> {
+        self.require(TaskFlavor::Read)?;
+        target.require(TaskFlavor::Control)?;
+        let size = range_size(&source)?;
+        if size > sys::mach_msg_type_number_t::MAX as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "copy too large"));
+        }
+        let mut data: sys::vm_offset_t = 0;
+        let mut count: sys::mach_msg_type_number_t = 0;
+        mach_call!(log op: "mach_vm_read", port: self.as_raw_port(), sys::mach_vm_read(self.as_raw_port(), source.start, size, &mut data, &mut count))?;
+        let result = mach_call!(log op: "mach_vm_write", port: target.as_raw_port(), sys::mach_vm_write(target.as_raw_port(), destination, data, count));
+        let _ = mach_call!(log op: "mach_vm_deallocate", sys::mach_vm_deallocate(mach_core::self_port::task_self(), data as _, count as _));
+        result?;
+        Ok(())
+    }
+}
+
+impl VmAllocation {
+    /// Creates a copy-on-write copy of this allocation.
+    pub fn try_clone(&self) -> io::Result<VmAllocation> {
+        let copy = VmAllocation::new(self.size() as usize)?;
+        // The copy was just allocated, so nothing else refers to the memory being overwritten
+        unsafe { Task::current().copy_memory(self.address()..(self.address() + self.size()), copy.address())?; }
+        Ok(copy)
+    }
+}
+
+fn range_size(range: &Range<u64>) -> io::Result<u64> {
+    if range.end < range.start {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+    }
+    Ok(range.end - range.start)
+}
@@ -0,0 +1,114 @@
+use crate::Task;
+use crate::vm::{VmAllocation, VmTag, Protection, round_page, trunc_page};
+
+use std::{io, slice, fmt};
+use std::ops::{Deref, DerefMut, Range};
+
+/// An allocation surrounded by inaccessible guard pages, so overruns fault immediately instead of corrupting
+/// neighbouring memory.
+///
+/// Useful for stack-like buffers and heaps holding untrusted IPC data.
+pub struct GuardedAllocation {
+    allocation: VmAllocation,
+    offset: u64,
+    size: u64,
+}
+
+impl GuardedAllocation {
+    /// Allocates at least `size` usable bytes, with at least `before` and `after` bytes of guard pages on either side.
+    pub fn new(size: usize, before: usize, after: usize) -> io::Result<GuardedAllocation> {
+        GuardedAllocation::with_tag(size, before, after, None)
+    }
+
+    /// Allocates a guarded allocation attributed to `tag`, or the [default tag](crate::vm::set_default_tag) if `tag`
+    /// is `None`.
+    pub fn with_tag(size: usize, before: usize, after: usize, tag: Option<VmTag>) -> io::Result<GuardedAllocation> {
+        if size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot allocate zero bytes of virtual memory"));
+        }
+        let (size, before, after) = (round_page(size as u64), round_page(before as u64), round_page(after as u64));
+        let total = size.checked_add(before).and_then(|total| total.checked_add(after))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "guarded allocation too large"))?;
+        let allocation = match tag {
+            Some(tag) => VmAllocation::with_tag(total as usize, tag)?,
+            None => VmAllocation::new(total as usize)?,
+        };
+        let task = Task::current();
+        // Safe code only reaches the usable range between the guards
+        unsafe {
+            if before != 0 {
+                task.install_guard(allocation.address()..(allocation.address() + before))?;
+            }
+            if after != 0 {
+                task.install_guard((allocation.address() + before + size)..(allocation.address() + total))?;
+            }
+        }
+        Ok(GuardedAllocation {
+            allocation,
+            offset: before,
+            size,
+        })
+    }
+
+    /// The address of the usable range.
+    #[inline]
+    pub fn address(&self) -> u64 {
+        self.allocation.address() + self.offset
+    }
+
+    /// The size of the usable range in bytes, which is a multiple of the page size.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The whole allocation, including the guard pages.
+    ///
+    /// # Safety
+    ///
+    /// The allocation dereferences to a slice covering the guard pages, which the caller must not access.
+    #[inline]
+    pub unsafe fn allocation(&self) -> &VmAllocation {
+        &self.allocation
+    }
+}
+
+impl Deref for GuardedAllocation {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.address() as *const u8, self.size as usize) }
+    }
+}
+
+impl DerefMut for GuardedAllocation {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.address() as *mut u8, self.size as usize) }
+    }
+}
+
+impl fmt::Debug for GuardedAllocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GuardedAllocation")
+            .field("address", &format_args!("{:#x?}", self.address()))
+            .field("size", &format_args!("{:#x?}", self.size))
+            .field("allocation", &self.allocation)
+            .finish()
+    }
+}
+
+impl Task {
+    /// Makes the pages overlapping `range` permanently inaccessible, so any access faults.
+    ///
+    /// The maximum protection is lowered as well, so the guard cannot be removed short of deallocating the memory.
+    ///
+    /// # Safety
+    ///
+    /// If this is the current task, the caller must ensure nothing reachable from safe code (such as a reference or a
+    /// [`VmAllocation`]'s slice) covers the guarded pages, since any access to them afterwards faults.
+    pub unsafe fn install_guard(&self, range: Range<u64>) -> io::Result<()> {
+        self.set_max_protection(trunc_page(range.start)..round_page(range.end), Protection::NONE)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::{VmAllocation, MemoryEntry, Protection, VmTag, Inheritance, round_page, tag_flags};
+
+use std::io;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Where [`MapBuilder`] places a mapping.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Placement {
+    /// The first free range that fits (`VM_FLAGS_ANYWHERE`).
+    Anywhere,
+    /// A randomly chosen free range (`VM_FLAGS_ANYWHERE | VM_FLAGS_RANDOM_ADDR`).
+    RandomAddress,
+    /// Exactly at the given address, failing if any part of the range is in use (`VM_FLAGS_FIXED`).
+    Fixed(u64),
+    /// Exactly at the given address, replacing anything already mapped there (`VM_FLAGS_FIXED | VM_FLAGS_OVERWRITE`).
+    Overwrite(u64),
+}
+
+/// Configures a call to `mach_vm_map`, for mapping anonymous memory or a [`MemoryEntry`] with precise control over
+/// placement, protection and inheritance.
+#[derive(Clone, Copy, Debug)]
+pub struct MapBuilder<'a> {
+    entry: Option<&'a MemoryEntry>,
+    size: u64,
+    offset: u64,
+    placement: Placement,
+    alignment_mask: u64,
+    protection: Protection,
+    max_protection: Protection,
+    inheritance: Inheritance,
+    copy: bool,
+    tag: Option<VmTag>,
+}
+
+impl<'a> MapBuilder<'a> {
+    /// Maps at least `size` bytes of new zero-filled memory. Defaults to read-write and [`Inheritance::Copy`].
+    pub fn anonymous(size: usize) -> MapBuilder<'static> {
+        MapBuilder {
+            entry: None,
+            size: round_page(size as u64),
+            offset: 0,
+            placement: Placement::Anywhere,
+            alignment_mask: 0,
+            protection: Protection::DEFAULT,
+            max_protection: Protection::ALL,
+            inheritance: Inheritance::Copy,
+            copy: false,
+            tag: None,
+        }
+    }
+
+    /// Maps the whole of `entry`, sharing its pages. Defaults to read-write and [`Inheritance::Share`].
+    pub fn entry(entry: &'a MemoryEntry) -> MapBuilder<'a> {
+        MapBuilder {
+            entry: Some(entry),
+            size: entry.size(),
+            offset: 0,
+            placement: Placement::Anywhere,
+            alignment_mask: 0,
+            protection: Protection::DEFAULT,
+            max_protection: Protection::DEFAULT,
+            inheritance: Inheritance::Share,
+            copy: false,
+            tag: None,
+        }
+    }
+
+    /// Maps only `size` bytes of the memory entry starting at `offset`, which must be page aligned.
+    pub fn range(mut self, offset: u64, size: u64) -> Self {
+        self.offset = offset;
+        self.size = round_page(size);
+        self
+    }
+
+    /// Defaults to [`Placement::Anywhere`].
+    pub fn placement(mut self, placement: Placement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    /// Requires the mapping's address to have the bits in `mask` clear (e.g. `0x1f_ffff` for 2MiB alignment).
+    pub fn alignment_mask(mut self, mask: u64) -> Self {
+        self.alignment_mask = mask;
+        self
+    }
+
+    pub fn protection(mut self, protection: Protection) -> Self {
+        self.protection = protection;
+        self
+    }
+
+    /// The most permissive protection the mapping can later be changed to.
+    pub fn max_protection(mut self, max_protection: Protection) -> Self {
+        self.max_protection = max_protection;
+        self
+    }
+
+    pub fn inheritance(mut self, inheritance: Inheritance) -> Self {
+        self.inheritance = inheritance;
+        self
+    }
+
+    /// Gives the mapping a copy-on-write copy of the memory entry's contents instead of sharing them. Defaults to
+    /// `false`.
+    pub fn copy(mut self, copy: bool) -> Self {
+        self.copy = copy;
+        self
+    }
+
+    /// Attributes the mapping to `tag`. Defaults to the [default tag](crate::vm::set_default_tag).
+    pub fn tag(mut self, tag: VmTag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Creates the mapping in the current task.
+    pub fn map(&self) -> io::Result<VmAllocation> {
+        unsafe {
+            let address = self.map_raw(mach_core::self_port::task_self())?;
+            Ok(VmAllocation::from_raw_parts(address, self.size))
+        }
+    }
+
+    /// Creates the mapping in another task, returning its address.
+    ///
+    /// The mapping is owned by the target task, and is not unmapped when anything in the current task is dropped.
+    pub fn map_in_task(&self, task: &Task) -> io::Result<u64> {
+        task.require(TaskFlavor::Control)?;
+        unsafe { self.map_raw(task.as_raw_port()) }
+    }
+
+    unsafe fn map_raw(&self, task: sys::vm_map_t) -> io::Result<u64> {
+        if self.size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot map zero bytes of virtual memory"));
+        }
+        let (mut address, placement_flags) = match self.placement {
+            Placement::Anywhere => (0, sys::VM_FLAGS_ANYWHERE),
+            Placement::RandomAddress => (0, sys::VM_FLAGS_ANYWHERE | sys::VM_FLAGS_RANDOM_ADDR),
+            Placement::Fixed(address) => (address, sys::VM_FLAGS_FIXED),
+            Placement::Overwrite(address) => (address, sys::VM_FLAGS_FIXED | sys::VM_FLAGS_OVERWRITE),
+        };
+        let object = self.entry.map(|entry| entry.port().as_raw_port()).unwrap_or(sys::MACH_PORT_NULL);
+        mach_call!(log op: "mach_vm_map", port: task, sys::mach_vm_map(
+            task, &mut address, self.size, self.alignment_mask, placement_flags as i32 | tag_flags(self.tag),
+            object, self.offset, self.copy as sys::boolean_t,
+            self.protection.0, self.max_protection.0, self.inheritance.as_raw(),
+        ))?;
+        Ok(address)
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::{Protection, round_page, trunc_page};
+
+use std::{io, mem, cmp};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+impl Task {
+    /// Copies memory at `address` in this task into `buffer`, returning the number of bytes read.
+    ///
+    /// Failures are not logged, since probing memory that may be unmapped is a normal part of debuggers and samplers.
+    pub fn read_memory(&self, address: u64, buffer: &mut [u8]) -> io::Result<usize> {
+        self.require(TaskFlavor::Read)?;
+        unsafe {
+            let mut out_size: sys::mach_vm_size_t = 0;
+            mach_call!(op: "mach_vm_read_overwrite", port: self.as_raw_port(), sys::mach_vm_read_overwrite(
+                self.as_raw_port(),
+                address,
+                buffer.len() as _,
+                buffer.as_mut_ptr() as sys::mach_vm_address_t,
+                &mut out_size,
+            ))?;
+            Ok(out_size as usize)
+        }
+    }
+
+    /// Writes `data` to memory at `address` in this task, which must be mapped writable.
+    ///
+    /// # Safety
+    ///
+    /// If this is the current task, the write is no different from one through a raw pointer: the caller must ensure
+    /// it doesn't overlap memory that Rust code holds a reference to or otherwise relies on.
+    pub unsafe fn write_memory(&self, address: u64, data: &[u8]) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        if data.len() > sys::mach_msg_type_number_t::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "write too large"));
+        }
+        mach_call!(log op: "mach_vm_write", port: self.as_raw_port(), sys::mach_vm_write(
+            self.as_raw_port(),
+            address,
+            data.as_ptr() as sys::vm_offset_t,
+            data.len() as sys::mach_msg_type_number_t,
+        ))?;
+        Ok(())
+    }
+
+    /// Writes `data` to memory at `address` in this task, temporarily making read-only pages (such as code) writable.
+    ///
+    /// Pages are made copy-on-write before being written, so shared mappings like those of system libraries are not
+    /// modified for other processes. The original protection of each region is restored afterwards.
+    ///
+    /// # Safety
+    ///
+    /// As with [`write_memory`](Task::write_memory), if this is the current task the caller must ensure the write
+    /// doesn't overlap memory that Rust code relies on, and that nothing executes or reads the affected pages while
+    /// their protection is temporarily changed.
+    pub unsafe fn force_write_memory(&self, address: u64, data: &[u8]) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        let end = address.checked_add(data.len() as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"))?;
+        let mut current = address;
+        while current < end {
+            let (region_start, region_end, protection) = self.region_protection(current)?;
+            if region_start > current {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "memory range is not mapped"));
+            }
+            let chunk_end = cmp::min(end, region_end);
+            let chunk = &data[((current - address) as usize)..((chunk_end - address) as usize)];
+            if protection.contains(Protection::WRITE) {
+                self.write_memory(current, chunk)?;
+            } else {
+                let pages = trunc_page(current)..round_page(chunk_end);
+                self.make_cow(pages.clone(), Protection::READ | Protection::WRITE)?;
+                let result = self.write_memory(current, chunk);
+                self.protect(pages, protection)?;
+                result?;
+            }
+            current = chunk_end;
+        }
+        Ok(())
+    }
+
+    // Finds the region containing or following `address`, returning its bounds and current protection
+    fn region_protection(&self, address: u64) -> io::Result<(u64, u64, Protection)> {
+        let mut address = address;
+        let mut size: sys::mach_vm_size_t = 0;
+        let mut info: sys::vm_region_basic_info_data_64_t = unsafe { mem::zeroed() };
+        let mut count = (mem::size_of::<sys::vm_region_basic_info_data_64_t>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+        let mut object_name: sys::mach_port_t = sys::MACH_PORT_NULL;
+        unsafe {
+            mach_call!(log op: "mach_vm_region", port: self.as_raw_port(), sys::mach_vm_region(
+                self.as_raw_port(),
+                &mut address,
+                &mut size,
+                sys::VM_REGION_BASIC_INFO_64 as _,
+                &mut info as *mut _ as sys::vm_region_info_t,
+                &mut count,
+                &mut object_name,
+            ))?;
+        }
+        Ok((address, address + size, Protection(info.protection)))
+    }
+}
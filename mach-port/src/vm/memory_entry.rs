@@ -0,0 +1,131 @@
+use crate::{Port, RawPort, Task, TaskFlavor};
+use crate::vm::{VmAllocation, MapBuilder, Protection, round_page, trunc_page};
+
+use std::{io, fmt};
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// A named memory entry: a send right to a VM object that can be mapped into any task holding it.
+///
+/// Sending the entry's [`port`](MemoryEntry::port) in a message is the canonical way to share memory between
+/// processes.
+pub struct MemoryEntry {
+    port: Port,
+    size: u64,
+}
+
+impl MemoryEntry {
+    /// Creates a new zero-filled memory object of at least `size` bytes, mappable with up to `protection`.
+    pub fn new(size: usize, protection: Protection) -> io::Result<MemoryEntry> {
+        unsafe {
+            MemoryEntry::make(mach_core::self_port::task_self(), 0, round_page(size as u64), protection.0 | sys::MAP_MEM_NAMED_CREATE as sys::vm_prot_t, sys::MACH_PORT_NULL)
+        }
+    }
+
+    /// Creates a memory entry sharing the memory of an existing allocation in the current task.
+    pub fn from_allocation(allocation: &VmAllocation, protection: Protection) -> io::Result<MemoryEntry> {
+        unsafe {
+            MemoryEntry::make(mach_core::self_port::task_self(), allocation.address(), allocation.size(), protection.0, sys::MACH_PORT_NULL)
+        }
+    }
+
+    /// Creates a memory entry sharing the memory mapped at `range` in `task`, e.g. to re-offer a region received from
+    /// another process with reduced permissions.
+    ///
+    /// The range is expanded to page boundaries.
+    pub fn from_range(task: &Task, range: Range<u64>, protection: Protection) -> io::Result<MemoryEntry> {
+        task.require(TaskFlavor::Control)?;
+        if range.end < range.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+        }
+        let start = trunc_page(range.start);
+        unsafe {
+            MemoryEntry::make(task.as_raw_port(), start, round_page(range.end) - start, protection.0, sys::MACH_PORT_NULL)
+        }
+    }
+
+    /// Creates a memory entry covering `size` bytes of this entry starting at `offset`, mappable with at most
+    /// `protection`.
+    ///
+    /// This allows a broker to hand out windows into a larger shared buffer without giving access to the rest of it.
+    /// The offset must be page aligned, and the protection cannot exceed this entry's.
+    pub fn sub_entry(&self, offset: u64, size: u64, protection: Protection) -> io::Result<MemoryEntry> {
+        if offset.checked_add(size).map(|end| end > self.size).unwrap_or(true) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "range exceeds memory entry"));
+        }
+        unsafe {
+            MemoryEntry::make(mach_core::self_port::task_self(), offset, round_page(size), protection.0, self.port.as_raw_port())
+        }
+    }
+
+    /// Creates a memory entry covering all of this entry with reduced permissions, e.g. a read-only view of a
+    /// writable buffer.
+    pub fn restrict(&self, protection: Protection) -> io::Result<MemoryEntry> {
+        self.sub_entry(0, self.size, protection)
+    }
+
+    unsafe fn make(task: sys::vm_map_t, offset: u64, size: u64, permission: sys::vm_prot_t, parent: sys::mach_port_t) -> io::Result<MemoryEntry> {
+        let mut size = size;
+        let mut port: sys::mach_port_t = sys::MACH_PORT_NULL;
+        mach_call!(log op: "mach_make_memory_entry_64", port: task, sys::mach_make_memory_entry_64(task, &mut size, offset, permission, &mut port, parent))?;
+        Ok(MemoryEntry {
+            port: Port::from_raw_port(port)?,
+            size,
+        })
+    }
+
+    /// Takes ownership of a send right to a memory entry (e.g. one received in a message) of the given size.
+    pub unsafe fn from_raw_port(port: RawPort, size: u64) -> io::Result<MemoryEntry> {
+        Ok(MemoryEntry {
+            port: Port::from_raw_port(port)?,
+            size,
+        })
+    }
+
+    /// The memory entry's port, which can be attached to messages to share the memory.
+    #[inline]
+    pub fn port(&self) -> &Port {
+        &self.port
+    }
+
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        self.port.into_raw_port()
+    }
+
+    /// Maps the whole entry anywhere in the current task's address space, sharing its pages.
+    ///
+    /// The mapping is attributed to the [default tag](crate::vm::set_default_tag) if one is set. Use [`MapBuilder`]
+    /// for more control over the mapping.
+    pub fn map(&self, protection: Protection) -> io::Result<VmAllocation> {
+        MapBuilder::entry(self)
+            .protection(protection)
+            .max_protection(protection)
+            .map()
+    }
+
+    /// Maps the whole entry anywhere in another task's address space, sharing its pages, and returns the address.
+    ///
+    /// The mapping is owned by the target task, and is not unmapped when anything in the current task is dropped.
+    pub fn map_in_task(&self, task: &Task, protection: Protection, max_protection: Protection) -> io::Result<u64> {
+        MapBuilder::entry(self)
+            .protection(protection)
+            .max_protection(max_protection)
+            .map_in_task(task)
+    }
+}
+
+impl fmt::Debug for MemoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryEntry")
+            .field("port", &format_args!("{:#x?}", self.port.as_raw_port()))
+            .field("size", &format_args!("{:#x?}", self.size))
+            .finish()
+    }
+}
@@ -0,0 +1,174 @@
+//! Virtual memory allocation, mapping and inspection.
+//!
+//! # User-space pagers
+//!
+//! Mach historically allowed any port to act as a memory object, with the kernel sending `memory_object_*` requests
+//! to a user-space pager that supplied pages on demand. Current versions of XNU only accept named entries (see
+//! [`MemoryEntry`]) as memory objects in `mach_vm_map`, and the `memory_object_control` interface needed to supply
+//! pages is kernel-only, so custom pagers cannot be implemented here. Lazily materialized data can instead be mapped
+//! with [`Protection::NONE`] and filled in from an `EXC_BAD_ACCESS` handler (see [`exceptions`](crate::exceptions))
+//! before raising the protection with [`Task::protect`](crate::Task::protect).
+
+mod allocation;
+mod behavior;
+mod copy;
+mod guard;
+mod map;
+mod memory_entry;
+mod memory;
+mod page;
+mod protect;
+mod region;
+mod remap;
+mod remote;
+mod shared;
+mod superpage;
+mod sync;
+
+pub use self::allocation::*;
+pub use self::behavior::*;
+pub use self::guard::*;
+pub use self::map::*;
+pub use self::memory_entry::*;
+pub use self::page::*;
+pub use self::remap::*;
+pub use self::remote::*;
+pub use self::shared::*;
+pub use self::superpage::*;
+pub use self::region::*;
+pub use self::sync::*;
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::ops::{BitOr, BitOrAssign};
+
+use mach_sys as sys;
+
+/// The size of a virtual memory page in the current task, in bytes.
+#[inline]
+pub fn page_size() -> usize {
+    unsafe { sys::vm_page_size as usize }
+}
+
+/// Rounds `size` up to a multiple of the page size.
+#[inline]
+pub fn round_page(size: u64) -> u64 {
+    sys::mach_vm_round_page(size)
+}
+
+/// Rounds `address` down to a page boundary.
+#[inline]
+pub fn trunc_page(address: u64) -> u64 {
+    sys::mach_vm_trunc_page(address)
+}
+
+/// A tag attributing memory to a subsystem in tools like `vmmap` and `footprint` (`VM_MAKE_TAG`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct VmTag(pub u8);
+
+impl VmTag {
+    pub const MALLOC: VmTag = VmTag(sys::VM_MEMORY_MALLOC as _);
+    pub const MALLOC_SMALL: VmTag = VmTag(sys::VM_MEMORY_MALLOC_SMALL as _);
+    pub const MALLOC_LARGE: VmTag = VmTag(sys::VM_MEMORY_MALLOC_LARGE as _);
+    pub const MALLOC_TINY: VmTag = VmTag(sys::VM_MEMORY_MALLOC_TINY as _);
+    pub const MACH_MSG: VmTag = VmTag(sys::VM_MEMORY_MACH_MSG as _);
+    pub const STACK: VmTag = VmTag(sys::VM_MEMORY_STACK as _);
+    pub const GUARD: VmTag = VmTag(sys::VM_MEMORY_GUARD as _);
+    pub const JAVASCRIPT_JIT: VmTag = VmTag(sys::VM_MEMORY_JAVASCRIPT_JIT_EXECUTABLE_ALLOCATOR as _);
+
+    /// The first of the 16 tags reserved for applications (`VM_MEMORY_APPLICATION_SPECIFIC_1`).
+    pub const APPLICATION_SPECIFIC_1: VmTag = VmTag(sys::VM_MEMORY_APPLICATION_SPECIFIC_1 as _);
+
+    /// One of the 16 tags reserved for applications, numbered from 1 like `VM_MEMORY_APPLICATION_SPECIFIC_1` through
+    /// `VM_MEMORY_APPLICATION_SPECIFIC_16`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not in `1..=16`.
+    #[inline]
+    pub fn application_specific(n: u8) -> VmTag {
+        assert!(n >= 1 && n <= 16, "application specific VM tags are numbered 1 through 16");
+        VmTag(VmTag::APPLICATION_SPECIFIC_1.0 + (n - 1))
+    }
+
+    /// Whether this is one of the tags reserved for applications.
+    #[inline]
+    pub fn is_application_specific(self) -> bool {
+        self.0 >= VmTag::APPLICATION_SPECIFIC_1.0
+    }
+
+    /// The allocation flag bits selecting this tag.
+    #[inline]
+    pub fn flags(self) -> i32 {
+        sys::VM_MAKE_TAG(self.0)
+    }
+}
+
+static DEFAULT_TAG: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the tag applied to memory the crate allocates or maps when no tag is given explicitly, or clears it with
+/// `None`.
+///
+/// This makes everything the crate maps (allocations, memory entry mappings) attributable to the application in
+/// `vmmap` and `footprint`.
+pub fn set_default_tag(tag: Option<VmTag>) {
+    DEFAULT_TAG.store(tag.map(|tag| tag.0).unwrap_or(0), Ordering::Relaxed);
+}
+
+/// The tag set with [`set_default_tag`], if any.
+pub fn default_tag() -> Option<VmTag> {
+    match DEFAULT_TAG.load(Ordering::Relaxed) {
+        0 => None,
+        tag => Some(VmTag(tag)),
+    }
+}
+
+// The allocation flag bits for an explicit tag, falling back to the default tag
+pub(crate) fn tag_flags(tag: Option<VmTag>) -> i32 {
+    tag.or_else(default_tag).map(VmTag::flags).unwrap_or(0)
+}
+
+/// A set of virtual memory access permissions (`vm_prot_t`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Protection(pub sys::vm_prot_t);
+
+impl Protection {
+    pub const NONE: Protection = Protection(sys::VM_PROT_NONE as _);
+    pub const READ: Protection = Protection(sys::VM_PROT_READ as _);
+    pub const WRITE: Protection = Protection(sys::VM_PROT_WRITE as _);
+    pub const EXECUTE: Protection = Protection(sys::VM_PROT_EXECUTE as _);
+    /// Read and write access, the default for new allocations.
+    pub const DEFAULT: Protection = Protection(sys::VM_PROT_DEFAULT as _);
+    pub const ALL: Protection = Protection(sys::VM_PROT_ALL as _);
+
+    #[inline]
+    pub fn contains(self, other: Protection) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Protection {
+    type Output = Protection;
+
+    #[inline]
+    fn bitor(self, rhs: Protection) -> Protection {
+        Protection(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Protection {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Protection) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Protection({}{}{})",
+            if self.contains(Protection::READ) { "r" } else { "-" },
+            if self.contains(Protection::WRITE) { "w" } else { "-" },
+            if self.contains(Protection::EXECUTE) { "x" } else { "-" },
+        )
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::{page_size, round_page, trunc_page};
+
+use std::{io, fmt};
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// The state of a single page of memory (`VM_PAGE_QUERY_*`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageDisposition(pub i32);
+
+impl PageDisposition {
+    /// The page is resident in physical memory.
+    pub const PRESENT: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_PRESENT as _);
+    /// The page has been accessed recently.
+    pub const REFERENCED: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_REF as _);
+    /// The page has been modified since it was last written to its backing store.
+    pub const DIRTY: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_DIRTY as _);
+    /// The page has been swapped out or compressed.
+    pub const PAGED_OUT: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_PAGED_OUT as _);
+    /// The page belongs to a copy-on-write copy of another object.
+    pub const COPIED: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_COPIED as _);
+    pub const SPECULATIVE: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_SPECULATIVE as _);
+    /// The page is backed by a file or external pager rather than anonymous memory.
+    pub const EXTERNAL: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_EXTERNAL as _);
+    /// The page has been marked reusable with `madvise(MADV_FREE_REUSABLE)`.
+    pub const REUSABLE: PageDisposition = PageDisposition(sys::VM_PAGE_QUERY_PAGE_REUSABLE as _);
+
+    #[inline]
+    pub fn contains(self, other: PageDisposition) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl fmt::Debug for PageDisposition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PageDisposition({:#x?})", self.0)
+    }
+}
+
+/// Page counts for a range of memory, as computed by [`Task::page_residency`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct PageResidency {
+    pub pages: u64,
+    pub resident: u64,
+    pub dirty: u64,
+    pub paged_out: u64,
+}
+
+impl PageResidency {
+    /// The number of resident bytes, using the page size of the current task.
+    #[inline]
+    pub fn resident_bytes(&self) -> u64 {
+        self.resident * page_size() as u64
+    }
+
+    #[inline]
+    pub fn dirty_bytes(&self) -> u64 {
+        self.dirty * page_size() as u64
+    }
+}
+
+impl Task {
+    /// Queries the state of the page containing `address`.
+    pub fn page_disposition(&self, address: u64) -> io::Result<PageDisposition> {
+        self.require(TaskFlavor::Read)?;
+        unsafe {
+            let mut disposition: sys::integer_t = 0;
+            let mut ref_count: sys::integer_t = 0;
+            mach_call!(log op: "mach_vm_page_query", port: self.as_raw_port(), sys::mach_vm_page_query(self.as_raw_port(), trunc_page(address), &mut disposition, &mut ref_count))?;
+            Ok(PageDisposition(disposition))
+        }
+    }
+
+    /// Queries the state of every page overlapping `range` in a single call.
+    pub fn page_dispositions(&self, range: Range<u64>) -> io::Result<Vec<PageDisposition>> {
+        self.require(TaskFlavor::Read)?;
+        if range.end < range.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+        }
+        let start = trunc_page(range.start);
+        let size = round_page(range.end) - start;
+        let mut dispositions: Vec<i32> = vec![0; (size / page_size() as u64) as usize];
+        unsafe {
+            let mut count = dispositions.len() as sys::mach_vm_size_t;
+            mach_call!(log op: "mach_vm_page_range_query", port: self.as_raw_port(), sys::mach_vm_page_range_query(
+                self.as_raw_port(),
+                start,
+                size,
+                dispositions.as_mut_ptr() as sys::mach_vm_address_t,
+                &mut count,
+            ))?;
+            dispositions.truncate(count as usize);
+        }
+        Ok(dispositions.into_iter().map(PageDisposition).collect())
+    }
+
+    /// Counts how many pages overlapping `range` are resident, dirty or paged out.
+    pub fn page_residency(&self, range: Range<u64>) -> io::Result<PageResidency> {
+        let mut residency = PageResidency::default();
+        for disposition in self.page_dispositions(range)? {
+            residency.pages += 1;
+            if disposition.contains(PageDisposition::PRESENT) {
+                residency.resident += 1;
+            }
+            if disposition.contains(PageDisposition::DIRTY) {
+                residency.dirty += 1;
+            }
+            if disposition.contains(PageDisposition::PAGED_OUT) {
+                residency.paged_out += 1;
+            }
+        }
+        Ok(residency)
+    }
+}
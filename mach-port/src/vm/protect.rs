@@ -0,0 +1,105 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::{Protection, VmAllocation};
+
+use std::io;
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+impl Task {
+    /// Sets the current protection of the pages overlapping `range`.
+    ///
+    /// Fails if `protection` exceeds the maximum protection of any of the pages.
+    ///
+    /// # Safety
+    ///
+    /// If this is the current task, the caller must ensure that no memory in `range` reachable from safe code (through
+    /// a reference, or the slice of a [`VmAllocation`]) is accessed in a way `protection` forbids while it applies.
+    pub unsafe fn protect(&self, range: Range<u64>, protection: Protection) -> io::Result<()> {
+        self.protect_raw(range, false, protection.0)
+    }
+
+    /// Sets the maximum protection of the pages overlapping `range`.
+    ///
+    /// The maximum protection can only ever be lowered, and the current protection is lowered along with it. This is
+    /// how code that should never become writable (or data that should never become executable) is locked down.
+    ///
+    /// # Safety
+    ///
+    /// As with [`protect`](Task::protect), except that the restriction is permanent.
+    pub unsafe fn set_max_protection(&self, range: Range<u64>, protection: Protection) -> io::Result<()> {
+        self.protect_raw(range, true, protection.0)
+    }
+
+    /// Replaces the pages overlapping `range` with private copy-on-write copies and sets their protection.
+    ///
+    /// This allows making shared or read-only mappings (such as those of system libraries) writable without the
+    /// changes being visible to other mappings of the same memory.
+    ///
+    /// # Safety
+    ///
+    /// The protection is replaced with `protection`, so the same obligation as for [`protect`](Task::protect) applies.
+    pub unsafe fn make_cow(&self, range: Range<u64>, protection: Protection) -> io::Result<()> {
+        self.protect_raw(range, false, protection.0 | sys::VM_PROT_COPY as sys::vm_prot_t)
+    }
+
+    fn protect_raw(&self, range: Range<u64>, set_maximum: bool, protection: sys::vm_prot_t) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        if range.end < range.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+        }
+        unsafe {
+            mach_call!(log op: "mach_vm_protect", port: self.as_raw_port(), sys::mach_vm_protect(
+                self.as_raw_port(), range.start, range.end - range.start, set_maximum as sys::boolean_t, protection,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+impl VmAllocation {
+    /// Sets the current protection of the whole allocation, e.g. to flip a JIT buffer between writable and executable.
+    ///
+    /// # Safety
+    ///
+    /// The allocation still dereferences to a slice afterwards, so the caller must not read it (or write it) through
+    /// `Deref` or `DerefMut` while `protection` lacks read (or write) access, or hand it to code that might.
+    pub unsafe fn protect(&self, protection: Protection) -> io::Result<()> {
+        Task::current().protect(self.range(), protection)
+    }
+
+    /// Sets the current protection of the pages overlapping `offset..(offset + len)`, e.g. to create guard pages.
+    ///
+    /// # Safety
+    ///
+    /// As with [`protect`](VmAllocation::protect), the caller must not access the affected pages through the
+    /// allocation in a way `protection` forbids.
+    pub unsafe fn protect_range(&self, offset: u64, len: u64, protection: Protection) -> io::Result<()> {
+        if offset.checked_add(len).map(|end| end > self.size()).unwrap_or(true) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "range exceeds allocation"));
+        }
+        Task::current().protect((self.address() + offset)..(self.address() + offset + len), protection)
+    }
+
+    /// Permanently limits the protection of the whole allocation.
+    ///
+    /// # Safety
+    ///
+    /// The current protection is lowered along with the maximum, and can never be raised back, so the caller must not
+    /// access the allocation in a way `protection` forbids for as long as it lives.
+    pub unsafe fn set_max_protection(&self, protection: Protection) -> io::Result<()> {
+        Task::current().set_max_protection(self.range(), protection)
+    }
+
+    /// Detaches the allocation from any memory it shares (e.g. when mapped from a [`MemoryEntry`](crate::vm::MemoryEntry)),
+    /// so later writes are private to this mapping.
+    ///
+    /// # Safety
+    ///
+    /// The protection is replaced with `protection`, so as with [`protect`](VmAllocation::protect), the caller must not
+    /// access the allocation in a way it forbids.
+    pub unsafe fn make_cow(&self, protection: Protection) -> io::Result<()> {
+        Task::current().make_cow(self.range(), protection)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::{Protection, VmTag};
+
+use std::{io, mem};
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// How the pages of a region are shared with other mappings (`SM_*`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ShareMode {
+    CopyOnWrite,
+    Private,
+    Empty,
+    Shared,
+    TrueShared,
+    PrivateAliased,
+    SharedAliased,
+    LargePage,
+    Other(u8),
+}
+
+impl ShareMode {
+    fn from_raw(raw: u8) -> ShareMode {
+        match raw as u32 {
+            sys::SM_COW => ShareMode::CopyOnWrite,
+            sys::SM_PRIVATE => ShareMode::Private,
+            sys::SM_EMPTY => ShareMode::Empty,
+            sys::SM_SHARED => ShareMode::Shared,
+            sys::SM_TRUESHARED => ShareMode::TrueShared,
+            sys::SM_PRIVATE_ALIASED => ShareMode::PrivateAliased,
+            sys::SM_SHARED_ALIASED => ShareMode::SharedAliased,
+            sys::SM_LARGE_PAGE => ShareMode::LargePage,
+            _ => ShareMode::Other(raw),
+        }
+    }
+}
+
+/// A mapped region of a task's address space, as reported by `mach_vm_region_recurse`.
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub range: Range<u64>,
+    pub protection: Protection,
+    pub max_protection: Protection,
+    pub share_mode: ShareMode,
+    /// The tag the memory was allocated with.
+    pub tag: VmTag,
+    /// An identifier for the VM object backing the region, which is shared by all mappings of the same memory.
+    pub object_id: u32,
+    /// The offset of the region into its backing object.
+    pub offset: u64,
+    /// How many submaps deep the region is nested, which is nonzero for regions of the shared cache.
+    pub depth: u32,
+    pub pages_resident: u32,
+    pub pages_dirtied: u32,
+    pub pages_swapped_out: u32,
+}
+
+impl Region {
+    #[inline]
+    pub fn contains(&self, address: u64) -> bool {
+        self.range.start <= address && address < self.range.end
+    }
+}
+
+impl Task {
+    /// Iterates over the mapped regions of this task in address order, descending into submaps.
+    pub fn regions(&self) -> Regions {
+        Regions {
+            task: self,
+            address: 0,
+            depth: 0,
+            done: false,
+        }
+    }
+
+    /// Finds the region containing `address`, if it is mapped.
+    pub fn region_at(&self, address: u64) -> io::Result<Option<Region>> {
+        let mut regions = Regions {
+            task: self,
+            address,
+            depth: 0,
+            done: false,
+        };
+        match regions.next() {
+            Some(Ok(region)) if region.contains(address) => Ok(Some(region)),
+            Some(Ok(_)) | None => Ok(None),
+            Some(Err(err)) => Err(err),
+        }
+    }
+}
+
+/// Iterator returned by [`Task::regions`].
+pub struct Regions<'a> {
+    task: &'a Task,
+    address: u64,
+    depth: sys::natural_t,
+    done: bool,
+}
+
+impl<'a> Iterator for Regions<'a> {
+    type Item = io::Result<Region>;
+
+    fn next(&mut self) -> Option<io::Result<Region>> {
+        if self.done {
+            return None;
+        }
+        if let Err(err) = self.task.require(TaskFlavor::Read) {
+            self.done = true;
+            return Some(Err(err));
+        }
+        loop {
+            let mut address = self.address;
+            let mut size: sys::mach_vm_size_t = 0;
+            let mut depth = self.depth;
+            let mut info: sys::vm_region_submap_info_data_64_t = unsafe { mem::zeroed() };
+            let mut count = (mem::size_of::<sys::vm_region_submap_info_data_64_t>() / mem::size_of::<sys::natural_t>()) as sys::mach_msg_type_number_t;
+            let result = unsafe {
+                sys::mach_vm_region_recurse(
+                    self.task.as_raw_port(),
+                    &mut address,
+                    &mut size,
+                    &mut depth,
+                    &mut info as *mut _ as sys::vm_region_recurse_info_t,
+                    &mut count,
+                )
+            };
+            if result as u32 == sys::KERN_INVALID_ADDRESS {
+                // No regions past this address
+                self.done = true;
+                return None;
+            }
+            if let Err(err) = mach_call!(log op: "mach_vm_region_recurse", port: self.task.as_raw_port(), result) {
+                self.done = true;
+                return Some(Err(err));
+            }
+
+            if info.is_submap != 0 {
+                // Look up the same address again one level down
+                self.depth = depth + 1;
+                continue;
+            }
+            self.depth = depth;
+            self.address = address + size;
+            return Some(Ok(Region {
+                range: address..(address + size),
+                protection: Protection(info.protection),
+                max_protection: Protection(info.max_protection),
+                share_mode: ShareMode::from_raw(info.share_mode),
+                tag: VmTag(info.user_tag as u8),
+                object_id: info.object_id,
+                offset: info.offset,
+                depth,
+                pages_resident: info.pages_resident,
+                pages_dirtied: info.pages_dirtied,
+                pages_swapped_out: info.pages_swapped_out,
+            }));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::Protection;
+
+use std::io;
+use std::ops::Range;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// How memory is inherited by child tasks (`vm_inherit_t`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Inheritance {
+    Share,
+    Copy,
+    None,
+}
+
+impl Inheritance {
+    pub(crate) fn as_raw(self) -> sys::vm_inherit_t {
+        (match self {
+            Inheritance::Share => sys::VM_INHERIT_SHARE,
+            Inheritance::Copy => sys::VM_INHERIT_COPY,
+            Inheritance::None => sys::VM_INHERIT_NONE,
+        }) as sys::vm_inherit_t
+    }
+}
+
+/// Options for [`Task::remap_from`].
+#[derive(Clone, Copy, Debug)]
+pub struct RemapOptions {
+    copy: bool,
+    address: Option<u64>,
+    alignment_mask: u64,
+    inheritance: Inheritance,
+}
+
+impl Default for RemapOptions {
+    fn default() -> RemapOptions {
+        RemapOptions {
+            copy: false,
+            address: None,
+            alignment_mask: 0,
+            inheritance: Inheritance::Share,
+        }
+    }
+}
+
+impl RemapOptions {
+    pub fn new() -> RemapOptions {
+        RemapOptions::default()
+    }
+
+    /// Gives the new mapping a copy-on-write copy of the source memory instead of sharing it. Defaults to `false`.
+    pub fn copy(mut self, copy: bool) -> Self {
+        self.copy = copy;
+        self
+    }
+
+    /// Places the mapping at exactly `address` instead of anywhere.
+    pub fn address(mut self, address: u64) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Requires the mapping's address to have the bits in `mask` clear (e.g. `0xffff` for 64KiB alignment).
+    pub fn alignment_mask(mut self, mask: u64) -> Self {
+        self.alignment_mask = mask;
+        self
+    }
+
+    /// Defaults to [`Inheritance::Share`].
+    pub fn inheritance(mut self, inheritance: Inheritance) -> Self {
+        self.inheritance = inheritance;
+        self
+    }
+}
+
+/// The result of a successful [`Task::remap_from`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Remapping {
+    /// The address of the mapping in the destination task. Unaligned source addresses keep their offset into the
+    /// page.
+    pub address: u64,
+    pub size: u64,
+    /// The strictest current protection of the source pages.
+    pub protection: Protection,
+    pub max_protection: Protection,
+}
+
+impl Task {
+    /// Maps the memory at `range` in `source` into this task.
+    ///
+    /// When this task is the current task, the mapping can be taken over with
+    /// [`VmAllocation::from_raw_parts`](crate::vm::VmAllocation::from_raw_parts) after truncating the address to a
+    /// page boundary and rounding the size up.
+    ///
+    /// Both tasks must be control ports: `mach_vm_remap` rejects read ports for the source even when copying, so use
+    /// [`Task::read_memory`] to copy out of a task only available for reading.
+    pub fn remap_from(&self, source: &Task, range: Range<u64>, options: &RemapOptions) -> io::Result<Remapping> {
+        self.require(TaskFlavor::Control)?;
+        source.require(TaskFlavor::Control)?;
+        if range.end < range.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+        }
+        let size = range.end - range.start;
+        let flags = if options.address.is_some() { sys::VM_FLAGS_FIXED } else { sys::VM_FLAGS_ANYWHERE };
+        unsafe {
+            let mut address = options.address.unwrap_or(0);
+            let mut protection: sys::vm_prot_t = 0;
+            let mut max_protection: sys::vm_prot_t = 0;
+            mach_call!(log op: "mach_vm_remap", port: self.as_raw_port(), sys::mach_vm_remap(
+                self.as_raw_port(), &mut address, size, options.alignment_mask, flags as _,
+                source.as_raw_port(), range.start, options.copy as _,
+                &mut protection, &mut max_protection, options.inheritance.as_raw(),
+            ))?;
+            Ok(Remapping {
+                address,
+                size,
+                protection: Protection(protection),
+                max_protection: Protection(max_protection),
+            })
+        }
+    }
+}
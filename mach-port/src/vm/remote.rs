@@ -0,0 +1,120 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::{MemoryEntry, MapBuilder, Protection, VmTag, round_page, tag_flags};
+
+use std::{io, mem, fmt};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// An owned range of virtual memory in another task, deallocated from that task when dropped.
+///
+/// This lets a parent stage buffers inside a child it holds the control port for, e.g. before resuming it. Use
+/// [`into_raw`](RemoteAllocation::into_raw) to hand ownership of the memory over to the target task.
+pub struct RemoteAllocation<'a> {
+    task: &'a Task,
+    address: sys::mach_vm_address_t,
+    size: sys::mach_vm_size_t,
+}
+
+impl<'a> Drop for RemoteAllocation<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = mach_call!(log op: "mach_vm_deallocate", port: self.task.as_raw_port(), sys::mach_vm_deallocate(self.task.as_raw_port(), self.address, self.size));
+        }
+    }
+}
+
+impl<'a> RemoteAllocation<'a> {
+    /// Allocates at least `size` bytes of zero-filled memory anywhere in `task`'s address space.
+    ///
+    /// The memory is attributed to `tag`, or the [default tag](crate::vm::set_default_tag) if `tag` is `None`.
+    pub fn new(task: &'a Task, size: usize, tag: Option<VmTag>) -> io::Result<RemoteAllocation<'a>> {
+        task.require(TaskFlavor::Control)?;
+        if size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot allocate zero bytes of virtual memory"));
+        }
+        let size = round_page(size as u64);
+        unsafe {
+            let mut address: sys::mach_vm_address_t = 0;
+            mach_call!(log op: "mach_vm_allocate", port: task.as_raw_port(), sys::mach_vm_allocate(task.as_raw_port(), &mut address, size, sys::VM_FLAGS_ANYWHERE as i32 | tag_flags(tag)))?;
+            Ok(RemoteAllocation::from_raw_parts(task, address, size))
+        }
+    }
+
+    /// Takes ownership of a page-aligned range of memory in `task`.
+    pub unsafe fn from_raw_parts(task: &'a Task, address: u64, size: u64) -> RemoteAllocation<'a> {
+        RemoteAllocation {
+            task,
+            address,
+            size,
+        }
+    }
+
+    #[inline]
+    pub fn task(&self) -> &'a Task {
+        self.task
+    }
+
+    /// The address of the memory in the target task.
+    #[inline]
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// The size of the allocation in bytes, which is a multiple of the page size.
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Copies `data` into the allocation at `offset`.
+    pub fn write(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        if offset.checked_add(data.len() as u64).map(|end| end > self.size).unwrap_or(true) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "range exceeds allocation"));
+        }
+        // Even in the current task, the allocation is never exposed as a slice, so nothing else can observe the write
+        unsafe { self.task.write_memory(self.address + offset, data) }
+    }
+
+    /// Copies memory from the allocation at `offset` into `buffer`.
+    pub fn read(&self, offset: u64, buffer: &mut [u8]) -> io::Result<()> {
+        if offset.checked_add(buffer.len() as u64).map(|end| end > self.size).unwrap_or(true) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "range exceeds allocation"));
+        }
+        if self.task.read_memory(self.address + offset, buffer)? != buffer.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "short read"));
+        }
+        Ok(())
+    }
+
+    /// Releases ownership of the memory, leaving it mapped in the target task, and returns its address and size.
+    pub fn into_raw(self) -> (u64, u64) {
+        let parts = (self.address, self.size);
+        mem::forget(self);
+        parts
+    }
+}
+
+impl<'a> fmt::Debug for RemoteAllocation<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RemoteAllocation")
+            .field("task", &format_args!("{:#x?}", self.task.as_raw_port()))
+            .field("address", &format_args!("{:#x?}", self.address))
+            .field("size", &format_args!("{:#x?}", self.size))
+            .finish()
+    }
+}
+
+impl MemoryEntry {
+    /// Maps the whole entry anywhere in another task's address space, sharing its pages.
+    ///
+    /// Unlike [`map_in_task`](MemoryEntry::map_in_task), the mapping is removed from the target task when the returned
+    /// allocation is dropped.
+    pub fn map_in<'a>(&self, task: &'a Task, protection: Protection, max_protection: Protection) -> io::Result<RemoteAllocation<'a>> {
+        let address = MapBuilder::entry(self)
+            .protection(protection)
+            .max_protection(max_protection)
+            .map_in_task(task)?;
+        Ok(unsafe { RemoteAllocation::from_raw_parts(task, address, self.size()) })
+    }
+}
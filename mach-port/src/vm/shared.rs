@@ -0,0 +1,195 @@
+use crate::vm::{VmAllocation, MemoryEntry, Protection};
+
+use std::{io, mem, ptr, slice, fmt, hint};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{self, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU8, AtomicU16, AtomicU32, AtomicUsize, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize};
+
+/// Types that can be read from memory shared with another process.
+///
+/// Another process can write any bit pattern into shared memory, so implementors must be valid for every bit
+/// pattern, contain no pointers or references, and be `#[repr(C)]` or `#[repr(transparent)]` if they are structs.
+/// This rules out `bool`, `char` and most enums.
+pub unsafe trait SharedPlain: Copy + 'static {}
+
+macro_rules! impl_shared_plain {
+    ($($ty:ty),*) => {
+        $(unsafe impl SharedPlain for $ty {})*
+    };
+}
+
+impl_shared_plain!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// Atomic types that can be placed in memory shared with another process.
+///
+/// `AtomicBool` is excluded since another process could store a value other than 0 or 1.
+pub unsafe trait SharedAtomic: Sync + 'static {}
+
+macro_rules! impl_shared_atomic {
+    ($($ty:ty),*) => {
+        $(unsafe impl SharedAtomic for $ty {})*
+    };
+}
+
+impl_shared_atomic!(AtomicU8, AtomicU16, AtomicU32, AtomicU64, AtomicUsize, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize);
+
+#[repr(C)]
+struct SeqLockLayout<T> {
+    sequence: AtomicU64,
+    value: UnsafeCell<T>,
+}
+
+/// A value in shared memory guarded by a sequence lock, so readers in any process always see a consistent copy.
+///
+/// Readers never block writers; they retry if a write was in progress while they were copying the value. Writers
+/// exclude each other by spinning, so writes should be short and infrequent relative to reads.
+pub struct SharedCell<T: SharedPlain> {
+    entry: MemoryEntry,
+    mapping: VmAllocation,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: SharedPlain> Send for SharedCell<T> {}
+unsafe impl<T: SharedPlain> Sync for SharedCell<T> {}
+
+impl<T: SharedPlain> SharedCell<T> {
+    /// Creates a new memory entry holding `value`.
+    pub fn create(value: T) -> io::Result<SharedCell<T>> {
+        let entry = MemoryEntry::new(mem::size_of::<SeqLockLayout<T>>(), Protection::DEFAULT)?;
+        let cell = SharedCell::open(entry)?;
+        unsafe { ptr::write_volatile(cell.layout().value.get(), value); }
+        Ok(cell)
+    }
+
+    /// Maps a memory entry created by [`create`](SharedCell::create), e.g. one received from another process.
+    pub fn open(entry: MemoryEntry) -> io::Result<SharedCell<T>> {
+        if entry.size() < mem::size_of::<SeqLockLayout<T>>() as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "memory entry is too small for shared value"));
+        }
+        let mapping = entry.map(Protection::DEFAULT)?;
+        Ok(SharedCell {
+            entry,
+            mapping,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The underlying memory entry, whose port can be sent to other processes so they can [`open`](SharedCell::open)
+    /// the cell.
+    #[inline]
+    pub fn entry(&self) -> &MemoryEntry {
+        &self.entry
+    }
+
+    /// Reads a consistent copy of the value.
+    pub fn load(&self) -> T {
+        let layout = self.layout();
+        loop {
+            let before = layout.sequence.load(Ordering::Acquire);
+            if before & 1 != 0 {
+                // A write is in progress
+                hint::spin_loop();
+                continue;
+            }
+            let value = unsafe { ptr::read_volatile(layout.value.get()) };
+            atomic::fence(Ordering::Acquire);
+            if layout.sequence.load(Ordering::Relaxed) == before {
+                return value;
+            }
+        }
+    }
+
+    /// Replaces the value, waiting for any concurrent writer to finish first.
+    pub fn store(&self, value: T) {
+        let layout = self.layout();
+        let mut sequence = layout.sequence.load(Ordering::Relaxed);
+        loop {
+            if sequence & 1 != 0 {
+                hint::spin_loop();
+                sequence = layout.sequence.load(Ordering::Relaxed);
+                continue;
+            }
+            match layout.sequence.compare_exchange_weak(sequence, sequence + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => sequence = current,
+            }
+        }
+        atomic::fence(Ordering::Release);
+        unsafe { ptr::write_volatile(layout.value.get(), value); }
+        layout.sequence.store(sequence + 2, Ordering::Release);
+    }
+
+    #[inline]
+    fn layout(&self) -> &SeqLockLayout<T> {
+        unsafe { &*(self.mapping.address() as *const SeqLockLayout<T>) }
+    }
+}
+
+impl<T: SharedPlain + fmt::Debug> fmt::Debug for SharedCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedCell")
+            .field("entry", &self.entry)
+            .field("value", &self.load())
+            .finish()
+    }
+}
+
+/// A slice of atomics in shared memory.
+///
+/// All access goes through the atomic types' own methods, so no further synchronization is needed to use it from
+/// several processes at once.
+pub struct SharedSlice<T: SharedAtomic> {
+    entry: MemoryEntry,
+    mapping: VmAllocation,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: SharedAtomic> SharedSlice<T> {
+    /// Creates a new memory entry holding `len` zeroed atomics.
+    pub fn create(len: usize) -> io::Result<SharedSlice<T>> {
+        let size = len.checked_mul(mem::size_of::<T>())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "shared slice too large"))?;
+        SharedSlice::open(MemoryEntry::new(size, Protection::DEFAULT)?)
+    }
+
+    /// Maps a memory entry as a slice of atomics covering as much of it as possible, e.g. one received from another
+    /// process.
+    pub fn open(entry: MemoryEntry) -> io::Result<SharedSlice<T>> {
+        let mapping = entry.map(Protection::DEFAULT)?;
+        let len = mapping.size() as usize / mem::size_of::<T>();
+        Ok(SharedSlice {
+            entry,
+            mapping,
+            len,
+            _marker: PhantomData,
+        })
+    }
+
+    /// The underlying memory entry, whose port can be sent to other processes so they can
+    /// [`open`](SharedSlice::open) the slice.
+    #[inline]
+    pub fn entry(&self) -> &MemoryEntry {
+        &self.entry
+    }
+}
+
+impl<T: SharedAtomic> Deref for SharedSlice<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.mapping.address() as *const T, self.len) }
+    }
+}
+
+impl<T: SharedAtomic + fmt::Debug> fmt::Debug for SharedSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedSlice")
+            .field("entry", &self.entry)
+            .field("values", &&**self)
+            .finish()
+    }
+}
@@ -0,0 +1,70 @@
+use crate::vm::{VmAllocation, VmTag, tag_flags};
+
+use std::io;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// The size of the superpages (large pages) backing an allocation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SuperpageSize {
+    /// Whatever superpage size the hardware supports (`VM_FLAGS_SUPERPAGE_SIZE_ANY`).
+    Any,
+    /// 2MiB superpages (`VM_FLAGS_SUPERPAGE_SIZE_2MB`), which are only supported on x86_64.
+    Size2Mb,
+}
+
+impl SuperpageSize {
+    fn flags(self) -> i32 {
+        (match self {
+            SuperpageSize::Any => sys::VM_FLAGS_SUPERPAGE_SIZE_ANY,
+            SuperpageSize::Size2Mb => sys::VM_FLAGS_SUPERPAGE_SIZE_2MB,
+        }) as i32
+    }
+}
+
+const SUPERPAGE_2MB: u64 = 2 * 1024 * 1024;
+
+impl VmAllocation {
+    /// Allocates at least `size` bytes backed by superpages where possible, falling back to regular pages if the
+    /// hardware or kernel does not support them or no physically contiguous memory is available.
+    ///
+    /// Superpages reduce TLB pressure for very large buffers, but are wired (never paged out) while allocated.
+    pub fn with_superpages(size: usize, superpage: SuperpageSize, tag: Option<VmTag>) -> io::Result<VmAllocation> {
+        if size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot allocate zero bytes of virtual memory"));
+        }
+        match VmAllocation::superpage_allocate(size, superpage, tag) {
+            Ok(allocation) => Ok(allocation),
+            Err(err) => {
+                debug!("superpage allocation of {:#x} bytes failed, falling back to regular pages: {:?}", size, err);
+                match tag {
+                    Some(tag) => VmAllocation::with_tag(size, tag),
+                    None => VmAllocation::new(size),
+                }
+            },
+        }
+    }
+
+    /// Allocates at least `size` bytes backed by superpages, failing if they are unavailable.
+    pub fn with_superpages_exact(size: usize, superpage: SuperpageSize, tag: Option<VmTag>) -> io::Result<VmAllocation> {
+        VmAllocation::superpage_allocate(size, superpage, tag)
+    }
+
+    fn superpage_allocate(size: usize, superpage: SuperpageSize, tag: Option<VmTag>) -> io::Result<VmAllocation> {
+        if size == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot allocate zero bytes of virtual memory"));
+        }
+        // Superpage allocations must be a whole number of superpages. 2MiB is the only size XNU supports, and also
+        // what SIZE_ANY resolves to.
+        let size = (size as u64 + SUPERPAGE_2MB - 1) & !(SUPERPAGE_2MB - 1);
+        unsafe {
+            let mut address: sys::mach_vm_address_t = 0;
+            // Not logged, since failure is expected on hardware without superpage support
+            mach_call!(op: "mach_vm_allocate", sys::mach_vm_allocate(
+                mach_core::self_port::task_self(), &mut address, size, sys::VM_FLAGS_ANYWHERE as i32 | superpage.flags() | tag_flags(tag),
+            ))?;
+            Ok(VmAllocation::from_raw_parts(address, size))
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Task, TaskFlavor};
+use crate::vm::VmAllocation;
+
+use std::{io, fmt};
+use std::ops::{BitOr, BitOrAssign, Range};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Options for [`Task::sync_memory`] (`VM_SYNC_*`), the Mach equivalent of `msync` flags.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SyncFlags(pub sys::vm_sync_t);
+
+impl SyncFlags {
+    /// Waits for modified pages to be written to their backing store.
+    pub const SYNCHRONOUS: SyncFlags = SyncFlags(sys::VM_SYNC_SYNCHRONOUS as _);
+    /// Starts writing modified pages to their backing store without waiting.
+    pub const ASYNCHRONOUS: SyncFlags = SyncFlags(sys::VM_SYNC_ASYNCHRONOUS as _);
+    /// Discards cached pages after they are written, so later accesses reread the backing store.
+    pub const INVALIDATE: SyncFlags = SyncFlags(sys::VM_SYNC_INVALIDATE as _);
+    /// Discards pages without writing them back.
+    pub const KILL_PAGES: SyncFlags = SyncFlags(sys::VM_SYNC_KILLPAGES as _);
+    pub const DEACTIVATE: SyncFlags = SyncFlags(sys::VM_SYNC_DEACTIVATE as _);
+    /// Fails with an error if the range contains unmapped holes.
+    pub const CONTIGUOUS: SyncFlags = SyncFlags(sys::VM_SYNC_CONTIGUOUS as _);
+
+    #[inline]
+    pub fn contains(self, other: SyncFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for SyncFlags {
+    type Output = SyncFlags;
+
+    #[inline]
+    fn bitor(self, rhs: SyncFlags) -> SyncFlags {
+        SyncFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for SyncFlags {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: SyncFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for SyncFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SyncFlags({:#x?})", self.0)
+    }
+}
+
+impl Task {
+    /// Flushes or invalidates the pages overlapping `range` with respect to their backing store, like `msync`.
+    pub fn sync_memory(&self, range: Range<u64>, flags: SyncFlags) -> io::Result<()> {
+        self.require(TaskFlavor::Control)?;
+        if range.end < range.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid memory range"));
+        }
+        unsafe {
+            mach_call!(log op: "mach_vm_msync", port: self.as_raw_port(), sys::mach_vm_msync(self.as_raw_port(), range.start, range.end - range.start, flags.0))?;
+        }
+        Ok(())
+    }
+}
+
+impl VmAllocation {
+    /// Flushes or invalidates the whole allocation with respect to its backing store, e.g. after writing to a mapped
+    /// [`MemoryEntry`](crate::vm::MemoryEntry) that other processes read through their own caches.
+    pub fn sync(&self, flags: SyncFlags) -> io::Result<()> {
+        Task::current().sync_memory(self.range(), flags)
+    }
+}
@@ -0,0 +1,188 @@
+//! Mach vouchers, which carry attributes such as importance and activity IDs along with messages.
+//!
+//! A [`Voucher`] is created from a [`VoucherRecipe`] describing its attributes, and attached to an outgoing message
+//! with [`Msg::set_voucher`](crate::Msg::set_voucher). The kernel (and libdispatch in the receiver) then applies the
+//! attributes while the message is processed, e.g. keeping the sender's importance boost or attributing the
+//! receiver's logging to the sender's activity.
+
+use crate::{Port, RawPort};
+
+use std::{io, fmt, mem, process};
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+// The bound on attribute contents and recipes in mach_voucher.defs
+const ATTR_CONTENT_MAX: usize = 4096;
+
+// The user data libdispatch stores in a voucher to propagate the current os_activity (_voucher_mach_udata_s), which
+// starts with this magic number
+const USER_DATA_MAGIC_V3: u32 = 0x0390_cefa;
+const USER_DATA_ACTIVITY_OFFSET: usize = 8;
+const USER_DATA_SIZE: usize = 32;
+
+/// A voucher attribute manager, identifying one kind of attribute (`mach_voucher_attr_key_t`).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct VoucherAttrKey(pub sys::mach_voucher_attr_key_t);
+
+impl VoucherAttrKey {
+    /// Every attribute, for copying a whole voucher.
+    pub const ALL: VoucherAttrKey = VoucherAttrKey(sys::MACH_VOUCHER_ATTR_KEY_ALL);
+    pub const IMPORTANCE: VoucherAttrKey = VoucherAttrKey(sys::MACH_VOUCHER_ATTR_KEY_IMPORTANCE);
+    pub const BANK: VoucherAttrKey = VoucherAttrKey(sys::MACH_VOUCHER_ATTR_KEY_BANK);
+    pub const PTHREAD_PRIORITY: VoucherAttrKey = VoucherAttrKey(sys::MACH_VOUCHER_ATTR_KEY_PTHPRIORITY);
+    /// Arbitrary bytes, which libdispatch uses to carry the current activity.
+    pub const USER_DATA: VoucherAttrKey = VoucherAttrKey(sys::MACH_VOUCHER_ATTR_KEY_USER_DATA);
+}
+
+/// The attributes to create a [`Voucher`] with, as a list of recipes applied in order.
+#[derive(Clone, Default)]
+pub struct VoucherRecipe {
+    data: Vec<u8>,
+}
+
+impl VoucherRecipe {
+    pub fn new() -> VoucherRecipe {
+        VoucherRecipe::default()
+    }
+
+    /// Copies the attributes of `voucher` for `key` (or all of them, with [`VoucherAttrKey::ALL`]).
+    pub fn copy(mut self, voucher: &Voucher, key: VoucherAttrKey) -> Self {
+        self.push(key.0, sys::MACH_VOUCHER_ATTR_COPY, voucher.as_raw_port(), &[]);
+        self
+    }
+
+    /// Adds an importance attribute referencing the current task.
+    ///
+    /// While a receiver holds a message carrying the voucher, the current task keeps any importance boost it would
+    /// otherwise lose (e.g. an app in the background waiting on a daemon's reply to a request made in the foreground).
+    pub fn importance(mut self) -> Self {
+        self.push(sys::MACH_VOUCHER_ATTR_KEY_IMPORTANCE, sys::MACH_VOUCHER_ATTR_IMPORTANCE_SELF, sys::MACH_PORT_NULL, &[]);
+        self
+    }
+
+    /// Stores arbitrary bytes as the voucher's user data, replacing any set by an earlier recipe.
+    pub fn user_data(mut self, data: &[u8]) -> Self {
+        self.push(sys::MACH_VOUCHER_ATTR_KEY_USER_DATA, sys::MACH_VOUCHER_ATTR_USER_DATA_STORE, sys::MACH_PORT_NULL, data);
+        self
+    }
+
+    /// Tags the voucher with an `os_activity` ID (and the ID of its parent activity, or 0), so the receiver's log
+    /// messages are attributed to that activity.
+    ///
+    /// This stores user data in the layout libdispatch uses, replacing any set by an earlier recipe.
+    pub fn activity(self, activity_id: u64, parent_id: u64) -> Self {
+        let mut data = [0u8; USER_DATA_SIZE];
+        data[0..4].copy_from_slice(&USER_DATA_MAGIC_V3.to_ne_bytes());
+        data[USER_DATA_ACTIVITY_OFFSET..USER_DATA_ACTIVITY_OFFSET + 8].copy_from_slice(&activity_id.to_ne_bytes());
+        data[16..24].copy_from_slice(&(process::id() as u64).to_ne_bytes());
+        data[24..32].copy_from_slice(&parent_id.to_ne_bytes());
+        self.user_data(&data)
+    }
+
+    // Appends a mach_voucher_attr_recipe_data_t followed by its content
+    fn push(&mut self, key: sys::mach_voucher_attr_key_t, command: sys::mach_voucher_attr_recipe_command_t, previous: RawPort, content: &[u8]) {
+        self.data.extend_from_slice(&key.to_ne_bytes());
+        self.data.extend_from_slice(&command.to_ne_bytes());
+        self.data.extend_from_slice(&previous.to_ne_bytes());
+        self.data.extend_from_slice(&(content.len() as u32).to_ne_bytes());
+        self.data.extend_from_slice(content);
+    }
+}
+
+impl fmt::Debug for VoucherRecipe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VoucherRecipe")
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+/// A send right to a Mach voucher.
+pub struct Voucher {
+    port: Port,
+}
+
+impl Voucher {
+    /// Creates a voucher with the attributes described by `recipe` (`host_create_mach_voucher`).
+    ///
+    /// The kernel returns an existing voucher if one with identical attributes exists.
+    pub fn create(recipe: &VoucherRecipe) -> io::Result<Voucher> {
+        if recipe.data.len() > sys::MACH_VOUCHER_ATTR_MAX_RAW_RECIPE_ARRAY_SIZE as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "voucher recipe too large"));
+        }
+        unsafe {
+            let mut port: sys::ipc_voucher_t = sys::MACH_PORT_NULL;
+            mach_call!(log op: "host_create_mach_voucher", sys::host_create_mach_voucher(
+                mach_core::self_port::host_self(),
+                recipe.data.as_ptr() as *mut _,
+                recipe.data.len() as _,
+                &mut port,
+            ))?;
+            Voucher::from_raw_port(port)
+        }
+    }
+
+    /// Takes ownership of a send right to a voucher.
+    pub unsafe fn from_raw_port(port: RawPort) -> io::Result<Voucher> {
+        Ok(Voucher {
+            port: Port::from_raw_port(port)?,
+        })
+    }
+
+    #[inline]
+    pub fn as_raw_port(&self) -> RawPort {
+        self.port.as_raw_port()
+    }
+
+    pub fn into_raw_port(self) -> RawPort {
+        self.port.into_raw_port()
+    }
+
+    /// The raw value of the attribute for `key` (`mach_voucher_extract_attr_content`).
+    pub fn attr_content(&self, key: VoucherAttrKey) -> io::Result<Vec<u8>> {
+        let mut content = vec![0u8; ATTR_CONTENT_MAX];
+        let mut len = content.len() as sys::mach_msg_type_number_t;
+        unsafe {
+            mach_call!(log op: "mach_voucher_extract_attr_content", port: self.as_raw_port(), sys::mach_voucher_extract_attr_content(self.as_raw_port(), key.0, content.as_mut_ptr(), &mut len))?;
+        }
+        content.truncate(len as usize);
+        Ok(content)
+    }
+
+    /// A recipe reproducing the attribute for `key` (`mach_voucher_extract_attr_recipe`), in the format consumed by
+    /// `host_create_mach_voucher`.
+    pub fn attr_recipe(&self, key: VoucherAttrKey) -> io::Result<Vec<u8>> {
+        let mut recipe = vec![0u8; ATTR_CONTENT_MAX];
+        let mut len = recipe.len() as sys::mach_msg_type_number_t;
+        unsafe {
+            mach_call!(log op: "mach_voucher_extract_attr_recipe", port: self.as_raw_port(), sys::mach_voucher_extract_attr_recipe(self.as_raw_port(), key.0, recipe.as_mut_ptr(), &mut len))?;
+        }
+        recipe.truncate(len as usize);
+        Ok(recipe)
+    }
+
+    /// The `os_activity` ID the voucher carries, if its user data is in libdispatch's format.
+    pub fn activity_id(&self) -> io::Result<Option<u64>> {
+        let data = self.attr_content(VoucherAttrKey::USER_DATA)?;
+        if data.len() < USER_DATA_SIZE {
+            return Ok(None);
+        }
+        let mut magic = [0u8; mem::size_of::<u32>()];
+        magic.copy_from_slice(&data[0..4]);
+        if u32::from_ne_bytes(magic) != USER_DATA_MAGIC_V3 {
+            return Ok(None);
+        }
+        let mut activity_id = [0u8; mem::size_of::<u64>()];
+        activity_id.copy_from_slice(&data[USER_DATA_ACTIVITY_OFFSET..USER_DATA_ACTIVITY_OFFSET + 8]);
+        Ok(Some(u64::from_ne_bytes(activity_id)))
+    }
+}
+
+impl fmt::Debug for Voucher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Voucher")
+            .field("port", &format_args!("{:#x?}", self.as_raw_port()))
+            .finish()
+    }
+}
@@ -0,0 +1,137 @@
+use crate::host::HostPriv;
+
+use std::{io, mem, ptr, slice};
+use std::os::raw::c_char;
+
+use mach_sys as sys;
+use mach_core::mach_call;
+
+/// Usage statistics for a kernel zone allocator.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ZoneInfo {
+    pub name: String,
+    /// The number of elements in use.
+    pub count: u64,
+    /// The number of bytes currently allocated to the zone.
+    pub cur_size: u64,
+    pub max_size: u64,
+    pub elem_size: u64,
+    /// The number of bytes allocated to the zone at a time when it grows.
+    pub alloc_size: u64,
+    /// The total number of bytes ever allocated from the zone.
+    pub sum_size: u64,
+    pub exhaustible: bool,
+    pub collectable: bool,
+    /// The number of bytes that could be reclaimed by a zone garbage collection.
+    pub collectable_bytes: u64,
+}
+
+/// Kernel memory usage attributed to a VM tag or allocation site.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct MemoryInfo {
+    pub flags: u64,
+    pub site: u64,
+    pub size: u64,
+    pub free: u64,
+    pub largest: u64,
+    pub collectable_bytes: u64,
+    pub mapped: u64,
+    pub peak: u64,
+    pub tag: u16,
+    pub zone: u16,
+    pub name: String,
+}
+
+impl HostPriv {
+    /// Retrieves usage statistics for every kernel zone (`mach_zone_info`).
+    pub fn zone_info(&self) -> io::Result<Vec<ZoneInfo>> {
+        unsafe {
+            let mut names: sys::mach_zone_name_array_t = ptr::null_mut();
+            let mut name_count: sys::mach_msg_type_number_t = 0;
+            let mut info: sys::mach_zone_info_array_t = ptr::null_mut();
+            let mut info_count: sys::mach_msg_type_number_t = 0;
+            mach_call!(log op: "mach_zone_info", port: self.as_raw_port(), sys::mach_zone_info(self.as_raw_port(), &mut names, &mut name_count, &mut info, &mut info_count))?;
+            let zones = collect_zones(names, name_count, info, info_count);
+            deallocate_array(names, name_count);
+            deallocate_array(info, info_count);
+            Ok(zones)
+        }
+    }
+
+    /// Retrieves zone statistics along with kernel memory usage broken down by VM tag and allocation site
+    /// (`mach_memory_info`).
+    pub fn memory_info(&self) -> io::Result<(Vec<ZoneInfo>, Vec<MemoryInfo>)> {
+        unsafe {
+            let mut names: sys::mach_zone_name_array_t = ptr::null_mut();
+            let mut name_count: sys::mach_msg_type_number_t = 0;
+            let mut info: sys::mach_zone_info_array_t = ptr::null_mut();
+            let mut info_count: sys::mach_msg_type_number_t = 0;
+            let mut memory: sys::mach_memory_info_array_t = ptr::null_mut();
+            let mut memory_count: sys::mach_msg_type_number_t = 0;
+            mach_call!(log op: "mach_memory_info", port: self.as_raw_port(), sys::mach_memory_info(
+                self.as_raw_port(),
+                &mut names, &mut name_count,
+                &mut info, &mut info_count,
+                &mut memory, &mut memory_count,
+            ))?;
+            let zones = collect_zones(names, name_count, info, info_count);
+            let sites = slice::from_raw_parts(memory, memory_count as usize).iter()
+                .map(|site| MemoryInfo {
+                    flags: site.flags,
+                    site: site.site,
+                    size: site.size,
+                    free: site.free,
+                    largest: site.largest,
+                    collectable_bytes: site.collectable_bytes,
+                    mapped: site.mapped,
+                    peak: site.peak,
+                    tag: site.tag,
+                    zone: site.zone,
+                    name: c_chars_to_string(&site.name),
+                })
+                .collect();
+            deallocate_array(names, name_count);
+            deallocate_array(info, info_count);
+            deallocate_array(memory, memory_count);
+            Ok((zones, sites))
+        }
+    }
+}
+
+unsafe fn collect_zones(names: sys::mach_zone_name_array_t, name_count: u32, info: sys::mach_zone_info_array_t, info_count: u32) -> Vec<ZoneInfo> {
+    let names = slice::from_raw_parts(names, name_count as usize);
+    let info = slice::from_raw_parts(info, info_count as usize);
+    names.iter().zip(info.iter())
+        .map(|(name, info)| ZoneInfo {
+            name: c_chars_to_string(&name.mzn_name),
+            count: info.mzi_count,
+            cur_size: info.mzi_cur_size,
+            max_size: info.mzi_max_size,
+            elem_size: info.mzi_elem_size,
+            alloc_size: info.mzi_alloc_size,
+            sum_size: info.mzi_sum_size,
+            exhaustible: info.mzi_exhaustible != 0,
+            // The low bit flags collectability, and the remaining bits hold the byte count
+            collectable: info.mzi_collectable & 1 != 0,
+            collectable_bytes: info.mzi_collectable >> 1,
+        })
+        .collect()
+}
+
+fn c_chars_to_string(chars: &[c_char]) -> String {
+    let bytes = unsafe { slice::from_raw_parts(chars.as_ptr() as *const u8, chars.len()) };
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+// The arrays are returned out-of-line, so they are mapped into our address space and must be unmapped
+unsafe fn deallocate_array<T>(array: *mut T, count: sys::mach_msg_type_number_t) {
+    if array.is_null() {
+        return;
+    }
+    let _ = mach_call!(log op: "mach_vm_deallocate(zone info array)", sys::mach_vm_deallocate(
+        mach_core::self_port::task_self(),
+        array as sys::mach_vm_address_t,
+        (count as usize * mem::size_of::<T>()) as sys::mach_vm_size_t,
+    ));
+}
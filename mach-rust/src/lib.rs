@@ -0,0 +1,87 @@
+//! A single dependency covering the `mach-sys`, `mach-core` and `mach-port` crates.
+//!
+//! Each module re-exports one area of the lower-level crates, behind a cargo feature of the same name (all but
+//! `bootstrap` are enabled by default). [`prelude`] brings in the most commonly used types of whichever are enabled.
+//! Anything not covered by a module is still reachable through the re-exported crates themselves.
+//!
+//! Fallible APIs return `io::Error`. Those caused by a failed Mach call carry a [`MachError`] with the exact code,
+//! recovered with [`MachErrorExt::mach_error`].
+//!
+//! On non-Apple targets, where mach-port serves ports and messages from its in-memory fake kernel, only the `port` and
+//! `msg` modules are available.
+
+pub use mach_sys as sys;
+pub use mach_core;
+pub use mach_port;
+
+pub use mach_core::error;
+pub use mach_core::error::{MachError, MachErrorExt};
+pub use mach_core::RawPort;
+
+/// Ports, and inspecting the rights a task holds.
+#[cfg(feature = "port")]
+pub mod port {
+    pub use mach_port::Port;
+    #[cfg(target_vendor = "apple")]
+    pub use mach_port::{PortNameInfo, PortSpaceSnapshot, PortSpaceDiff, ReceiveStatus, PeekedMessage};
+    pub use mach_core::RawPort;
+    pub use mach_core::label::{set_label, label, clear_label};
+}
+
+/// Building, sending and receiving messages, and the vouchers attached to them.
+#[cfg(feature = "msg")]
+pub mod msg {
+    pub use mach_port::{
+        Msg, MsgBuffer, MsgDescriptor, MsgPortDescriptor, MsgDescriptorKind, MsgDescriptorKindMut, MsgDescriptorIter,
+        MsgDescriptorIterMut, PortMoveMode, PortCopyMode,
+    };
+    #[cfg(target_vendor = "apple")]
+    pub use mach_port::voucher::{Voucher, VoucherRecipe, VoucherAttrKey};
+}
+
+/// Tasks, their policies, resource usage and identity.
+#[cfg(all(target_vendor = "apple", feature = "task"))]
+pub mod task {
+    pub use mach_port::{
+        Task, TaskSuspendGuard, TaskFlavor, TaskSpecialPort, TaskRole, LatencyQosTier, ThroughputQosTier,
+        TaskSuppressionPolicy, TaskVmInfo, TaskPowerInfo, TaskPowerInfoV2, TaskIdentityToken, TaskUsageSample,
+    };
+}
+
+/// Virtual memory.
+#[cfg(all(target_vendor = "apple", feature = "vm"))]
+pub mod vm {
+    pub use mach_port::vm::*;
+}
+
+/// Host information and statistics, plus the privileged host APIs with the `host-priv` feature.
+#[cfg(all(target_vendor = "apple", feature = "host"))]
+pub mod host {
+    pub use mach_port::host::*;
+}
+
+/// Raw bindings to the bootstrap server (`servers/bootstrap.h`).
+///
+/// There is no safe wrapper for these yet.
+#[cfg(all(target_vendor = "apple", feature = "bootstrap"))]
+pub mod bootstrap {
+    pub use mach_sys::{
+        bootstrap_port, bootstrap_look_up, bootstrap_look_up2, bootstrap_check_in, bootstrap_check_in2,
+        bootstrap_subset, bootstrap_parent, bootstrap_status, bootstrap_strerror, name_t, BOOTSTRAP_MAX_NAME_LEN,
+        BOOTSTRAP_SUCCESS, BOOTSTRAP_NOT_PRIVILEGED, BOOTSTRAP_NAME_IN_USE, BOOTSTRAP_UNKNOWN_SERVICE,
+        BOOTSTRAP_SERVICE_ACTIVE, BOOTSTRAP_BAD_COUNT, BOOTSTRAP_NO_MEMORY, BOOTSTRAP_NO_CHILDREN,
+        BOOTSTRAP_PER_PID_SERVICE, BOOTSTRAP_PRIVILEGED_SERVER, BOOTSTRAP_FORCE_LOCAL, BOOTSTRAP_SPECIFIC_INSTANCE,
+        BOOTSTRAP_STRICT_CHECKIN, BOOTSTRAP_STRICT_LOOKUP,
+    };
+}
+
+/// The most commonly used types of the enabled modules, for glob importing.
+pub mod prelude {
+    pub use mach_core::error::{MachError, MachErrorExt};
+    #[cfg(feature = "port")]
+    pub use crate::port::Port;
+    #[cfg(feature = "msg")]
+    pub use crate::msg::{Msg, MsgBuffer};
+    #[cfg(all(target_vendor = "apple", feature = "task"))]
+    pub use crate::task::Task;
+}
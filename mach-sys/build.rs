@@ -1,55 +1,297 @@
-use std::{env};
-use std::path::PathBuf;
+use std::env;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "bindgen")]
 use std::io::{Write, BufWriter};
+#[cfg(feature = "bindgen")]
 use std::fs::File;
+#[cfg(feature = "bindgen")]
+use std::process::Command;
+
+// The headers each header feature includes. Features not listed here (e.g. `bindgen` or `derives`) configure the build
+// instead.
+#[cfg(feature = "bindgen")]
+const FEATURE_HEADERS: &[(&str, &[&str])] = &[
+    ("mach_init", &["mach/mach_init.h"]),
+    ("port", &["mach/port.h"]),
+    ("message", &["mach/message.h"]),
+    ("notify", &["mach/notify.h"]),
+    ("mach_port", &["mach/mach_port.h"]),
+    ("task", &["mach/task.h", "mach/task_info.h", "mach/task_policy.h", "mach/task_special_ports.h"]),
+    ("thread_act", &["mach/thread_act.h"]),
+    ("thread_switch", &["mach/thread_switch.h"]),
+    ("mach_vm", &["mach/mach_vm.h"]),
+    ("mach_host", &["mach/mach_host.h"]),
+    ("clock", &["mach/clock.h"]),
+    ("semaphore", &["mach/semaphore.h", "mach/sync_policy.h"]),
+    ("host_priv", &["mach/host_priv.h"]),
+    ("host_special_ports", &["mach/host_special_ports.h"]),
+    ("processor_set", &["mach/processor_set.h"]),
+    ("mach_time", &["mach/mach_time.h"]),
+    ("mach_traps", &["mach/mach_traps.h"]),
+    ("mach_voucher", &["mach/mach_voucher.h", "mach/mach_voucher_types.h"]),
+    // Whole subsystems
+    ("vm", &["mach/mach_vm.h", "mach/vm_map.h", "mach/vm_statistics.h", "mach/vm_region.h", "mach/vm_purgable.h", "mach/vm_behavior.h", "mach/vm_inherit.h", "mach/vm_prot.h", "mach/vm_sync.h"]),
+    ("thread", &["mach/thread_act.h", "mach/thread_info.h", "mach/thread_policy.h", "mach/thread_status.h", "mach/thread_switch.h", "mach/thread_special_ports.h"]),
+    ("exception", &["mach/exc.h", "mach/exception.h", "mach/exception_types.h"]),
+    ("bootstrap", &["servers/bootstrap.h"]),
+];
+
+// Macros that bindgen would translate, but which the handwritten files included for a feature define with proper types
+#[cfg(feature = "bindgen")]
+const HANDWRITTEN_MACROS: &[(&str, &str)] = &[
+    ("port", "MACH_PORT_NULL"),
+];
+
+// Items always generated, for the headers included regardless of features
+#[cfg(feature = "bindgen")]
+const BASE_ITEMS: &[&str] = &[
+    "(natural|integer|boolean|kern_return|policy|mach_error|cpu_type|cpu_subtype|cpu_threadtype)_t", "KERN_.*",
+    "mach_error.*", "time_value.*",
+];
+
+// Patterns for the functions, types and variables each feature's headers declare. Only matching items (and the types
+// they depend on) are generated, rather than everything the headers transitively include.
+#[cfg(feature = "bindgen")]
+const FEATURE_ITEMS: &[(&str, &[&str])] = &[
+    ("mach_init", &["mach_task_self_", "mach_host_self", "mach_thread_self", "vm_page_(size|mask|shift)", "vm_kernel_page_.*"]),
+    ("port", &["mach_port_.*", "MACH_PORT_.*"]),
+    ("message", &["mach_msg.*", "MACH_MSG.*", "MACH_SEND_.*", "MACH_RCV_.*", "NDR_.*", "MIG_.*", "(audit|security)_token_t"]),
+    ("notify", &["mach_.*_notification_t", "MACH_NOTIFY_.*"]),
+    ("mach_port", &["mach_port_.*", "MACH_PORT_.*", "ipc_info_.*"]),
+    ("task", &["task_.*", "TASK_.*", "mach_task_.*", "exception_.*", "EXC_.*", "EXCEPTION_.*", "MACH_EXCEPTION_.*"]),
+    ("thread_act", &["thread_.*", "THREAD_.*", "TH_.*", "act_.*", "x86_.*", "arm_.*", "ARM_.*", "exception_.*", "EXC_.*", "EXCEPTION_.*", "MACH_EXCEPTION_.*"]),
+    ("thread_switch", &["thread_switch", "SWITCH_OPTION_.*"]),
+    ("mach_vm", &["mach_vm_.*", "mach_make_memory_entry.*", "vm_.*", "VM_.*", "SM_.*", "MAP_MEM_.*"]),
+    ("mach_host", &["host_.*", "HOST_.*", "processor_.*", "PROCESSOR_.*", "CPU_STATE_.*", "LOAD_SCALE", "mach_zone_.*", "mach_memory_info.*", "kmod_.*"]),
+    ("clock", &["clock_.*", "CLOCK_.*", "(CALENDAR|SYSTEM|REALTIME)_CLOCK", "TIME_(ABSOLUTE|RELATIVE)", "alarm_type_t", "mach_timespec.*"]),
+    ("semaphore", &["semaphore_.*", "SYNC_POLICY_.*", "sync_policy_t"]),
+    ("host_priv", &["host_.*", "HOST_.*", "processor_.*", "PROCESSOR_.*", "vm_wire", "mach_zone_.*", "mach_memory_info.*", "kmod_.*", "KMOD_.*"]),
+    ("host_special_ports", &["host_.*", "HOST_.*"]),
+    ("processor_set", &["processor_set_.*", "PROCESSOR_SET_.*"]),
+    ("mach_time", &["mach_absolute_time", "mach_approximate_time", "mach_continuous_.*", "mach_timebase_info.*", "mach_wait_until"]),
+    ("mach_traps", &["mach_reply_port", ".*_self_trap", ".*_trap", "task_for_pid", "pid_for_task", "task_(name|inspect|read)_for_pid", "swtch(_pri)?"]),
+    ("mach_voucher", &["mach_voucher_.*", "MACH_VOUCHER_.*", "ipc_voucher_t"]),
+    ("vm", &["mach_vm_.*", "mach_make_memory_entry.*", "vm_.*", "VM_.*", "SM_.*", "MAP_MEM_.*"]),
+    ("thread", &["thread_.*", "THREAD_.*", "TH_.*", "act_.*", "x86_.*", "arm_.*", "ARM_.*", "SWITCH_OPTION_.*"]),
+    ("exception", &["exception_.*", "EXC_.*", "EXCEPTION_.*", "MACH_EXCEPTION_.*", "(mach_)?exc_.*", "catch_.*"]),
+    ("bootstrap", &["bootstrap_.*", "BOOTSTRAP_.*", "name_t", "cmd_t"]),
+];
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SDKROOT");
+    println!("cargo:rerun-if-env-changed=MACH_SYS_INCLUDE");
+    println!("cargo:rerun-if-env-changed=MACH_SYS_NO_ALLOWLIST");
+    println!("cargo:rerun-if-env-changed=MACH_SYS_EXTRA_HEADERS");
     let target = env::var("TARGET").unwrap();
     let out_path = PathBuf::from(env::var_os("OUT_DIR").unwrap());
 
+    // Other targets have no Mach headers, and use the handwritten subset in src/stub.rs instead
     if target.contains("apple") {
-        // Generate header from requested features
-        let mach_header_path = out_path.join("headers.h");
+        generate(&target, &out_path);
+    }
+}
 
-        {
-            let header_out = File::create(&mach_header_path).expect("failed to open header file output");
-            let mut header_out = BufWriter::new(header_out);
+// Converts a Rust target triple into the spelling clang expects, so headers are parsed for the right platform and
+// architecture (e.g. the iOS SDK's availability annotations and arm64 thread state).
+#[cfg(feature = "bindgen")]
+fn clang_target(target: &str) -> String {
+    let simulator = is_simulator(target);
+    let target = target.strip_suffix("-sim").unwrap_or(target);
+    let target = match target.strip_prefix("aarch64-") {
+        Some(rest) => format!("arm64-{}", rest),
+        None => target.to_owned(),
+    };
+    if simulator {
+        format!("{}-simulator", target)
+    } else {
+        target
+    }
+}
 
-            // Always include some headers
-            for header_name in ["mach_types", "boolean", "kern_return", "error", "mach_error"].iter() {
-                writeln!(header_out, "#include <mach/{}.h>", header_name).unwrap();
-            }
+// The x86 iOS and tvOS targets predate the `-sim` suffix, but only ever run in the simulator
+#[cfg(feature = "bindgen")]
+fn is_simulator(target: &str) -> bool {
+    target.ends_with("-sim")
+        || ((target.starts_with("x86_64-") || target.starts_with("i386-")) && (target.ends_with("-ios") || target.ends_with("-tvos")))
+}
 
-            for (feature_env, _) in env::vars_os() {
-                const PREFIX: &str = "CARGO_FEATURE_";
-                let feature_env = if let Some(feature_env) = feature_env.to_str() { feature_env } else {
-                    continue
-                };
-                if !feature_env.starts_with(PREFIX) {
-                    continue;
-                }
-                let feature_name = feature_env[PREFIX.len()..].to_ascii_lowercase();
-                if feature_name == "default" {
-                    continue;
+// The xcrun SDK name for a target, used to find its SDK when SDKROOT isn't set
+#[cfg(feature = "bindgen")]
+fn sdk_name(target: &str) -> &'static str {
+    let simulator = is_simulator(target);
+    if target.contains("-ios") && !target.ends_with("-macabi") {
+        if simulator { "iphonesimulator" } else { "iphoneos" }
+    } else if target.contains("-tvos") {
+        if simulator { "appletvsimulator" } else { "appletvos" }
+    } else if target.contains("-watchos") {
+        if simulator { "watchsimulator" } else { "watchos" }
+    } else {
+        "macosx"
+    }
+}
+
+// Finds the SDK to parse headers from: SDKROOT if set, otherwise whatever xcrun reports for the target's platform
+// (if xcrun exists, i.e. the host is a Mac)
+#[cfg(feature = "bindgen")]
+fn sysroot(target: &str) -> Option<PathBuf> {
+    if let Some(sdkroot) = env::var_os("SDKROOT") {
+        return Some(PathBuf::from(sdkroot));
+    }
+    let output = Command::new("xcrun")
+        .args(["--sdk", sdk_name(target), "--show-sdk-path"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let path = String::from_utf8(output.stdout).ok()?;
+    Some(PathBuf::from(path.trim()))
+}
+
+#[cfg(feature = "bindgen")]
+fn generate(target: &str, out_path: &Path) {
+    let features = enabled_features();
+    let extra_headers = extra_headers();
+
+    // Generate header from requested features
+    let mach_header_path = out_path.join("headers.h");
+
+    {
+        let header_out = File::create(&mach_header_path).expect("failed to open header file output");
+        let mut header_out = BufWriter::new(header_out);
+
+        // Always include some headers
+        for header_name in ["mach_types", "boolean", "kern_return", "error", "mach_error"].iter() {
+            writeln!(header_out, "#include <mach/{}.h>", header_name).unwrap();
+        }
+
+        for &(name, headers) in FEATURE_HEADERS.iter() {
+            if features.contains(&name) {
+                for header in headers.iter() {
+                    writeln!(header_out, "#include <{}>", header).unwrap();
                 }
-                writeln!(header_out, "#include <mach/{}.h>", feature_name).unwrap();
             }
         }
+        for header in extra_headers.iter() {
+            writeln!(header_out, "#include <{}>", header).unwrap();
+        }
+    }
+
+    // Debug output for every struct is a lot of extra code, so it's opt-in
+    let derives = env::var_os("CARGO_FEATURE_DERIVES").is_some();
+    let mut bindings = bindgen::Builder::default()
+        .header(mach_header_path.to_str().unwrap())
+        .clang_arg(format!("--target={}", clang_target(target)))
+        .use_core()
+        .ctypes_prefix("::core::ffi")
+        .derive_copy(true)
+        .derive_debug(derives)
+        // Implements Debug by hand for structs bindgen can't derive it for (e.g. those with large arrays)
+        .impl_debug(derives)
+        .derive_default(derives)
+        // Checks the generated structs (notably the per-architecture thread states) against the sizes and field offsets
+        // clang computed, when running `cargo test`
+        .layout_tests(true)
+        .parse_callbacks(Box::new(SkipHandwrittenMacros(handwritten_macros(&features))));
+    if let Some(sysroot) = sysroot(target) {
+        bindings = bindings.clang_arg(format!("-isysroot{}", sysroot.display()));
+    }
+    // Extra header directories, searched before the sysroot. Cross-compiling from a non-Apple host works by pointing
+    // this at headers extracted from an SDK.
+    if let Some(include) = env::var_os("MACH_SYS_INCLUDE") {
+        for path in env::split_paths(&include) {
+            bindings = bindings.clang_arg(format!("-I{}", path.display()));
+        }
+    }
+    if let Some(patterns) = allowlist(&features, &extra_headers) {
+        for pattern in patterns {
+            bindings = bindings
+                .whitelist_function(pattern)
+                .whitelist_type(pattern)
+                .whitelist_var(pattern);
+        }
+    }
+    if env::var_os("DEBUG").is_some() {
+        bindings = bindings.rustfmt_bindings(true);
+    }
+    let bindings = bindings
+        .generate()
+        .expect("failed to generate bindings");
+
+    bindings
+        .write_to_file(out_path.join("mach.rs"))
+        .expect("failed to write bindings");
+}
 
+#[cfg(feature = "bindgen")]
+#[derive(Debug)]
+struct SkipHandwrittenMacros(Vec<&'static str>);
 
-        let mut bindings = bindgen::Builder::default()
-            .header(mach_header_path.to_str().unwrap())
-            .derive_debug(false);
-        if env::var_os("DEBUG").is_some() {
-            bindings = bindings.rustfmt_bindings(true);
+#[cfg(feature = "bindgen")]
+impl bindgen::callbacks::ParseCallbacks for SkipHandwrittenMacros {
+    fn will_parse_macro(&self, name: &str) -> bindgen::callbacks::MacroParsingBehavior {
+        if self.0.contains(&name) {
+            bindgen::callbacks::MacroParsingBehavior::Ignore
+        } else {
+            bindgen::callbacks::MacroParsingBehavior::Default
         }
-        let bindings = bindings
-            .generate()
-            .expect("failed to generate bindings");
+    }
+}
+
+#[cfg(feature = "bindgen")]
+fn handwritten_macros(features: &[&str]) -> Vec<&'static str> {
+    HANDWRITTEN_MACROS.iter()
+        .filter(|(feature, _)| features.contains(feature))
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+// The enabled features that name headers
+#[cfg(feature = "bindgen")]
+fn enabled_features() -> Vec<&'static str> {
+    FEATURE_HEADERS.iter()
+        .map(|&(name, _)| name)
+        .filter(|name| env::var_os(format!("CARGO_FEATURE_{}", name.to_ascii_uppercase())).is_some())
+        .collect()
+}
+
+// Headers to include in addition to those of the enabled features, from MACH_SYS_EXTRA_HEADERS (separated by commas or
+// whitespace, e.g. `mach/vm_page_size.h,mach/mach_voucher.h`)
+#[cfg(feature = "bindgen")]
+fn extra_headers() -> Vec<String> {
+    match env::var("MACH_SYS_EXTRA_HEADERS") {
+        Ok(headers) => headers
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|header| !header.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
 
-        bindings
-            .write_to_file(out_path.join("mach.rs"))
-            .expect("failed to write bindings");
+// Item patterns to restrict generation to, or None to generate everything the headers declare. That happens when
+// MACH_SYS_NO_ALLOWLIST is set (for reaching an item no pattern covers yet), or when there are extra headers, since
+// filtering would drop everything they declare.
+#[cfg(feature = "bindgen")]
+fn allowlist(features: &[&str], extra_headers: &[String]) -> Option<Vec<&'static str>> {
+    if env::var_os("MACH_SYS_NO_ALLOWLIST").is_some() || !extra_headers.is_empty() {
+        return None;
     }
-}
\ No newline at end of file
+    let mut patterns = BASE_ITEMS.to_vec();
+    for feature_name in features.iter() {
+        let (_, items) = FEATURE_ITEMS.iter().find(|(name, _)| name == feature_name)?;
+        for item in items.iter() {
+            if !patterns.contains(item) {
+                patterns.push(*item);
+            }
+        }
+    }
+    Some(patterns)
+}
+
+#[cfg(not(feature = "bindgen"))]
+fn generate(_target: &str, _out_path: &Path) {
+    // No pregenerated bindings are checked in yet, so there is nothing to fall back to
+    panic!("mach-sys has no prebuilt bindings, and requires the `bindgen` feature (which needs libclang) on Apple targets");
+}
@@ -0,0 +1,28 @@
+// Private variants of the servers/bootstrap.h calls (from bootstrap_priv.h, which the SDK doesn't ship). They are
+// exported from libSystem like the public ones.
+
+pub const BOOTSTRAP_PER_PID_SERVICE: u64 = 1 << 0;
+pub const BOOTSTRAP_PRIVILEGED_SERVER: u64 = 1 << 1;
+pub const BOOTSTRAP_FORCE_LOCAL: u64 = 1 << 2;
+pub const BOOTSTRAP_SPECIFIC_INSTANCE: u64 = 1 << 3;
+pub const BOOTSTRAP_STRICT_CHECKIN: u64 = 1 << 4;
+pub const BOOTSTRAP_STRICT_LOOKUP: u64 = 1 << 5;
+
+extern "C" {
+    // Looks up a service, optionally in the per-PID namespace of `target_pid` (with BOOTSTRAP_PER_PID_SERVICE) or
+    // only in the local domain (with BOOTSTRAP_FORCE_LOCAL)
+    pub fn bootstrap_look_up2(
+        bp: mach_port_t,
+        service_name: *const ::core::ffi::c_char,
+        sp: *mut mach_port_t,
+        target_pid: ::core::ffi::c_int,
+        flags: u64,
+    ) -> kern_return_t;
+
+    pub fn bootstrap_check_in2(
+        bp: mach_port_t,
+        service_name: *const ::core::ffi::c_char,
+        sp: *mut mach_port_t,
+        flags: u64,
+    ) -> kern_return_t;
+}
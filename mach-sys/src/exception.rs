@@ -0,0 +1,4 @@
+// The EXC_MASK_* bit for an exception type (mach/exception_types.h), or None for types outside the mask's bits
+pub const fn EXC_MASK(exception: exception_type_t) -> Option<exception_mask_t> {
+    (1 as exception_mask_t).checked_shl(exception as u32)
+}
@@ -0,0 +1,113 @@
+// Function-like macros and static inline functions from the Mach headers, which bindgen can't generate. They are
+// reimplemented from the header definitions rather than compiled from C, so callers don't need a C toolchain.
+
+// mach/vm_param.h
+
+#[cfg(feature = "mach_init")]
+#[inline]
+pub fn trunc_page(address: vm_offset_t) -> vm_offset_t {
+    address & !unsafe { vm_page_mask }
+}
+
+#[cfg(feature = "mach_init")]
+#[inline]
+pub fn round_page(address: vm_offset_t) -> vm_offset_t {
+    let mask = unsafe { vm_page_mask };
+    (address + mask) & !mask
+}
+
+#[cfg(all(feature = "mach_init", any(feature = "mach_vm", feature = "vm")))]
+#[inline]
+pub fn mach_vm_trunc_page(address: mach_vm_offset_t) -> mach_vm_offset_t {
+    address & !(unsafe { vm_page_mask } as mach_vm_offset_t)
+}
+
+#[cfg(all(feature = "mach_init", any(feature = "mach_vm", feature = "vm")))]
+#[inline]
+pub fn mach_vm_round_page(address: mach_vm_offset_t) -> mach_vm_offset_t {
+    let mask = unsafe { vm_page_mask } as mach_vm_offset_t;
+    (address + mask) & !mask
+}
+
+// mach/vm_statistics.h
+
+#[cfg(any(feature = "mach_vm", feature = "vm"))]
+pub const fn VM_MAKE_TAG(tag: u8) -> i32 {
+    (tag as i32) << 24
+}
+
+// mach/task_special_ports.h
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_get_kernel_port(task: task_t, port: *mut mach_port_t) -> kern_return_t {
+    task_get_special_port(task, TASK_KERNEL_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_set_kernel_port(task: task_t, port: mach_port_t) -> kern_return_t {
+    task_set_special_port(task, TASK_KERNEL_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_get_host_port(task: task_t, port: *mut mach_port_t) -> kern_return_t {
+    task_get_special_port(task, TASK_HOST_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_set_host_port(task: task_t, port: mach_port_t) -> kern_return_t {
+    task_set_special_port(task, TASK_HOST_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_get_bootstrap_port(task: task_t, port: *mut mach_port_t) -> kern_return_t {
+    task_get_special_port(task, TASK_BOOTSTRAP_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_set_bootstrap_port(task: task_t, port: mach_port_t) -> kern_return_t {
+    task_set_special_port(task, TASK_BOOTSTRAP_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_get_task_access_port(task: task_t, port: *mut mach_port_t) -> kern_return_t {
+    task_get_special_port(task, TASK_ACCESS_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_set_task_access_port(task: task_t, port: mach_port_t) -> kern_return_t {
+    task_set_special_port(task, TASK_ACCESS_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_get_debug_control_port(task: task_t, port: *mut mach_port_t) -> kern_return_t {
+    task_get_special_port(task, TASK_DEBUG_CONTROL_PORT as _, port)
+}
+
+#[cfg(feature = "task")]
+#[inline]
+pub unsafe fn task_set_debug_control_port(task: task_t, port: mach_port_t) -> kern_return_t {
+    task_set_special_port(task, TASK_DEBUG_CONTROL_PORT as _, port)
+}
+
+// mach/thread_special_ports.h
+
+#[cfg(any(feature = "thread_act", feature = "thread"))]
+#[inline]
+pub unsafe fn thread_get_kernel_port(thread: thread_act_t, port: *mut mach_port_t) -> kern_return_t {
+    thread_get_special_port(thread, THREAD_KERNEL_PORT as _, port)
+}
+
+#[cfg(any(feature = "thread_act", feature = "thread"))]
+#[inline]
+pub unsafe fn thread_set_kernel_port(thread: thread_act_t, port: mach_port_t) -> kern_return_t {
+    thread_set_special_port(thread, THREAD_KERNEL_PORT as _, port)
+}
@@ -0,0 +1,59 @@
+// Conversions to and from the same structures as declared by libc and mach2, for projects using several binding crates
+// at once. Port names, return codes and the other scalar typedefs are plain integer aliases in every crate, so they
+// already pass between them unchanged; only structs need converting, and they share a layout, so this is free.
+
+macro_rules! interop {
+    ($($(#[$attr:meta])* $sys:ident <=> $other:ty;)*) => {
+        $(
+            $(#[$attr])*
+            impl From<$sys> for $other {
+                #[inline]
+                fn from(value: $sys) -> $other {
+                    unsafe { ::core::mem::transmute::<$sys, $other>(value) }
+                }
+            }
+
+            $(#[$attr])*
+            impl From<$other> for $sys {
+                #[inline]
+                fn from(value: $other) -> $sys {
+                    unsafe { ::core::mem::transmute::<$other, $sys>(value) }
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "libc")]
+interop! {
+    #[cfg(feature = "mach_time")]
+    mach_timebase_info <=> ::libc::mach_timebase_info;
+    #[cfg(any(feature = "mach_host", feature = "host_priv"))]
+    vm_statistics64 <=> ::libc::vm_statistics64;
+    #[cfg(feature = "task")]
+    mach_task_basic_info <=> ::libc::mach_task_basic_info;
+    #[cfg(any(feature = "thread_act", feature = "thread"))]
+    thread_basic_info <=> ::libc::thread_basic_info;
+    #[cfg(any(feature = "thread_act", feature = "thread"))]
+    thread_identifier_info <=> ::libc::thread_identifier_info;
+}
+
+#[cfg(feature = "mach2")]
+interop! {
+    #[cfg(feature = "mach_time")]
+    mach_timebase_info <=> ::mach2::mach_time::mach_timebase_info;
+    #[cfg(feature = "message")]
+    mach_msg_header_t <=> ::mach2::message::mach_msg_header_t;
+    #[cfg(feature = "message")]
+    mach_msg_body_t <=> ::mach2::message::mach_msg_body_t;
+    #[cfg(feature = "message")]
+    mach_msg_base_t <=> ::mach2::message::mach_msg_base_t;
+    #[cfg(feature = "message")]
+    mach_msg_port_descriptor_t <=> ::mach2::message::mach_msg_port_descriptor_t;
+    #[cfg(feature = "message")]
+    mach_msg_ool_descriptor_t <=> ::mach2::message::mach_msg_ool_descriptor_t;
+    #[cfg(feature = "message")]
+    mach_msg_ool_ports_descriptor_t <=> ::mach2::message::mach_msg_ool_ports_descriptor_t;
+    #[cfg(feature = "message")]
+    mach_msg_trailer_t <=> ::mach2::message::mach_msg_trailer_t;
+}
@@ -1,9 +1,17 @@
+// Nothing here needs std, but the layout tests bindgen generates run under the standard test harness
+#![cfg_attr(not(test), no_std)]
 #![allow(bad_style)]
 #![allow(dead_code)]
 
+#[cfg(target_vendor = "apple")]
 include!(concat!(env!("OUT_DIR"), "/mach.rs"));
 
-#[cfg(feature = "mach_init")]
+// Elsewhere there are no headers to generate bindings from, so only the port and message types are available (enough
+// for mach-port's fake kernel)
+#[cfg(not(target_vendor = "apple"))]
+include!("stub.rs");
+
+#[cfg(all(target_vendor = "apple", feature = "mach_init"))]
 include!("mach_init.rs");
 
 #[cfg(feature = "port")]
@@ -11,3 +19,21 @@ include!("port.rs");
 
 #[cfg(feature = "message")]
 include!("message.rs");
+
+#[cfg(all(target_vendor = "apple", any(feature = "task", feature = "thread_act", feature = "thread", feature = "exception")))]
+include!("exception.rs");
+
+#[cfg(all(target_vendor = "apple", any(feature = "thread_act", feature = "thread")))]
+include!("thread_status.rs");
+
+#[cfg(all(target_vendor = "apple", feature = "bootstrap"))]
+include!("bootstrap.rs");
+
+#[cfg(all(target_vendor = "apple", feature = "mach_voucher"))]
+include!("voucher.rs");
+
+#[cfg(target_vendor = "apple")]
+include!("inline.rs");
+
+#[cfg(all(target_vendor = "apple", any(feature = "libc", feature = "mach2")))]
+include!("interop.rs");
@@ -1 +1,63 @@
-pub const MACH_MSG_TIMEOUT_NONE: mach_msg_timeout_t = 0;
\ No newline at end of file
+pub const MACH_MSG_TIMEOUT_NONE: mach_msg_timeout_t = 0;
+
+// Header bits (mach/message.h)
+
+pub const fn MACH_MSGH_BITS(remote: mach_msg_type_name_t, local: mach_msg_type_name_t) -> mach_msg_bits_t {
+    remote | (local << 8)
+}
+
+pub const fn MACH_MSGH_BITS_SET_PORTS(remote: mach_msg_type_name_t, local: mach_msg_type_name_t, voucher: mach_msg_type_name_t) -> mach_msg_bits_t {
+    (remote & MACH_MSGH_BITS_REMOTE_MASK) | ((local << 8) & MACH_MSGH_BITS_LOCAL_MASK) | ((voucher << 16) & MACH_MSGH_BITS_VOUCHER_MASK)
+}
+
+pub const fn MACH_MSGH_BITS_SET(remote: mach_msg_type_name_t, local: mach_msg_type_name_t, voucher: mach_msg_type_name_t, other: mach_msg_bits_t) -> mach_msg_bits_t {
+    MACH_MSGH_BITS_SET_PORTS(remote, local, voucher) | (other & !MACH_MSGH_BITS_PORTS_MASK)
+}
+
+pub const fn MACH_MSGH_BITS_REMOTE(bits: mach_msg_bits_t) -> mach_msg_type_name_t {
+    bits & MACH_MSGH_BITS_REMOTE_MASK
+}
+
+pub const fn MACH_MSGH_BITS_LOCAL(bits: mach_msg_bits_t) -> mach_msg_type_name_t {
+    (bits & MACH_MSGH_BITS_LOCAL_MASK) >> 8
+}
+
+pub const fn MACH_MSGH_BITS_VOUCHER(bits: mach_msg_bits_t) -> mach_msg_type_name_t {
+    (bits & MACH_MSGH_BITS_VOUCHER_MASK) >> 16
+}
+
+pub const fn MACH_MSGH_BITS_PORTS(bits: mach_msg_bits_t) -> mach_msg_bits_t {
+    bits & MACH_MSGH_BITS_PORTS_MASK
+}
+
+pub const fn MACH_MSGH_BITS_OTHER(bits: mach_msg_bits_t) -> mach_msg_bits_t {
+    bits & !MACH_MSGH_BITS_PORTS_MASK
+}
+
+// Receive trailer options (mach/message.h)
+
+pub const fn MACH_RCV_TRAILER_TYPE(trailer_type: u32) -> mach_msg_option_t {
+    ((trailer_type & 0xf) << 28) as mach_msg_option_t
+}
+
+pub const fn MACH_RCV_TRAILER_ELEMENTS(elements: u32) -> mach_msg_option_t {
+    ((elements & 0xf) << 24) as mach_msg_option_t
+}
+
+pub const fn GET_RCV_ELEMENTS(option: mach_msg_option_t) -> u32 {
+    ((option as u32) >> 24) & 0xf
+}
+
+// Disposition classes (mach/message.h)
+
+pub const fn MACH_MSG_TYPE_PORT_ANY(disposition: mach_msg_type_name_t) -> bool {
+    disposition >= MACH_MSG_TYPE_MOVE_RECEIVE && disposition <= MACH_MSG_TYPE_MAKE_SEND_ONCE
+}
+
+pub const fn MACH_MSG_TYPE_PORT_ANY_SEND(disposition: mach_msg_type_name_t) -> bool {
+    disposition >= MACH_MSG_TYPE_MOVE_SEND && disposition <= MACH_MSG_TYPE_MAKE_SEND_ONCE
+}
+
+pub const fn MACH_MSG_TYPE_PORT_ANY_RIGHT(disposition: mach_msg_type_name_t) -> bool {
+    disposition >= MACH_MSG_TYPE_MOVE_RECEIVE && disposition <= MACH_MSG_TYPE_MOVE_SEND_ONCE
+}
@@ -1,11 +1,41 @@
+// Defined here rather than generated, since the header leaves MACH_PORT_NULL untyped and bindgen can't evaluate the
+// casts in the rest
+
+pub const MACH_PORT_NULL: mach_port_t = 0;
+// Also how a send right to a port whose receive right has been destroyed appears when received in a message
 pub const MACH_PORT_DEAD: mach_port_name_t = !0;
 
 pub const MACH_PORT_RIGHT_SEND: mach_port_right_t = 0;
 pub const MACH_PORT_RIGHT_RECEIVE: mach_port_right_t = 1;
+pub const MACH_PORT_RIGHT_SEND_ONCE: mach_port_right_t = 2;
+pub const MACH_PORT_RIGHT_PORT_SET: mach_port_right_t = 3;
+pub const MACH_PORT_RIGHT_DEAD_NAME: mach_port_right_t = 4;
+// Obsolete, but still occupies a right number
+pub const MACH_PORT_RIGHT_LABELH: mach_port_right_t = 5;
+pub const MACH_PORT_RIGHT_NUMBER: mach_port_right_t = 6;
 
+pub const MACH_PORT_TYPE_NONE: mach_port_type_t = 0;
 pub const MACH_PORT_TYPE_SEND: mach_port_type_t = MACH_PORT_TYPE(MACH_PORT_RIGHT_SEND);
 pub const MACH_PORT_TYPE_RECEIVE: mach_port_type_t = MACH_PORT_TYPE(MACH_PORT_RIGHT_RECEIVE);
+pub const MACH_PORT_TYPE_SEND_ONCE: mach_port_type_t = MACH_PORT_TYPE(MACH_PORT_RIGHT_SEND_ONCE);
+pub const MACH_PORT_TYPE_PORT_SET: mach_port_type_t = MACH_PORT_TYPE(MACH_PORT_RIGHT_PORT_SET);
+// A name whose send or send-once rights died with their port. It keeps the name (and its user references) until they
+// are released, but no longer refers to anything.
+pub const MACH_PORT_TYPE_DEAD_NAME: mach_port_type_t = MACH_PORT_TYPE(MACH_PORT_RIGHT_DEAD_NAME);
+pub const MACH_PORT_TYPE_LABELH: mach_port_type_t = MACH_PORT_TYPE(MACH_PORT_RIGHT_LABELH);
+
+pub const MACH_PORT_TYPE_SEND_RECEIVE: mach_port_type_t = MACH_PORT_TYPE_SEND | MACH_PORT_TYPE_RECEIVE;
+pub const MACH_PORT_TYPE_SEND_RIGHTS: mach_port_type_t = MACH_PORT_TYPE_SEND | MACH_PORT_TYPE_SEND_ONCE;
+pub const MACH_PORT_TYPE_PORT_RIGHTS: mach_port_type_t = MACH_PORT_TYPE_SEND_RIGHTS | MACH_PORT_TYPE_RECEIVE;
+pub const MACH_PORT_TYPE_PORT_OR_DEAD: mach_port_type_t = MACH_PORT_TYPE_PORT_RIGHTS | MACH_PORT_TYPE_DEAD_NAME;
+pub const MACH_PORT_TYPE_ALL_RIGHTS: mach_port_type_t = MACH_PORT_TYPE_PORT_OR_DEAD | MACH_PORT_TYPE_PORT_SET;
 
-const fn MACH_PORT_TYPE(right: mach_port_right_t) -> mach_port_type_t {
+pub const MACH_PORT_UREFS_MAX: mach_port_urefs_t = (1 << 16) - 1;
+
+pub const fn MACH_PORT_TYPE(right: mach_port_right_t) -> mach_port_type_t {
     1 << (right + 16)
-}
\ No newline at end of file
+}
+
+pub const fn MACH_PORT_VALID(name: mach_port_name_t) -> bool {
+    name != MACH_PORT_NULL && name != MACH_PORT_DEAD
+}
@@ -0,0 +1,328 @@
+// Stands in for the generated bindings on targets without Mach headers. Only the port and message types, return codes
+// and constants needed to build mach-core and mach-port's in-memory fake kernel are defined, transcribed from the
+// 64-bit Darwin headers. There are no functions, since there is no kernel to call.
+
+// mach/machine/vm_types.h, mach/kern_return.h, mach/mach_error.h
+
+pub type natural_t = ::core::ffi::c_uint;
+pub type integer_t = ::core::ffi::c_int;
+pub type boolean_t = ::core::ffi::c_int;
+pub type kern_return_t = ::core::ffi::c_int;
+pub type mach_error_t = kern_return_t;
+
+pub const KERN_SUCCESS: u32 = 0;
+pub const KERN_INVALID_ADDRESS: u32 = 1;
+pub const KERN_PROTECTION_FAILURE: u32 = 2;
+pub const KERN_NO_SPACE: u32 = 3;
+pub const KERN_INVALID_ARGUMENT: u32 = 4;
+pub const KERN_FAILURE: u32 = 5;
+pub const KERN_RESOURCE_SHORTAGE: u32 = 6;
+pub const KERN_NOT_RECEIVER: u32 = 7;
+pub const KERN_NO_ACCESS: u32 = 8;
+pub const KERN_MEMORY_FAILURE: u32 = 9;
+pub const KERN_MEMORY_ERROR: u32 = 10;
+pub const KERN_ALREADY_IN_SET: u32 = 11;
+pub const KERN_NOT_IN_SET: u32 = 12;
+pub const KERN_NAME_EXISTS: u32 = 13;
+pub const KERN_ABORTED: u32 = 14;
+pub const KERN_INVALID_NAME: u32 = 15;
+pub const KERN_INVALID_TASK: u32 = 16;
+pub const KERN_INVALID_RIGHT: u32 = 17;
+pub const KERN_INVALID_VALUE: u32 = 18;
+pub const KERN_UREFS_OVERFLOW: u32 = 19;
+pub const KERN_INVALID_CAPABILITY: u32 = 20;
+pub const KERN_RIGHT_EXISTS: u32 = 21;
+pub const KERN_INVALID_HOST: u32 = 22;
+pub const KERN_MEMORY_PRESENT: u32 = 23;
+pub const KERN_MEMORY_DATA_MOVED: u32 = 24;
+pub const KERN_MEMORY_RESTART_COPY: u32 = 25;
+pub const KERN_INVALID_PROCESSOR_SET: u32 = 26;
+pub const KERN_POLICY_LIMIT: u32 = 27;
+pub const KERN_INVALID_POLICY: u32 = 28;
+pub const KERN_INVALID_OBJECT: u32 = 29;
+pub const KERN_ALREADY_WAITING: u32 = 30;
+pub const KERN_DEFAULT_SET: u32 = 31;
+pub const KERN_EXCEPTION_PROTECTED: u32 = 32;
+pub const KERN_INVALID_LEDGER: u32 = 33;
+pub const KERN_INVALID_MEMORY_CONTROL: u32 = 34;
+pub const KERN_INVALID_SECURITY: u32 = 35;
+pub const KERN_NOT_DEPRESSED: u32 = 36;
+pub const KERN_TERMINATED: u32 = 37;
+pub const KERN_LOCK_SET_DESTROYED: u32 = 38;
+pub const KERN_LOCK_UNSTABLE: u32 = 39;
+pub const KERN_LOCK_OWNED: u32 = 40;
+pub const KERN_LOCK_OWNED_SELF: u32 = 41;
+pub const KERN_SEMAPHORE_DESTROYED: u32 = 42;
+pub const KERN_RPC_SERVER_TERMINATED: u32 = 43;
+pub const KERN_RPC_TERMINATE_ORPHAN: u32 = 44;
+pub const KERN_RPC_CONTINUE_ORPHAN: u32 = 45;
+pub const KERN_NOT_SUPPORTED: u32 = 46;
+pub const KERN_NODE_DOWN: u32 = 47;
+pub const KERN_NOT_WAITING: u32 = 48;
+pub const KERN_OPERATION_TIMED_OUT: u32 = 49;
+pub const KERN_CODESIGN_ERROR: u32 = 50;
+pub const KERN_POLICY_STATIC: u32 = 51;
+pub const KERN_INSUFFICIENT_BUFFER_SIZE: u32 = 52;
+pub const KERN_DENIED: u32 = 53;
+pub const KERN_MISSING_KC: u32 = 54;
+pub const KERN_INVALID_KC: u32 = 55;
+pub const KERN_NOT_FOUND: u32 = 56;
+pub const KERN_RETURN_MAX: u32 = 0x100;
+
+// mach/port.h
+
+pub type mach_port_name_t = natural_t;
+pub type mach_port_t = mach_port_name_t;
+pub type mach_port_right_t = natural_t;
+pub type mach_port_type_t = natural_t;
+pub type mach_port_urefs_t = natural_t;
+pub type mach_port_delta_t = integer_t;
+pub type mach_port_seqno_t = natural_t;
+pub type mach_port_mscount_t = natural_t;
+pub type mach_port_msgcount_t = natural_t;
+
+pub const MACH_PORT_QLIMIT_DEFAULT: u32 = 5;
+
+// mach/message.h
+
+pub type mach_msg_bits_t = ::core::ffi::c_uint;
+pub type mach_msg_size_t = natural_t;
+pub type mach_msg_id_t = integer_t;
+pub type mach_msg_type_name_t = ::core::ffi::c_uint;
+pub type mach_msg_copy_options_t = ::core::ffi::c_uint;
+pub type mach_msg_descriptor_type_t = ::core::ffi::c_uint;
+pub type mach_msg_type_number_t = natural_t;
+pub type mach_msg_option_t = integer_t;
+pub type mach_msg_timeout_t = natural_t;
+pub type mach_msg_return_t = kern_return_t;
+pub type mach_msg_trailer_type_t = ::core::ffi::c_uint;
+pub type mach_msg_trailer_size_t = ::core::ffi::c_uint;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct mach_msg_header_t {
+    pub msgh_bits: mach_msg_bits_t,
+    pub msgh_size: mach_msg_size_t,
+    pub msgh_remote_port: mach_port_t,
+    pub msgh_local_port: mach_port_t,
+    pub msgh_voucher_port: mach_port_name_t,
+    pub msgh_id: mach_msg_id_t,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct mach_msg_body_t {
+    pub msgh_descriptor_count: mach_msg_size_t,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct mach_msg_trailer_t {
+    pub msgh_trailer_type: mach_msg_trailer_type_t,
+    pub msgh_trailer_size: mach_msg_trailer_size_t,
+}
+
+// Descriptors pack their 8-bit fields into a single 32-bit bitfield unit, which is exposed through accessors named like
+// bindgen's
+macro_rules! bitfield_accessors {
+    ($ty:ident { $($get:ident, $set:ident: $field_ty:ty = $shift:expr;)* }) => {
+        impl $ty {
+            $(
+                #[inline]
+                pub fn $get(&self) -> $field_ty {
+                    ((self._bitfield_1 >> $shift) & 0xff) as $field_ty
+                }
+
+                #[inline]
+                pub fn $set(&mut self, val: $field_ty) {
+                    self._bitfield_1 = (self._bitfield_1 & !(0xff << $shift)) | ((val as u32 & 0xff) << $shift);
+                }
+            )*
+        }
+    };
+}
+
+#[repr(C, packed(4))]
+#[derive(Copy, Clone)]
+pub struct mach_msg_type_descriptor_t {
+    pub pad1: natural_t,
+    pub pad2: mach_msg_size_t,
+    pub _bitfield_1: u32,
+}
+
+bitfield_accessors!(mach_msg_type_descriptor_t {
+    type_, set_type: mach_msg_descriptor_type_t = 24;
+});
+
+#[repr(C, packed(4))]
+#[derive(Copy, Clone)]
+pub struct mach_msg_port_descriptor_t {
+    pub name: mach_port_t,
+    pub pad1: mach_msg_size_t,
+    pub _bitfield_1: u32,
+}
+
+bitfield_accessors!(mach_msg_port_descriptor_t {
+    disposition, set_disposition: mach_msg_type_name_t = 16;
+    type_, set_type: mach_msg_descriptor_type_t = 24;
+});
+
+#[repr(C, packed(4))]
+#[derive(Copy, Clone)]
+pub struct mach_msg_ool_descriptor_t {
+    pub address: *mut ::core::ffi::c_void,
+    pub _bitfield_1: u32,
+    pub size: mach_msg_size_t,
+}
+
+bitfield_accessors!(mach_msg_ool_descriptor_t {
+    deallocate, set_deallocate: boolean_t = 0;
+    copy, set_copy: mach_msg_copy_options_t = 8;
+    type_, set_type: mach_msg_descriptor_type_t = 24;
+});
+
+#[repr(C, packed(4))]
+#[derive(Copy, Clone)]
+pub struct mach_msg_ool_ports_descriptor_t {
+    pub address: *mut ::core::ffi::c_void,
+    pub _bitfield_1: u32,
+    pub count: mach_msg_size_t,
+}
+
+bitfield_accessors!(mach_msg_ool_ports_descriptor_t {
+    deallocate, set_deallocate: boolean_t = 0;
+    copy, set_copy: mach_msg_copy_options_t = 8;
+    disposition, set_disposition: mach_msg_type_name_t = 16;
+    type_, set_type: mach_msg_descriptor_type_t = 24;
+});
+
+#[repr(C, packed(4))]
+#[derive(Copy, Clone)]
+pub union mach_msg_descriptor_t {
+    pub port: mach_msg_port_descriptor_t,
+    pub out_of_line: mach_msg_ool_descriptor_t,
+    pub ool_ports: mach_msg_ool_ports_descriptor_t,
+    pub type_: mach_msg_type_descriptor_t,
+}
+
+pub const MACH_MSGH_BITS_ZERO: u32 = 0;
+pub const MACH_MSGH_BITS_REMOTE_MASK: u32 = 0x0000_001f;
+pub const MACH_MSGH_BITS_LOCAL_MASK: u32 = 0x0000_1f00;
+pub const MACH_MSGH_BITS_VOUCHER_MASK: u32 = 0x001f_0000;
+pub const MACH_MSGH_BITS_PORTS_MASK: u32 = 0x001f_1f1f;
+pub const MACH_MSGH_BITS_COMPLEX: u32 = 0x8000_0000;
+pub const MACH_MSGH_BITS_USER: u32 = 0x801f_1f1f;
+
+pub const MACH_MSG_TYPE_MOVE_RECEIVE: u32 = 16;
+pub const MACH_MSG_TYPE_MOVE_SEND: u32 = 17;
+pub const MACH_MSG_TYPE_MOVE_SEND_ONCE: u32 = 18;
+pub const MACH_MSG_TYPE_COPY_SEND: u32 = 19;
+pub const MACH_MSG_TYPE_MAKE_SEND: u32 = 20;
+pub const MACH_MSG_TYPE_MAKE_SEND_ONCE: u32 = 21;
+pub const MACH_MSG_TYPE_COPY_RECEIVE: u32 = 22;
+pub const MACH_MSG_TYPE_DISPOSE_RECEIVE: u32 = 24;
+pub const MACH_MSG_TYPE_DISPOSE_SEND: u32 = 25;
+pub const MACH_MSG_TYPE_DISPOSE_SEND_ONCE: u32 = 26;
+pub const MACH_MSG_TYPE_PORT_NAME: u32 = 15;
+pub const MACH_MSG_TYPE_PORT_RECEIVE: u32 = MACH_MSG_TYPE_MOVE_RECEIVE;
+pub const MACH_MSG_TYPE_PORT_SEND: u32 = MACH_MSG_TYPE_MOVE_SEND;
+pub const MACH_MSG_TYPE_PORT_SEND_ONCE: u32 = MACH_MSG_TYPE_MOVE_SEND_ONCE;
+pub const MACH_MSG_TYPE_LAST: u32 = 22;
+
+pub const MACH_MSG_PHYSICAL_COPY: u32 = 0;
+pub const MACH_MSG_VIRTUAL_COPY: u32 = 1;
+pub const MACH_MSG_ALLOCATE: u32 = 2;
+
+pub const MACH_MSG_PORT_DESCRIPTOR: u32 = 0;
+pub const MACH_MSG_OOL_DESCRIPTOR: u32 = 1;
+pub const MACH_MSG_OOL_PORTS_DESCRIPTOR: u32 = 2;
+pub const MACH_MSG_OOL_VOLATILE_DESCRIPTOR: u32 = 3;
+pub const MACH_MSG_GUARDED_PORT_DESCRIPTOR: u32 = 4;
+
+pub const MACH_MSG_TRAILER_FORMAT_0: u32 = 0;
+pub const MACH_RCV_TRAILER_NULL: u32 = 0;
+pub const MACH_RCV_TRAILER_SEQNO: u32 = 1;
+pub const MACH_RCV_TRAILER_SENDER: u32 = 2;
+pub const MACH_RCV_TRAILER_AUDIT: u32 = 3;
+pub const MACH_RCV_TRAILER_CTX: u32 = 4;
+
+pub const MACH_MSG_OPTION_NONE: u32 = 0;
+pub const MACH_SEND_MSG: u32 = 0x0000_0001;
+pub const MACH_RCV_MSG: u32 = 0x0000_0002;
+pub const MACH_RCV_LARGE: u32 = 0x0000_0004;
+pub const MACH_RCV_LARGE_IDENTITY: u32 = 0x0000_0008;
+pub const MACH_SEND_TIMEOUT: u32 = 0x0000_0010;
+pub const MACH_SEND_OVERRIDE: u32 = 0x0000_0020;
+pub const MACH_SEND_INTERRUPT: u32 = 0x0000_0040;
+pub const MACH_SEND_NOTIFY: u32 = 0x0000_0080;
+pub const MACH_RCV_TIMEOUT: u32 = 0x0000_0100;
+pub const MACH_RCV_INTERRUPT: u32 = 0x0000_0400;
+pub const MACH_RCV_VOUCHER: u32 = 0x0000_0800;
+
+pub const MACH_MSG_SUCCESS: u32 = 0x0000_0000;
+pub const MACH_MSG_MASK: u32 = 0x0000_3e00;
+pub const MACH_MSG_IPC_SPACE: u32 = 0x0000_2000;
+pub const MACH_MSG_VM_SPACE: u32 = 0x0000_1000;
+pub const MACH_MSG_IPC_KERNEL: u32 = 0x0000_0800;
+pub const MACH_MSG_VM_KERNEL: u32 = 0x0000_0400;
+
+pub const MACH_SEND_IN_PROGRESS: u32 = 0x1000_0001;
+pub const MACH_SEND_INVALID_DATA: u32 = 0x1000_0002;
+pub const MACH_SEND_INVALID_DEST: u32 = 0x1000_0003;
+pub const MACH_SEND_TIMED_OUT: u32 = 0x1000_0004;
+pub const MACH_SEND_INVALID_VOUCHER: u32 = 0x1000_0005;
+pub const MACH_SEND_INTERRUPTED: u32 = 0x1000_0007;
+pub const MACH_SEND_MSG_TOO_SMALL: u32 = 0x1000_0008;
+pub const MACH_SEND_INVALID_REPLY: u32 = 0x1000_0009;
+pub const MACH_SEND_INVALID_RIGHT: u32 = 0x1000_000a;
+pub const MACH_SEND_INVALID_NOTIFY: u32 = 0x1000_000b;
+pub const MACH_SEND_INVALID_MEMORY: u32 = 0x1000_000c;
+pub const MACH_SEND_NO_BUFFER: u32 = 0x1000_000d;
+pub const MACH_SEND_TOO_LARGE: u32 = 0x1000_000e;
+pub const MACH_SEND_INVALID_TYPE: u32 = 0x1000_000f;
+pub const MACH_SEND_INVALID_HEADER: u32 = 0x1000_0010;
+pub const MACH_SEND_INVALID_TRAILER: u32 = 0x1000_0011;
+pub const MACH_SEND_INVALID_CONTEXT: u32 = 0x1000_0012;
+
+pub const MACH_RCV_IN_PROGRESS: u32 = 0x1000_4001;
+pub const MACH_RCV_INVALID_NAME: u32 = 0x1000_4002;
+pub const MACH_RCV_TIMED_OUT: u32 = 0x1000_4003;
+pub const MACH_RCV_TOO_LARGE: u32 = 0x1000_4004;
+pub const MACH_RCV_INTERRUPTED: u32 = 0x1000_4005;
+pub const MACH_RCV_PORT_CHANGED: u32 = 0x1000_4006;
+pub const MACH_RCV_INVALID_NOTIFY: u32 = 0x1000_4007;
+pub const MACH_RCV_INVALID_DATA: u32 = 0x1000_4008;
+pub const MACH_RCV_PORT_DIED: u32 = 0x1000_4009;
+pub const MACH_RCV_IN_SET: u32 = 0x1000_400a;
+pub const MACH_RCV_HEADER_ERROR: u32 = 0x1000_400b;
+pub const MACH_RCV_BODY_ERROR: u32 = 0x1000_400c;
+pub const MACH_RCV_INVALID_TYPE: u32 = 0x1000_400d;
+pub const MACH_RCV_SCATTER_SMALL: u32 = 0x1000_400e;
+pub const MACH_RCV_INVALID_TRAILER: u32 = 0x1000_400f;
+pub const MACH_RCV_IN_PROGRESS_TIMED: u32 = 0x1000_4011;
+pub const MACH_RCV_INVALID_REPLY: u32 = 0x1000_4012;
+
+// mach/ndr.h
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct NDR_record_t {
+    pub mig_vers: ::core::ffi::c_uchar,
+    pub if_vers: ::core::ffi::c_uchar,
+    pub reserved1: ::core::ffi::c_uchar,
+    pub mig_encoding: ::core::ffi::c_uchar,
+    pub int_rep: ::core::ffi::c_uchar,
+    pub char_rep: ::core::ffi::c_uchar,
+    pub float_rep: ::core::ffi::c_uchar,
+    pub reserved2: ::core::ffi::c_uchar,
+}
+
+// mach/notify.h
+
+pub const MACH_NOTIFY_FIRST: u32 = 0o100;
+pub const MACH_NOTIFY_PORT_DELETED: u32 = MACH_NOTIFY_FIRST + 0o01;
+pub const MACH_NOTIFY_SEND_POSSIBLE: u32 = MACH_NOTIFY_FIRST + 0o02;
+pub const MACH_NOTIFY_PORT_DESTROYED: u32 = MACH_NOTIFY_FIRST + 0o05;
+pub const MACH_NOTIFY_NO_SENDERS: u32 = MACH_NOTIFY_FIRST + 0o06;
+pub const MACH_NOTIFY_SEND_ONCE: u32 = MACH_NOTIFY_FIRST + 0o07;
+pub const MACH_NOTIFY_DEAD_NAME: u32 = MACH_NOTIFY_FIRST + 0o10;
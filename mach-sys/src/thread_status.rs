@@ -0,0 +1,19 @@
+// The *_COUNT sizes of the thread state flavors (mach/<arch>/thread_status.h), which the headers compute with sizeof
+
+#[cfg(target_arch = "x86_64")]
+pub const x86_THREAD_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<x86_thread_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+#[cfg(target_arch = "x86_64")]
+pub const x86_FLOAT_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<x86_float_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+#[cfg(target_arch = "x86_64")]
+pub const x86_EXCEPTION_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<x86_exception_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+#[cfg(target_arch = "x86_64")]
+pub const x86_DEBUG_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<x86_debug_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+
+#[cfg(target_arch = "aarch64")]
+pub const ARM_THREAD_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<arm_thread_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+#[cfg(target_arch = "aarch64")]
+pub const ARM_NEON_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<arm_neon_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+#[cfg(target_arch = "aarch64")]
+pub const ARM_EXCEPTION_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<arm_exception_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+#[cfg(target_arch = "aarch64")]
+pub const ARM_DEBUG_STATE64_COUNT: mach_msg_type_number_t = (::core::mem::size_of::<arm_debug_state64_t>() / ::core::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
@@ -0,0 +1,134 @@
+use mach_port::{Port, PortCopyMode, PortNameInfo, PortSpaceSnapshot, MsgBuffer, RawPort, Task, TaskFlavor};
+
+use std::{env, io, process, thread};
+use std::time::Duration;
+
+pub fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let result = match args.as_slice() {
+        ["ports", pid] => ports(parse_pid(pid)),
+        ["status", pid, name] => status(parse_pid(pid), parse_name(name)),
+        ["peek", pid, name] => peek(parse_pid(pid), parse_name(name)),
+        ["watch", pid] => watch(parse_pid(pid), Duration::from_secs(1)),
+        ["watch", pid, interval] => watch(parse_pid(pid), Duration::from_millis(parse_number(interval) as u64)),
+        ["notify", pid, name] => notify(parse_pid(pid), parse_name(name)),
+        _ => usage(),
+    };
+    if let Err(err) = result {
+        eprintln!("machctl: {}", err);
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: machctl ports <pid>");
+    eprintln!("       machctl status <pid> <name>");
+    eprintln!("       machctl peek <pid> <name>");
+    eprintln!("       machctl watch <pid> [interval-ms]");
+    eprintln!("       machctl notify <pid> <name>");
+    process::exit(2);
+}
+
+fn parse_number(arg: &str) -> u32 {
+    let parsed = match arg.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    parsed.unwrap_or_else(|_| {
+        eprintln!("machctl: invalid number {:?}", arg);
+        usage()
+    })
+}
+
+fn parse_pid(arg: &str) -> i32 {
+    parse_number(arg) as i32
+}
+
+fn parse_name(arg: &str) -> RawPort {
+    parse_number(arg) as RawPort
+}
+
+fn ports(pid: i32) -> io::Result<()> {
+    let task = Task::for_pid(pid, TaskFlavor::Read)?;
+    let snapshot = task.port_space()?;
+    println!("table size: {}, names in use: {}", snapshot.table_size, snapshot.names.len());
+    println!("{:>10}  {:<16}  {:>6}  {:>10}", "NAME", "RIGHTS", "UREFS", "OBJECT");
+    for info in snapshot.names.iter() {
+        print_name(info);
+    }
+    Ok(())
+}
+
+fn status(pid: i32, name: RawPort) -> io::Result<()> {
+    let task = Task::for_pid(pid, TaskFlavor::Read)?;
+    println!("{:#?}", task.receive_status(name)?);
+    Ok(())
+}
+
+fn peek(pid: i32, name: RawPort) -> io::Result<()> {
+    let task = Task::for_pid(pid, TaskFlavor::Read)?;
+    match task.peek_message(name, None)? {
+        Some(message) => println!("seqno {} id {} size {}", message.seqno, message.id, message.size),
+        None => println!("queue is empty"),
+    }
+    Ok(())
+}
+
+fn watch(pid: i32, interval: Duration) -> io::Result<()> {
+    let task = Task::for_pid(pid, TaskFlavor::Read)?;
+    let mut previous = task.port_space()?;
+    print_summary(&previous);
+    loop {
+        thread::sleep(interval);
+        let current = task.port_space()?;
+        let diff = current.diff(&previous);
+        if !diff.is_empty() {
+            for info in diff.added.iter() {
+                print!("+ ");
+                print_name(info);
+            }
+            for info in diff.removed.iter() {
+                print!("- ");
+                print_name(info);
+            }
+            for (_, info) in diff.changed.iter() {
+                print!("~ ");
+                print_name(info);
+            }
+            print_summary(&current);
+        }
+        previous = current;
+    }
+}
+
+fn notify(pid: i32, name: RawPort) -> io::Result<()> {
+    let task = Task::for_pid(pid, TaskFlavor::Control)?;
+    let port = task.copy_right_out(name, PortCopyMode::Send)?;
+    let notify_port = Port::new()?;
+    port.request_dead_name_notification(&notify_port)?;
+    println!("waiting for {:#x} to die...", name);
+    let mut buffer = MsgBuffer::new();
+    notify_port.recv(&mut buffer, None)?;
+    println!("received notification with message ID {}", buffer.id());
+    Ok(())
+}
+
+fn print_summary(snapshot: &PortSpaceSnapshot) {
+    println!("{} names in use ({} receive, {} send, {} dead)",
+        snapshot.names.len(),
+        snapshot.names.iter().filter(|info| info.has_receive()).count(),
+        snapshot.names.iter().filter(|info| info.has_send()).count(),
+        snapshot.names.iter().filter(|info| info.is_dead_name()).count(),
+    );
+}
+
+fn print_name(info: &PortNameInfo) {
+    let mut rights = Vec::new();
+    if info.has_receive() { rights.push("recv"); }
+    if info.has_send() { rights.push("send"); }
+    if info.has_send_once() { rights.push("once"); }
+    if info.is_port_set() { rights.push("pset"); }
+    if info.is_dead_name() { rights.push("dead"); }
+    println!("{:>#10x}  {:<16}  {:>6}  {:>#10x}", info.name, rights.join(","), info.urefs, info.object);
+}
@@ -0,0 +1,27 @@
+//! Inspects the Mach port namespace of a running process.
+//!
+//! ```text
+//! machctl ports <pid>                 list the names in the task's port space
+//! machctl status <pid> <name>         dump the state of a receive right
+//! machctl peek <pid> <name>           show the message at the head of a receive right's queue
+//! machctl watch <pid> [interval-ms]   print changes to the port space as they happen
+//! machctl notify <pid> <name>         wait for the receive right behind a send right to die
+//! ```
+//!
+//! Names may be given in decimal or as `0x`-prefixed hex. Most commands require the privileges to obtain a read or
+//! control port for the target (i.e. root, or the `com.apple.security.get-task-allow` entitlement on the target).
+
+#[cfg(target_os = "macos")]
+mod cli;
+
+#[cfg(target_os = "macos")]
+fn main() {
+    cli::main()
+}
+
+// Other tasks' ports can't be obtained by PID outside macOS
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("machctl: only supported on macOS");
+    std::process::exit(1);
+}
@@ -0,0 +1,39 @@
+//! Runs the binary against the test process itself, which can always get a port for its own task.
+
+#![cfg(target_os = "macos")]
+
+use mach_port::Port;
+
+use std::process::{self, Command, Output};
+
+fn machctl(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_machctl")).args(args).output().unwrap();
+    assert!(output.status.success(), "machctl {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+#[test]
+fn ports_lists_own_receive_right() {
+    let port = Port::new().unwrap();
+    let output = machctl(&["ports", &process::id().to_string()]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("table size: "), "unexpected output: {}", stdout);
+    let line = stdout.lines()
+        .find(|line| line.split_whitespace().next() == Some(format!("{:#x}", port.as_raw_port()).as_str()))
+        .unwrap_or_else(|| panic!("port {:#x} missing from output: {}", port.as_raw_port(), stdout));
+    assert!(line.contains("recv"), "unexpected line: {}", line);
+}
+
+#[test]
+fn status_accepts_hex_names() {
+    let port = Port::new().unwrap();
+    let output = machctl(&["status", &process::id().to_string(), &format!("{:#x}", port.as_raw_port())]);
+    assert!(!output.stdout.is_empty());
+}
+
+#[test]
+fn invalid_number_prints_usage() {
+    let output = Command::new(env!("CARGO_BIN_EXE_machctl")).args(["ports", "0xzz"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid number \"0xzz\""));
+}